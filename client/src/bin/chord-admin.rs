@@ -0,0 +1,1193 @@
+//! Diagnostic commands for a running Chord node.
+//!
+//! Usage: `chord-admin <node address> <command> [--hmac-key <key>]
+//! [--tls-ca <path> [--tls-server-name <name>]]`
+//!
+//! Node addresses are written `ip:port`, followed by `#k`
+//! to reach virtual node `k` of a process (`vnodes`).
+//!
+//! Requests are signed with `--hmac-key` (or the `CHORD_HMAC_KEY`
+//! environment variable), the key of a ring authenticating its messages.
+//! Connections are encrypted with TLS with `--tls-ca`, the certificates
+//! trusted for the nodes of a ring requiring TLS, issued for
+//! `--tls-server-name` (`chord-node` by default).
+//!
+//! Commands:
+//! - `fingers`: prints the located fingers of the node,
+//!   with the ring position each of them targets.
+//! - `members`: walks the ring from the node, following successor
+//!   pointers, and prints the ring membership in ring order, with
+//!   the inconsistencies found on the way (successors whose
+//!   predecessor is another node, duplicate identifiers, broken cycle,
+//!   owned ranges which do not tile the ring), then the share
+//!   of the ring owned by each process, next to its weight: its
+//!   share of the virtual nodes (`vnodes`), which a process should
+//!   be given in proportion to its capacity.
+//! - `topology`: walks the ring from the node, following successor
+//!   pointers, and prints it as a Graphviz DOT digraph: each node
+//!   labeled by its short identifier and address, with an edge to
+//!   its successor and a dashed edge to its predecessor. Pointers
+//!   which the pointed node does not mirror are drawn in red.
+//! - `convergence [watch]`: walks the ring from the node, reads the
+//!   gossip data held by each process, and reports how many of them
+//!   hold the latest update. With `watch`, polls every second until
+//!   all of them do.
+//! - `owner <key>`: walks the ring from the node, computes the
+//!   owner of the application key from the members met, and checks
+//!   it against the owner a lookup from the node returns, to verify
+//!   the routing. Exits with status 1 if they differ.
+//! - `versions`: walks the ring from the node, reads the version,
+//!   uptime and ring parameters of each process, and flags the ones
+//!   whose version or ring parameters differ from most processes,
+//!   to check the ring before a rolling change. Exits with status 1
+//!   if a process differs or does not answer.
+//! - `set-predecessor <predecessor address> <admin secret>`:
+//!   overwrites the predecessor of the node, to repair a node
+//!   whose stabilization does not converge (e.g. after a partition).
+//! - `drain <admin secret>`: drains the node before its removal (e.g.
+//!   for a rolling upgrade): it rejects new data updates, hands off
+//!   its gossip state to its successor, then leaves the network.
+
+use std::{
+    collections::BTreeMap,
+    env, fmt,
+    io,
+    net::SocketAddr,
+    process, thread,
+    time::Duration,
+};
+
+use node::{
+    client::Client,
+    identity,
+    ring::{self, Node},
+};
+
+/// Maximum number of nodes visited by the `members` ring walk,
+/// bounding the walk if the ring is broken.
+const MAX_RING_WALK_LENGTH: usize = 4096;
+
+/// Interval between two polls of `convergence watch`.
+const CONVERGENCE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// A node, as listed in a response: its ring position and address.
+type RingNode = (u128, NodeAddr);
+
+/// Gossip data held by a node: the data and its timestamp.
+type GossipState = (String, u128);
+
+/// Version, uptime and ring parameters of a node (`GET_NODE_INFO`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NodeInfo {
+    version: String,
+    protocol_version: u32,
+    uptime: Duration,
+    ring_bit_length: u32,
+    successor_list_length: u32,
+    hash_function: String,
+}
+
+impl NodeInfo {
+    /// Version of the node and of its protocol.
+    fn release(&self) -> String {
+        format!("version {} (protocol {})", self.version, self.protocol_version)
+    }
+
+    /// Parameters the nodes of a ring must agree on.
+    fn ring_parameters(&self) -> String {
+        format!(
+            "{}-bit ring, {} successors, {} hash",
+            self.ring_bit_length, self.successor_list_length, self.hash_function
+        )
+    }
+}
+
+/// Address of a node: the socket address of its process,
+/// and its index among the virtual nodes of the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NodeAddr {
+    socket_addr: SocketAddr,
+    vnode: u16,
+}
+
+impl NodeAddr {
+    /// Parses `ip:port`, or `ip:port#k` for virtual node `k` > 0.
+    fn parse(text: &str) -> Option<Self> {
+        let (socket_addr, vnode) = match text.split_once('#') {
+            Some((socket_addr, vnode)) => (socket_addr, vnode.parse::<u16>().ok().filter(|vnode| *vnode > 0)?),
+            None => (text, 0),
+        };
+
+        Some(Self {
+            socket_addr: socket_addr.parse().ok()?,
+            vnode,
+        })
+    }
+}
+
+impl fmt::Display for NodeAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.vnode {
+            0 => write!(f, "{}", self.socket_addr),
+            vnode => write!(f, "{}#{}", self.socket_addr, vnode),
+        }
+    }
+}
+
+fn main() -> Result<(), io::Error> {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+
+    let client = &Client::from_args(&mut args).unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    });
+
+    let mut args = args.into_iter();
+
+    let remote_addr = {
+        let remote_addr = args.next().unwrap_or_else(|| {
+            eprintln!("error: remote node address is missing");
+            process::exit(1);
+        });
+
+        NodeAddr::parse(&remote_addr).unwrap_or_else(|| {
+            eprintln!("error: invalid remote node address");
+            process::exit(1);
+        })
+    };
+
+    let command = args.next().unwrap_or_else(|| {
+        eprintln!("error: command is missing (expected: fingers, members, topology, convergence, owner, versions, set-predecessor, drain)");
+        process::exit(1);
+    });
+
+    match command.as_str() {
+        "fingers" => print_finger_table(client, remote_addr),
+        "members" => print_ring_members(client, remote_addr),
+        "topology" => print_topology(client, remote_addr),
+        "convergence" => match args.next().as_deref() {
+            None => print_convergence(client, remote_addr, false),
+            Some("watch") => print_convergence(client, remote_addr, true),
+            Some(_) => {
+                eprintln!("error: expected convergence [watch]");
+                process::exit(1);
+            }
+        },
+        "owner" => {
+            let key = args.next().unwrap_or_else(|| {
+                eprintln!("error: expected owner <key>");
+                process::exit(1);
+            });
+
+            print_owner(client, remote_addr, &key)
+        }
+        "versions" => print_versions(client, remote_addr),
+        "set-predecessor" => {
+            let (predecessor_addr, admin_secret) = match (args.next(), args.next()) {
+                (Some(predecessor_addr), Some(admin_secret)) => (predecessor_addr, admin_secret),
+                _ => {
+                    eprintln!("error: expected set-predecessor <predecessor address> <admin secret>");
+                    process::exit(1);
+                }
+            };
+
+            let predecessor_addr = predecessor_addr.parse::<SocketAddr>().unwrap_or_else(|_| {
+                eprintln!("error: invalid predecessor address");
+                process::exit(1);
+            });
+
+            set_predecessor(client, remote_addr, predecessor_addr, &admin_secret)
+        }
+        "drain" => {
+            let admin_secret = args.next().unwrap_or_else(|| {
+                eprintln!("error: expected drain <admin secret>");
+                process::exit(1);
+            });
+
+            drain(client, remote_addr, &admin_secret)
+        }
+        _ => {
+            eprintln!("error: unknown command [{}] (expected: fingers, members, topology, convergence, owner, versions, set-predecessor, drain)", command);
+            process::exit(1);
+        }
+    }
+}
+
+/// Sends `request_msg` to the node at `remote_addr`
+/// and returns the response.
+fn send_request(client: &Client, remote_addr: NodeAddr, request_msg: &str) -> Result<String, io::Error> {
+    // Requests without index are served by virtual node 0
+    match remote_addr.vnode {
+        0 => client.send(remote_addr.socket_addr, request_msg),
+        vnode => client.send(remote_addr.socket_addr, &format!("VNODE=[{}];{}", vnode, request_msg)),
+    }
+}
+
+/// Parses a node listed in a response: `[id][address]`.
+fn parse_ring_node(text: &str) -> Option<RingNode> {
+    let (id, addr) = text.strip_prefix('[')?.strip_suffix(']')?.split_once("][")?;
+
+    Some((parse_position(id)?, NodeAddr::parse(addr)?))
+}
+
+/// Parses an identifier or a key of a response: whole bytes in hexadecimal
+/// format, up to 128 bits, into a position left-aligned on 128 bits.
+///
+/// Identifiers of a ring of fewer bits (`ring-bits`) are shorter, so that
+/// positions of any ring are compared (and shared out) on 128 bits.
+fn parse_position(text: &str) -> Option<u128> {
+    if text.is_empty() || !text.len().is_multiple_of(2) || text.len() > 32 || !text.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+
+    Some(u128::from_str_radix(text, 16).ok()? << (128 - text.len() * 4))
+}
+
+/// Formats `position` as an identifier of a ring of `ring_bit_length` bits.
+fn format_position(position: u128, ring_bit_length: u32) -> String {
+    format!("{:0width$x}", position >> (128 - ring_bit_length), width = ring_bit_length as usize / 4)
+}
+
+/// Returns the first entry of the successor list of the node at `remote_addr`.
+fn get_successor(client: &Client, remote_addr: NodeAddr) -> Result<RingNode, String> {
+    let response_msg = send_request(client, remote_addr, "GET_SUCCESSOR_LIST;").map_err(|err| err.to_string())?;
+
+    parse_successor_list_response(&response_msg).ok_or(format!("invalid response: {}", response_msg))
+}
+
+/// Parses `SUCCESSOR_LIST={[id][address],...};` into its first entry.
+fn parse_successor_list_response(response_msg: &str) -> Option<RingNode> {
+    let successor_list = response_msg.strip_prefix("SUCCESSOR_LIST={")?.strip_suffix("};")?;
+
+    parse_ring_node(successor_list.split(',').next()?)
+}
+
+/// Returns the predecessor of the node at `remote_addr`, if it has one,
+/// and the first entry of its successor list, read together by the node.
+fn get_neighborhood(client: &Client, remote_addr: NodeAddr) -> Result<(Option<RingNode>, RingNode), String> {
+    let response_msg = send_request(client, remote_addr, "GET_NEIGHBORHOOD;").map_err(|err| err.to_string())?;
+
+    parse_neighborhood_response(&response_msg).ok_or(format!("invalid response: {}", response_msg))
+}
+
+/// Parses `NEIGHBORHOOD=<[id][address] or NONE>{[id][address],...};`
+/// into the predecessor and the first entry of the successor list.
+fn parse_neighborhood_response(response_msg: &str) -> Option<(Option<RingNode>, RingNode)> {
+    let (predecessor, successor_list) = response_msg.strip_prefix("NEIGHBORHOOD=")?.strip_suffix("};")?.split_once('{')?;
+
+    let predecessor = match predecessor {
+        "NONE" => None,
+        predecessor => Some(parse_ring_node(predecessor)?),
+    };
+
+    Some((predecessor, parse_ring_node(successor_list.split(',').next()?)?))
+}
+
+/// Returns the range of keys owned by the node at `remote_addr`,
+/// as its excluded start and included end positions.
+fn get_owned_range(client: &Client, remote_addr: NodeAddr) -> Result<(u128, u128), String> {
+    let response_msg = send_request(client, remote_addr, "GET_OWNED_RANGE;").map_err(|err| err.to_string())?;
+
+    parse_owned_range_response(&response_msg).ok_or(format!("invalid response: {}", response_msg))
+}
+
+/// Parses `OWNED_RANGE=[start][end];`.
+fn parse_owned_range_response(response_msg: &str) -> Option<(u128, u128)> {
+    let (start, end) = response_msg.strip_prefix("OWNED_RANGE=[")?.strip_suffix("];")?.split_once("][")?;
+
+    Some((parse_position(start)?, parse_position(end)?))
+}
+
+/// Walks the ring from the node at `remote_addr`, following successor
+/// pointers until the walk returns to it, and prints the members met.
+///
+/// Each successor is checked to have the previous node as predecessor,
+/// and each member to own the keys from the previous member (excluded)
+/// to itself (included), so that the owned ranges tile the ring.
+/// The walk stops early if it enters a cycle which does not go through
+/// `remote_addr`, if a node does not answer, or after `MAX_RING_WALK_LENGTH` nodes.
+fn print_ring_members(client: &Client, remote_addr: NodeAddr) -> Result<(), io::Error> {
+    let mut members: Vec<RingNode> = Vec::new();
+    let mut problems = Vec::new();
+    let mut current_addr = remote_addr;
+
+    // One request per member: the neighborhood of a node gives
+    // both its successor and the predecessor to check
+    let mut current_neighborhood = get_neighborhood(client, remote_addr);
+
+    loop {
+        if members.len() >= MAX_RING_WALK_LENGTH {
+            problems.push(format!("walk stopped after {} nodes", MAX_RING_WALK_LENGTH));
+            break;
+        }
+
+        let successor = match current_neighborhood {
+            Ok((_, successor)) => successor,
+            Err(err) => {
+                problems.push(format!("{}: cannot get its neighborhood: {}", current_addr, err));
+                break;
+            }
+        };
+
+        let successor_neighborhood = get_neighborhood(client, successor.1);
+
+        match successor_neighborhood {
+            Ok((Some((_, predecessor_addr)), _)) if predecessor_addr == current_addr => (),
+            Ok((Some((_, predecessor_addr)), _)) => problems.push(format!(
+                "{}: successor of {}, but its predecessor is {}",
+                successor.1, current_addr, predecessor_addr
+            )),
+            Ok((None, _)) => problems.push(format!("{}: successor of {}, but it has no predecessor", successor.1, current_addr)),
+            Err(ref err) => {
+                problems.push(format!("{}: cannot get its neighborhood: {}", successor.1, err));
+                break;
+            }
+        }
+
+        if members.iter().any(|member| member.1 == successor.1) {
+            problems.push(format!(
+                "broken cycle: {} leads back to {}, without going through {}",
+                current_addr, successor.1, remote_addr
+            ));
+            break;
+        }
+
+        members.push(successor);
+
+        if successor.1 == remote_addr {
+            break;
+        }
+
+        current_addr = successor.1;
+        current_neighborhood = successor_neighborhood;
+    }
+
+    // The walk ends with the starting node, if it completed
+    if members.last().is_some_and(|member| member.1 == remote_addr) {
+        members.rotate_right(1);
+    }
+
+    for (i, member) in members.iter().enumerate() {
+        if members[..i].iter().any(|other| other.0 == member.0) {
+            problems.push(format!("{}: duplicate identifier [{:032x}]", member.1, member.0));
+        }
+    }
+
+    // Share of the ring owned by each process, its virtual nodes summed up,
+    // positions being left-aligned on 128 bits whatever the ring's bit length
+    let mut process_shares: BTreeMap<SocketAddr, (usize, u128)> = BTreeMap::new();
+
+    for (i, member) in members.iter().enumerate() {
+        let previous_member = members[(i + members.len() - 1) % members.len()];
+
+        let owned_keys = member.0.wrapping_sub(previous_member.0);
+        let process_share = process_shares.entry(member.1.socket_addr).or_default();
+        process_share.0 += 1;
+        process_share.1 = process_share.1.wrapping_add(owned_keys);
+
+        match get_owned_range(client, member.1) {
+            Ok(owned_range) if owned_range == (previous_member.0, member.0) => (),
+            Ok((start, end)) => problems.push(format!(
+                "{}: owns ({:032x}, {:032x}], instead of ({:032x}, {:032x}]",
+                member.1, start, end, previous_member.0, member.0
+            )),
+            Err(err) => problems.push(format!("{}: cannot get its owned range: {}", member.1, err)),
+        }
+    }
+
+    // Positions increase along the ring, wrapping around once
+    let wraps = (0..members.len())
+        .filter(|i| members[(i + 1) % members.len()].0 <= members[*i].0)
+        .count();
+
+    if members.len() > 1 && wraps > 1 {
+        problems.push(format!("the successor pointers wrap around the ring {} times", wraps));
+    }
+
+    println!("{} member(s), from node {}:", members.len(), remote_addr);
+
+    for (position, addr) in members.iter() {
+        println!("  [{:032x}]  {}", position, addr);
+    }
+
+    // A process alone in the ring owns all the keys, which wrap around to none
+    if process_shares.len() > 1 {
+        println!("{} process(es):", process_shares.len());
+
+        for (socket_addr, (vnodes, owned_keys)) in process_shares.iter() {
+            println!(
+                "  {}  {} node(s)  {:6.2}% of the keys  (weight {:6.2}%)",
+                socket_addr,
+                vnodes,
+                *owned_keys as f64 / 2f64.powi(128) * 100.0,
+                *vnodes as f64 / members.len() as f64 * 100.0
+            );
+        }
+    }
+
+    if problems.is_empty() {
+        println!("no inconsistency found");
+    } else {
+        println!("{} inconsistency(ies):", problems.len());
+
+        for problem in problems {
+            println!("  {}", problem);
+        }
+    }
+
+    Ok(())
+}
+
+/// Walks the ring from the node at `remote_addr`, following successor
+/// pointers until the walk returns to a node met, and prints the
+/// nodes met with their pointers as a Graphviz DOT digraph.
+///
+/// A successor edge is drawn in red if the successor has another
+/// predecessor, and a predecessor edge if the predecessor has another
+/// successor. Nodes pointed to but not walked (e.g. unreachable
+/// ones) are drawn dashed. Walk problems are printed to stderr,
+/// keeping stdout a valid DOT file.
+fn print_topology(client: &Client, remote_addr: NodeAddr) -> Result<(), io::Error> {
+    // Predecessor and successor of each node walked, in ring order
+    let mut neighborhoods: Vec<(NodeAddr, Option<RingNode>, RingNode)> = Vec::new();
+    let mut current_addr = remote_addr;
+
+    loop {
+        if neighborhoods.len() >= MAX_RING_WALK_LENGTH {
+            eprintln!("warning: walk stopped after {} nodes", MAX_RING_WALK_LENGTH);
+            break;
+        }
+
+        let (predecessor, successor) = match get_neighborhood(client, current_addr) {
+            Ok(neighborhood) => neighborhood,
+            Err(err) => {
+                eprintln!("warning: {}: cannot get its neighborhood: {}", current_addr, err);
+                break;
+            }
+        };
+
+        neighborhoods.push((current_addr, predecessor, successor));
+
+        if neighborhoods.iter().any(|(addr, _, _)| *addr == successor.1) {
+            break;
+        }
+
+        current_addr = successor.1;
+    }
+
+    // Every node met, walked or only pointed to, with its identifier
+    // if a pointer gave it (the starting node is only known by address
+    // if no node points to it)
+    let mut nodes: Vec<(NodeAddr, Option<u128>)> = Vec::new();
+
+    for (addr, predecessor, successor) in neighborhoods.iter() {
+        for (node_addr, id) in [Some((*addr, None)), predecessor.map(|(id, addr)| (addr, Some(id))), Some((successor.1, Some(successor.0)))]
+            .into_iter()
+            .flatten()
+        {
+            match nodes.iter_mut().find(|(other_addr, _)| *other_addr == node_addr) {
+                Some((_, other_id)) => *other_id = other_id.or(id),
+                None => nodes.push((node_addr, id)),
+            }
+        }
+    }
+
+    let neighborhood_of = |node_addr: NodeAddr| neighborhoods.iter().find(|(addr, _, _)| *addr == node_addr);
+
+    println!("digraph ring {{");
+    println!("  node [shape=box, fontname=monospace];");
+
+    for (addr, id) in nodes.iter() {
+        let label = match id {
+            Some(id) => format!("{}\\n{}", &format!("{:032x}", id)[..8], addr),
+            None => addr.to_string(),
+        };
+        let style = match neighborhood_of(*addr) {
+            Some(_) => "",
+            None => ", style=dashed",
+        };
+
+        println!("  \"{}\" [label=\"{}\"{}];", addr, label, style);
+    }
+
+    for (addr, predecessor, successor) in neighborhoods.iter() {
+        let is_mirrored = match neighborhood_of(successor.1) {
+            Some((_, successor_predecessor, _)) => successor_predecessor.is_some_and(|(_, other_addr)| other_addr == *addr),
+            None => true,
+        };
+        let color = if is_mirrored { "black" } else { "red" };
+
+        println!("  \"{}\" -> \"{}\" [color={}];", addr, successor.1, color);
+
+        if let Some((_, predecessor_addr)) = predecessor {
+            let is_mirrored = match neighborhood_of(*predecessor_addr) {
+                Some((_, _, predecessor_successor)) => predecessor_successor.1 == *addr,
+                None => true,
+            };
+            let color = if is_mirrored { "gray" } else { "red" };
+
+            println!("  \"{}\" -> \"{}\" [style=dashed, color={}];", addr, predecessor_addr, color);
+        }
+    }
+
+    println!("}}");
+
+    Ok(())
+}
+
+/// Returns the gossip data held by the node at `remote_addr`, if any.
+fn get_data(client: &Client, remote_addr: NodeAddr) -> Result<Option<GossipState>, String> {
+    let response_msg = send_request(client, remote_addr, "GET_DATA;").map_err(|err| err.to_string())?;
+
+    parse_data_response(&response_msg).ok_or(format!("invalid response: {}", response_msg))
+}
+
+/// Parses `RESPONSE=IGNORE;` (no data), or `RESPONSE=[<data>][<timestamp>];`
+/// followed by `[TTL=<seconds>]` if any. The data is escaped, so that
+/// the fields following it are parsed from the end.
+fn parse_data_response(response_msg: &str) -> Option<Option<GossipState>> {
+    if response_msg == "RESPONSE=IGNORE;" {
+        return Some(None);
+    }
+
+    let state = response_msg.strip_prefix("RESPONSE=[")?.strip_suffix("];")?;
+
+    let state = match state.rsplit_once("][") {
+        Some((state, ttl)) if ttl.strip_prefix("TTL=").is_some_and(|ttl| ttl.parse::<u64>().is_ok()) => state,
+        _ => state,
+    };
+
+    let (data, timestamp) = state.rsplit_once("][")?;
+
+    Some(Some((unescape_data(data), timestamp.parse::<u128>().ok()?)))
+}
+
+/// Reverts the escaping of the delimiters of the gossip
+/// data (`%XX`, their hexadecimal code).
+fn unescape_data(data: &str) -> String {
+    data.split("%25").map(|part| part.replace("%5B", "[").replace("%5D", "]")).collect::<Vec<_>>().join("%")
+}
+
+/// Walks the ring from the node at `remote_addr`, following successor
+/// pointers until the walk returns to it, and returns the processes met
+/// (their virtual nodes share the gossip data of virtual node 0).
+fn get_ring_processes(client: &Client, remote_addr: NodeAddr) -> Result<Vec<SocketAddr>, String> {
+    let mut processes = vec![remote_addr.socket_addr];
+    let mut current_addr = remote_addr;
+
+    for _ in 0..MAX_RING_WALK_LENGTH {
+        let (_, successor_addr) = get_successor(client, current_addr).map_err(|err| format!("{}: cannot get its successor list: {}", current_addr, err))?;
+
+        if successor_addr == remote_addr {
+            return Ok(processes);
+        }
+
+        if !processes.contains(&successor_addr.socket_addr) {
+            processes.push(successor_addr.socket_addr);
+        }
+
+        current_addr = successor_addr;
+    }
+
+    Err(format!("walk stopped after {} nodes", MAX_RING_WALK_LENGTH))
+}
+
+/// Reports how many processes of the ring of the node at `remote_addr`
+/// hold the latest gossip update, the most recent one (ties being broken
+/// by the data, as the nodes do). Without `watch`, the data held by each
+/// process is listed. With `watch`, the report is printed every
+/// `CONVERGENCE_POLL_INTERVAL` until every process holds the update.
+fn print_convergence(client: &Client, remote_addr: NodeAddr, watch: bool) -> Result<(), io::Error> {
+    let processes = get_ring_processes(client, remote_addr).unwrap_or_else(|err| {
+        eprintln!("error: cannot walk the ring: {}", err);
+        process::exit(1);
+    });
+
+    loop {
+        let states = processes
+            .iter()
+            .map(|socket_addr| get_data(client, NodeAddr { socket_addr: *socket_addr, vnode: 0 }))
+            .collect::<Vec<_>>();
+
+        let latest_state = states
+            .iter()
+            .filter_map(|state| state.as_ref().ok().cloned().flatten())
+            .max_by(|a, b| (a.1, &a.0).cmp(&(b.1, &b.0)));
+
+        let converged = states.iter().filter(|state| matches!((state, &latest_state), (Ok(Some(state)), Some(latest_state)) if state == latest_state)).count();
+
+        match latest_state {
+            Some((ref data, timestamp)) => println!("converged: {}/{} nodes hold [{}] @ {}", converged, processes.len(), data, timestamp),
+            None => println!("converged: 0/{} nodes, no data in the ring", processes.len()),
+        }
+
+        if !watch {
+            for (socket_addr, state) in processes.iter().zip(states.iter()) {
+                match state {
+                    Ok(Some((data, timestamp))) => println!("  {}  [{}] @ {}", socket_addr, data, timestamp),
+                    Ok(None) => println!("  {}  no data", socket_addr),
+                    Err(err) => println!("  {}  unreachable: {}", socket_addr, err),
+                }
+            }
+
+            return Ok(());
+        }
+
+        if latest_state.is_some() && converged == processes.len() {
+            return Ok(());
+        }
+
+        thread::sleep(CONVERGENCE_POLL_INTERVAL);
+    }
+}
+
+/// Walks the ring from the node at `remote_addr`, following successor
+/// pointers until the walk returns to it, and returns the members met,
+/// `remote_addr` last.
+fn get_ring_members(client: &Client, remote_addr: NodeAddr) -> Result<Vec<RingNode>, String> {
+    let mut members = Vec::new();
+    let mut current_addr = remote_addr;
+
+    for _ in 0..MAX_RING_WALK_LENGTH {
+        let successor = get_successor(client, current_addr).map_err(|err| format!("{}: cannot get its successor list: {}", current_addr, err))?;
+
+        if members.contains(&successor) {
+            return Err(format!("{}: cycle which does not go through {}", successor.1, remote_addr));
+        }
+
+        members.push(successor);
+
+        if successor.1 == remote_addr {
+            return Ok(members);
+        }
+
+        current_addr = successor.1;
+    }
+
+    Err(format!("walk stopped after {} nodes", MAX_RING_WALK_LENGTH))
+}
+
+/// Returns the successor of `key_position`, found by a lookup from the
+/// node at `remote_addr`, following the redirects of iterative routing.
+fn find_successor_of_key(client: &Client, remote_addr: NodeAddr, key_position: u128, ring_bit_length: u32) -> Result<RingNode, String> {
+    let request_msg = format!("FIND_SUCCESSOR_OF_KEY=[{}];", format_position(key_position, ring_bit_length));
+    let mut current_addr = remote_addr;
+
+    for _ in 0..MAX_RING_WALK_LENGTH {
+        let response_msg = send_request(client, current_addr, &request_msg).map_err(|err| format!("{}: {}", current_addr, err))?;
+
+        match parse_lookup_response(&response_msg) {
+            Some(LookupStep::Successor(successor)) => return Ok(successor),
+            Some(LookupStep::Redirect(next_addr)) => current_addr = next_addr,
+            None => return Err(format!("{}: invalid response: {}", current_addr, response_msg)),
+        }
+    }
+
+    Err(format!("lookup stopped after {} redirects", MAX_RING_WALK_LENGTH))
+}
+
+/// Step of a lookup: its outcome, or the node to ask next.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LookupStep {
+    Successor(RingNode),
+    Redirect(NodeAddr),
+}
+
+/// Parses the response to a lookup: `SUCCESSOR=[id][address];`,
+/// or `REDIRECT=[id][address];` with iterative routing.
+fn parse_lookup_response(response_msg: &str) -> Option<LookupStep> {
+    if let Some(successor) = response_msg.strip_prefix("SUCCESSOR=").and_then(|response| response.strip_suffix(';')) {
+        return Some(LookupStep::Successor(parse_ring_node(successor)?));
+    }
+
+    let (_, next_addr) = parse_ring_node(response_msg.strip_prefix("REDIRECT=")?.strip_suffix(';')?)?;
+
+    Some(LookupStep::Redirect(next_addr))
+}
+
+/// Returns the member of `members` owning `key_position`,
+/// computed as the nodes route (see `ring::owner_of`).
+fn owner_among(key_position: u128, members: &[RingNode], ring_bit_length: u32) -> RingNode {
+    let nodes = members
+        .iter()
+        .map(|(position, addr)| Node::at_position(*position, addr.socket_addr, addr.vnode, ring_bit_length as usize))
+        .collect::<Vec<_>>();
+
+    let owner = ring::owner_of(key_position, &nodes);
+
+    (
+        owner.get_ring_position(),
+        NodeAddr {
+            socket_addr: owner.get_public_addr(),
+            vnode: owner.get_vnode(),
+        },
+    )
+}
+
+/// Prints the ring position of the application `key` and its owner,
+/// computed from the members of the ring of the node at `remote_addr`
+/// and found by a lookup from this node. Exits with status 1 if they differ.
+fn print_owner(client: &Client, remote_addr: NodeAddr, key: &str) -> Result<(), io::Error> {
+    let ring_bit_length = get_node_info(client, remote_addr).map_err(io::Error::other)?.ring_bit_length;
+    let key_position = u128::from_be_bytes(ring::key_position(key.as_bytes(), ring_bit_length as usize));
+
+    let members = get_ring_members(client, remote_addr).unwrap_or_else(|err| {
+        eprintln!("error: cannot walk the ring: {}", err);
+        process::exit(1);
+    });
+    let expected_owner = owner_among(key_position, &members, ring_bit_length);
+
+    let found_owner = find_successor_of_key(client, remote_addr, key_position, ring_bit_length).unwrap_or_else(|err| {
+        eprintln!("error: the lookup failed: {}", err);
+        process::exit(1);
+    });
+
+    println!("key [{}] at [{}]", key, format_position(key_position, ring_bit_length));
+    println!("owner in the ring ({} members): {} [{}]", members.len(), expected_owner.1, format_position(expected_owner.0, ring_bit_length));
+    println!("owner found by a lookup from {}: {} [{}]", remote_addr, found_owner.1, format_position(found_owner.0, ring_bit_length));
+
+    if found_owner != expected_owner {
+        eprintln!("error: the lookup does not return the owner in the ring");
+        process::exit(1);
+    }
+
+    Ok(())
+}
+
+/// Returns the version, uptime and ring parameters of the node at `remote_addr`.
+fn get_node_info(client: &Client, remote_addr: NodeAddr) -> Result<NodeInfo, String> {
+    let response_msg = send_request(client, remote_addr, "GET_NODE_INFO;").map_err(|err| err.to_string())?;
+
+    parse_node_info_response(&response_msg).ok_or(format!("invalid response: {}", response_msg))
+}
+
+/// Parses `NODE_INFO=[VERSION=<version>][PROTOCOL=<n>][UPTIME=<seconds>]
+/// [RING_BITS=<n>][SUCCESSORS=<n>][HASH=<name>];`, whatever the order
+/// of the fields.
+fn parse_node_info_response(response_msg: &str) -> Option<NodeInfo> {
+    let fields = response_msg.strip_prefix("NODE_INFO=[")?.strip_suffix("];")?;
+    let fields = fields.split("][").map(|field| field.split_once('=')).collect::<Option<BTreeMap<_, _>>>()?;
+
+    Some(NodeInfo {
+        version: fields.get("VERSION")?.to_string(),
+        protocol_version: fields.get("PROTOCOL")?.parse().ok()?,
+        uptime: Duration::from_secs(fields.get("UPTIME")?.parse().ok()?),
+        ring_bit_length: fields
+            .get("RING_BITS")?
+            .parse()
+            .ok()
+            .filter(|ring_bit_length: &u32| ring_bit_length.is_multiple_of(8) && (8..=128).contains(ring_bit_length))?,
+        successor_list_length: fields.get("SUCCESSORS")?.parse().ok()?,
+        hash_function: fields.get("HASH")?.to_string(),
+    })
+}
+
+/// Returns the value of `values` shared by most of them,
+/// the first one met on a tie.
+fn most_common(values: &[String]) -> Option<&String> {
+    let mut most_common: Option<(&String, usize)> = None;
+
+    for value in values {
+        let count = values.iter().filter(|other| *other == value).count();
+
+        if most_common.is_none_or(|(_, most_common_count)| count > most_common_count) {
+            most_common = Some((value, count));
+        }
+    }
+
+    most_common.map(|(value, _)| value)
+}
+
+/// Formats `uptime` as days, hours and minutes.
+fn format_uptime(uptime: Duration) -> String {
+    let minutes = uptime.as_secs() / 60;
+
+    match (minutes / (24 * 60), minutes / 60 % 24, minutes % 60) {
+        (0, 0, minutes) => format!("{}m", minutes),
+        (0, hours, minutes) => format!("{}h{:02}m", hours, minutes),
+        (days, hours, minutes) => format!("{}d{:02}h{:02}m", days, hours, minutes),
+    }
+}
+
+/// Walks the ring from the node at `remote_addr`, reads the version,
+/// uptime and ring parameters of each process met, and prints them.
+///
+/// The version and the ring parameters of most processes are taken as
+/// the reference: a process which differs, or does not answer, is
+/// flagged, and the command exits with status 1, so that a rolling
+/// change is not started on an inconsistent ring.
+fn print_versions(client: &Client, remote_addr: NodeAddr) -> Result<(), io::Error> {
+    let processes = get_ring_processes(client, remote_addr).unwrap_or_else(|err| {
+        eprintln!("error: cannot walk the ring: {}", err);
+        process::exit(1);
+    });
+
+    let node_infos = processes
+        .iter()
+        .map(|socket_addr| get_node_info(client, NodeAddr { socket_addr: *socket_addr, vnode: 0 }))
+        .collect::<Vec<_>>();
+
+    let answered = node_infos.iter().filter_map(|node_info| node_info.as_ref().ok()).collect::<Vec<_>>();
+    let releases = answered.iter().map(|node_info| node_info.release()).collect::<Vec<_>>();
+    let ring_parameters = answered.iter().map(|node_info| node_info.ring_parameters()).collect::<Vec<_>>();
+    let (reference_release, reference_ring_parameters) = (most_common(&releases), most_common(&ring_parameters));
+
+    println!("{} process(es), from node {}:", processes.len(), remote_addr);
+
+    let mut problems = Vec::new();
+
+    for (socket_addr, node_info) in processes.iter().zip(node_infos.iter()) {
+        let node_info = match node_info {
+            Ok(node_info) => node_info,
+            Err(err) => {
+                println!("  {}  unreachable", socket_addr);
+                problems.push(format!("{}: cannot get its node info: {}", socket_addr, err));
+                continue;
+            }
+        };
+
+        println!("  {}  {}  {}  up {}", socket_addr, node_info.release(), node_info.ring_parameters(), format_uptime(node_info.uptime));
+
+        if Some(&node_info.release()) != reference_release {
+            problems.push(format!("{}: runs {}, while most processes run {}", socket_addr, node_info.release(), reference_release.unwrap()));
+        }
+
+        if Some(&node_info.ring_parameters()) != reference_ring_parameters {
+            problems.push(format!(
+                "{}: uses a {}, while most processes use a {}",
+                socket_addr,
+                node_info.ring_parameters(),
+                reference_ring_parameters.unwrap()
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        println!("every process runs {}, with a {}", reference_release.unwrap(), reference_ring_parameters.unwrap());
+        return Ok(());
+    }
+
+    println!("{} mismatch(es):", problems.len());
+
+    for problem in problems {
+        println!("  {}", problem);
+    }
+
+    process::exit(1);
+}
+
+/// Overwrites the predecessor of the node at `remote_addr`
+/// with the node at `predecessor_addr`.
+///
+/// The predecessor's identifier is derived from `predecessor_addr`, on
+/// the ring of the node, so it assumes the predecessor's identifier is
+/// hashed (not sequential).
+fn set_predecessor(client: &Client, remote_addr: NodeAddr, predecessor_addr: SocketAddr, admin_secret: &str) -> Result<(), io::Error> {
+    let ring_bit_length = get_node_info(client, remote_addr).map_err(io::Error::other)?.ring_bit_length;
+
+    let request_msg = format!(
+        "SET_PREDECESSOR=[{}][{}][{:?}];",
+        admin_secret,
+        identity::hash_id_of(predecessor_addr, ring_bit_length as usize),
+        predecessor_addr
+    );
+
+    let response_msg = send_request(client, remote_addr, &request_msg)?;
+
+    if response_msg != "ACKNOWLEDGED;" {
+        eprintln!("error: the predecessor was not set: {}", response_msg);
+        process::exit(1);
+    }
+
+    println!("node {}: predecessor set to {}", remote_addr, predecessor_addr);
+
+    Ok(())
+}
+
+/// Drains the node at `remote_addr`, which leaves the
+/// network once its gossip state is handed off.
+fn drain(client: &Client, remote_addr: NodeAddr, admin_secret: &str) -> Result<(), io::Error> {
+    let response_msg = send_request(client, remote_addr, &format!("DRAIN=[{}];", admin_secret))?;
+
+    if response_msg != "ACKNOWLEDGED;" {
+        eprintln!("error: the node is not draining: {}", response_msg);
+        process::exit(1);
+    }
+
+    println!("node {}: draining, it leaves the network once its gossip state is handed off", remote_addr);
+
+    Ok(())
+}
+
+/// Prints the populated finger entries of the node at `remote_addr`.
+///
+/// Target positions are derived from `remote_addr`, so they
+/// assume the node's public address is `remote_addr` and its
+/// identifier is hashed (not sequential).
+fn print_finger_table(client: &Client, remote_addr: NodeAddr) -> Result<(), io::Error> {
+    let response_msg = send_request(client, remote_addr, "GET_FINGER_TABLE;")?;
+    let ring_bit_length = get_node_info(client, remote_addr).map_err(io::Error::other)?.ring_bit_length;
+
+    let fingers = parse_finger_table_response(&response_msg, ring_bit_length).unwrap_or_else(|| {
+        eprintln!("error: invalid response from the remote node: {}", response_msg);
+        process::exit(1);
+    });
+
+    let self_position = u128::from_be_bytes(identity::identifier_prefix(
+        identity::virtual_identifier_of(remote_addr.socket_addr, remote_addr.vnode),
+        ring_bit_length as usize,
+    ));
+
+    println!("node {} [{}]", remote_addr, format_position(self_position, ring_bit_length));
+
+    for (index, (finger_position, finger_addr)) in fingers {
+        let target_position = self_position.wrapping_add(1 << (index + 128 - ring_bit_length));
+
+        println!(
+            "finger {:>3}  target [{}]  -> {} [{}]",
+            index,
+            format_position(target_position, ring_bit_length),
+            finger_addr,
+            format_position(finger_position, ring_bit_length)
+        );
+    }
+
+    Ok(())
+}
+
+/// Parses `FINGER_TABLE={[index][id][address],...};` into the located
+/// fingers of a node of a ring of `ring_bit_length` bits, with their index.
+fn parse_finger_table_response(response_msg: &str, ring_bit_length: u32) -> Option<Vec<(u32, RingNode)>> {
+    let fingers = response_msg.strip_prefix("FINGER_TABLE={")?.strip_suffix("};")?;
+
+    // Entries never contain commas: `[index][id][address]`
+    fingers
+        .split(',')
+        .filter(|finger| !finger.is_empty())
+        .map(|finger| {
+            let (index, finger_node) = finger.strip_prefix('[')?.split_once(']')?;
+            let index = index.parse::<u32>().ok().filter(|index| *index < ring_bit_length)?;
+
+            Some((index, parse_ring_node(finger_node)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod owner_test {
+    use node::ring;
+
+    use super::{LookupStep, NodeAddr, RingNode, owner_among, parse_lookup_response};
+
+    /// Member of an 8-bit ring at `id`, on a port of its own.
+    fn member(id: u8, port: u16) -> RingNode {
+        ((id as u128) << 120, NodeAddr::parse(&format!("127.0.0.1:{}", port)).unwrap())
+    }
+
+    #[test]
+    fn owner_among_members_test() {
+        let members = [member(0x20, 8000), member(0x60, 8001), member(0xa0, 8002), member(0xe0, 8003)];
+
+        for (key_position, owner) in [(0x10, members[0]), (0x20, members[0]), (0x21, members[1]), (0xa1, members[3]), (0xf0, members[0])] {
+            assert_eq!(owner_among(key_position << 120, &members, 8), owner, "{:02x}", key_position);
+        }
+
+        // Members in any order
+        assert_eq!(owner_among(0x90 << 120, &[members[3], members[0], members[2], members[1]], 8), members[2]);
+
+        // A key hashes into the space of the node identifiers
+        let key_position = u128::from_be_bytes(ring::key_position(b"user:42", 8));
+        assert_eq!(key_position, 0x56 << 120);
+        assert_eq!(owner_among(key_position, &members, 8), members[1]);
+    }
+
+    #[test]
+    fn parse_lookup_response_test() {
+        assert_eq!(parse_lookup_response("SUCCESSOR=[60][127.0.0.1:8001];"), Some(LookupStep::Successor(member(0x60, 8001))));
+        assert_eq!(
+            parse_lookup_response("REDIRECT=[a0][127.0.0.1:8002#3];"),
+            Some(LookupStep::Redirect(NodeAddr::parse("127.0.0.1:8002#3").unwrap()))
+        );
+
+        for response_msg in ["ERROR=[FATAL][unknown request];", "SUCCESSOR=[60][127.0.0.1:8001]", "SUCCESSOR=[6][127.0.0.1:8001];", "REDIRECT=[a0];", ""] {
+            assert_eq!(parse_lookup_response(response_msg), None, "{}", response_msg);
+        }
+    }
+}
+
+#[cfg(test)]
+mod response_test {
+    use std::time::Duration;
+
+    use super::{
+        NodeAddr, NodeInfo, RingNode, parse_data_response, parse_finger_table_response, parse_neighborhood_response, parse_node_info_response,
+        parse_owned_range_response, parse_position, parse_ring_node, parse_successor_list_response, unescape_data,
+    };
+
+    /// Error response of a node, which no parser accepts.
+    const ERROR_RESPONSE: &str = "ERROR=[RETRYABLE][the node is not part of a ring yet];";
+
+    fn ring_node(position: u128, addr: &str) -> RingNode {
+        (position, NodeAddr::parse(addr).unwrap())
+    }
+
+    #[test]
+    fn parse_position_test() {
+        assert_eq!(parse_position("60"), Some(0x60 << 120));
+        assert_eq!(parse_position("0102"), Some(0x0102 << 112));
+        assert_eq!(parse_position("cf4b19e32ce29fef04468ac9d2a6787d"), Some(0xcf4b19e32ce29fef04468ac9d2a6787d));
+
+        for text in ["", "6", "cf4b19e32ce29fef04468ac9d2a6787d00", "zz", "+6"] {
+            assert_eq!(parse_position(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parse_ring_node_test() {
+        assert_eq!(parse_ring_node("[60][127.0.0.1:8001]"), Some(ring_node(0x60 << 120, "127.0.0.1:8001")));
+        assert_eq!(parse_ring_node("[60][127.0.0.1:8001#2]"), Some(ring_node(0x60 << 120, "127.0.0.1:8001#2")));
+        assert_eq!(parse_ring_node("[60][[2001:db8::1]:4040]"), Some(ring_node(0x60 << 120, "[2001:db8::1]:4040")));
+
+        // Virtual node 0 is addressed without index
+        for text in ["[60][127.0.0.1:8001#0]", "[60][127.0.0.1]", "[6][127.0.0.1:8001]", "60][127.0.0.1:8001]", "[60]", ""] {
+            assert_eq!(parse_ring_node(text), None, "{}", text);
+        }
+    }
+
+    #[test]
+    fn parse_successor_list_response_test() {
+        assert_eq!(
+            parse_successor_list_response("SUCCESSOR_LIST={[60][127.0.0.1:8001],[a0][127.0.0.1:8002#1],[e0][127.0.0.1:8003],[20][127.0.0.1:8000],[60][127.0.0.1:8001]};"),
+            Some(ring_node(0x60 << 120, "127.0.0.1:8001"))
+        );
+
+        for response_msg in ["SUCCESSOR_LIST={[60][127.0.0.1:8001]", "SUCCESSOR_LIST={};", "SUCCESSOR=[60][127.0.0.1:8001];", ERROR_RESPONSE, ""] {
+            assert_eq!(parse_successor_list_response(response_msg), None, "{}", response_msg);
+        }
+    }
+
+    #[test]
+    fn parse_neighborhood_response_test() {
+        assert_eq!(
+            parse_neighborhood_response("NEIGHBORHOOD=[20][127.0.0.1:8000]{[a0][[2001:db8::1]:4040#3],[e0][127.0.0.1:8003]};"),
+            Some((Some(ring_node(0x20 << 120, "127.0.0.1:8000")), ring_node(0xa0 << 120, "[2001:db8::1]:4040#3")))
+        );
+        assert_eq!(
+            parse_neighborhood_response("NEIGHBORHOOD=NONE{[a0][127.0.0.1:8002],[e0][127.0.0.1:8003]};"),
+            Some((None, ring_node(0xa0 << 120, "127.0.0.1:8002")))
+        );
+
+        for response_msg in [
+            "NEIGHBORHOOD=NONE{[a0][127.0.0.1:8002]",
+            "NEIGHBORHOOD=[20]{[a0][127.0.0.1:8002]};",
+            "NEIGHBORHOOD=NONE{};",
+            "PREDECESSOR=NONE;",
+            ERROR_RESPONSE,
+            "",
+        ] {
+            assert_eq!(parse_neighborhood_response(response_msg), None, "{}", response_msg);
+        }
+    }
+
+    #[test]
+    fn parse_owned_range_response_test() {
+        assert_eq!(parse_owned_range_response("OWNED_RANGE=[20][60];"), Some((0x20 << 120, 0x60 << 120)));
+
+        // A single node owns the whole ring, from itself (excluded) to itself
+        assert_eq!(parse_owned_range_response("OWNED_RANGE=[e0][e0];"), Some((0xe0 << 120, 0xe0 << 120)));
+
+        for response_msg in ["OWNED_RANGE=[20][60]", "OWNED_RANGE=[20];", "OWNED_RANGE=[20][6];", ERROR_RESPONSE, ""] {
+            assert_eq!(parse_owned_range_response(response_msg), None, "{}", response_msg);
+        }
+    }
+
+    #[test]
+    fn parse_data_response_test() {
+        assert_eq!(parse_data_response("RESPONSE=IGNORE;"), Some(None));
+        assert_eq!(parse_data_response("RESPONSE=[value][1700000000000];"), Some(Some(("value".to_string(), 1700000000000))));
+        assert_eq!(parse_data_response("RESPONSE=[value][1700000000000][TTL=60];"), Some(Some(("value".to_string(), 1700000000000))));
+
+        // Escaped delimiters, which would otherwise be taken for fields
+        assert_eq!(
+            parse_data_response("RESPONSE=[a%5D%5B0%5D%5BTTL=5%25][1700000000000];"),
+            Some(Some(("a][0][TTL=5%".to_string(), 1700000000000)))
+        );
+
+        for response_msg in [
+            "RESPONSE=[value][1700000000000]",
+            "RESPONSE=[value];",
+            "RESPONSE=[value][now];",
+            "RESPONSE=[value][TTL=60];",
+            "RESPONSE=ACKNOWLEDGED[1][1];",
+            ERROR_RESPONSE,
+            "",
+        ] {
+            assert_eq!(parse_data_response(response_msg), None, "{}", response_msg);
+        }
+    }
+
+    #[test]
+    fn parse_node_info_response_test() {
+        let node_info = NodeInfo {
+            version: "0.2.0-rc.1".to_string(),
+            protocol_version: 1,
+            uptime: Duration::from_secs(3600),
+            ring_bit_length: 32,
+            successor_list_length: 5,
+            hash_function: "MD5".to_string(),
+        };
+
+        assert_eq!(
+            parse_node_info_response("NODE_INFO=[VERSION=0.2.0-rc.1][PROTOCOL=1][UPTIME=3600][RING_BITS=32][SUCCESSORS=5][HASH=MD5];"),
+            Some(node_info.clone())
+        );
+        assert_eq!(
+            parse_node_info_response("NODE_INFO=[HASH=MD5][SUCCESSORS=5][RING_BITS=32][UPTIME=3600][PROTOCOL=1][VERSION=0.2.0-rc.1];"),
+            Some(node_info)
+        );
+
+        for response_msg in [
+            // Missing uptime
+            "NODE_INFO=[VERSION=0.2.0][PROTOCOL=1][RING_BITS=32][SUCCESSORS=5][HASH=MD5];",
+            "NODE_INFO=[VERSION=0.2.0][PROTOCOL=1][UPTIME=3600][RING_BITS=12][SUCCESSORS=5][HASH=MD5];",
+            "NODE_INFO=[VERSION=0.2.0][PROTOCOL=1][UPTIME=3600][RING_BITS=256][SUCCESSORS=5][HASH=MD5];",
+            "NODE_INFO=[VERSION=0.2.0][PROTOCOL=one][UPTIME=3600][RING_BITS=32][SUCCESSORS=5][HASH=MD5];",
+            "NODE_INFO=[VERSION=0.2.0][PROTOCOL=1][UPTIME=3600][RING_BITS=32][SUCCESSORS=5][HASH=MD5]",
+            ERROR_RESPONSE,
+            "",
+        ] {
+            assert_eq!(parse_node_info_response(response_msg), None, "{}", response_msg);
+        }
+    }
+
+    #[test]
+    fn parse_finger_table_response_test() {
+        assert_eq!(
+            parse_finger_table_response("FINGER_TABLE={[0][60][127.0.0.1:8001],[7][a0][127.0.0.1:8002#2]};", 8),
+            Some(vec![(0, ring_node(0x60 << 120, "127.0.0.1:8001")), (7, ring_node(0xa0 << 120, "127.0.0.1:8002#2"))])
+        );
+        assert_eq!(parse_finger_table_response("FINGER_TABLE={};", 8), Some(Vec::new()));
+
+        for response_msg in [
+            // Out of range finger index
+            "FINGER_TABLE={[8][60][127.0.0.1:8001]};",
+            "FINGER_TABLE={[0][60]};",
+            "FINGER_TABLE={[x][60][127.0.0.1:8001]};",
+            "FINGER_TABLE={[0][60][127.0.0.1:8001]",
+            ERROR_RESPONSE,
+            "",
+        ] {
+            assert_eq!(parse_finger_table_response(response_msg, 8), None, "{}", response_msg);
+        }
+    }
+
+    #[test]
+    fn unescape_data_test() {
+        assert_eq!(unescape_data("a%5Bb%5D"), "a[b]");
+        assert_eq!(unescape_data("100%25"), "100%");
+
+        // An escaped `%` followed by what looks like an escape
+        assert_eq!(unescape_data("%255B"), "%5B");
+        assert_eq!(unescape_data("plain text"), "plain text");
+    }
+}
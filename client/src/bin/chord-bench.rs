@@ -0,0 +1,316 @@
+//! Lookup benchmark against a running Chord network.
+//!
+//! Usage: `chord-bench <seed address> [--requests N | --duration S] [--hmac-key <key>]
+//! [--tls-ca <path> [--tls-server-name <name>]]`
+//!
+//! Issues lookups of random keys to the seed node, one at a time, on
+//! the ring of the seed node (its `ring-bits`, read first),
+//! until `N` lookups were issued (1000 by default) or `S` seconds
+//! elapsed, then prints the failure rate, the throughput, the
+//! latency distribution and the mean hop count.
+//!
+//! Requests are signed with `--hmac-key` (or the `CHORD_HMAC_KEY`
+//! environment variable), the key of a ring authenticating its messages.
+//! Connections are encrypted with TLS with `--tls-ca`, the certificates
+//! trusted for the nodes of a ring requiring TLS, issued for
+//! `--tls-server-name` (`chord-node` by default).
+//!
+//! Lookups are traced (`[TRACE=<id>]`): the node locating the owner
+//! of a key answers with the number of nodes the lookup went through,
+//! counted by each node forwarding it (recursive routing), to which
+//! the redirections followed by the benchmark are added (iterative
+//! routing). Each node logs the traced lookups it handles.
+
+use std::{
+    env,
+    net::SocketAddr,
+    process,
+    time::{Duration, Instant},
+};
+
+use node::client::Client;
+
+/// Number of lookups issued, without `--requests` or `--duration`.
+const DEFAULT_REQUEST_COUNT: u64 = 1000;
+
+/// Maximum number of redirections followed by a single lookup.
+const MAX_LOOKUP_REDIRECTS: u32 = 128;
+
+/// When the benchmark stops.
+enum Limit {
+    Requests(u64),
+    Duration(Duration),
+}
+
+/// Outcome of a completed lookup.
+struct Lookup {
+    latency: Duration,
+    hops: u32,
+}
+
+/// Response to a traced lookup.
+#[derive(Debug, PartialEq, Eq)]
+enum LookupStep {
+    /// The owner of the key was located, after the given number of hops.
+    Located(u32),
+    /// The lookup continues at the given address and virtual node index.
+    Redirect(SocketAddr, u16),
+}
+
+fn main() {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+
+    let client = &Client::from_args(&mut args).unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    });
+
+    let mut args = args.into_iter();
+
+    let seed_addr = {
+        let seed_addr = args.next().unwrap_or_else(|| {
+            eprintln!("error: seed node address is missing");
+            process::exit(1);
+        });
+
+        seed_addr.parse::<SocketAddr>().unwrap_or_else(|_| {
+            eprintln!("error: invalid seed node address");
+            process::exit(1);
+        })
+    };
+
+    let mut limit = Limit::Requests(DEFAULT_REQUEST_COUNT);
+
+    while let Some(arg) = args.next() {
+        let value = args.next().unwrap_or_else(|| {
+            eprintln!("error: missing value for [{}]", arg);
+            process::exit(1);
+        });
+        let value = value.parse::<u64>().ok().filter(|value| *value > 0);
+
+        limit = match arg.as_str() {
+            "--requests" => Limit::Requests(value.unwrap_or_else(|| {
+                eprintln!("error: invalid request count (expected --requests N, with N > 0)");
+                process::exit(1);
+            })),
+            "--duration" => Limit::Duration(Duration::from_secs(value.unwrap_or_else(|| {
+                eprintln!("error: invalid duration (expected --duration S, with S > 0 seconds)");
+                process::exit(1);
+            }))),
+            _ => {
+                eprintln!("error: unknown argument [{}]", arg);
+                process::exit(1);
+            }
+        };
+    }
+
+    let ring_bit_length = get_ring_bit_length(client, seed_addr).unwrap_or_else(|err| {
+        eprintln!("error: cannot read the ring parameters of the seed node: {}", err);
+        process::exit(1);
+    });
+
+    let mut lookups = Vec::new();
+    let mut failures = 0;
+    let started_at = Instant::now();
+
+    loop {
+        let is_done = match limit {
+            Limit::Requests(requests) => lookups.len() as u64 + failures >= requests,
+            Limit::Duration(duration) => started_at.elapsed() >= duration,
+        };
+
+        if is_done {
+            break;
+        }
+
+        match lookup(client, seed_addr, &key_text(rand::random::<u128>(), ring_bit_length)) {
+            Ok(lookup) => lookups.push(lookup),
+            Err(err) => {
+                if failures == 0 {
+                    eprintln!("lookup failed: {}", err);
+                }
+                failures += 1;
+            }
+        }
+    }
+
+    print_summary(&mut lookups, failures, started_at.elapsed());
+}
+
+/// Returns the bit length of the ring of the node at `remote_addr`,
+/// read from its `NODE_INFO=[...][RING_BITS=<n>][...];` response.
+fn get_ring_bit_length(client: &Client, remote_addr: SocketAddr) -> Result<u32, String> {
+    let response_msg = client.send(remote_addr, "GET_NODE_INFO;").map_err(|err| err.to_string())?;
+
+    response_msg
+        .strip_prefix("NODE_INFO=[")
+        .and_then(|response| response.strip_suffix("];"))
+        .and_then(|fields| fields.split("][").find_map(|field| field.strip_prefix("RING_BITS=")))
+        .and_then(|ring_bit_length| ring_bit_length.parse::<u32>().ok())
+        .filter(|ring_bit_length| ring_bit_length.is_multiple_of(8) && (8..=128).contains(ring_bit_length))
+        .ok_or(format!("invalid response: {}", response_msg))
+}
+
+/// Returns the leading `ring_bit_length` bits of `key`, as the
+/// identifier of a key in a ring of `ring_bit_length` bits.
+fn key_text(key: u128, ring_bit_length: u32) -> String {
+    format!("{:0width$x}", key >> (128 - ring_bit_length), width = ring_bit_length as usize / 4)
+}
+
+/// Looks up the owner of `key` with a traced lookup,
+/// starting from `seed_addr` and following redirections.
+fn lookup(client: &Client, seed_addr: SocketAddr, key: &str) -> Result<Lookup, String> {
+    let request_msg = format!("FIND_SUCCESSOR_OF_KEY=[{}][TRACE={:016x}];", key, rand::random::<u64>());
+    let started_at = Instant::now();
+    let mut remote_addr = seed_addr;
+    // Index of the virtual node redirected to, 0 being reached without index
+    let mut vnode = 0;
+
+    for redirects in 0..=MAX_LOOKUP_REDIRECTS {
+        let addressed_request_msg = match vnode {
+            0 => request_msg.clone(),
+            vnode => format!("VNODE=[{}];{}", vnode, request_msg),
+        };
+
+        let response_msg = client
+            .send(remote_addr, &addressed_request_msg)
+            .map_err(|err| format!("[{}]: {}", remote_addr, err))?;
+
+        match parse_lookup_response(&response_msg) {
+            Some(LookupStep::Located(hops)) => {
+                return Ok(Lookup {
+                    latency: started_at.elapsed(),
+                    hops: hops + redirects,
+                });
+            }
+            Some(LookupStep::Redirect(next_addr, next_vnode)) => (remote_addr, vnode) = (next_addr, next_vnode),
+            None => return Err(format!("[{}]: unexpected response {}", remote_addr, response_msg)),
+        }
+    }
+
+    Err("too many redirects".to_string())
+}
+
+/// Parses the response to a traced lookup: `SUCCESSOR=[id][address][HOPS=n];`
+/// or `REDIRECT=[id][address];`, the address of virtual node `k` > 0 ending
+/// with `#k`.
+///
+/// A successor without hop count (a node not counting the hops of
+/// traced lookups) is not a valid response.
+fn parse_lookup_response(response_msg: &str) -> Option<LookupStep> {
+    if let Some(successor) = response_msg.strip_prefix("SUCCESSOR=[") {
+        let hops = successor.strip_suffix("];")?.rsplit_once("][HOPS=")?.1;
+        return hops.parse::<u32>().ok().map(LookupStep::Located);
+    }
+
+    let next_addr = response_msg
+        .strip_prefix("REDIRECT=[")?
+        .strip_suffix("];")?
+        .split_once("][")?
+        .1;
+
+    match next_addr.split_once('#') {
+        Some((next_addr, vnode)) => Some(LookupStep::Redirect(next_addr.parse().ok()?, vnode.parse().ok()?)),
+        None => Some(LookupStep::Redirect(next_addr.parse().ok()?, 0)),
+    }
+}
+
+/// Returns the latency under which `quantile` of the
+/// `latencies` fall, `latencies` being sorted.
+fn percentile(latencies: &[Duration], quantile: f64) -> Duration {
+    let rank = ((latencies.len() as f64 * quantile).ceil() as usize).max(1);
+    latencies[rank.min(latencies.len()) - 1]
+}
+
+fn print_summary(lookups: &mut [Lookup], failures: u64, elapsed: Duration) {
+    let requests = lookups.len() as u64 + failures;
+
+    println!(
+        "lookups: {} ({} failed, {:.2}% failure rate)",
+        requests,
+        failures,
+        failures as f64 * 100.0 / requests.max(1) as f64
+    );
+    println!(
+        "throughput: {:.1} lookups/s over {:.2?}",
+        requests as f64 / elapsed.as_secs_f64(),
+        elapsed
+    );
+
+    if lookups.is_empty() {
+        return;
+    }
+
+    lookups.sort_by_key(|lookup| lookup.latency);
+
+    let latencies = lookups
+        .iter()
+        .map(|lookup| lookup.latency)
+        .collect::<Vec<_>>();
+
+    println!(
+        "latency: p50 {:.2?}, p90 {:.2?}, p99 {:.2?}, max {:.2?}",
+        percentile(&latencies, 0.5),
+        percentile(&latencies, 0.9),
+        percentile(&latencies, 0.99),
+        latencies[latencies.len() - 1]
+    );
+
+    let total_hops = lookups.iter().map(|lookup| lookup.hops as u64).sum::<u64>();
+    let max_hops = lookups.iter().map(|lookup| lookup.hops).max().unwrap_or(0);
+
+    println!(
+        "hops: mean {:.2}, max {}",
+        total_hops as f64 / lookups.len() as f64,
+        max_hops
+    );
+}
+
+#[cfg(test)]
+mod bench_test {
+    use std::time::Duration;
+
+    use super::{LookupStep, parse_lookup_response, percentile};
+
+    #[test]
+    fn percentile_test() {
+        let latencies = (1..=10).map(Duration::from_millis).collect::<Vec<_>>();
+
+        assert_eq!(percentile(&latencies, 0.5), Duration::from_millis(5));
+        assert_eq!(percentile(&latencies, 0.9), Duration::from_millis(9));
+        assert_eq!(percentile(&latencies, 0.99), Duration::from_millis(10));
+        assert_eq!(percentile(&latencies, 1.0), Duration::from_millis(10));
+        assert_eq!(percentile(&latencies, 0.0), Duration::from_millis(1));
+
+        assert_eq!(percentile(&[Duration::from_millis(7)], 0.5), Duration::from_millis(7));
+    }
+
+    #[test]
+    fn parse_lookup_response_test() {
+        assert_eq!(
+            parse_lookup_response("SUCCESSOR=[cf4b19e32ce29fef04468ac9d2a6787d][17.5.7.3:1450#2][HOPS=3];"),
+            Some(LookupStep::Located(3))
+        );
+        assert_eq!(
+            parse_lookup_response("REDIRECT=[cf4b19e32ce29fef04468ac9d2a6787d][17.5.7.3:1450];"),
+            Some(LookupStep::Redirect("17.5.7.3:1450".parse().unwrap(), 0))
+        );
+        assert_eq!(
+            parse_lookup_response("REDIRECT=[cf4b19e32ce29fef04468ac9d2a6787d][[2001:db8::1]:8080#4];"),
+            Some(LookupStep::Redirect("[2001:db8::1]:8080".parse().unwrap(), 4))
+        );
+
+        for response_msg in [
+            // Untraced successor
+            "SUCCESSOR=[cf4b19e32ce29fef04468ac9d2a6787d][17.5.7.3:1450];",
+            "SUCCESSOR=[cf4b19e32ce29fef04468ac9d2a6787d][17.5.7.3:1450][HOPS=-1];",
+            "REDIRECT=[cf4b19e32ce29fef04468ac9d2a6787d][localhost:1450];",
+            "REDIRECT=[cf4b19e32ce29fef04468ac9d2a6787d][17.5.7.3:1450#x];",
+            "REDIRECT=[cf4b19e32ce29fef04468ac9d2a6787d];",
+            "ERROR=[RETRYABLE][no active node precedes the key in the successor list];",
+            "",
+        ] {
+            assert_eq!(parse_lookup_response(response_msg), None, "{}", response_msg);
+        }
+    }
+}
@@ -1,30 +1,256 @@
-use std::{env, io::{self, Write}, net::{SocketAddr, TcpStream}, process};
-
-fn main() -> Result<(), io::Error> {
-    let mut args = env::args().skip(1);
-    
-    let remote_addr = {
-        let remote_addr = args.next().unwrap_or_else(|| {
-            eprintln!("error: remote node address is missing");
-            process::exit(1);
-        });
+use std::{env, net::SocketAddr, process};
+
+use node::client::Client;
+
+/// Data update sent to a node, described by the arguments.
+#[derive(Debug, PartialEq, Eq)]
+struct Update {
+    remote_addr: SocketAddr,
+    data: String,
+    /// Number of nodes which must accept the data
+    /// before the write is acknowledged (ack=K).
+    required_acks: Option<usize>,
+    /// Lifetime of the data, in seconds (ttl=S).
+    ttl: Option<u64>,
+    /// Idempotency key of the update, so
+    /// that its retries are written once (key=K).
+    key: Option<String>,
+}
+
+fn main() {
+    let mut args = env::args().skip(1).collect::<Vec<_>>();
+
+    // Connection options: the key signing the requests (--hmac-key K), and
+    // the certificates trusted for the nodes of a ring requiring TLS
+    // (--tls-ca P), issued for a name (--tls-server-name N)
+    let client = Client::from_args(&mut args).unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    });
 
-        remote_addr.parse::<SocketAddr>().unwrap_or_else(|_| {
-            eprintln!("error: invalid remote node address");
+    let update = Update::parse(args.into_iter()).unwrap_or_else(|err| {
+        eprintln!("error: {}", err);
+        process::exit(1);
+    });
+
+    match send_update(&client, &update) {
+        Ok(Some(acknowledgment)) => println!("{}", acknowledgment),
+        Ok(None) => (),
+        Err(err) => {
+            eprintln!("error: {}", err);
             process::exit(1);
+        }
+    }
+}
+
+impl Update {
+    /// Parses `<remote address> <data> [ack=K] [ttl=S] [key=K]`.
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let remote_addr = args
+            .next()
+            .ok_or("remote node address is missing")?
+            .parse::<SocketAddr>()
+            .map_err(|_| "invalid remote node address")?;
+
+        let data = args.next().ok_or("no data provided for dissemination")?;
+
+        let mut update = Self {
+            remote_addr,
+            data,
+            required_acks: None,
+            ttl: None,
+            key: None,
+        };
+
+        for arg in args {
+            if let Some(value) = arg.strip_prefix("ack=") {
+                let value = value.parse::<usize>().ok().filter(|value| *value > 0);
+                update.required_acks = Some(value.ok_or("invalid acknowledgment count (expected ack=K, with K > 0)")?);
+            } else if let Some(value) = arg.strip_prefix("ttl=") {
+                update.ttl = Some(value.parse::<u64>().map_err(|_| "invalid TTL (expected ttl=S, in seconds)")?);
+            } else if let Some(value) = arg.strip_prefix("key=") {
+                if value.is_empty() || value.len() > 64 || !value.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+                    return Err("invalid idempotency key (expected key=K, with up to 64 letters, digits, `-` or `_`)".to_string());
+                }
+
+                update.key = Some(value.to_string());
+            } else {
+                return Err(format!("unknown argument [{}]", arg));
+            }
+        }
+
+        Ok(update)
+    }
+
+    /// Returns the `UPDATE_DATA` (or `UPDATE_DATA_WITH_ACK`) request of the update.
+    fn request_msg(&self) -> String {
+        let ttl_field = match self.ttl {
+            Some(ttl) => format!("[TTL={}]", ttl),
+            None => String::new(),
+        };
+
+        let key_field = match self.key {
+            Some(ref key) => format!("[KEY={}]", key),
+            None => String::new(),
+        };
+
+        match self.required_acks {
+            Some(required_acks) => format!("UPDATE_DATA_WITH_ACK=[{}][{}]{}{};", escape_data(&self.data), required_acks, ttl_field, key_field),
+            None => format!("UPDATE_DATA=[{}]{}{};", escape_data(&self.data), ttl_field, key_field),
+        }
+    }
+}
+
+/// Sends `update` to its node, and returns the acknowledgment
+/// to print, if the update was acknowledged by a quorum.
+///
+/// Returns an error if the node did not accept the update.
+fn send_update(client: &Client, update: &Update) -> Result<Option<String>, String> {
+    let response_msg = client.send(update.remote_addr, &update.request_msg()).map_err(|err| err.to_string())?;
+
+    check_response(&response_msg, update.required_acks.is_some())
+}
+
+/// Checks that `response_msg`, the response to an update (`with_ack`
+/// if written by a quorum), accepts it, and returns the acknowledgment
+/// to print, if any.
+fn check_response(response_msg: &str, with_ack: bool) -> Result<Option<String>, String> {
+    // `ERROR=[<kind>][<message>];`
+    if let Some(error) = response_msg.strip_prefix("ERROR=").and_then(|response| response.strip_suffix("];")) {
+        let message = error.split_once("][").map_or(error, |(_, message)| message);
+        return Err(format!("the update was rejected: {}", unescape_error_message(message)));
+    }
+
+    // Unauthenticated requests are dropped without response
+    if response_msg.is_empty() {
+        return Err("the remote node dropped the update without response (check --hmac-key)".to_string());
+    }
+
+    if !with_ack {
+        if response_msg != "RESPONSE=IGNORE;" {
+            return Err("invalid response from the remote node".to_string());
+        }
+
+        return Ok(None);
+    }
+
+    match response_msg
+        .strip_prefix("RESPONSE=ACKNOWLEDGED[")
+        .and_then(|response| response.strip_suffix("];"))
+        .and_then(|response| response.split_once("]["))
+        .and_then(|(acks, required_acks)| Some((acks.parse::<usize>().ok()?, required_acks.parse::<usize>().ok()?)))
+    {
+        Some((acks, required_acks)) if acks >= required_acks => Ok(Some(format!("acknowledged by {}/{} nodes", acks, required_acks))),
+        Some((acks, required_acks)) => Err(format!("only acknowledged by {}/{} nodes", acks, required_acks)),
+        None => Err("invalid response from the remote node".to_string()),
+    }
+}
+
+/// Escapes the delimiters of the data (`%XX`, their hexadecimal code),
+/// so that the data cannot be mistaken for the fields following it.
+fn escape_data(data: &str) -> String {
+    data.replace('%', "%25").replace('[', "%5B").replace(']', "%5D")
+}
+
+/// Reverts the escaping of the delimiters and line breaks
+/// of an error message (`%XX`, their hexadecimal code).
+fn unescape_error_message(message: &str) -> String {
+    [("%5B", "["), ("%5D", "]"), ("%3B", ";"), ("%0A", "\n"), ("%0D", "\r")]
+        .iter()
+        .fold(message.split("%25").map(str::to_string).collect::<Vec<_>>(), |parts, (escape, c)| {
+            parts.iter().map(|part| part.replace(escape, c)).collect()
         })
+        .join("%")
+}
+
+#[cfg(test)]
+mod update_test {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread::{self, JoinHandle},
     };
 
-    let data = args.next().unwrap_or_else(|| {
-        eprintln!("error: no data provided for dissemination");
-        process::exit(1);
-    });
+    use node::client::Client;
+
+    use super::{Update, check_response, send_update};
+
+    /// Node answering a single request with `response_msg`, or
+    /// closing the connection without response if `None`.
+    ///
+    /// Returns the update sent to it, and the
+    /// thread returning the request it received.
+    fn one_shot_node(data: &str, response_msg: Option<&'static str>) -> (Update, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let update = Update::parse([listener.local_addr().unwrap().to_string(), data.to_string()].into_iter()).unwrap();
+
+        let node = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+
+            let mut request_msg = String::new();
+            stream.read_to_string(&mut request_msg).unwrap();
+
+            if let Some(response_msg) = response_msg {
+                stream.write_all(response_msg.as_bytes()).unwrap();
+            }
+
+            request_msg
+        });
+
+        (update, node)
+    }
+
+    #[test]
+    fn accepted_update_test() {
+        let (update, node) = one_shot_node("a [b]", Some("RESPONSE=IGNORE;"));
+
+        assert_eq!(send_update(&Client::new(), &update), Ok(None));
+        assert_eq!(node.join().unwrap(), "UPDATE_DATA=[a %5Bb%5D];");
+    }
+
+    #[test]
+    fn rejected_update_test() {
+        let (update, node) = one_shot_node("value", Some("ERROR=[FATAL][the node is draining%3B retry];"));
+
+        assert_eq!(send_update(&Client::new(), &update), Err("the update was rejected: the node is draining; retry".to_string()));
+        node.join().unwrap();
+    }
+
+    #[test]
+    fn update_dropped_without_response_test() {
+        let (update, node) = one_shot_node("value", None);
+
+        assert_eq!(
+            send_update(&Client::new(), &update),
+            Err("the remote node dropped the update without response (check --hmac-key)".to_string())
+        );
+        node.join().unwrap();
+    }
+
+    #[test]
+    fn check_response_test() {
+        assert_eq!(check_response("RESPONSE=ACKNOWLEDGED[3][2];", true), Ok(Some("acknowledged by 3/2 nodes".to_string())));
+        assert_eq!(check_response("RESPONSE=ACKNOWLEDGED[1][2];", true), Err("only acknowledged by 1/2 nodes".to_string()));
 
-    let request_msg = format!("UPDATE_DATA=[{}];", data);
-    
-    let mut request_stream = TcpStream::connect(remote_addr)?;
+        for (response_msg, with_ack) in [("RESPONSE=IGNORE;", true), ("RESPONSE=ACKNOWLEDGED[1][1];", false), ("PREDECESSOR=NONE;", false)] {
+            assert_eq!(check_response(response_msg, with_ack), Err("invalid response from the remote node".to_string()), "{}", response_msg);
+        }
+    }
 
-    request_stream.write(request_msg.as_bytes())?;
+    #[test]
+    fn parse_test() {
+        let update = Update::parse(["127.0.0.1:8000", "value", "ack=2", "ttl=60", "key=retry-1"].map(String::from).into_iter()).unwrap();
+        assert_eq!(update.request_msg(), "UPDATE_DATA_WITH_ACK=[value][2][TTL=60][KEY=retry-1];");
 
-    Ok(())
+        for (args, err) in [
+            (&[][..], "remote node address is missing"),
+            (&["localhost:8000", "value"][..], "invalid remote node address"),
+            (&["127.0.0.1:8000"][..], "no data provided for dissemination"),
+            (&["127.0.0.1:8000", "value", "ack=0"][..], "invalid acknowledgment count (expected ack=K, with K > 0)"),
+            (&["127.0.0.1:8000", "value", "key=a b"][..], "invalid idempotency key (expected key=K, with up to 64 letters, digits, `-` or `_`)"),
+            (&["127.0.0.1:8000", "value", "retries=3"][..], "unknown argument [retries=3]"),
+        ] {
+            assert_eq!(Update::parse(args.iter().map(|arg| arg.to_string())), Err(err.to_string()), "{:?}", args);
+        }
+    }
 }
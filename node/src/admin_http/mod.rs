@@ -0,0 +1,580 @@
+//! Optional admin HTTP API of a node (`admin-http-addr`),
+//! for dashboards and curl-based tooling which do not
+//! speak the text protocol:
+//!
+//! - `GET /status`: the node, whether it is part of the ring yet,
+//!   its predecessor, successor list, fingers, asymmetric successor
+//!   (`reachability-audit`) and live gossip state, as JSON,
+//! - `POST /data[?ttl=<seconds>]`: replaces the gossip data with
+//!   the request body, like `UPDATE_DATA`, and returns the new state
+//!   (rejected while the node drains),
+//! - `GET /owner?key=<key>`: the ring position of the application
+//!   key (percent-encoded in the query) and the node owning it,
+//!   located by a lookup from the node, as JSON.
+//!
+//! The API serves virtual node 0, whose gossip data is shared
+//! by the other virtual nodes. It is not authenticated: it
+//! should be bound to a loopback or private address.
+
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    sync::atomic::Ordering,
+};
+
+use crate::{
+    chord::{self, protocol::ChordResponse},
+    context::{NodeContext, VirtualNodes},
+    gossip::{self, protocol::GossipRequest, request_handler, State},
+    runtime::report::node_json,
+    transport::framing::MAX_FRAME_LENGTH,
+};
+
+/// Maximum length of the request line and of each header line.
+const MAX_LINE_LENGTH: u64 = 8 * 1024;
+
+/// Maximum number of headers of a request.
+const MAX_HEADERS: usize = 64;
+
+/// An admin HTTP request.
+#[derive(Debug, PartialEq, Eq)]
+struct HttpRequest {
+    method: String,
+    path: String,
+    query: Option<String>,
+    body: Vec<u8>,
+}
+
+/// An admin HTTP response, with its status code and JSON body.
+#[derive(Debug, PartialEq, Eq)]
+struct HttpResponse {
+    status: u16,
+    body: String,
+}
+
+impl HttpResponse {
+    fn ok(body: String) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            body: format!("{{\"error\":{}}}", json_string(message)),
+        }
+    }
+
+    /// Returns the HTTP/1.1 text of the response,
+    /// which closes the connection.
+    fn to_http_text(&self) -> String {
+        let reason = match self.status {
+            200 => "OK",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            413 => "Payload Too Large",
+            503 => "Service Unavailable",
+            _ => "Error",
+        };
+
+        format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            self.status,
+            reason,
+            self.body.len(),
+            self.body
+        )
+    }
+}
+
+/// Serves the admin HTTP requests received on `listener`,
+/// addressed to `virtual_nodes`, one connection at a time.
+///
+/// A connection is dropped if it stays idle longer
+/// than the read timeout of the node (`read-timeout`).
+/// Returns once the shutdown of the node is requested.
+pub(crate) fn serve(listener: TcpListener, virtual_nodes: VirtualNodes) {
+    let shutdown = virtual_nodes.first().shutdown;
+
+    if let Ok(local_addr) = listener.local_addr() {
+        shutdown.register_listener(local_addr);
+    }
+
+    for stream in listener.incoming() {
+        if shutdown.is_requested() {
+            break;
+        }
+
+        match stream {
+            Ok(stream) => handle_connection(stream, &virtual_nodes.first()),
+            Err(err) => eprintln!("failed to accept an admin HTTP connection: {}", err),
+        }
+    }
+}
+
+/// Reads a single request from `stream`, then writes its response.
+fn handle_connection(mut stream: TcpStream, context: &NodeContext) {
+    if stream
+        .set_read_timeout(Some(context.options.read_timeout))
+        .is_err()
+    {
+        return;
+    }
+
+    let response = match read_request(&mut BufReader::new(&stream)) {
+        Ok(request) => route(&request, context),
+        Err(response) => response,
+    };
+
+    let _ = stream.write_all(response.to_http_text().as_bytes());
+}
+
+/// Reads an HTTP request from `reader`.
+///
+/// Returns the error response if the request is malformed
+/// or too large (the connection may also have failed).
+fn read_request(reader: &mut impl BufRead) -> Result<HttpRequest, HttpResponse> {
+    let request_line = read_line(reader)?;
+
+    let (method, target) = match request_line.split(' ').collect::<Vec<_>>()[..] {
+        [method, target, version] if version.starts_with("HTTP/") => (method, target),
+        _ => return Err(HttpResponse::error(400, "malformed request line")),
+    };
+
+    let (path, query) = match target.split_once('?') {
+        Some((path, query)) => (path, Some(query.to_string())),
+        None => (target, None),
+    };
+
+    let mut content_length = 0;
+
+    for _ in 0..=MAX_HEADERS {
+        let header = read_line(reader)?;
+
+        if header.is_empty() {
+            let mut body = Vec::new();
+
+            reader
+                .take(content_length as u64)
+                .read_to_end(&mut body)
+                .map_err(|_| HttpResponse::error(400, "truncated request body"))?;
+
+            if body.len() != content_length {
+                return Err(HttpResponse::error(400, "truncated request body"));
+            }
+
+            return Ok(HttpRequest {
+                method: method.to_string(),
+                path: path.to_string(),
+                query,
+                body,
+            });
+        }
+
+        if let Some((name, value)) = header.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| HttpResponse::error(400, "invalid Content-Length header"))?;
+
+                if content_length > MAX_FRAME_LENGTH {
+                    return Err(HttpResponse::error(413, "request body too large"));
+                }
+            }
+        }
+    }
+
+    Err(HttpResponse::error(400, "too many headers"))
+}
+
+/// Reads a line of the request head, without its line ending.
+fn read_line(reader: &mut impl BufRead) -> Result<String, HttpResponse> {
+    let mut line = String::new();
+
+    match reader.take(MAX_LINE_LENGTH).read_line(&mut line) {
+        Ok(_) if line.ends_with('\n') => Ok(line.trim_end_matches(['\r', '\n']).to_string()),
+        Ok(_) => Err(HttpResponse::error(
+            400,
+            "truncated or too long request head",
+        )),
+        Err(err) if err.kind() == io::ErrorKind::InvalidData => {
+            Err(HttpResponse::error(400, "request head is not UTF-8 text"))
+        }
+        Err(_) => Err(HttpResponse::error(400, "cannot read the request")),
+    }
+}
+
+/// Answers `request`, on behalf of the node running in `context`.
+fn route(request: &HttpRequest, context: &NodeContext) -> HttpResponse {
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/status") => HttpResponse::ok(status_json(context)),
+        ("POST", "/data") => update_data(request, context),
+        ("GET", "/owner") => locate_owner(request, context),
+        (_, "/status" | "/data" | "/owner") => HttpResponse::error(405, "method not allowed"),
+        _ => HttpResponse::error(404, "not found"),
+    }
+}
+
+/// Replaces the gossip data with the body of `request`,
+/// living for the `ttl` of its query (if any).
+///
+/// The data goes through the `UPDATE_DATA` parser, so that
+/// only data the gossip protocol can carry is accepted.
+fn update_data(request: &HttpRequest, context: &NodeContext) -> HttpResponse {
+    // Updates accepted now would not be handed off
+    if context.is_draining.load(Ordering::SeqCst) {
+        return HttpResponse::error(503, "the node is draining");
+    }
+
+    let data = match std::str::from_utf8(&request.body) {
+        Ok(data) => data,
+        Err(_) => return HttpResponse::error(400, "the data must be UTF-8 text"),
+    };
+
+    let ttl = match request.query.as_deref() {
+        None => String::new(),
+        Some(query) => match query.strip_prefix("ttl=").map(str::parse::<u64>) {
+            Some(Ok(ttl)) => format!("[TTL={}]", ttl),
+            _ => return HttpResponse::error(400, "invalid query: expected ttl=<seconds>"),
+        },
+    };
+
+    match GossipRequest::parse(&format!("UPDATE_DATA=[{}]{};", data, ttl)) {
+        Ok(GossipRequest::UpdateData(received_data, ttl, None)) if received_data == data => {
+            request_handler::update_data_request_handler(
+                context.self_node_gossip_data.clone(),
+                &context.gossip_clock,
+                received_data,
+                ttl,
+                None,
+                context.gossip_journal.as_deref(),
+                context.observer.as_ref(),
+            )
+            .unwrap();
+
+            HttpResponse::ok(state_json(
+                gossip::live_state(&context.self_node_gossip_data, context.gossip_clock.now())
+                    .as_ref(),
+            ))
+        }
+        _ => HttpResponse::error(
+            400,
+            "invalid data: expected a non-empty single line of text",
+        ),
+    }
+}
+
+/// Locates the owner of the application key of the query of
+/// `request`, by a lookup from the node running in `context`.
+fn locate_owner(request: &HttpRequest, context: &NodeContext) -> HttpResponse {
+    let key = match request
+        .query
+        .as_deref()
+        .and_then(|query| query.strip_prefix("key="))
+        .and_then(percent_decode)
+    {
+        Some(key) if !key.is_empty() => key,
+        _ => return HttpResponse::error(400, "invalid query: expected key=<key>"),
+    };
+
+    let bit_length = context.self_node.get_ring_bit_length();
+    let position = chord::key_position(&key, bit_length);
+
+    let response = chord::request_handler::find_successor_of_key_request_handler(
+        context.transport.as_ref(),
+        context.self_node.clone(),
+        context.self_node_successor_list.read().unwrap().clone(),
+        u128::from_be_bytes(position),
+        None,
+        context.options.routing_mode,
+        None,
+    );
+
+    match response {
+        ChordResponse::Successor(owner) => HttpResponse::ok(format!(
+            "{{\"key\":{},\"position\":\"{}\",\"owner\":{}}}",
+            json_string(&String::from_utf8_lossy(&key)),
+            hex::encode(&position[..bit_length / 8]),
+            node_json(&owner)
+        )),
+        ChordResponse::Error(_, err) => {
+            HttpResponse::error(502, &format!("the lookup failed: {}", err))
+        }
+        _ => HttpResponse::error(502, "the lookup failed: unexpected response"),
+    }
+}
+
+/// Decodes the percent-encoded `text` (`%XX`, the
+/// hexadecimal code of a byte), or returns `None` if
+/// an escape is invalid.
+fn percent_decode(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    let mut remaining = text.as_bytes();
+
+    while let Some((&byte, rest)) = remaining.split_first() {
+        if byte == b'%' {
+            let code = std::str::from_utf8(rest.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(code, 16).ok()?);
+            remaining = &rest[2..];
+        } else {
+            bytes.push(byte);
+            remaining = rest;
+        }
+    }
+
+    Some(bytes)
+}
+
+/// Returns the status of the node running in `context`, as JSON.
+fn status_json(context: &NodeContext) -> String {
+    let predecessor = match *context.self_node_predecessor.read().unwrap() {
+        Some(ref predecessor) => node_json(predecessor),
+        None => "null".to_string(),
+    };
+
+    let successor_list = context
+        .self_node_successor_list
+        .read()
+        .unwrap()
+        .iter()
+        .map(node_json)
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let fingers = context
+        .self_node_finger_table
+        .read()
+        .unwrap()
+        .entries()
+        .iter()
+        .map(|(index, finger)| format!("{{\"index\":{},\"node\":{}}}", index, node_json(finger)))
+        .collect::<Vec<_>>()
+        .join(",");
+
+    let asymmetric_successor = match *context.self_node_asymmetric_successor.read().unwrap() {
+        Some(ref successor) => node_json(successor),
+        None => "null".to_string(),
+    };
+
+    format!(
+        "{{\"node\":{},\"ready\":{},\"predecessor\":{},\"successor_list\":[{}],\
+         \"fingers\":[{}],\"asymmetric_successor\":{},\"gossip_state\":{}}}",
+        node_json(&context.self_node),
+        context.self_node_is_ready.load(Ordering::Relaxed),
+        predecessor,
+        successor_list,
+        fingers,
+        asymmetric_successor,
+        state_json(
+            gossip::live_state(&context.self_node_gossip_data, context.gossip_clock.now()).as_ref()
+        )
+    )
+}
+
+/// Returns `state` as JSON (`null` without state).
+fn state_json(state: Option<&State>) -> String {
+    match state {
+        Some(state) => format!(
+            "{{\"data\":{},\"timestamp\":{},\"ttl\":{}}}",
+            json_string(&state.data),
+            state.timestamp,
+            match state.ttl {
+                Some(ttl) => ttl.to_string(),
+                None => "null".to_string(),
+            }
+        ),
+        None => "null".to_string(),
+    }
+}
+
+/// Returns `value` as a JSON string literal.
+fn json_string(value: &str) -> String {
+    let mut literal = String::from("\"");
+
+    for c in value.chars() {
+        match c {
+            '"' => literal.push_str("\\\""),
+            '\\' => literal.push_str("\\\\"),
+            '\n' => literal.push_str("\\n"),
+            '\r' => literal.push_str("\\r"),
+            '\t' => literal.push_str("\\t"),
+            c if c.is_control() => literal.push_str(&format!("\\u{:04x}", c as u32)),
+            c => literal.push(c),
+        }
+    }
+
+    literal.push('"');
+    literal
+}
+
+#[cfg(test)]
+mod admin_http_test {
+    use std::{
+        io::{Read, Write},
+        net::{SocketAddr, TcpListener, TcpStream},
+        sync::{Arc, RwLock},
+        thread,
+        time::Duration,
+    };
+
+    use crate::{
+        chord::Node,
+        cli::Options,
+        context::{NodeContext, VirtualNodes},
+    };
+
+    use super::{json_string, serve};
+
+    /// Serves the admin API of a single-node ring on
+    /// an ephemeral loopback port, and returns its address.
+    fn serve_single_node() -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let admin_addr = listener.local_addr().unwrap();
+        let self_node = Node::create_from(1u128.to_be_bytes(), "127.0.0.1:7000".parse().unwrap());
+
+        let context = NodeContext {
+            self_node: self_node.clone(),
+            self_node_successor_list: Arc::new(RwLock::new(std::array::from_fn(|_| {
+                self_node.clone()
+            }))),
+            options: Options {
+                read_timeout: Duration::from_secs(1),
+                ..Options::default()
+            },
+            ..NodeContext::for_test(self_node.get_public_addr())
+        };
+
+        thread::spawn(move || serve(listener, VirtualNodes::new(context)));
+
+        admin_addr
+    }
+
+    /// Sends the raw HTTP `request` to `admin_addr`,
+    /// and returns the status line and the body of the response.
+    fn send(admin_addr: SocketAddr, request: &str) -> (String, String) {
+        let mut stream = TcpStream::connect(admin_addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status_line = head.lines().next().unwrap().to_string();
+
+        (status_line, body.to_string())
+    }
+
+    fn post_data(admin_addr: SocketAddr, target: &str, data: &str) -> (String, String) {
+        send(
+            admin_addr,
+            &format!(
+                "POST {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+                target,
+                data.len(),
+                data
+            ),
+        )
+    }
+
+    #[test]
+    fn status_and_data_test() {
+        let admin_addr = serve_single_node();
+        let get_status = "GET /status HTTP/1.1\r\nHost: localhost\r\n\r\n";
+
+        let (status_line, body) = send(admin_addr, get_status);
+        assert_eq!(status_line, "HTTP/1.1 200 OK");
+        assert!(body.starts_with(concat!(
+            r#"{"node":{"id":"00000000000000000000000000000001","ring_position":"1","#,
+            r#""addr":"127.0.0.1:7000"},"ready":false,"predecessor":null,"successor_list":[{"#
+        )));
+        assert!(
+            body.ends_with(r#"],"fingers":[],"asymmetric_successor":null,"gossip_state":null}"#)
+        );
+
+        let (status_line, body) = post_data(admin_addr, "/data?ttl=60", "say \"hi\"");
+        assert_eq!(status_line, "HTTP/1.1 200 OK");
+        assert!(body.starts_with(r#"{"data":"say \"hi\"","timestamp":"#));
+        assert!(body.ends_with(r#","ttl":60}"#));
+
+        let (_, body) = send(admin_addr, get_status);
+        assert!(body.contains(r#""gossip_state":{"data":"say \"hi\"","#));
+    }
+
+    #[test]
+    fn owner_of_key_test() {
+        let admin_addr = serve_single_node();
+
+        let (status_line, body) = send(
+            admin_addr,
+            "GET /owner?key=user%3A42 HTTP/1.1\r\nHost: localhost\r\n\r\n",
+        );
+        assert_eq!(status_line, "HTTP/1.1 200 OK");
+        assert_eq!(
+            body,
+            concat!(
+                r#"{"key":"user:42","position":"56dadf1868c3ba340467c942a86c29de","#,
+                r#""owner":{"id":"00000000000000000000000000000001","ring_position":"1","#,
+                r#""addr":"127.0.0.1:7000"}}"#
+            )
+        );
+
+        for (target, expected_status_line) in [
+            ("/owner", "HTTP/1.1 400 Bad Request"),
+            ("/owner?key=", "HTTP/1.1 400 Bad Request"),
+            ("/owner?key=user%3", "HTTP/1.1 400 Bad Request"),
+            ("/owner?id=user", "HTTP/1.1 400 Bad Request"),
+        ] {
+            assert_eq!(
+                send(
+                    admin_addr,
+                    &format!("GET {} HTTP/1.1\r\nHost: localhost\r\n\r\n", target)
+                )
+                .0,
+                expected_status_line,
+                "{}",
+                target
+            );
+        }
+
+        assert_eq!(
+            post_data(admin_addr, "/owner?key=user", "").0,
+            "HTTP/1.1 405 Method Not Allowed"
+        );
+    }
+
+    #[test]
+    fn invalid_requests_are_rejected_test() {
+        let admin_addr = serve_single_node();
+
+        for (target, data, expected_status_line) in [
+            ("/data", "", "HTTP/1.1 400 Bad Request"),
+            ("/data", "two\nlines", "HTTP/1.1 400 Bad Request"),
+            ("/data?ttl=soon", "value", "HTTP/1.1 400 Bad Request"),
+            ("/status", "value", "HTTP/1.1 405 Method Not Allowed"),
+            ("/shutdown", "", "HTTP/1.1 404 Not Found"),
+        ] {
+            assert_eq!(
+                post_data(admin_addr, target, data).0,
+                expected_status_line,
+                "{}",
+                target
+            );
+        }
+
+        assert_eq!(
+            send(admin_addr, "garbage\r\n\r\n").0,
+            "HTTP/1.1 400 Bad Request"
+        );
+    }
+
+    #[test]
+    fn json_string_test() {
+        assert_eq!(json_string("plain"), r#""plain""#);
+        assert_eq!(
+            json_string("a \"quote\", a \\ and a\ttab\u{1}"),
+            r#""a \"quote\", a \\ and a\ttab\u0001""#
+        );
+    }
+}
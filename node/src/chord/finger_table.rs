@@ -0,0 +1,108 @@
+//! Finger table of a Chord node.
+//!
+//! Finger `i` of a node at position `n` is the successor
+//! of position `n + 2^i` (modulo `2^m`), on a ring of `m` bits.
+//! Positions are left-aligned in the raw bytes of the identifiers,
+//! so `2^i` is shifted by the bits unused by the ring.
+
+use super::{Node, MAX_RING_BIT_LENGTH};
+
+/// Fingers of a node, one slot per bit of the ring.
+///
+/// A slot is empty until its finger is
+/// located by the periodic refresh.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct FingerTable {
+    fingers: Vec<Option<Node>>,
+}
+
+impl FingerTable {
+    /// Creates a finger table with empty slots,
+    /// for a ring of `bit_length` bits.
+    pub(crate) fn new(bit_length: usize) -> Self {
+        Self {
+            fingers: vec![None; bit_length],
+        }
+    }
+
+    /// Returns the position targeted by
+    /// finger `index` of `self_node`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not lower than the bit length
+    /// of the ring of `self_node`.
+    pub(crate) fn target_position(self_node: &Node, index: usize) -> u128 {
+        let bit_length = self_node.get_ring_bit_length();
+        assert!(index < bit_length, "invalid finger index");
+        self_node
+            .get_ring_position()
+            .wrapping_add(1 << (index + MAX_RING_BIT_LENGTH - bit_length))
+    }
+
+    /// Sets (or clears) finger `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not lower than the ring's bit length.
+    pub(crate) fn set(&mut self, index: usize, finger: Option<Node>) {
+        self.fingers[index] = finger;
+    }
+
+    /// Returns the located fingers with their
+    /// index, skipping the empty slots.
+    pub(crate) fn entries(&self) -> Vec<(usize, Node)> {
+        self.fingers
+            .iter()
+            .enumerate()
+            .filter_map(|(index, finger)| Some((index, finger.clone()?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod finger_table_test {
+    use crate::chord::{Node, MAX_RING_BIT_LENGTH};
+
+    use super::FingerTable;
+
+    #[test]
+    fn target_position_wraps_around_test() {
+        let self_node = Node::for_test_at(100);
+
+        assert_eq!(FingerTable::target_position(&self_node, 0), 101);
+        assert_eq!(FingerTable::target_position(&self_node, 4), 116);
+        assert_eq!(
+            FingerTable::target_position(&self_node, MAX_RING_BIT_LENGTH - 1),
+            100 + (1 << 127)
+        );
+
+        let self_node = Node::for_test_at(u128::MAX - 1);
+
+        assert_eq!(FingerTable::target_position(&self_node, 0), u128::MAX);
+        assert_eq!(FingerTable::target_position(&self_node, 1), 0);
+        assert_eq!(FingerTable::target_position(&self_node, 3), 6);
+    }
+
+    #[test]
+    fn entries_skip_empty_slots_test() {
+        let mut finger_table = FingerTable::new(MAX_RING_BIT_LENGTH);
+        assert!(finger_table.entries().is_empty());
+
+        finger_table.set(0, Some(Node::for_test_at(200)));
+        finger_table.set(7, Some(Node::for_test_at(300)));
+        finger_table.set(MAX_RING_BIT_LENGTH - 1, Some(Node::for_test_at(400)));
+
+        assert_eq!(
+            finger_table.entries(),
+            vec![
+                (0, Node::for_test_at(200)),
+                (7, Node::for_test_at(300)),
+                (MAX_RING_BIT_LENGTH - 1, Node::for_test_at(400))
+            ]
+        );
+
+        finger_table.set(7, None);
+        assert_eq!(finger_table.entries().len(), 2);
+    }
+}
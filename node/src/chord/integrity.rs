@@ -0,0 +1,334 @@
+//! Integrity check of the ring around a node (`ring-integrity-check`).
+//!
+//! Stabilization only keeps each node consistent with its
+//! neighbors. If the successor pointers fork (e.g. a node whose
+//! predecessor skips it), following them from the node loops
+//! without ever coming back to it: lookups are answered
+//! inconsistently, without any visible failure. The check walks
+//! the successors around the ring, reports such a fork, and
+//! notifies the node which should precede the skipped one, so
+//! that the stabilization of the ring repairs it.
+
+use std::time::{Duration, Instant};
+
+use crate::transport::Transport;
+
+use super::{protocol::ChordResponse, request_initiator, Node};
+
+/// Nodes visited by a walk around the ring, at most.
+pub(crate) const MAX_RING_WALK_LENGTH: usize = 1024;
+
+/// Outcome of a walk following the successors
+/// around the ring, from a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum RingWalk {
+    /// The successors lead back to the node, through
+    /// the given number of nodes (the node included).
+    Cycle(usize),
+    /// The successors loop through the given nodes,
+    /// in ring order, without reaching the node back.
+    Fork(Vec<Node>),
+    /// A node of the walk did not answer, which
+    /// is handled by the stabilization.
+    Interrupted,
+    /// No loop within `MAX_RING_WALK_LENGTH` nodes.
+    TooLong,
+}
+
+/// Follows the successors around the ring, from
+/// `successor`, the successor of `self_node`.
+pub(crate) fn walk_ring(transport: &dyn Transport, self_node: &Node, successor: &Node) -> RingWalk {
+    let mut visited_nodes: Vec<Node> = Vec::new();
+    let mut current_node = successor.clone();
+
+    loop {
+        if current_node == *self_node {
+            return RingWalk::Cycle(visited_nodes.len() + 1);
+        }
+
+        if let Some(loop_start) = visited_nodes.iter().position(|node| *node == current_node) {
+            return RingWalk::Fork(visited_nodes.split_off(loop_start));
+        }
+
+        if visited_nodes.len() == MAX_RING_WALK_LENGTH {
+            return RingWalk::TooLong;
+        }
+
+        current_node =
+            match request_initiator::get_successor_list(transport, current_node.get_node_addr()) {
+                ChordResponse::SuccessorList(successor_list) => {
+                    visited_nodes.push(current_node);
+                    successor_list[0].clone()
+                }
+                _ => return RingWalk::Interrupted,
+            };
+    }
+}
+
+/// Returns the node of the forked `ring_loop` which
+/// `self_node` should directly precede: the one whose
+/// predecessor in the loop skips `self_node`.
+pub(crate) fn skipping_node<'a>(self_node: &Node, ring_loop: &'a [Node]) -> Option<&'a Node> {
+    if ring_loop.len() == 1 {
+        return ring_loop.first();
+    }
+
+    ring_loop
+        .iter()
+        .zip(ring_loop.iter().cycle().skip(1))
+        .find(|(node, next_node)| {
+            self_node.is_position_stictly_between(
+                node.get_ring_position(),
+                next_node.get_ring_position(),
+            )
+        })
+        .map(|(_, next_node)| next_node)
+}
+
+/// Periodic integrity check of the ring around a node.
+#[derive(Debug)]
+pub(crate) struct RingIntegrityCheck {
+    interval: Duration,
+    next_check: Instant,
+    /// Whether the last check found a fork, so
+    /// that its repair is logged once.
+    is_forked: bool,
+}
+
+impl RingIntegrityCheck {
+    /// Creates a check running every `interval`, after `now`.
+    ///
+    /// A joining node is on a branch of the ring until its
+    /// predecessor adopts it: the first check waits for
+    /// an interval, not to report this as a fork.
+    pub(crate) fn new(interval: Duration, now: Instant) -> Self {
+        Self {
+            interval,
+            next_check: now + interval,
+            is_forked: false,
+        }
+    }
+
+    /// Walks the ring from `successor`, the successor of
+    /// `self_node`, if a check is due at `now`.
+    ///
+    /// A fork is logged, and the node which `self_node` should
+    /// precede is notified by it: that node then sets `self_node`
+    /// as its predecessor, which the node skipping `self_node`
+    /// adopts as successor at its next stabilization.
+    ///
+    /// Returns the outcome of the walk, if any.
+    pub(crate) fn run_if_due(
+        &mut self,
+        transport: &dyn Transport,
+        self_node: &Node,
+        successor: &Node,
+        now: Instant,
+    ) -> Option<RingWalk> {
+        if now < self.next_check || successor == self_node {
+            return None;
+        }
+
+        self.next_check = now + self.interval;
+
+        let ring_walk = walk_ring(transport, self_node, successor);
+
+        match ring_walk {
+            RingWalk::Cycle(_) if self.is_forked => {
+                eprintln!(
+                    "forked ring repaired: the successors lead back to [{:?}]",
+                    self_node.get_public_addr()
+                );
+                self.is_forked = false;
+            }
+            RingWalk::Fork(ref ring_loop) => {
+                eprintln!(
+                    "forked ring: the successors of [{:?}] loop through {} node(s) without reaching it back",
+                    self_node.get_public_addr(),
+                    ring_loop.len()
+                );
+                self.is_forked = true;
+
+                if let Some(skipping_node) = skipping_node(self_node, ring_loop) {
+                    if let ChordResponse::Error(_, err) = request_initiator::notify_remote_node(
+                        transport,
+                        self_node,
+                        skipping_node.get_node_addr(),
+                    ) {
+                        eprintln!(
+                            "cannot repair the forked ring through [{:?}]: {}",
+                            skipping_node.get_public_addr(),
+                            err
+                        );
+                    }
+                }
+            }
+            RingWalk::TooLong => eprintln!(
+                "ring integrity check stopped after {} nodes",
+                MAX_RING_WALK_LENGTH
+            ),
+            RingWalk::Cycle(_) | RingWalk::Interrupted => (),
+        }
+
+        Some(ring_walk)
+    }
+}
+
+#[cfg(test)]
+mod ring_integrity_test {
+    use std::{
+        collections::HashMap,
+        io,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        chord::{
+            protocol::{ChordRequest, ChordResponse, ErrorKind},
+            Node,
+        },
+        transport::mock::MockTransport,
+    };
+
+    use super::{skipping_node, walk_ring, RingIntegrityCheck, RingWalk};
+
+    /// Mock transport of a ring where the node at each
+    /// position of `successors` has the given successor.
+    /// Other nodes are unreachable.
+    fn transport(successors: &[(u128, u128)]) -> MockTransport {
+        let successors = successors
+            .iter()
+            .map(|&(position, successor)| {
+                (Node::for_test_at(position).get_public_addr(), successor)
+            })
+            .collect::<HashMap<_, _>>();
+
+        MockTransport::new(move |remote_addr, request_msg| {
+            let successor = *successors
+                .get(&remote_addr)
+                .ok_or(io::Error::from(io::ErrorKind::ConnectionRefused))?;
+
+            let response = match ChordRequest::parse(request_msg) {
+                Ok(ChordRequest::GetSuccessorList) => {
+                    ChordResponse::SuccessorList(std::array::from_fn(|_| {
+                        Node::for_test_at(successor)
+                    }))
+                }
+                Ok(ChordRequest::NotificationBy(_)) => ChordResponse::Acknowledged,
+                _ => ChordResponse::Error(ErrorKind::Fatal, "unexpected request".to_string()),
+            };
+
+            Ok(response.to_protocol_text())
+        })
+    }
+
+    #[test]
+    fn consistent_ring_is_a_cycle_test() {
+        let transport = transport(&[(100, 200), (200, 300), (300, 10)]);
+
+        assert_eq!(
+            walk_ring(&transport, &Node::for_test_at(10), &Node::for_test_at(100)),
+            RingWalk::Cycle(4)
+        );
+    }
+
+    #[test]
+    fn unreachable_node_interrupts_the_walk_test() {
+        let transport = transport(&[(100, 200)]);
+
+        assert_eq!(
+            walk_ring(&transport, &Node::for_test_at(10), &Node::for_test_at(100)),
+            RingWalk::Interrupted
+        );
+    }
+
+    #[test]
+    fn forked_ring_is_detected_and_repaired_test() {
+        // 300 skips 10, which is only reached from its predecessor
+        let forked_transport = transport(&[(100, 200), (200, 300), (300, 100)]);
+        let start = Instant::now();
+        let mut check = RingIntegrityCheck::new(Duration::from_secs(60), start);
+
+        // The first check waits for an interval
+        assert_eq!(
+            check.run_if_due(
+                &forked_transport,
+                &Node::for_test_at(10),
+                &Node::for_test_at(100),
+                start
+            ),
+            None
+        );
+
+        let now = start + Duration::from_secs(60);
+
+        assert_eq!(
+            check.run_if_due(
+                &forked_transport,
+                &Node::for_test_at(10),
+                &Node::for_test_at(100),
+                now
+            ),
+            Some(RingWalk::Fork(vec![
+                Node::for_test_at(100),
+                Node::for_test_at(200),
+                Node::for_test_at(300)
+            ]))
+        );
+        assert!(check.is_forked);
+
+        // 100 is notified, so that 300 adopts 10 as successor
+        let notification = forked_transport.sent_requests().pop().unwrap();
+        assert_eq!(notification.0, Node::for_test_at(100).get_public_addr());
+        assert_eq!(
+            ChordRequest::parse(&notification.1).unwrap(),
+            ChordRequest::NotificationBy(Node::for_test_at(10))
+        );
+
+        // Not checked again before the interval
+        assert_eq!(
+            check.run_if_due(
+                &forked_transport,
+                &Node::for_test_at(10),
+                &Node::for_test_at(100),
+                now + Duration::from_secs(30)
+            ),
+            None
+        );
+
+        let repaired_transport = transport(&[(100, 200), (200, 300), (300, 10)]);
+        assert_eq!(
+            check.run_if_due(
+                &repaired_transport,
+                &Node::for_test_at(10),
+                &Node::for_test_at(100),
+                now + Duration::from_secs(60)
+            ),
+            Some(RingWalk::Cycle(4))
+        );
+        assert!(!check.is_forked);
+    }
+
+    #[test]
+    fn skipping_node_test() {
+        let ring_loop = [
+            Node::for_test_at(100),
+            Node::for_test_at(200),
+            Node::for_test_at(300),
+        ];
+
+        assert_eq!(
+            skipping_node(&Node::for_test_at(10), &ring_loop),
+            Some(&Node::for_test_at(100))
+        );
+        assert_eq!(
+            skipping_node(&Node::for_test_at(250), &ring_loop),
+            Some(&Node::for_test_at(300))
+        );
+        assert_eq!(
+            skipping_node(&Node::for_test_at(10), &[Node::for_test_at(100)]),
+            Some(&Node::for_test_at(100))
+        );
+        assert_eq!(skipping_node(&Node::for_test_at(10), &[]), None);
+    }
+}
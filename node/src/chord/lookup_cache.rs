@@ -0,0 +1,131 @@
+//! Cache of recently resolved lookups.
+//!
+//! Maps a target ring position to the node found owning it,
+//! so that repeated lookups of the same position are not
+//! forwarded again through the ring.
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use super::Node;
+
+/// Maximum number of cached lookups.
+const LOOKUP_CACHE_CAPACITY: usize = 1024;
+
+/// Owners of recently resolved ring positions.
+///
+/// An entry is served for at most `ttl` after the lookup
+/// which resolved it, which bounds how long a stale owner
+/// is returned after the ring changes.
+#[derive(Debug)]
+pub(crate) struct LookupCache {
+    ttl: Duration,
+    entries: HashMap<u128, (Node, Instant)>,
+}
+
+impl LookupCache {
+    pub(crate) fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            entries: HashMap::new(),
+        }
+    }
+
+    /// Returns the cached owner of `position`,
+    /// unless it was resolved more than `ttl` before `now`.
+    pub(crate) fn get(&self, position: u128, now: Instant) -> Option<Node> {
+        self.entries
+            .get(&position)
+            .filter(|(_, resolved_at)| !is_expired(*resolved_at, now, self.ttl))
+            .map(|(owner, _)| owner.clone())
+    }
+
+    /// Caches `owner` as the owner of `position`, resolved at `now`.
+    ///
+    /// Expired entries are dropped when the cache is full. If every
+    /// entry is still fresh, the new one is not cached.
+    pub(crate) fn insert(&mut self, position: u128, owner: Node, now: Instant) {
+        if self.entries.len() >= LOOKUP_CACHE_CAPACITY && !self.entries.contains_key(&position) {
+            let ttl = self.ttl;
+            self.entries
+                .retain(|_, (_, resolved_at)| !is_expired(*resolved_at, now, ttl));
+
+            if self.entries.len() >= LOOKUP_CACHE_CAPACITY {
+                return;
+            }
+        }
+
+        self.entries.insert(position, (owner, now));
+    }
+
+    /// Drops every entry, once the
+    /// neighborhood of the node has changed.
+    pub(crate) fn clear(&mut self) {
+        self.entries.clear();
+    }
+}
+
+fn is_expired(resolved_at: Instant, now: Instant, ttl: Duration) -> bool {
+    now.saturating_duration_since(resolved_at) >= ttl
+}
+
+#[cfg(test)]
+mod lookup_cache_test {
+    use std::time::{Duration, Instant};
+
+    use crate::chord::Node;
+
+    use super::{LookupCache, LOOKUP_CACHE_CAPACITY};
+
+    #[test]
+    fn entries_expire_after_ttl_test() {
+        let mut cache = LookupCache::new(Duration::from_secs(5));
+        let resolved_at = Instant::now();
+
+        cache.insert(1000, Node::for_test_at(500), resolved_at);
+
+        assert_eq!(cache.get(1000, resolved_at), Some(Node::for_test_at(500)));
+        assert_eq!(
+            cache.get(1000, resolved_at + Duration::from_millis(4999)),
+            Some(Node::for_test_at(500))
+        );
+        assert_eq!(cache.get(1000, resolved_at + Duration::from_secs(5)), None);
+        assert_eq!(cache.get(2000, resolved_at), None);
+
+        // A new lookup refreshes the entry
+        cache.insert(
+            1000,
+            Node::for_test_at(600),
+            resolved_at + Duration::from_secs(5),
+        );
+        assert_eq!(
+            cache.get(1000, resolved_at + Duration::from_secs(6)),
+            Some(Node::for_test_at(600))
+        );
+
+        cache.clear();
+        assert_eq!(cache.get(1000, resolved_at + Duration::from_secs(6)), None);
+    }
+
+    #[test]
+    fn full_cache_drops_expired_entries_test() {
+        let mut cache = LookupCache::new(Duration::from_secs(5));
+        let resolved_at = Instant::now();
+
+        for position in 0..LOOKUP_CACHE_CAPACITY as u128 {
+            cache.insert(position, Node::for_test_at(500), resolved_at);
+        }
+
+        // Every entry is fresh: the new one is not cached
+        cache.insert(5000, Node::for_test_at(600), resolved_at);
+        assert_eq!(cache.get(5000, resolved_at), None);
+
+        // Every entry has expired: they make room for the new one
+        let now = resolved_at + Duration::from_secs(5);
+        cache.insert(5000, Node::for_test_at(600), now);
+        assert_eq!(cache.get(5000, now), Some(Node::for_test_at(600)));
+        assert_eq!(cache.entries.len(), 1);
+    }
+}
@@ -3,86 +3,343 @@
 
 use std::{
     error::Error,
-    io::{self, Write},
-    net::{IpAddr, SocketAddr, TcpListener, TcpStream},
+    fmt,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::RwLock,
+    thread,
+    time::{Duration, Instant},
 };
 
-use protocol::ChordResponse;
+use finger_table::FingerTable;
+use peer_pool::PeerPool;
+use protocol::{ChordResponse, ErrorKind};
+use ring_range::RingRange;
+use stabilization::{PredecessorAction, SuccessorDecision, SuccessorProbe};
+use trace::TraceId;
 
-use crate::cli::Args;
+use crate::identity;
 
+use crate::{
+    cli::{AddressFamily, RoutingMode, MAX_VNODES},
+    observer::NodeObserver,
+    transport::Transport,
+};
+
+pub(crate) mod finger_table;
+pub(crate) mod integrity;
+pub(crate) mod lookup_cache;
+pub(crate) mod node_info;
+pub(crate) mod peer_pool;
 pub(crate) mod protocol;
+pub(crate) mod reachability;
 pub(crate) mod request_handler;
 pub(crate) mod request_initiator;
+pub(crate) mod ring_range;
+pub(crate) mod snapshot;
+pub(crate) mod stabilization;
+pub(crate) mod trace;
 pub(crate) mod utils;
 
-pub(crate) const RING_BIT_LENGTH: usize = 128;
-pub(crate) const RING_BYTE_LENGTH: usize = RING_BIT_LENGTH / 8;
+/// Maximum bit length of the identifiers,
+/// which is the length of an MD5 hash.
+pub(crate) const MAX_RING_BIT_LENGTH: usize = 128;
+/// Length of an identifier in raw bytes format.
+///
+/// On a ring of fewer bits, the identifier occupies the leading
+/// bytes and the others are zero, so that ring positions keep
+/// the same `u128` arithmetic whatever the bit length.
+pub(crate) const RING_BYTE_LENGTH: usize = MAX_RING_BIT_LENGTH / 8;
 pub(crate) const SUCCESSOR_LIST_LENGTH: usize = 5;
-pub(crate) const RING_MAX_POSITION: u128 = u128::MAX;
+
+/// Number of retries of the lookup of a joining
+/// node's successor, after a retryable error.
+const JOIN_LOOKUP_RETRIES: u32 = 3;
+
+/// Delay before the first retry of the lookup of a
+/// joining node's successor, doubling after each retry.
+const JOIN_LOOKUP_RETRY_DELAY: Duration = Duration::from_millis(200);
+
+/// Number of probes sent again, during a stabilization pass, to
+/// a successor list entry which timed out: it may be alive, only
+/// slow. A refused entry is failed over right away.
+const SLOW_SUCCESSOR_RETRIES: u32 = 1;
+
+/// Byte sent by a node to its own public socket address,
+/// to recognize the connection on its local listener.
+const PUBLIC_ADDR_SENTINEL: u8 = b'#';
+
+/// Time given to the connection of a node to its own public
+/// socket address to reach its local listener.
+const PUBLIC_ADDR_VERIFICATION_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Delay between two polls of the local listener,
+/// while verifying the public socket address.
+const PUBLIC_ADDR_VERIFICATION_POLL: Duration = Duration::from_millis(10);
+
+/// Returns the position, in raw bytes format, of an arbitrary
+/// application `key` in a ring of `bit_length` bits: its hash, cut
+/// as the node identifiers are (see `Node::generate_identifier`).
+///
+/// The owner of the key is the successor of this position.
+pub fn key_position(key: &[u8], bit_length: usize) -> [u8; RING_BYTE_LENGTH] {
+    identity::identifier_prefix(identity::identifier_of_key(key), bit_length)
+}
+
+/// Address of a node: the public socket address of the
+/// process running it, and its index among the virtual
+/// nodes of this process (`vnodes`).
+///
+/// Written `ip:port` for virtual node 0, which is reached
+/// by requests without index, and `ip:port#index` otherwise.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct NodeAddr {
+    socket_addr: SocketAddr,
+    vnode: u16,
+}
+
+impl NodeAddr {
+    /// Prefix of the requests addressed to a virtual node other than 0.
+    const VNODE_PREFIX: &'static str = "VNODE=";
+
+    pub(crate) fn new(socket_addr: SocketAddr, vnode: u16) -> Self {
+        Self { socket_addr, vnode }
+    }
+
+    pub(crate) fn get_socket_addr(&self) -> SocketAddr {
+        self.socket_addr
+    }
+
+    pub(crate) fn get_vnode(&self) -> u16 {
+        self.vnode
+    }
+
+    /// Returns `request_msg`, prefixed with
+    /// the index of the addressed virtual node.
+    pub(crate) fn address_request(&self, request_msg: &str) -> String {
+        match self.vnode {
+            0 => request_msg.to_string(),
+            vnode => format!("{}[{}];{}", Self::VNODE_PREFIX, vnode, request_msg),
+        }
+    }
+
+    /// Parses a node address written by `Display`.
+    pub(crate) fn parse(node_addr: &str) -> Option<Self> {
+        match node_addr.split_once('#') {
+            Some((socket_addr, vnode)) => Some(Self::new(
+                socket_addr.parse().ok()?,
+                vnode.parse().ok().filter(|vnode| *vnode > 0)?,
+            )),
+            None => Some(Self::from(node_addr.parse::<SocketAddr>().ok()?)),
+        }
+    }
+
+    /// Splits a received request into the index of
+    /// the addressed virtual node and the request itself.
+    ///
+    /// Returns `None` if the index is malformed.
+    pub(crate) fn split_request(request_msg: &str) -> Option<(u16, &str)> {
+        let Some(addressed_request) = request_msg.strip_prefix(Self::VNODE_PREFIX) else {
+            return Some((0, request_msg));
+        };
+
+        let (vnode, request_msg) = addressed_request.strip_prefix('[')?.split_once("];")?;
+
+        Some((vnode.parse().ok().filter(|vnode| *vnode > 0)?, request_msg))
+    }
+}
+
+impl From<SocketAddr> for NodeAddr {
+    fn from(socket_addr: SocketAddr) -> Self {
+        Self::new(socket_addr, 0)
+    }
+}
+
+impl fmt::Display for NodeAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.vnode {
+            0 => write!(f, "{:?}", self.socket_addr),
+            vnode => write!(f, "{:?}#{}", self.socket_addr, vnode),
+        }
+    }
+}
 
 /// Contains information about a Chord Node,
 /// including identifier and the public socket
 /// address for accessing the node.
+///
+/// The ring bit length (`ring-bits`) is carried by each node,
+/// so that rings of different bit lengths can run in a process.
 #[derive(Debug, Clone, PartialEq, Eq)]
-pub(crate) struct Node {
+pub struct Node {
     id: [u8; RING_BYTE_LENGTH],
     public_addr: SocketAddr,
+    /// Index among the virtual nodes of its process.
+    vnode: u16,
+    /// Bit length of the identifiers of the ring of the node.
+    bit_length: usize,
 }
 
 impl Node {
     /// Creates a new Chord node with the given
     /// public socket address for accessing the node.
     ///
-    /// The node's identifier is an MD5 hash
-    /// of the public socket address.
+    /// The node's identifier is the MD5 hash of the
+    /// public socket address, on a ring of
+    /// `MAX_RING_BIT_LENGTH` bits (see `on_ring`).
     pub(crate) fn new(public_addr: SocketAddr) -> Self {
+        Self::new_virtual(public_addr, 0)
+    }
+
+    /// Creates virtual node `vnode` of the process
+    /// reachable at the given public socket address.
+    ///
+    /// The node's identifier is the hash of the public
+    /// socket address and of `vnode` (see `new` for 0).
+    pub(crate) fn new_virtual(public_addr: SocketAddr, vnode: u16) -> Self {
         Self {
-            id: Self::generate_identifier(public_addr),
+            id: Self::generate_identifier(public_addr, vnode, MAX_RING_BIT_LENGTH),
             public_addr,
+            vnode,
+            bit_length: MAX_RING_BIT_LENGTH,
         }
     }
 
-    /// Creates a Chord node using the provided
-    /// identifier and public socket address.
+    /// Creates a Chord node whose identifier is the
+    /// operator-supplied `id`, instead of a hash, on
+    /// a ring of `bit_length` bits.
+    ///
+    /// Only meant for small debugging deployments,
+    /// where ring positions must be readable.
+    /// `id` must be lower than `2^bit_length`.
+    pub(crate) fn with_sequential_id(id: u128, public_addr: SocketAddr, bit_length: usize) -> Self {
+        let position = id << (MAX_RING_BIT_LENGTH - bit_length);
+
+        Self::create_from(position.to_be_bytes(), public_addr).on_ring(bit_length)
+    }
+
+    /// Creates virtual node `vnode` of the process reachable at
+    /// the given public socket address, at ring `position` (see
+    /// `get_ring_position`) of a ring of `bit_length` bits, e.g.
+    /// to rebuild a node listed in a response.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bit_length` is not a multiple of 8, at most 128.
+    pub fn at_position(
+        position: u128,
+        public_addr: SocketAddr,
+        vnode: u16,
+        bit_length: usize,
+    ) -> Self {
+        Self::create_at(position.to_be_bytes(), NodeAddr::new(public_addr, vnode))
+            .on_ring(bit_length)
+    }
+
+    /// Creates a Chord node at ring `position`, reachable at
+    /// a port of `10.0.0.1` derived from the position.
+    #[cfg(test)]
+    pub(crate) fn for_test_at(position: u128) -> Self {
+        Self::create_from(
+            position.to_be_bytes(),
+            SocketAddr::from(([10, 0, 0, 1], position as u16)),
+        )
+    }
+
+    /// Creates a Chord node using the provided identifier and
+    /// public socket address, on a ring of `MAX_RING_BIT_LENGTH`
+    /// bits (see `on_ring`).
     pub(crate) fn create_from(id: [u8; RING_BYTE_LENGTH], public_addr: SocketAddr) -> Self {
-        Self { id, public_addr }
+        Self::create_at(id, NodeAddr::from(public_addr))
+    }
+
+    /// Creates a Chord node using the provided identifier and
+    /// node address, on a ring of `MAX_RING_BIT_LENGTH` bits.
+    pub(crate) fn create_at(id: [u8; RING_BYTE_LENGTH], node_addr: NodeAddr) -> Self {
+        Self {
+            id,
+            public_addr: node_addr.get_socket_addr(),
+            vnode: node_addr.get_vnode(),
+            bit_length: MAX_RING_BIT_LENGTH,
+        }
+    }
+
+    /// Returns the node on a ring of `bit_length` bits: its
+    /// identifier is cut to its leading `bit_length` bits.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bit_length` is not a multiple of 8, at most 128.
+    pub(crate) fn on_ring(mut self, bit_length: usize) -> Self {
+        self.id = identity::identifier_prefix(self.id, bit_length);
+        self.bit_length = bit_length;
+        self
+    }
+
+    /// Returns the bit length of the ring of the node.
+    pub fn get_ring_bit_length(&self) -> usize {
+        self.bit_length
     }
 
     /// Returns the position of the current
     /// node in the Chord ring.
-    pub(crate) fn get_ring_position(&self) -> u128 {
+    pub fn get_ring_position(&self) -> u128 {
         u128::from_be_bytes(self.id)
     }
 
+    /// Returns the current node's identifier, without
+    /// the zero padding of the raw bytes format, as a
+    /// number lower than `2^bit_length`.
+    pub fn get_identifier(&self) -> u128 {
+        self.get_ring_position() >> (MAX_RING_BIT_LENGTH - self.bit_length)
+    }
+
     /// Returns the current node's identifier
-    /// as a hash string (hexadecimal format).
-    pub(crate) fn get_hash_id(&self) -> String {
-        hex::encode(self.id)
+    /// as a hash string (hexadecimal format),
+    /// one digit per 4 bits of the ring.
+    pub fn get_hash_id(&self) -> String {
+        hex::encode(&self.id[..self.bit_length / 8])
     }
 
     /// Returns the current node's public
     /// socket address.
-    pub(crate) fn get_public_addr(&self) -> SocketAddr {
+    pub fn get_public_addr(&self) -> SocketAddr {
         self.public_addr
     }
 
-    /// Generates an identifier, in raw bytes format,
-    /// for a Chord node, by hashing the given public
-    /// socket address with MD5 hash function.
-    pub(crate) fn generate_identifier(public_addr: SocketAddr) -> [u8; 16] {
-        let mut socket_addr_bytes = Vec::new();
-
-        match public_addr.ip() {
-            IpAddr::V4(ip_v4) => socket_addr_bytes.extend_from_slice(&ip_v4.octets()),
-            IpAddr::V6(ip_v6) => socket_addr_bytes.extend_from_slice(&ip_v6.octets()),
-        }
+    /// Returns the index of the current node among
+    /// the virtual nodes of its process.
+    pub fn get_vnode(&self) -> u16 {
+        self.vnode
+    }
 
-        socket_addr_bytes.extend_from_slice(&public_addr.port().to_be_bytes());
+    /// Returns the address the current node
+    /// is reached at, virtual node index included.
+    pub(crate) fn get_node_addr(&self) -> NodeAddr {
+        NodeAddr::new(self.public_addr, self.vnode)
+    }
 
-        let socket_addr_hash = md5::compute(&socket_addr_bytes);
+    /// Generates an identifier, in raw bytes format, for
+    /// virtual node `vnode` of a process, by hashing the given
+    /// public socket address (and `vnode`) with MD5 hash function,
+    /// cut to the leading `bit_length` bits of the hash.
+    pub(crate) fn generate_identifier(
+        public_addr: SocketAddr,
+        vnode: u16,
+        bit_length: usize,
+    ) -> [u8; RING_BYTE_LENGTH] {
+        identity::identifier_prefix(
+            identity::virtual_identifier_of(public_addr, vnode),
+            bit_length,
+        )
+    }
 
-        socket_addr_hash.0
+    /// Checks if the current node's identifier is the hash of
+    /// its public socket address and virtual node index, the
+    /// latter being lower than `MAX_VNODES`.
+    pub(crate) fn has_hashed_identifier(&self) -> bool {
+        self.vnode < MAX_VNODES
+            && self.id == Self::generate_identifier(self.public_addr, self.vnode, self.bit_length)
     }
 
     /// Checks if the current node's position is strictly between `start` and `end`
@@ -93,77 +350,1781 @@ impl Node {
     ///
     /// Return `false` if `start` and `end` are equal.
     pub(crate) fn is_position_stictly_between(&self, start: u128, end: u128) -> bool {
-        if start > end {
-            return self.get_ring_position() > start
-                && self.get_ring_position() <= RING_MAX_POSITION
-                || self.get_ring_position() < end;
+        start != end && RingRange::new(start, end).contains_strictly(self.get_ring_position())
+    }
+}
+
+/// Membership policy, deciding whether a
+/// new node is allowed to join the Chord network.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum MembershipPolicy {
+    /// Any node can join the network.
+    Open,
+    /// Nodes can join as long as the estimated
+    /// ring size is below the given maximum.
+    MaxNodes(u128),
+}
+
+impl MembershipPolicy {
+    /// Decides whether a new node can join the network,
+    /// based on `self_node`'s view of the ring.
+    ///
+    /// Returns an informative rejection message otherwise.
+    pub(crate) fn admit(
+        &self,
+        self_node: &Node,
+        self_node_successor_list: &[Node; SUCCESSOR_LIST_LENGTH],
+    ) -> Result<(), String> {
+        match *self {
+            Self::Open => Ok(()),
+            Self::MaxNodes(max_nodes) => {
+                let estimated_ring_size =
+                    utils::estimate_ring_size(self_node, self_node_successor_list);
+
+                if estimated_ring_size >= max_nodes {
+                    return Err(format!(
+                        "the network has reached its maximum capacity ({} nodes, about {} estimated)",
+                        max_nodes, estimated_ring_size
+                    ));
+                }
+
+                Ok(())
+            }
         }
+    }
+}
+
+/// Where a node landed in the ring once it
+/// joined the network (or initiated it).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct JoinOutcome {
+    /// Initial successor list of the node.
+    pub(crate) successor_list: [Node; SUCCESSOR_LIST_LENGTH],
+    /// Immediate successor of the node, itself
+    /// if it is the first node of the ring.
+    pub(crate) successor: Node,
+    /// Ring position of the node (see `Node::get_identifier`).
+    pub(crate) ring_position: u128,
+    /// Whether the node is the first node of the ring,
+    /// and so its own successor.
+    pub(crate) is_first_node: bool,
+}
 
-        if start < end {
-            return self.get_ring_position() > start && self.get_ring_position() < end;
+impl JoinOutcome {
+    /// Creates the outcome of the join of `self_node`,
+    /// given its initial `successor_list`.
+    pub(crate) fn new(self_node: &Node, successor_list: [Node; SUCCESSOR_LIST_LENGTH]) -> Self {
+        let successor = successor_list[0].clone();
+
+        Self {
+            is_first_node: successor == *self_node,
+            ring_position: self_node.get_identifier(),
+            successor,
+            successor_list,
         }
+    }
+}
 
-        false
+impl fmt::Display for JoinOutcome {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_first_node {
+            write!(
+                f,
+                "first node of the ring, at position {}",
+                self.ring_position
+            )
+        } else {
+            write!(
+                f,
+                "at position {}, before successor [{}]",
+                self.ring_position,
+                self.successor.get_node_addr()
+            )
+        }
     }
 }
 
 /// Initializes the successor_list of the current node
-/// `self_node`, based on the provided argument.
+/// `self_node`, which joins the network through the node
+/// at `remote_addr`, or initiates a new network without it.
+/// Successors of another family than `address_family` are left out.
+///
+/// Returns the outcome of the join, with the successor list.
+///
+/// The lookup of the successor of a joining node is traced. It is
+/// retried up to `JOIN_LOOKUP_RETRIES` times after a retryable error,
+/// while a fatal one (e.g. identifier collision) aborts the join.
 pub(crate) fn initialize_self_node_successor_list(
+    transport: &dyn Transport,
     self_node: &Node,
-    args: &Args,
-) -> Result<[Node; SUCCESSOR_LIST_LENGTH], Box<dyn Error>> {
-    match *args {
-        Args::Init {
-            self_port: _,
-            public_addr: _,
-        } => {
+    remote_addr: Option<NodeAddr>,
+    address_family: AddressFamily,
+) -> Result<JoinOutcome, Box<dyn Error>> {
+    let remote_addr = match remote_addr {
+        Some(remote_addr) => remote_addr,
+        None => {
             let sucessor_list: [Node; SUCCESSOR_LIST_LENGTH] =
                 std::array::from_fn(|_| self_node.clone());
 
-            Ok(sucessor_list)
+            return Ok(JoinOutcome::new(self_node, sucessor_list));
+        }
+    };
+
+    let trace_id = Some(TraceId::generate());
+
+    trace::log(trace_id, || {
+        format!(
+            "node [{}] joining through [{}]",
+            self_node.get_node_addr(),
+            remote_addr
+        )
+    });
+
+    let mut retry_delay = JOIN_LOOKUP_RETRY_DELAY;
+    let mut retries = 0;
+
+    let successor = loop {
+        match request_initiator::find_successor_of_node(transport, self_node, remote_addr, trace_id)
+        {
+            ChordResponse::Successor(node) | ChordResponse::TracedSuccessor(node, _) => break node,
+            ChordResponse::Error(ErrorKind::Retryable | ErrorKind::Timeout, err)
+                if retries < JOIN_LOOKUP_RETRIES =>
+            {
+                eprintln!(
+                    "failed to locate the successor of node [{:?}], retrying in {:?}: {}",
+                    self_node.get_public_addr(),
+                    retry_delay,
+                    err
+                );
+
+                thread::sleep(retry_delay);
+                retry_delay *= 2;
+                retries += 1;
+            }
+            ChordResponse::Error(_, err) => {
+                return Err(From::from(format!(
+                    "failed to locate the successor of node [{:?}]: {}",
+                    self_node.get_public_addr(),
+                    err
+                )))
+            }
+            _ => {
+                return Err(From::from(format!(
+                "failed to locate the successor of node [{:?}]: invalid response (protocol error)",
+                self_node.get_public_addr()
+            )))
+            }
+        }
+    };
+
+    trace::log(trace_id, || {
+        format!(
+            "node [{:?}] joining: successor [{:?}]",
+            self_node.get_public_addr(),
+            successor.get_public_addr()
+        )
+    });
+
+    let remote_successor_list = match request_initiator::get_successor_list(transport, successor.get_node_addr()) {
+        ChordResponse::SuccessorList(successor_list) => successor_list,
+        ChordResponse::Error(_, err) => return Err(From::from(format!("failed to retrieve the successor list of the remote node [{:?}]: {}", successor.get_public_addr(), err))),
+        _ => return Err(From::from(format!("failed to retrieve the successor list of the remote node [{:?}]: invalid response (protocol error)", successor.get_public_addr()))),
+    };
+
+    let successor_list = utils::build_successor_list(
+        self_node,
+        &successor,
+        &remote_successor_list,
+        &[],
+        address_family,
+    )
+    .map_err(|err| {
+        format!(
+            "failed to build the successor list of node [{:?}]: {}",
+            self_node.get_public_addr(),
+            err
+        )
+    })?;
+
+    Ok(JoinOutcome::new(self_node, successor_list))
+}
+
+/// Runs one network stabilization pass for `self_node`:
+///
+/// - selects the first reachable successor (probing again the entries
+///   timing out, see `SLOW_SUCCESSOR_RETRIES`), adopting its predecessor
+///   instead if it sits between `self_node` and that successor, and
+///   if its address belongs to the `address_family` `self_node` reaches,
+/// - notifies the selected successor and rebuilds
+///   `self_node_successor_list` from its successor list, leaving
+///   out the entries found unreachable (the list is kept if the
+///   notification fails with a retryable error or times out),
+/// - clears `self_node_predecessor` if it is no longer active.
+///
+/// Returns the updated successor list.
+pub(crate) fn stabilize(
+    transport: &dyn Transport,
+    self_node: &Node,
+    self_node_predecessor: &RwLock<Option<Node>>,
+    self_node_successor_list: &RwLock<[Node; SUCCESSOR_LIST_LENGTH]>,
+    address_family: AddressFamily,
+    observer: &dyn NodeObserver,
+) -> Result<[Node; SUCCESSOR_LIST_LENGTH], String> {
+    let self_node_successor_list_value = self_node_successor_list.read().unwrap().clone();
+
+    // Probes the successor list entries,
+    // until the first reachable one.
+    let mut probes = Vec::new();
+
+    for successor in self_node_successor_list_value.iter() {
+        let mut retries = 0;
+
+        let probe = loop {
+            match request_initiator::get_predecessor(transport, successor.get_node_addr()) {
+                ChordResponse::Predecessor(node) => break SuccessorProbe::Predecessor(node),
+                ChordResponse::Error(ErrorKind::Timeout, err)
+                    if retries < SLOW_SUCCESSOR_RETRIES =>
+                {
+                    eprintln!(
+                        "successor [{:?}] timed out during network stabilization, probing it again: {}",
+                        successor.get_public_addr(),
+                        err
+                    );
+                    retries += 1;
+                }
+                _ => {
+                    observer.successor_unreachable(successor);
+                    break SuccessorProbe::Unreachable;
+                }
+            }
+        };
+
+        let is_reachable = probe != SuccessorProbe::Unreachable;
+        probes.push((successor.clone(), probe));
+
+        if is_reachable {
+            break;
         }
-        Args::Join {
-            self_port: _,
-            public_addr: _,
-            remote_addr,
+    }
+
+    let current_successor = match stabilization::decide_successor(self_node, &probes) {
+        SuccessorDecision::Isolated => {
+            return Err(
+                "all successor list entries are unreachable during network stabilization"
+                    .to_string(),
+            )
+        }
+        SuccessorDecision::Notify(successor) => successor,
+        SuccessorDecision::AdoptIfActive {
+            candidate,
+            fallback,
         } => {
-            let successor = match request_initiator::find_successor_of_node(self_node, remote_addr) {
-                ChordResponse::Successor(node) => node,
-                ChordResponse::Error(err) => return  Err(From::from(format!("failed to locate the successor of node [{:?}]: {}", self_node.get_public_addr(), err))),
-                _ => return  Err(From::from(format!("failed to locate the successor of node [{:?}]: invalid response (protocol error)", self_node.get_public_addr()))),
-            };
+            if address_family.can_reach(candidate.get_public_addr())
+                && request_initiator::check_remote_node(transport, candidate.get_node_addr())
+                    == ChordResponse::Active
+            {
+                candidate
+            } else {
+                fallback
+            }
+        }
+    };
+
+    let remote_successor_list = match request_initiator::notify_remote_node(
+        transport,
+        self_node,
+        current_successor.get_node_addr(),
+    ) {
+        ChordResponse::SuccessorList(successor_list) => Some(successor_list),
+        ChordResponse::Error(ErrorKind::Retryable | ErrorKind::Timeout, err) => {
+            eprintln!(
+                "failed to notify the current successor [{:?}], retrying on the next pass: {}",
+                current_successor.get_public_addr(),
+                err
+            );
+            None
+        }
+        ChordResponse::Error(ErrorKind::Fatal, err) => {
+            return Err(format!(
+                "the current successor [{:?}] rejected the notification: {}",
+                current_successor.get_public_addr(),
+                err
+            ))
+        }
+        _ => {
+            return Err(
+                "the current successor is unreachable during network stabilization".to_string(),
+            )
+        }
+    };
+
+    // Updates self_node successor list, unless
+    // the notification is to be retried.
+    let new_successor_list = match remote_successor_list {
+        Some(remote_successor_list) => {
+            let unreachable_nodes = probes
+                .iter()
+                .filter(|(_, probe)| *probe == SuccessorProbe::Unreachable)
+                .map(|(successor, _)| successor.clone())
+                .collect::<Vec<_>>();
+
+            // Repaired by `build_successor_list`
+            if !utils::is_clockwise_ordered(&current_successor, &remote_successor_list) {
+                eprintln!(
+                    "the successor list of [{:?}] is misordered, sorting it by ring position",
+                    current_successor.get_public_addr()
+                );
+            }
+
+            let new_successor_list = utils::build_successor_list(
+                self_node,
+                &current_successor,
+                &remote_successor_list,
+                &unreachable_nodes,
+                address_family,
+            )?;
+
+            let mut self_node_successor_list_lock = self_node_successor_list.write().unwrap();
+            *self_node_successor_list_lock = new_successor_list.clone();
+
+            new_successor_list
+        }
+        None => self_node_successor_list_value,
+    };
 
-            let remote_successor_list = match request_initiator::get_successor_list(successor.get_public_addr()) {
+    // Checks if `self_node_predecessor` is active.
+    // If not, sets `self_node_predecessor` to `NONE`.
+    let self_node_predecessor_value = self_node_predecessor.read().unwrap().clone();
+
+    let is_predecessor_active = match self_node_predecessor_value {
+        Some(ref predecessor) => {
+            request_initiator::check_remote_node(transport, predecessor.get_node_addr())
+                == ChordResponse::Active
+        }
+        None => false,
+    };
+
+    if stabilization::decide_predecessor(
+        self_node_predecessor_value.as_ref(),
+        is_predecessor_active,
+    ) == PredecessorAction::Clear
+    {
+        let mut self_node_predecessor_lock = self_node_predecessor.write().unwrap();
+        *self_node_predecessor_lock = None;
+    }
+
+    Ok(new_successor_list)
+}
+
+/// Records the peers known by `self_node` in `peer_pool`:
+/// its fingers, its predecessor, then its successors, so
+/// that the nearest successor is the most recently seen.
+pub(crate) fn harvest_peers(
+    peer_pool: &mut PeerPool,
+    self_node_predecessor: &RwLock<Option<Node>>,
+    self_node_successor_list: &RwLock<[Node; SUCCESSOR_LIST_LENGTH]>,
+    self_node_finger_table: &RwLock<FingerTable>,
+) {
+    for (_, finger) in self_node_finger_table.read().unwrap().entries() {
+        peer_pool.record(&finger);
+    }
+
+    if let Some(ref predecessor) = *self_node_predecessor.read().unwrap() {
+        peer_pool.record(predecessor);
+    }
+
+    for successor in self_node_successor_list.read().unwrap().iter().rev() {
+        peer_pool.record(successor);
+    }
+}
+
+/// Recovers `self_node` from a failed stabilization pass (e.g.
+/// all of its successors are dead), by rebuilding
+/// `self_node_successor_list` from the first live peer of
+/// `peer_pool`, the nearest peer clockwise first.
+///
+/// The rebuilt list may skip live nodes preceding this peer: the
+/// following stabilization passes adopt them as successors.
+/// Dead peers are removed from `peer_pool`.
+///
+/// Returns the peer the node rejoined the network through.
+pub(crate) fn rejoin(
+    transport: &dyn Transport,
+    self_node: &Node,
+    self_node_successor_list: &RwLock<[Node; SUCCESSOR_LIST_LENGTH]>,
+    peer_pool: &mut PeerPool,
+    address_family: AddressFamily,
+) -> Result<Node, String> {
+    let mut peers = peer_pool.peers();
+    peers.retain(|peer| address_family.can_reach(peer.get_public_addr()));
+    peers.sort_by_key(|peer| {
+        peer.get_ring_position()
+            .wrapping_sub(self_node.get_ring_position())
+    });
+
+    for peer in peers {
+        let remote_successor_list =
+            match request_initiator::get_successor_list(transport, peer.get_node_addr()) {
                 ChordResponse::SuccessorList(successor_list) => successor_list,
-                ChordResponse::Error(err) => return Err(From::from(format!("failed to retrieve the successor list of the remote node [{:?}]: {}", successor.get_public_addr(), err))),
-                _ => return Err(From::from(format!("failed to retrieve the successor list of the remote node [{:?}]: invalid response (protocol error)", successor.get_public_addr()))),
+                _ => {
+                    peer_pool.remove(&peer);
+                    continue;
+                }
             };
 
-            let mut successor_list = Vec::new();
-            successor_list.push(successor.clone());
-            successor_list.extend_from_slice(&remote_successor_list[0..4]);
+        let successor_list = match utils::build_successor_list(
+            self_node,
+            &peer,
+            &remote_successor_list,
+            &[],
+            address_family,
+        ) {
+            Ok(successor_list) => successor_list,
+            Err(_) => continue,
+        };
+
+        *self_node_successor_list.write().unwrap() = successor_list;
+
+        return Ok(peer);
+    }
+
+    Err("no live peer to rejoin the network through".to_string())
+}
+
+/// Refreshes all the fingers of `self_node_finger_table`.
+///
+/// A finger is only looked up if the previous one does not
+/// already succeed its target, so that a refresh costs about
+/// one lookup per distinct finger. Fingers which cannot be
+/// located are cleared.
+pub(crate) fn fix_fingers(
+    transport: &dyn Transport,
+    self_node: &Node,
+    self_node_successor_list: &RwLock<[Node; SUCCESSOR_LIST_LENGTH]>,
+    self_node_finger_table: &RwLock<FingerTable>,
+) {
+    let self_node_successor_list_value = self_node_successor_list.read().unwrap().clone();
+    let mut fingers: Vec<Option<Node>> = Vec::with_capacity(self_node.get_ring_bit_length());
+
+    for index in 0..self_node.get_ring_bit_length() {
+        let target_position = FingerTable::target_position(self_node, index);
+
+        let finger = match fingers.last() {
+            Some(Some(previous_finger))
+                if RingRange::new(
+                    self_node.get_ring_position(),
+                    previous_finger.get_ring_position(),
+                )
+                .contains(target_position) =>
+            {
+                Some(previous_finger.clone())
+            }
+            // `self_node` initiates the lookup: forwarded
+            // lookups follow the redirects of iterative routing
+            _ => match request_handler::find_successor_of_key_request_handler(
+                transport,
+                self_node.clone(),
+                self_node_successor_list_value.clone(),
+                target_position,
+                None,
+                RoutingMode::Recursive,
+                None,
+            ) {
+                ChordResponse::Successor(finger) => Some(finger),
+                _ => None,
+            },
+        };
+
+        fingers.push(finger);
+    }
+
+    let mut self_node_finger_table_lock = self_node_finger_table.write().unwrap();
+
+    for (index, finger) in fingers.into_iter().enumerate() {
+        self_node_finger_table_lock.set(index, finger);
+    }
+}
+
+/// Announces to `self_node`'s successor and predecessor
+/// that `self_node` is leaving the network, so that they
+/// stop relying on it without waiting for it to time out.
+///
+/// Unreachable neighbors are ignored: their own
+/// stabilization eventually drops `self_node`.
+pub(crate) fn leave(
+    transport: &dyn Transport,
+    self_node: &Node,
+    self_node_predecessor: &RwLock<Option<Node>>,
+    self_node_successor_list: &RwLock<[Node; SUCCESSOR_LIST_LENGTH]>,
+) {
+    let mut neighbors: Vec<Node> = Vec::new();
 
-            Ok(successor_list.try_into().unwrap())
+    if let Some(successor) = self_node_successor_list
+        .read()
+        .unwrap()
+        .iter()
+        .find(|successor| *successor != self_node)
+    {
+        neighbors.push(successor.clone());
+    }
+
+    if let Some(predecessor) = self_node_predecessor.read().unwrap().clone() {
+        if predecessor != *self_node && !neighbors.contains(&predecessor) {
+            neighbors.push(predecessor);
+        }
+    }
+
+    for neighbor in neighbors {
+        if let ChordResponse::Error(_, err) =
+            request_initiator::notify_leaving(transport, self_node, neighbor.get_node_addr())
+        {
+            eprintln!(
+                "failed to notify node [{:?}] about the departure: {}",
+                neighbor.get_public_addr(),
+                err
+            );
         }
     }
 }
 
 /// Verifies if the current node's (`self_node`) public socket
 /// address refers to the specified local listener (server).
+///
+/// A sentinel byte is sent to the public socket address, and
+/// the local listener is polled until it accepts a connection
+/// delivering it, within `PUBLIC_ADDR_VERIFICATION_TIMEOUT`.
 pub(crate) fn verify_self_node_public_addr(
     self_node_public_addr: SocketAddr,
     local_listener: &TcpListener,
 ) -> Result<(), io::Error> {
-    let mut request_stream = TcpStream::connect(self_node_public_addr)?;
+    let mut request_stream =
+        TcpStream::connect_timeout(&self_node_public_addr, PUBLIC_ADDR_VERIFICATION_TIMEOUT)?;
 
-    request_stream.write(&[])?;
+    request_stream.write_all(&[PUBLIC_ADDR_SENTINEL])?;
 
     local_listener.set_nonblocking(true)?;
 
-    local_listener.accept()?;
+    let result = accept_public_addr_sentinel(local_listener);
 
     local_listener.set_nonblocking(false)?;
 
-    Ok(())
+    result
+}
+
+/// Polls the nonblocking `local_listener` until it accepts
+/// a connection delivering `PUBLIC_ADDR_SENTINEL`.
+///
+/// Other connections are dropped: the verification only
+/// succeeds once the node's own connection is received.
+fn accept_public_addr_sentinel(local_listener: &TcpListener) -> Result<(), io::Error> {
+    let deadline = Instant::now() + PUBLIC_ADDR_VERIFICATION_TIMEOUT;
+
+    loop {
+        match local_listener.accept() {
+            Ok((mut stream, _)) => {
+                // Accepted streams inherit the nonblocking
+                // mode of the listener on some platforms
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(
+                    deadline
+                        .saturating_duration_since(Instant::now())
+                        .max(PUBLIC_ADDR_VERIFICATION_POLL),
+                ))?;
+
+                let mut sentinel = [0u8; 1];
+
+                if stream.read_exact(&mut sentinel).is_ok() && sentinel[0] == PUBLIC_ADDR_SENTINEL {
+                    return Ok(());
+                }
+            }
+            Err(err)
+                if err.kind() == io::ErrorKind::WouldBlock
+                    || err.kind() == io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+
+        if Instant::now() >= deadline {
+            return Err(io::Error::new(
+                io::ErrorKind::TimedOut,
+                "no connection to the public socket address reached the local listener",
+            ));
+        }
+
+        thread::sleep(PUBLIC_ADDR_VERIFICATION_POLL);
+    }
+}
+
+#[cfg(test)]
+mod public_addr_test {
+    use std::net::TcpListener;
+
+    use super::verify_self_node_public_addr;
+
+    #[test]
+    fn own_listener_is_verified_test() {
+        let local_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        for _ in 0..20 {
+            verify_self_node_public_addr(local_listener.local_addr().unwrap(), &local_listener)
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn other_listener_is_rejected_test() {
+        let local_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let other_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+
+        let err =
+            verify_self_node_public_addr(other_listener.local_addr().unwrap(), &local_listener)
+                .unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::TimedOut);
+    }
+}
+
+#[cfg(test)]
+mod membership_policy_test {
+    use super::{MembershipPolicy, Node, SUCCESSOR_LIST_LENGTH};
+
+    #[test]
+    fn max_nodes_policy_rejects_full_ring_test() {
+        // Evenly spaced ring of 16 nodes
+        let self_node = Node::for_test_at(0);
+        let step = u128::MAX / 16 + 1;
+        let successor_list: [Node; SUCCESSOR_LIST_LENGTH] =
+            std::array::from_fn(|i| Node::for_test_at(step * (i as u128 + 1)));
+
+        assert!(MembershipPolicy::Open
+            .admit(&self_node, &successor_list)
+            .is_ok());
+        assert!(MembershipPolicy::MaxNodes(32)
+            .admit(&self_node, &successor_list)
+            .is_ok());
+
+        let err = MembershipPolicy::MaxNodes(16)
+            .admit(&self_node, &successor_list)
+            .unwrap_err();
+
+        assert!(err.contains("maximum capacity"));
+    }
+}
+
+#[cfg(test)]
+mod node_identifier_test {
+    use std::net::SocketAddr;
+
+    use crate::identity;
+
+    use super::{Node, MAX_RING_BIT_LENGTH};
+
+    #[test]
+    fn public_identity_matches_node_identifier_test() {
+        for public_addr in ["127.0.0.1:8000", "17.5.7.3:1450", "[2001:db8::1]:4040"] {
+            let public_addr = public_addr.parse::<SocketAddr>().unwrap();
+
+            for bit_length in [32, MAX_RING_BIT_LENGTH] {
+                let node = Node::new(public_addr).on_ring(bit_length);
+
+                assert_eq!(
+                    identity::ring_position_of(public_addr, bit_length),
+                    node.get_ring_position()
+                );
+                assert_eq!(
+                    identity::hash_id_of(public_addr, bit_length),
+                    node.get_hash_id()
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod ring_formation_test {
+    use std::{
+        io,
+        net::SocketAddr,
+        sync::{Arc, RwLock},
+        thread,
+    };
+
+    use crate::{
+        cli::{AddressFamily, IdentityMode, Options, RoutingMode},
+        context::NodeContext,
+        observer::NoopObserver,
+        transport::{mock::MockTransport, Transport},
+    };
+
+    use super::{
+        finger_table::FingerTable,
+        fix_fingers, harvest_peers, initialize_self_node_successor_list, key_position,
+        peer_pool::PeerPool,
+        protocol::{ChordRequest, ChordResponse, ErrorKind},
+        rejoin, request_handler, request_initiator,
+        ring_range::RingRange,
+        stabilize, utils, Node, NodeAddr, MAX_RING_BIT_LENGTH, SLOW_SUCCESSOR_RETRIES,
+        SUCCESSOR_LIST_LENGTH,
+    };
+
+    /// In-memory state of a node, served by the mock transport.
+    #[derive(Clone)]
+    struct InMemoryNode {
+        node: Node,
+        predecessor: Arc<RwLock<Option<Node>>>,
+        successor_list: Arc<RwLock<[Node; SUCCESSOR_LIST_LENGTH]>>,
+    }
+
+    impl InMemoryNode {
+        /// A node alone in its network.
+        fn init(port: u16) -> Self {
+            Self::init_virtual(port, 0)
+        }
+
+        /// Virtual node `vnode` of a process, alone in its network.
+        fn init_virtual(port: u16, vnode: u16) -> Self {
+            Self::init_node(Node::new_virtual(
+                SocketAddr::from(([10, 0, 0, 1], port)),
+                vnode,
+            ))
+        }
+
+        /// A node on a ring of `bit_length` bits, alone in its network.
+        fn init_on_ring(port: u16, bit_length: usize) -> Self {
+            Self::init_node(Node::new(SocketAddr::from(([10, 0, 0, 1], port))).on_ring(bit_length))
+        }
+
+        fn init_node(node: Node) -> Self {
+            Self {
+                predecessor: Arc::new(RwLock::new(None)),
+                successor_list: Arc::new(RwLock::new(std::array::from_fn(|_| node.clone()))),
+                node,
+            }
+        }
+
+        fn successor(&self) -> Node {
+            self.successor_list.read().unwrap()[0].clone()
+        }
+
+        fn predecessor(&self) -> Option<Node> {
+            self.predecessor.read().unwrap().clone()
+        }
+
+        fn stabilize(&self, transport: &MockTransport) {
+            stabilize(
+                transport,
+                &self.node,
+                &self.predecessor,
+                &self.successor_list,
+                AddressFamily::Any,
+                &NoopObserver,
+            )
+            .unwrap();
+        }
+
+        fn handle(&self, request: ChordRequest) -> ChordResponse {
+            match request {
+                ChordRequest::FindSuccessorOfNode(target_node, trace_id) => {
+                    request_handler::find_successor_of_node_request_handler(
+                        &NodeContext {
+                            self_node: self.node.clone(),
+                            self_node_successor_list: Arc::clone(&self.successor_list),
+                            self_node_predecessor: Arc::clone(&self.predecessor),
+                            transport: Arc::new(all_active_transport()),
+                            options: Options {
+                                routing_mode: RoutingMode::Iterative,
+                                ..Options::default()
+                            },
+                            ..NodeContext::for_test(self.node.get_public_addr())
+                        },
+                        target_node,
+                        trace_id,
+                    )
+                }
+                ChordRequest::FindSuccessorOfKey(key, trace_id) => {
+                    request_handler::find_successor_of_key_request_handler(
+                        &all_active_transport(),
+                        self.node.clone(),
+                        self.successor_list.read().unwrap().clone(),
+                        key,
+                        trace_id,
+                        RoutingMode::Iterative,
+                        None,
+                    )
+                }
+                ChordRequest::GetSuccessorList => {
+                    request_handler::get_successor_list_request_handler(
+                        self.successor_list.read().unwrap().clone(),
+                    )
+                }
+                ChordRequest::GetPredecessor => {
+                    request_handler::get_predecessor_request_handler(self.predecessor())
+                }
+                ChordRequest::NotificationBy(external_node) => {
+                    request_handler::node_notification_request_handler(
+                        self.node.clone(),
+                        Arc::clone(&self.predecessor),
+                        self.successor_list.read().unwrap().clone(),
+                        external_node,
+                        IdentityMode::Hashed,
+                        &NoopObserver,
+                    )
+                }
+                ChordRequest::CheckNode => request_handler::check_node_request_handler(),
+                _ => ChordResponse::Error(ErrorKind::Fatal, "unsupported request".to_string()),
+            }
+        }
+    }
+
+    /// Transport used by the in-memory nodes to check their
+    /// lookup candidates: lookups (of keys and of joining nodes,
+    /// e.g. joining concurrently through a seed) are redirected
+    /// to the closest preceding entry, which the initiator then
+    /// contacts (and fails to, if the entry is dead).
+    fn all_active_transport() -> MockTransport {
+        MockTransport::new(|_, _| Ok(ChordResponse::Active.to_protocol_text()))
+    }
+
+    /// Mock transport routing each request
+    /// to the addressed in-memory node.
+    fn transport(nodes: &[InMemoryNode]) -> MockTransport {
+        let nodes = nodes.to_vec();
+
+        MockTransport::new(move |remote_addr, request_msg| {
+            let (vnode, request_msg) = NodeAddr::split_request(request_msg)
+                .ok_or_else(|| io::Error::from(io::ErrorKind::InvalidData))?;
+
+            let node = nodes
+                .iter()
+                .find(|node| node.node.get_node_addr() == NodeAddr::new(remote_addr, vnode))
+                .ok_or_else(|| io::Error::from(io::ErrorKind::ConnectionRefused))?;
+
+            let request = ChordRequest::parse(request_msg)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+            Ok(node.handle(request).to_protocol_text())
+        })
+    }
+
+    /// Joins `joining_node` to the network of `init_node`.
+    fn join(transport: &MockTransport, joining_node: &InMemoryNode, init_node: &InMemoryNode) {
+        let join_outcome = initialize_self_node_successor_list(
+            transport,
+            &joining_node.node,
+            Some(init_node.node.get_node_addr()),
+            AddressFamily::Any,
+        )
+        .unwrap();
+        *joining_node.successor_list.write().unwrap() = join_outcome.successor_list;
+    }
+
+    /// Joins a node to a network whose first lookup fails with
+    /// `error`, and returns whether the join succeeded along
+    /// with the number of lookups sent.
+    fn join_after_error(error: ChordResponse) -> (bool, usize) {
+        let a = InMemoryNode::init(8000);
+        let b = InMemoryNode::init(8001);
+        let nodes = transport(std::slice::from_ref(&a));
+        let lookups = Arc::new(RwLock::new(0));
+
+        let transport = {
+            let lookups = Arc::clone(&lookups);
+
+            MockTransport::new(move |remote_addr, request_msg| {
+                if let Ok(ChordRequest::FindSuccessorOfNode(..)) = ChordRequest::parse(request_msg)
+                {
+                    let mut lookups = lookups.write().unwrap();
+                    *lookups += 1;
+
+                    if *lookups == 1 {
+                        return Ok(error.to_protocol_text());
+                    }
+                }
+
+                nodes.send(remote_addr, request_msg)
+            })
+        };
+
+        let is_joined = initialize_self_node_successor_list(
+            &transport,
+            &b.node,
+            Some(a.node.get_node_addr()),
+            AddressFamily::Any,
+        )
+        .is_ok();
+        let lookups = *lookups.read().unwrap();
+
+        (is_joined, lookups)
+    }
+
+    #[test]
+    fn join_retries_only_retryable_errors_test() {
+        let retryable_error =
+            ChordResponse::Error(ErrorKind::Retryable, "too many connections".to_string());
+        assert_eq!(join_after_error(retryable_error), (true, 2));
+
+        let fatal_error = ChordResponse::Error(
+            ErrorKind::Fatal,
+            "the node's identifier already exists in the network".to_string(),
+        );
+        assert_eq!(join_after_error(fatal_error), (false, 1));
+    }
+
+    fn assert_two_node_ring(a: &InMemoryNode, b: &InMemoryNode) {
+        assert_eq!(a.successor(), b.node);
+        assert_eq!(a.predecessor(), Some(b.node.clone()));
+        assert_eq!(b.successor(), a.node);
+        assert_eq!(b.predecessor(), Some(a.node.clone()));
+    }
+
+    #[test]
+    fn join_outcome_test() {
+        let a = InMemoryNode::init(8000);
+        let b = InMemoryNode::init(8001);
+        let transport = transport(&[a.clone(), b.clone()]);
+
+        let init_outcome =
+            initialize_self_node_successor_list(&transport, &a.node, None, AddressFamily::Any)
+                .unwrap();
+        assert_eq!(init_outcome.successor, a.node);
+        assert_eq!(init_outcome.ring_position, a.node.get_identifier());
+        assert!(init_outcome.is_first_node);
+
+        let join_outcome = initialize_self_node_successor_list(
+            &transport,
+            &b.node,
+            Some(a.node.get_node_addr()),
+            AddressFamily::Any,
+        )
+        .unwrap();
+        assert_eq!(join_outcome.successor, a.node);
+        assert_eq!(join_outcome.successor, join_outcome.successor_list[0]);
+        assert_eq!(join_outcome.ring_position, b.node.get_identifier());
+        assert!(!join_outcome.is_first_node);
+
+        // Logged by the joining node
+        assert_eq!(
+            init_outcome.to_string(),
+            format!(
+                "first node of the ring, at position {}",
+                a.node.get_identifier()
+            )
+        );
+        assert_eq!(
+            join_outcome.to_string(),
+            format!(
+                "at position {}, before successor [10.0.0.1:8000]",
+                b.node.get_identifier()
+            )
+        );
+    }
+
+    #[test]
+    fn lone_node_has_no_predecessor_test() {
+        let a = InMemoryNode::init(8000);
+        let transport = transport(std::slice::from_ref(&a));
+
+        for _ in 0..3 {
+            a.stabilize(&transport);
+        }
+
+        assert_eq!(a.successor(), a.node);
+        assert_eq!(a.predecessor(), None);
+    }
+
+    #[test]
+    fn joining_node_stabilizes_first_test() {
+        let a = InMemoryNode::init(8000);
+        let b = InMemoryNode::init(8001);
+        let transport = transport(&[a.clone(), b.clone()]);
+
+        a.stabilize(&transport);
+        join(&transport, &b, &a);
+
+        assert_eq!(b.successor(), a.node);
+        assert_eq!(b.predecessor(), None);
+
+        b.stabilize(&transport);
+        assert_eq!(a.predecessor(), Some(b.node.clone()));
+
+        a.stabilize(&transport);
+        assert_two_node_ring(&a, &b);
+
+        // The ring is stable
+        a.stabilize(&transport);
+        b.stabilize(&transport);
+        assert_two_node_ring(&a, &b);
+    }
+
+    #[test]
+    fn init_node_stabilizes_first_test() {
+        let a = InMemoryNode::init(8000);
+        let b = InMemoryNode::init(8001);
+        let transport = transport(&[a.clone(), b.clone()]);
+
+        join(&transport, &b, &a);
+
+        // `a` does not know `b` yet
+        a.stabilize(&transport);
+        assert_eq!(a.successor(), a.node);
+        assert_eq!(a.predecessor(), None);
+
+        b.stabilize(&transport);
+        a.stabilize(&transport);
+        assert_two_node_ring(&a, &b);
+
+        b.stabilize(&transport);
+        assert_two_node_ring(&a, &b);
+    }
+
+    #[test]
+    fn two_node_ring_successor_lists_test() {
+        let a = InMemoryNode::init(8000);
+        let b = InMemoryNode::init(8001);
+        let transport = transport(&[a.clone(), b.clone()]);
+
+        // The init node lists itself only: its entries are not
+        // borrowed, the list of `b` is padded with `b` instead
+        join(&transport, &b, &a);
+        assert_eq!(
+            *b.successor_list.read().unwrap(),
+            [&a.node, &b.node, &b.node, &b.node, &b.node].map(Node::clone)
+        );
+
+        for _ in 0..3 {
+            b.stabilize(&transport);
+            a.stabilize(&transport);
+            assert_eq!(
+                *a.successor_list.read().unwrap(),
+                [&b.node, &a.node, &a.node, &a.node, &a.node].map(Node::clone)
+            );
+            assert_eq!(
+                *b.successor_list.read().unwrap(),
+                [&a.node, &b.node, &b.node, &b.node, &b.node].map(Node::clone)
+            );
+        }
+
+        assert_two_node_ring(&a, &b);
+    }
+
+    /// Forms a ring of nodes listening on `ports`,
+    /// returned in ring order.
+    fn form_ring(ports: std::ops::Range<u16>) -> Vec<InMemoryNode> {
+        form_ring_of(ports.map(InMemoryNode::init).collect())
+    }
+
+    /// Same as `form_ring`, with the given nodes.
+    fn form_ring_of(mut nodes: Vec<InMemoryNode>) -> Vec<InMemoryNode> {
+        let transport = transport(&nodes);
+
+        // Each node joins through the node preceding it, which
+        // answers the lookup without forwarding it
+        for (i, joining_node) in nodes.iter().enumerate().skip(1) {
+            let ring = nodes[..i]
+                .iter()
+                .map(|node| node.node.clone())
+                .collect::<Vec<_>>();
+            let successor = utils::owner_of(joining_node.node.get_ring_position(), &ring);
+            let predecessor = nodes[..i]
+                .iter()
+                .find(|node| node.successor() == *successor)
+                .unwrap();
+
+            join(&transport, joining_node, predecessor);
+
+            for _ in 0..i {
+                for node in nodes[..=i].iter() {
+                    node.stabilize(&transport);
+                }
+            }
+        }
+
+        nodes.sort_by_key(|node| node.node.get_ring_position());
+        nodes
+    }
+
+    /// Asserts that the lookups of the keys at and right after
+    /// each node's position, issued to each node, resolve to
+    /// their owner among `nodes`.
+    fn assert_lookups_resolve(transport: &MockTransport, nodes: &[InMemoryNode]) {
+        let ring = nodes
+            .iter()
+            .map(|node| node.node.clone())
+            .collect::<Vec<_>>();
+
+        for node in nodes {
+            for key in ring.iter().flat_map(|n| {
+                let position = n.get_ring_position();
+                [position, position.wrapping_add(1)]
+            }) {
+                assert_eq!(
+                    request_initiator::find_successor_of_key(
+                        transport,
+                        key,
+                        node.node.get_node_addr(),
+                        None
+                    ),
+                    ChordResponse::Successor(utils::owner_of(key, &ring).clone()),
+                    "lookup of key [{:032x}] at [{:?}]",
+                    key,
+                    node.node.get_public_addr()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn application_key_is_located_test() {
+        let key = u128::from_be_bytes(key_position(b"user:42", MAX_RING_BIT_LENGTH));
+        assert_eq!(key, 0x56dadf1868c3ba340467c942a86c29de);
+
+        let nodes = form_ring(8000..8007);
+        let transport = transport(&nodes);
+
+        // The first node at or after the key's position,
+        // clockwise, nodes being in ring order
+        let owner = nodes
+            .iter()
+            .find(|node| node.node.get_ring_position() >= key)
+            .unwrap_or(&nodes[0]);
+
+        for node in nodes.iter() {
+            assert_eq!(
+                request_initiator::find_successor_of_key(
+                    &transport,
+                    key,
+                    node.node.get_node_addr(),
+                    None
+                ),
+                ChordResponse::Successor(owner.node.clone())
+            );
+        }
+    }
+
+    #[test]
+    fn ring_of_32_bits_resolves_lookups_test() {
+        let nodes = form_ring_of(
+            (8000..8003)
+                .map(|port| InMemoryNode::init_on_ring(port, 32))
+                .collect(),
+        );
+        let transport = transport(&nodes);
+        let ring = nodes
+            .iter()
+            .map(|node| node.node.clone())
+            .collect::<Vec<_>>();
+
+        for node in nodes.iter() {
+            assert_eq!(node.node.get_hash_id().len(), 8);
+            assert_eq!(node.predecessor().unwrap().get_ring_bit_length(), 32);
+            assert_eq!(node.successor().get_ring_bit_length(), 32);
+        }
+
+        // Keys at, and right after, the position of each node
+        // on the ring of 32 bits
+        for node in nodes.iter() {
+            for key in ring.iter().flat_map(|n| {
+                let position = n.get_ring_position();
+                [position, position.wrapping_add(1 << 96)]
+            }) {
+                assert_eq!(
+                    request_initiator::find_successor_of_key(
+                        &transport,
+                        key,
+                        node.node.get_node_addr(),
+                        None
+                    ),
+                    ChordResponse::Successor(utils::owner_of(key, &ring).clone())
+                );
+            }
+        }
+
+        // Keys are written with at most 8 hexadecimal digits
+        for (_, request) in transport.sent_requests() {
+            let key = request
+                .strip_prefix("FIND_SUCCESSOR_OF_KEY=[")
+                .and_then(|request| request.strip_suffix("];"))
+                .unwrap();
+            assert!(key.len() <= 8, "request [{}]", request);
+        }
+    }
+
+    #[test]
+    fn successor_lists_hold_distinct_nodes_test() {
+        let nodes = form_ring(8000..8007);
+
+        let ring = nodes
+            .iter()
+            .map(|node| node.node.clone())
+            .collect::<Vec<_>>();
+
+        for node in nodes.iter() {
+            let successor_list = node.successor_list.read().unwrap().clone();
+            let position = ring.iter().position(|n| *n == node.node).unwrap();
+
+            let expected_successor_list: [Node; SUCCESSOR_LIST_LENGTH] =
+                std::array::from_fn(|i| ring[(position + i + 1) % ring.len()].clone());
+
+            // Five distinct nodes, the ring being large enough
+            assert_eq!(successor_list, expected_successor_list);
+        }
+
+        assert_lookups_resolve(&transport(&nodes), &nodes);
+    }
+
+    #[test]
+    fn owned_ranges_tile_the_ring_test() {
+        // A node alone in its network owns the whole ring
+        let single_node = InMemoryNode::init(8000);
+        let single_node_position = single_node.node.get_ring_position();
+
+        assert_eq!(
+            request_handler::get_owned_range_request_handler(
+                &single_node.node,
+                single_node.predecessor()
+            ),
+            ChordResponse::OwnedRange(RingRange::new(single_node_position, single_node_position))
+        );
+
+        let nodes = form_ring(8000..8007);
+
+        let owned_ranges = nodes
+            .iter()
+            .map(|node| {
+                match request_handler::get_owned_range_request_handler(
+                    &node.node,
+                    node.predecessor(),
+                ) {
+                    ChordResponse::OwnedRange(owned_range) => owned_range,
+                    response => panic!("unexpected response: {:?}", response),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        // Each range starts where the previous one ends
+        for (i, owned_range) in owned_ranges.iter().enumerate() {
+            let previous_range = owned_ranges[(i + owned_ranges.len() - 1) % owned_ranges.len()];
+
+            assert_eq!(owned_range.end(), nodes[i].node.get_ring_position());
+            assert_eq!(owned_range.start(), previous_range.end());
+        }
+
+        // Every key is owned by exactly one node
+        for key in nodes.iter().flat_map(|node| {
+            let position = node.node.get_ring_position();
+            [position.wrapping_sub(1), position, position.wrapping_add(1)]
+        }) {
+            assert_eq!(
+                owned_ranges
+                    .iter()
+                    .filter(|owned_range| owned_range.contains(key))
+                    .count(),
+                1
+            );
+        }
+    }
+
+    #[test]
+    fn virtual_nodes_take_distinct_positions_test() {
+        const VNODES: u16 = 8;
+
+        // A process running 8 virtual nodes, and a process running one
+        let nodes = form_ring_of(
+            (0..VNODES)
+                .map(|vnode| InMemoryNode::init_virtual(8000, vnode))
+                .chain([InMemoryNode::init(8001)])
+                .collect(),
+        );
+
+        let mut positions = nodes
+            .iter()
+            .map(|node| node.node.get_ring_position())
+            .collect::<Vec<_>>();
+        positions.dedup();
+        assert_eq!(positions.len(), VNODES as usize + 1);
+
+        // Each virtual node owns the keys
+        // from its predecessor, itself included
+        for (i, node) in nodes.iter().enumerate() {
+            let previous_node = &nodes[(i + nodes.len() - 1) % nodes.len()];
+
+            assert_eq!(
+                request_handler::get_owned_range_request_handler(&node.node, node.predecessor()),
+                ChordResponse::OwnedRange(RingRange::new(
+                    previous_node.node.get_ring_position(),
+                    node.node.get_ring_position()
+                ))
+            );
+        }
+
+        // Lookups address the virtual nodes by index
+        assert_lookups_resolve(&transport(&nodes), &nodes);
+    }
+
+    #[test]
+    fn weighted_virtual_nodes_own_proportional_shares_test() {
+        // A large process running 32 virtual nodes,
+        // and two small ones running 4 each
+        let vnodes = [(8000, 32), (8001, 4), (8002, 4)];
+
+        let mut nodes = vnodes
+            .iter()
+            .flat_map(|&(port, vnodes)| {
+                (0..vnodes).map(move |vnode| {
+                    Node::new_virtual(SocketAddr::from(([10, 0, 0, 1], port)), vnode)
+                })
+            })
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|node| node.get_ring_position());
+
+        // Share of the ring owned by each process, its virtual nodes summed up
+        let share_of = |port: u16| {
+            nodes
+                .iter()
+                .enumerate()
+                .filter(|(_, node)| node.get_public_addr().port() == port)
+                .map(|(i, node)| {
+                    let predecessor = nodes[(i + nodes.len() - 1) % nodes.len()].clone();
+
+                    match request_handler::get_owned_range_request_handler(node, Some(predecessor))
+                    {
+                        ChordResponse::OwnedRange(owned_range) => {
+                            owned_range.end().wrapping_sub(owned_range.start()) as f64
+                                / 2f64.powi(128)
+                        }
+                        response => panic!("unexpected response: {:?}", response),
+                    }
+                })
+                .sum::<f64>()
+        };
+
+        let total_vnodes = vnodes.iter().map(|(_, vnodes)| *vnodes).sum::<u16>() as f64;
+
+        // Each process owns about its weight, hash
+        // positions spreading its virtual nodes unevenly
+        for (port, vnodes) in vnodes {
+            let weight = vnodes as f64 / total_vnodes;
+
+            assert!(
+                (share_of(port) - weight).abs() < 0.1,
+                "process {} owns {:.3} of the ring, for a weight of {:.3}",
+                port,
+                share_of(port),
+                weight
+            );
+        }
+
+        assert!(share_of(8000) > 4.0 * share_of(8001).max(share_of(8002)));
+    }
+
+    #[test]
+    fn concurrent_joins_form_consistent_ring_test() {
+        let seed = InMemoryNode::init(8000);
+        let joining_nodes = (8001..8012).map(InMemoryNode::init).collect::<Vec<_>>();
+        let mut nodes = [vec![seed.clone()], joining_nodes.clone()].concat();
+        let transport = Arc::new(transport(&nodes));
+
+        // Every node joins through the seed at once, while
+        // the seed and the joined nodes keep stabilizing
+        let threads = nodes
+            .iter()
+            .cloned()
+            .map(|node| {
+                let transport = Arc::clone(&transport);
+                let seed = seed.clone();
+
+                thread::spawn(move || {
+                    if node.node != seed.node {
+                        join(&transport, &node, &seed);
+                    }
+
+                    for _ in 0..12 {
+                        node.stabilize(&transport);
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for thread in threads {
+            thread.join().unwrap();
+        }
+
+        for _ in 0..nodes.len() {
+            for node in nodes.iter() {
+                node.stabilize(&transport);
+            }
+        }
+
+        nodes.sort_by_key(|node| node.node.get_ring_position());
+
+        for (i, node) in nodes.iter().enumerate() {
+            let next = &nodes[(i + 1) % nodes.len()];
+            let previous = &nodes[(i + nodes.len() - 1) % nodes.len()];
+
+            assert_eq!(node.successor(), next.node);
+            assert_eq!(node.predecessor(), Some(previous.node.clone()));
+        }
+
+        assert_lookups_resolve(&transport, &nodes);
+    }
+
+    #[test]
+    fn ring_shrinks_after_mass_node_death_test() {
+        let nodes = form_ring(8000..8007);
+
+        // All nodes but two die. The survivors are not adjacent,
+        // so that each one still has the other in its list.
+        let (a, b) = (nodes[0].clone(), nodes[3].clone());
+        let transport = transport(&[a.clone(), b.clone()]);
+
+        for _ in 0..3 {
+            a.stabilize(&transport);
+            b.stabilize(&transport);
+        }
+
+        // Dead nodes are dropped, the remainder is padded with self_node
+        for (node, other) in [(&a, &b), (&b, &a)] {
+            let expected_successor_list: [Node; SUCCESSOR_LIST_LENGTH] = std::array::from_fn(|i| {
+                if i == 0 {
+                    other.node.clone()
+                } else {
+                    node.node.clone()
+                }
+            });
+
+            assert_eq!(
+                node.successor_list.read().unwrap().clone(),
+                expected_successor_list
+            );
+        }
+
+        assert_two_node_ring(&a, &b);
+        assert_lookups_resolve(&transport, &[a.clone(), b]);
+
+        // The last survivor is alone in its ring
+        let transport = self::transport(std::slice::from_ref(&a));
+
+        for _ in 0..2 {
+            a.stabilize(&transport);
+        }
+
+        assert_eq!(
+            a.successor_list.read().unwrap().clone(),
+            std::array::from_fn(|_| a.node.clone())
+        );
+        assert_eq!(a.predecessor(), None);
+        assert_lookups_resolve(&transport, std::slice::from_ref(&a));
+    }
+
+    #[test]
+    fn isolated_node_rejoins_through_learned_peer_test() {
+        let nodes = form_ring(8000..8008);
+        let a = nodes[0].clone();
+
+        // Besides its successors, `a` learned about
+        // its predecessor through its notifications
+        let mut peer_pool = PeerPool::new(a.node.clone());
+        harvest_peers(
+            &mut peer_pool,
+            &a.predecessor,
+            &a.successor_list,
+            &RwLock::new(FingerTable::new(MAX_RING_BIT_LENGTH)),
+        );
+
+        // Churn: all the successors of `a` die, including
+        // any node it could have joined the network through
+        let survivors = [a.clone(), nodes[6].clone(), nodes[7].clone()];
+        let transport = transport(&survivors);
+
+        assert!(stabilize(
+            &transport,
+            &a.node,
+            &a.predecessor,
+            &a.successor_list,
+            AddressFamily::Any,
+            &NoopObserver,
+        )
+        .is_err());
+
+        assert_eq!(
+            rejoin(
+                &transport,
+                &a.node,
+                &a.successor_list,
+                &mut peer_pool,
+                AddressFamily::Any,
+            ),
+            Ok(nodes[7].node.clone())
+        );
+
+        // Dead peers are forgotten
+        assert_eq!(peer_pool.peers(), [nodes[7].node.clone()]);
+
+        for _ in 0..3 {
+            for node in survivors.iter() {
+                node.stabilize(&transport);
+            }
+        }
+
+        for (i, node) in survivors.iter().enumerate() {
+            let next_node = &survivors[(i + 1) % survivors.len()];
+            let previous_node = &survivors[(i + survivors.len() - 1) % survivors.len()];
+
+            assert_eq!(node.successor(), next_node.node);
+            assert_eq!(node.predecessor(), Some(previous_node.node.clone()));
+        }
+
+        assert_lookups_resolve(&transport, &survivors);
+    }
+
+    #[test]
+    fn restarted_node_rejoins_test() {
+        let nodes = form_ring(8000..8003);
+
+        // The node restarts with the same address,
+        // without memory of its place in the ring
+        let restarted_node = InMemoryNode::init(nodes[1].node.get_public_addr().port());
+        let nodes = [nodes[0].clone(), restarted_node, nodes[2].clone()];
+        let transport = transport(&nodes);
+
+        join(&transport, &nodes[1], &nodes[0]);
+        assert_eq!(nodes[1].successor(), nodes[2].node);
+
+        for _ in 0..2 {
+            for node in nodes.iter() {
+                node.stabilize(&transport);
+            }
+        }
+
+        for (i, node) in nodes.iter().enumerate() {
+            assert_eq!(node.successor(), nodes[(i + 1) % nodes.len()].node);
+            assert_eq!(
+                node.predecessor(),
+                Some(nodes[(i + nodes.len() - 1) % nodes.len()].node.clone())
+            );
+        }
+
+        assert_lookups_resolve(&transport, &nodes);
+    }
+
+    #[test]
+    fn fingers_of_two_node_ring_test() {
+        let a = InMemoryNode::init(8000);
+        let b = InMemoryNode::init(8001);
+        let transport = transport(&[a.clone(), b.clone()]);
+
+        join(&transport, &b, &a);
+        b.stabilize(&transport);
+        a.stabilize(&transport);
+
+        let finger_table = RwLock::new(FingerTable::new(MAX_RING_BIT_LENGTH));
+        fix_fingers(&transport, &a.node, &a.successor_list, &finger_table);
+
+        let fingers = finger_table.read().unwrap().entries();
+        assert_eq!(fingers.len(), MAX_RING_BIT_LENGTH);
+
+        let ring = [a.node.clone(), b.node.clone()];
+
+        // Each finger is the owner of its target
+        for (index, finger) in fingers {
+            let target_position = FingerTable::target_position(&a.node, index);
+            let owner = utils::owner_of(target_position, &ring);
+
+            assert_eq!(finger, *owner, "finger {}", index);
+        }
+    }
+
+    /// Stabilizes the first node of a ring of three, whose
+    /// successor fails the first `failures` probes with `error`,
+    /// and returns the ring along with the probed addresses.
+    fn stabilize_with_failing_successor(
+        failures: usize,
+        error: io::ErrorKind,
+    ) -> (Vec<InMemoryNode>, Vec<SocketAddr>) {
+        let nodes = form_ring(8000..8003);
+        let ring = transport(&nodes);
+
+        let successor_addr = nodes[1].node.get_public_addr();
+        let failed_probes = Arc::new(RwLock::new(0));
+
+        let transport = MockTransport::new(move |remote_addr, request_msg| {
+            if remote_addr == successor_addr
+                && ChordRequest::parse(request_msg) == Ok(ChordRequest::GetPredecessor)
+            {
+                let mut failed_probes = failed_probes.write().unwrap();
+
+                if *failed_probes < failures {
+                    *failed_probes += 1;
+                    return Err(io::Error::from(error));
+                }
+            }
+
+            ring.send(remote_addr, request_msg)
+        });
+
+        nodes[0].stabilize(&transport);
+
+        let probed_addrs = transport
+            .sent_requests()
+            .into_iter()
+            .filter(|(_, request_msg)| {
+                ChordRequest::parse(request_msg) == Ok(ChordRequest::GetPredecessor)
+            })
+            .map(|(remote_addr, _)| remote_addr)
+            .collect();
+
+        (nodes, probed_addrs)
+    }
+
+    #[test]
+    fn slow_successor_is_retried_refused_one_failed_over_test() {
+        // A successor timing out once is probed again, and kept
+        let (nodes, probed_addrs) = stabilize_with_failing_successor(1, io::ErrorKind::WouldBlock);
+        let (b, c) = (
+            nodes[1].node.get_public_addr(),
+            nodes[2].node.get_public_addr(),
+        );
+
+        assert_eq!(probed_addrs, [b, b]);
+        assert_eq!(nodes[0].successor(), nodes[1].node);
+
+        // A successor refusing the connection is
+        // failed over right away, to the next entry
+        let (_, probed_addrs) =
+            stabilize_with_failing_successor(1, io::ErrorKind::ConnectionRefused);
+        assert_eq!(probed_addrs, [b, c]);
+
+        // A successor still timing out is failed over eventually
+        let (_, probed_addrs) =
+            stabilize_with_failing_successor(usize::MAX, io::ErrorKind::TimedOut);
+        let mut expected_probed_addrs = vec![b; SLOW_SUCCESSOR_RETRIES as usize + 1];
+        expected_probed_addrs.push(c);
+        assert_eq!(probed_addrs, expected_probed_addrs);
+    }
+
+    #[test]
+    fn ipv4_node_skips_ipv6_successors_test() {
+        let v4_node_at = |position: u128, host: u8| {
+            Node::create_from(
+                position.to_be_bytes(),
+                SocketAddr::from(([10, 0, 0, host], 8000)),
+            )
+        };
+        let v6_node_at = |position: u128, host: u16| {
+            Node::create_from(
+                position.to_be_bytes(),
+                SocketAddr::from(([0x2001, 0xdb8, 0, 0, 0, 0, 0, host], 8000)),
+            )
+        };
+
+        let self_node = v4_node_at(100, 1);
+        // Dual-stack successor, whose predecessor and
+        // successor list mix IPv4 and IPv6 nodes
+        let successor = v4_node_at(200, 2);
+        let successor_predecessor = v6_node_at(150, 3);
+        let successor_successor_list = [
+            v6_node_at(300, 4),
+            v4_node_at(400, 5),
+            v6_node_at(500, 6),
+            v4_node_at(600, 7),
+            v4_node_at(700, 8),
+        ];
+
+        let transport = MockTransport::new(move |_, request_msg| {
+            let response = match ChordRequest::parse(request_msg).unwrap() {
+                ChordRequest::GetPredecessor => {
+                    ChordResponse::Predecessor(Some(successor_predecessor.clone()))
+                }
+                ChordRequest::NotificationBy(_) => {
+                    ChordResponse::SuccessorList(successor_successor_list.clone())
+                }
+                _ => ChordResponse::Active,
+            };
+
+            Ok(response.to_protocol_text())
+        });
+
+        let self_node_predecessor = RwLock::new(None);
+        let self_node_successor_list = RwLock::new(std::array::from_fn(|_| successor.clone()));
+
+        let successor_list = stabilize(
+            &transport,
+            &self_node,
+            &self_node_predecessor,
+            &self_node_successor_list,
+            AddressFamily::Ipv4,
+            &NoopObserver,
+        )
+        .unwrap();
+
+        assert_eq!(
+            successor_list,
+            [
+                successor,
+                v4_node_at(400, 5),
+                v4_node_at(600, 7),
+                v4_node_at(700, 8),
+                self_node,
+            ]
+        );
+
+        // The IPv6 predecessor of the successor is not even contacted
+        assert!(transport
+            .sent_requests()
+            .iter()
+            .all(|(remote_addr, _)| remote_addr.is_ipv4()));
+    }
+
+    #[test]
+    fn misordered_remote_successor_list_is_sorted_test() {
+        let self_node = Node::for_test_at(100);
+        let successor = Node::for_test_at(200);
+
+        // Scrambled by a buggy or malicious successor
+        let successor_successor_list = [
+            Node::for_test_at(500),
+            Node::for_test_at(300),
+            Node::for_test_at(600),
+            Node::for_test_at(400),
+            Node::for_test_at(700),
+        ];
+
+        let transport = MockTransport::new(move |_, request_msg| {
+            let response = match ChordRequest::parse(request_msg).unwrap() {
+                ChordRequest::GetPredecessor => {
+                    ChordResponse::Predecessor(Some(Node::for_test_at(100)))
+                }
+                ChordRequest::NotificationBy(_) => {
+                    ChordResponse::SuccessorList(successor_successor_list.clone())
+                }
+                _ => ChordResponse::Active,
+            };
+
+            Ok(response.to_protocol_text())
+        });
+
+        let self_node_predecessor = RwLock::new(None);
+        let self_node_successor_list = RwLock::new(std::array::from_fn(|_| successor.clone()));
+
+        let successor_list = stabilize(
+            &transport,
+            &self_node,
+            &self_node_predecessor,
+            &self_node_successor_list,
+            AddressFamily::Any,
+            &NoopObserver,
+        )
+        .unwrap();
+
+        assert_eq!(
+            successor_list,
+            [
+                successor,
+                Node::for_test_at(300),
+                Node::for_test_at(400),
+                Node::for_test_at(500),
+                Node::for_test_at(600),
+            ]
+        );
+        assert_eq!(*self_node_successor_list.read().unwrap(), successor_list);
+    }
 }
@@ -0,0 +1,44 @@
+//! Metadata of a running node (`GET_NODE_INFO`), for fleet
+//! management: before a rolling change, the members of a ring
+//! are audited for mismatched versions or ring parameters,
+//! which the nodes would not agree on.
+
+use std::time::Duration;
+
+use super::SUCCESSOR_LIST_LENGTH;
+
+/// Version of the text protocol between nodes,
+/// bumped on a change older nodes do not understand.
+pub(crate) const PROTOCOL_VERSION: u32 = 2;
+
+/// Hash function of the identifiers (see `identity`).
+pub(crate) const HASH_FUNCTION: &str = "MD5";
+
+/// Version, uptime and ring parameters of a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct NodeInfo {
+    /// Version of the node crate.
+    pub version: String,
+    pub protocol_version: u32,
+    /// Time since the process of the node started,
+    /// to the second.
+    pub uptime: Duration,
+    pub ring_bit_length: usize,
+    pub successor_list_length: usize,
+    pub hash_function: String,
+}
+
+impl NodeInfo {
+    /// Returns the metadata of this node, on a ring of
+    /// `ring_bit_length` bits, whose process started `uptime` ago.
+    pub(crate) fn current(uptime: Duration, ring_bit_length: usize) -> Self {
+        Self {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            protocol_version: PROTOCOL_VERSION,
+            uptime: Duration::from_secs(uptime.as_secs()),
+            ring_bit_length,
+            successor_list_length: SUCCESSOR_LIST_LENGTH,
+            hash_function: HASH_FUNCTION.to_string(),
+        }
+    }
+}
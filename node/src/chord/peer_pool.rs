@@ -0,0 +1,94 @@
+//! Pool of recently seen live peers of a node.
+//!
+//! The peers are harvested from the successor list, the predecessor
+//! and the finger table after each stabilization pass. They are the
+//! fallback contacts of a node which lost all of its successors,
+//! once the node it joined through may have left the network.
+
+use std::collections::VecDeque;
+
+use super::Node;
+
+/// Maximum number of peers in a pool.
+pub(crate) const PEER_POOL_CAPACITY: usize = 16;
+
+/// Recently seen live peers of `self_node`,
+/// most recently seen first.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct PeerPool {
+    self_node: Node,
+    peers: VecDeque<Node>,
+}
+
+impl PeerPool {
+    /// Creates an empty pool for `self_node`.
+    pub(crate) fn new(self_node: Node) -> Self {
+        Self {
+            self_node,
+            peers: VecDeque::with_capacity(PEER_POOL_CAPACITY),
+        }
+    }
+
+    /// Records `peer` as the most recently seen peer.
+    ///
+    /// The least recently seen peer is evicted once the
+    /// pool is full. `self_node` is never recorded.
+    pub(crate) fn record(&mut self, peer: &Node) {
+        if *peer == self.self_node {
+            return;
+        }
+
+        self.remove(peer);
+
+        if self.peers.len() == PEER_POOL_CAPACITY {
+            self.peers.pop_back();
+        }
+
+        self.peers.push_front(peer.clone());
+    }
+
+    /// Forgets `peer`, found dead.
+    pub(crate) fn remove(&mut self, peer: &Node) {
+        self.peers.retain(|entry| entry != peer);
+    }
+
+    /// Returns the peers, most recently seen first.
+    pub(crate) fn peers(&self) -> Vec<Node> {
+        self.peers.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod peer_pool_test {
+    use crate::chord::Node;
+
+    use super::{PeerPool, PEER_POOL_CAPACITY};
+
+    #[test]
+    fn least_recently_seen_peer_is_evicted_test() {
+        let mut peer_pool = PeerPool::new(Node::for_test_at(1));
+
+        for position in 0..PEER_POOL_CAPACITY as u128 {
+            peer_pool.record(&Node::for_test_at(100 + position));
+        }
+
+        // Seen again, the oldest peer is kept
+        peer_pool.record(&Node::for_test_at(100));
+        peer_pool.record(&Node::for_test_at(1000));
+        // `self_node` is not a peer
+        peer_pool.record(&Node::for_test_at(1));
+
+        let peers = peer_pool.peers();
+
+        assert_eq!(peers.len(), PEER_POOL_CAPACITY);
+        assert_eq!(
+            peers[..2],
+            [Node::for_test_at(1000), Node::for_test_at(100)]
+        );
+        assert!(!peers.contains(&Node::for_test_at(101)));
+        assert!(!peers.contains(&Node::for_test_at(1)));
+
+        peer_pool.remove(&Node::for_test_at(100));
+        assert!(!peer_pool.peers().contains(&Node::for_test_at(100)));
+    }
+}
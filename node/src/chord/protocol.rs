@@ -2,21 +2,79 @@
 //!
 //! A custom and application-specific protocol tailored to the Chord mechanism.
 
-use std::net::SocketAddr;
-
 use regex::Regex;
 
-use super::{Node, SUCCESSOR_LIST_LENGTH};
+use std::{sync::LazyLock, time::Duration};
+
+use super::{
+    node_info::NodeInfo, ring_range::RingRange, trace::TraceId, Node, NodeAddr, RING_BYTE_LENGTH,
+    SUCCESSOR_LIST_LENGTH,
+};
 
 /// Represents a response for the
 /// protocol used in the Chord Network.
 #[derive(Debug, PartialEq, Eq)]
+#[allow(clippy::large_enum_variant)]
 pub(crate) enum ChordResponse {
     Successor(Node),
+    /// Successor located by a traced lookup, with the number
+    /// of nodes the lookup went through (`[HOPS=<n>]`).
+    TracedSuccessor(Node, u32),
     SuccessorList([Node; SUCCESSOR_LIST_LENGTH]),
     Predecessor(Option<Node>),
+    /// Predecessor and successor list of the
+    /// node, taken together (`GET_NEIGHBORHOOD`).
+    Neighborhood(Option<Node>, [Node; SUCCESSOR_LIST_LENGTH]),
     Active,
-    Error(String),
+    Acknowledged,
+    /// Located fingers, with their index
+    /// in the finger table (empty slots are skipped).
+    FingerTable(Vec<(usize, Node)>),
+    /// Next node to contact, in iterative routing:
+    /// the initiator of the lookup forwards it itself.
+    Redirect(Node),
+    /// Range of keys owned by the node, `(predecessor, self]`.
+    OwnedRange(RingRange),
+    /// Version, uptime and ring parameters of the node.
+    NodeInfo(NodeInfo),
+    /// The node probed back on behalf of the
+    /// requester (`PROBE_BACK`) did not answer.
+    Unreachable,
+    Error(ErrorKind, String),
+}
+
+/// Whether the request failing with an error
+/// response is worth sending again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ErrorKind {
+    /// Transient failure (e.g. busy or unreachable node,
+    /// ring being stabilized): the request may succeed later.
+    Retryable,
+    /// Permanent failure (e.g. identifier collision, invalid
+    /// secret): the request fails the same way if sent again.
+    Fatal,
+    /// The remote node did not answer in time: unlike a refused
+    /// connection, it may be alive but slow, and answer a retry.
+    Timeout,
+}
+
+impl ErrorKind {
+    fn parse(error_kind: &str) -> Option<Self> {
+        match error_kind {
+            "RETRYABLE" => Some(Self::Retryable),
+            "FATAL" => Some(Self::Fatal),
+            "TIMEOUT" => Some(Self::Timeout),
+            _ => None,
+        }
+    }
+
+    fn to_protocol_text(self) -> &'static str {
+        match self {
+            Self::Retryable => "RETRYABLE",
+            Self::Fatal => "FATAL",
+            Self::Timeout => "TIMEOUT",
+        }
+    }
 }
 
 impl ChordResponse {
@@ -38,11 +96,46 @@ impl ChordResponse {
             return Ok(chord_response);
         }
 
+        // NEIGHBORHOOD text protocol parsing
+        if let Some(chord_response) = Self::parse_neighborhood_response_protocol(response)? {
+            return Ok(chord_response);
+        }
+
         // ACTIVE text protocol parsing
         if let Some(gossip_response) = Self::parse_active_response_protocol(response) {
             return Ok(gossip_response);
         }
 
+        // ACKNOWLEDGED text protocol parsing
+        if let Some(chord_response) = Self::parse_acknowledged_response_protocol(response) {
+            return Ok(chord_response);
+        }
+
+        // FINGER TABLE text protocol parsing
+        if let Some(chord_response) = Self::parse_finger_table_response_protocol(response)? {
+            return Ok(chord_response);
+        }
+
+        // REDIRECT text protocol parsing
+        if let Some(chord_response) = Self::parse_redirect_response_protocol(response)? {
+            return Ok(chord_response);
+        }
+
+        // OWNED RANGE text protocol parsing
+        if let Some(chord_response) = Self::parse_owned_range_response_protocol(response) {
+            return Ok(chord_response);
+        }
+
+        // NODE INFO text protocol parsing
+        if let Some(chord_response) = Self::parse_node_info_response_protocol(response) {
+            return Ok(chord_response);
+        }
+
+        // UNREACHABLE text protocol parsing
+        if response == "UNREACHABLE;" {
+            return Ok(Self::Unreachable);
+        }
+
         // ERROR text protocol parsing
         if let Some(gossip_response) = Self::parse_error_response_protocol(response) {
             return Ok(gossip_response);
@@ -52,20 +145,30 @@ impl ChordResponse {
     }
 
     fn parse_successor_response_protocol(response: &str) -> Result<Option<Self>, &'static str> {
-        let successor_response_regex =
-            Regex::new(r"^SUCCESSOR=\[([0-9a-f]{32})\]\[([0-9a-f:.\[\]]+)\];$").unwrap();
-
-        if successor_response_regex.is_match(response) {
-            let response_datas = successor_response_regex.captures(response).unwrap();
+        static SUCCESSOR_RESPONSE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+                r"^SUCCESSOR=\[({id})\]\[([0-9a-f:.\[\]#]+)\](?:\[HOPS=([0-9]+)\])?;$",
+                id = id_pattern()
+            ))
+            .unwrap()
+        });
+
+        if SUCCESSOR_RESPONSE_REGEX.is_match(response) {
+            let response_datas = SUCCESSOR_RESPONSE_REGEX.captures(response).unwrap();
             let successor_id = response_datas[1].to_string();
-            let successor_public_addr = response_datas[2]
-                .parse::<SocketAddr>()
-                .map_err(|_| "invalid response (invalid socket address)")?;
-
-            return Ok(Some(Self::Successor(Node::create_from(
-                hex::decode(successor_id).unwrap().try_into().unwrap(),
-                successor_public_addr,
-            ))));
+            let successor_addr = NodeAddr::parse(&response_datas[2])
+                .ok_or("invalid response (invalid socket address)")?;
+            let successor = decode_node(&successor_id, successor_addr);
+
+            return match response_datas.get(3) {
+                Some(hops) => Ok(Some(Self::TracedSuccessor(
+                    successor,
+                    hops.as_str()
+                        .parse::<u32>()
+                        .map_err(|_| "invalid response (invalid hop count)")?,
+                ))),
+                None => Ok(Some(Self::Successor(successor))),
+            };
         }
 
         Ok(None)
@@ -74,24 +177,23 @@ impl ChordResponse {
     fn parse_successor_list_response_protocol(
         response: &str,
     ) -> Result<Option<Self>, &'static str> {
-        let successor_list_response_regex = Regex::new(
-            r"^SUCCESSOR_LIST=\{\[([0-9a-f]{32})\]\[([0-9a-f:.\[\]]+)\],\[([0-9a-f]{32})\]\[([0-9a-f:.\[\]]+)\],\[([0-9a-f]{32})\]\[([0-9a-f:.\[\]]+)\],\[([0-9a-f]{32})\]\[([0-9a-f:.\[\]]+)\],\[([0-9a-f]{32})\]\[([0-9a-f:.\[\]]+)\]\};$"
-        ).unwrap();
-
-        if successor_list_response_regex.is_match(response) {
-            let response_datas = successor_list_response_regex.captures(response).unwrap();
+        static SUCCESSOR_LIST_RESPONSE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+            r"^SUCCESSOR_LIST=\{{\[({id})\]\[([0-9a-f:.\[\]#]+)\],\[({id})\]\[([0-9a-f:.\[\]#]+)\],\[({id})\]\[([0-9a-f:.\[\]#]+)\],\[({id})\]\[([0-9a-f:.\[\]#]+)\],\[({id})\]\[([0-9a-f:.\[\]#]+)\]\}};$",
+            id = id_pattern()
+        )).unwrap()
+        });
+
+        if SUCCESSOR_LIST_RESPONSE_REGEX.is_match(response) {
+            let response_datas = SUCCESSOR_LIST_RESPONSE_REGEX.captures(response).unwrap();
             let mut successor_list = Vec::new();
 
             for i in 1..=SUCCESSOR_LIST_LENGTH {
                 let successor_id = response_datas[2 * i - 1].to_string();
-                let successor_public_addr = response_datas[2 * i]
-                    .parse::<SocketAddr>()
-                    .map_err(|_| "invalid response (invalid socket address)")?;
+                let successor_addr = NodeAddr::parse(&response_datas[2 * i])
+                    .ok_or("invalid response (invalid socket address)")?;
 
-                successor_list.push(Node::create_from(
-                    hex::decode(successor_id).unwrap().try_into().unwrap(),
-                    successor_public_addr,
-                ));
+                successor_list.push(decode_node(&successor_id, successor_addr));
             }
 
             return Ok(Some(Self::SuccessorList(
@@ -107,25 +209,62 @@ impl ChordResponse {
             return Ok(Some(Self::Predecessor(None)));
         }
 
-        let predecessor_exist_response_regex =
-            Regex::new(r"^PREDECESSOR=\[([0-9a-f]{32})\]\[([0-9a-f:.\[\]]+)\];$").unwrap();
+        static PREDECESSOR_EXIST_RESPONSE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+                r"^PREDECESSOR=\[({id})\]\[([0-9a-f:.\[\]#]+)\];$",
+                id = id_pattern()
+            ))
+            .unwrap()
+        });
 
-        if predecessor_exist_response_regex.is_match(response) {
-            let response_datas = predecessor_exist_response_regex.captures(response).unwrap();
+        if PREDECESSOR_EXIST_RESPONSE_REGEX.is_match(response) {
+            let response_datas = PREDECESSOR_EXIST_RESPONSE_REGEX.captures(response).unwrap();
             let predecessor_id = response_datas[1].to_string();
-            let predecessor_public_addr = response_datas[2]
-                .parse::<SocketAddr>()
-                .map_err(|_| "invalid response (invalid socket address)")?;
+            let predecessor_addr = NodeAddr::parse(&response_datas[2])
+                .ok_or("invalid response (invalid socket address)")?;
 
-            return Ok(Some(Self::Predecessor(Some(Node::create_from(
-                hex::decode(predecessor_id).unwrap().try_into().unwrap(),
-                predecessor_public_addr,
+            return Ok(Some(Self::Predecessor(Some(decode_node(
+                &predecessor_id,
+                predecessor_addr,
             )))));
         }
 
         Ok(None)
     }
 
+    /// Parses `NEIGHBORHOOD=<predecessor>{<successor list>};`, the
+    /// predecessor and the successor list being written as in the
+    /// PREDECESSOR and SUCCESSOR_LIST responses.
+    fn parse_neighborhood_response_protocol(response: &str) -> Result<Option<Self>, &'static str> {
+        let neighborhood = match response.strip_prefix("NEIGHBORHOOD=") {
+            Some(neighborhood) => neighborhood,
+            None => return Ok(None),
+        };
+
+        // Braces never appear in a node
+        let (predecessor, successor_list) = neighborhood
+            .split_once('{')
+            .ok_or("invalid response (invalid neighborhood)")?;
+
+        let predecessor = match Self::parse_predecessor_response_protocol(&format!(
+            "PREDECESSOR={};",
+            predecessor
+        ))? {
+            Some(Self::Predecessor(predecessor)) => predecessor,
+            _ => return Err("invalid response (invalid neighborhood)"),
+        };
+
+        let successor_list = match Self::parse_successor_list_response_protocol(&format!(
+            "SUCCESSOR_LIST={{{}",
+            successor_list
+        ))? {
+            Some(Self::SuccessorList(successor_list)) => successor_list,
+            _ => return Err("invalid response (invalid neighborhood)"),
+        };
+
+        Ok(Some(Self::Neighborhood(predecessor, successor_list)))
+    }
+
     fn parse_active_response_protocol(response: &str) -> Option<Self> {
         if response == "ACTIVE;" {
             return Some(Self::Active);
@@ -133,13 +272,124 @@ impl ChordResponse {
         None
     }
 
-    fn parse_error_response_protocol(response: &str) -> Option<Self> {
-        let error_response_regex = Regex::new(r"^ERROR=\[(.+)\];$").unwrap();
+    fn parse_acknowledged_response_protocol(response: &str) -> Option<Self> {
+        if response == "ACKNOWLEDGED;" {
+            return Some(Self::Acknowledged);
+        }
+        None
+    }
+
+    fn parse_finger_table_response_protocol(response: &str) -> Result<Option<Self>, &'static str> {
+        static FINGER_TABLE_RESPONSE_REGEX: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^FINGER_TABLE=\{(.*)\};$").unwrap());
+        static FINGER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+                r"^\[([0-9]{{1,3}})\]\[({id})\]\[([0-9a-f:.\[\]#]+)\]$",
+                id = id_pattern()
+            ))
+            .unwrap()
+        });
+
+        let fingers = match FINGER_TABLE_RESPONSE_REGEX.captures(response) {
+            Some(response_datas) => response_datas[1].to_string(),
+            None => return Ok(None),
+        };
+
+        let mut finger_table = Vec::new();
+
+        // Fingers are separated by commas,
+        // which never appear inside an entry
+        for finger in fingers.split(',').filter(|finger| !finger.is_empty()) {
+            let finger_datas = FINGER_REGEX
+                .captures(finger)
+                .ok_or("invalid response (invalid finger table entry)")?;
+            let finger_id = finger_datas[2].to_string();
+            let finger_addr = NodeAddr::parse(&finger_datas[3])
+                .ok_or("invalid response (invalid socket address)")?;
+            let finger = decode_node(&finger_id, finger_addr);
+            let index = finger_datas[1]
+                .parse::<usize>()
+                .ok()
+                .filter(|index| *index < finger.get_ring_bit_length())
+                .ok_or("invalid response (invalid finger index)")?;
+
+            finger_table.push((index, finger));
+        }
+
+        Ok(Some(Self::FingerTable(finger_table)))
+    }
 
-        if error_response_regex.is_match(response) {
-            let response_datas = error_response_regex.captures(response).unwrap();
-            let error_msg = response_datas[1].to_string();
-            return Some(Self::Error(error_msg));
+    fn parse_redirect_response_protocol(response: &str) -> Result<Option<Self>, &'static str> {
+        static REDIRECT_RESPONSE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+                r"^REDIRECT=\[({id})\]\[([0-9a-f:.\[\]#]+)\];$",
+                id = id_pattern()
+            ))
+            .unwrap()
+        });
+
+        if REDIRECT_RESPONSE_REGEX.is_match(response) {
+            let response_datas = REDIRECT_RESPONSE_REGEX.captures(response).unwrap();
+            let next_hop_id = response_datas[1].to_string();
+            let next_hop_addr = NodeAddr::parse(&response_datas[2])
+                .ok_or("invalid response (invalid socket address)")?;
+
+            return Ok(Some(Self::Redirect(decode_node(
+                &next_hop_id,
+                next_hop_addr,
+            ))));
+        }
+
+        Ok(None)
+    }
+
+    fn parse_owned_range_response_protocol(response: &str) -> Option<Self> {
+        static OWNED_RANGE_RESPONSE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+                r"^OWNED_RANGE=\[({id})\]\[({id})\];$",
+                id = id_pattern()
+            ))
+            .unwrap()
+        });
+
+        let response_datas = OWNED_RANGE_RESPONSE_REGEX.captures(response)?;
+
+        Some(Self::OwnedRange(RingRange::new(
+            u128::from_be_bytes(decode_id(&response_datas[1])),
+            u128::from_be_bytes(decode_id(&response_datas[2])),
+        )))
+    }
+
+    fn parse_node_info_response_protocol(response: &str) -> Option<Self> {
+        static NODE_INFO_RESPONSE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(
+            r"^NODE_INFO=\[VERSION=([0-9A-Za-z.+-]+)\]\[PROTOCOL=([0-9]+)\]\[UPTIME=([0-9]+)\]\[RING_BITS=([0-9]+)\]\[SUCCESSORS=([0-9]+)\]\[HASH=([0-9A-Za-z-]+)\];$",
+        ).unwrap()
+        });
+
+        let response_datas = NODE_INFO_RESPONSE_REGEX.captures(response)?;
+
+        Some(Self::NodeInfo(NodeInfo {
+            version: response_datas[1].to_string(),
+            protocol_version: response_datas[2].parse().ok()?,
+            uptime: Duration::from_secs(response_datas[3].parse().ok()?),
+            ring_bit_length: response_datas[4].parse().ok()?,
+            successor_list_length: response_datas[5].parse().ok()?,
+            hash_function: response_datas[6].to_string(),
+        }))
+    }
+
+    fn parse_error_response_protocol(response: &str) -> Option<Self> {
+        // Unescaped messages, sent by older nodes, are matched as well
+        static ERROR_RESPONSE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"(?s)^ERROR=\[(RETRYABLE|FATAL|TIMEOUT)\]\[(.*)\];$").unwrap()
+        });
+
+        if ERROR_RESPONSE_REGEX.is_match(response) {
+            let response_datas = ERROR_RESPONSE_REGEX.captures(response).unwrap();
+            let error_kind = ErrorKind::parse(&response_datas[1])?;
+            let error_msg = unescape_error_message(&response_datas[2]);
+            return Some(Self::Error(error_kind, error_msg));
         }
 
         None
@@ -152,15 +402,23 @@ impl ChordResponse {
         match *self {
             Self::Successor(ref successor) => {
                 format!(
-                    "SUCCESSOR=[{}][{:?}];",
+                    "SUCCESSOR=[{}][{}];",
+                    successor.get_hash_id(),
+                    successor.get_node_addr()
+                )
+            }
+            Self::TracedSuccessor(ref successor, hops) => {
+                format!(
+                    "SUCCESSOR=[{}][{}][HOPS={}];",
                     successor.get_hash_id(),
-                    successor.get_public_addr()
+                    successor.get_node_addr(),
+                    hops
                 )
             }
             Self::SuccessorList(ref successors) => {
                 let successors_string = successors
                     .iter()
-                    .map(|node| format!("[{}][{:?}]", node.get_hash_id(), node.get_public_addr()))
+                    .map(|node| format!("[{}][{}]", node.get_hash_id(), node.get_node_addr()))
                     .collect::<Vec<_>>()
                     .join(",");
 
@@ -169,24 +427,86 @@ impl ChordResponse {
             Self::Predecessor(None) => "PREDECESSOR=NONE;".to_string(),
             Self::Predecessor(Some(ref predecessor)) => {
                 format!(
-                    "PREDECESSOR=[{}][{:?}];",
+                    "PREDECESSOR=[{}][{}];",
                     predecessor.get_hash_id(),
-                    predecessor.get_public_addr()
+                    predecessor.get_node_addr()
+                )
+            }
+            Self::Neighborhood(ref predecessor, ref successors) => {
+                let predecessor_text = Self::Predecessor(predecessor.clone()).to_protocol_text();
+                let successor_list_text =
+                    Self::SuccessorList(successors.clone()).to_protocol_text();
+
+                format!(
+                    "NEIGHBORHOOD={}{}",
+                    predecessor_text
+                        .trim_start_matches("PREDECESSOR=")
+                        .trim_end_matches(';'),
+                    successor_list_text.trim_start_matches("SUCCESSOR_LIST=")
+                )
+            }
+            Self::Error(error_kind, ref err) => {
+                format!(
+                    "ERROR=[{}][{}];",
+                    error_kind.to_protocol_text(),
+                    escape_error_message(err)
                 )
             }
-            Self::Error(ref err) => format!("ERROR=[{}];", err),
             Self::Active => "ACTIVE;".to_string(),
+            Self::Acknowledged => "ACKNOWLEDGED;".to_string(),
+            Self::Unreachable => "UNREACHABLE;".to_string(),
+            Self::FingerTable(ref fingers) => {
+                let fingers_string = fingers
+                    .iter()
+                    .map(|(index, node)| {
+                        format!(
+                            "[{}][{}][{}]",
+                            index,
+                            node.get_hash_id(),
+                            node.get_node_addr()
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(",");
+
+                format!("FINGER_TABLE={{{}}};", fingers_string)
+            }
+            Self::OwnedRange(owned_range) => {
+                format!(
+                    "OWNED_RANGE=[{}][{}];",
+                    position_to_protocol_text(owned_range.start()),
+                    position_to_protocol_text(owned_range.end())
+                )
+            }
+            Self::Redirect(ref next_hop) => {
+                format!(
+                    "REDIRECT=[{}][{}];",
+                    next_hop.get_hash_id(),
+                    next_hop.get_node_addr()
+                )
+            }
+            Self::NodeInfo(ref node_info) => {
+                format!(
+                    "NODE_INFO=[VERSION={}][PROTOCOL={}][UPTIME={}][RING_BITS={}][SUCCESSORS={}][HASH={}];",
+                    node_info.version,
+                    node_info.protocol_version,
+                    node_info.uptime.as_secs(),
+                    node_info.ring_bit_length,
+                    node_info.successor_list_length,
+                    node_info.hash_function
+                )
+            }
         }
     }
 }
 
 #[cfg(test)]
 mod chord_response_protocol_test {
-    use std::net::SocketAddr;
+    use std::{net::SocketAddr, time::Duration};
 
     use crate::chord::Node;
 
-    use super::ChordResponse;
+    use super::{ChordResponse, ErrorKind, NodeInfo, RingRange};
 
     #[test]
     fn successor_ipv4_response_parse_test() {
@@ -205,6 +525,32 @@ mod chord_response_protocol_test {
         }
     }
 
+    #[test]
+    fn traced_successor_response_protocol_test() {
+        let response = "SUCCESSOR=[cf4b19e32ce29fef04468ac9d2a6787d][17.5.7.3:1450#2][HOPS=3];";
+
+        let chord_response = ChordResponse::parse(response).unwrap();
+
+        if let ChordResponse::TracedSuccessor(ref node, hops) = chord_response {
+            assert_eq!(node.get_hash_id(), "cf4b19e32ce29fef04468ac9d2a6787d");
+            assert_eq!(node.get_vnode(), 2);
+            assert_eq!(hops, 3);
+        } else {
+            panic!("parsing error");
+        }
+
+        assert_eq!(chord_response.to_protocol_text(), response);
+
+        assert!(ChordResponse::parse(
+            "SUCCESSOR=[cf4b19e32ce29fef04468ac9d2a6787d][17.5.7.3:1450][HOPS=99999999999];"
+        )
+        .is_err());
+        assert!(ChordResponse::parse(
+            "SUCCESSOR=[cf4b19e32ce29fef04468ac9d2a6787d][17.5.7.3:1450][HOPS=];"
+        )
+        .is_err());
+    }
+
     #[test]
     fn successor_ipv6_response_parse_test() {
         let response = "SUCCESSOR=[cf4b19e32ce29fef04468ac9d2a6787d][[2001:0db8:85a3:0000:0000:8a2e:0370:7334]:8080];";
@@ -302,17 +648,244 @@ mod chord_response_protocol_test {
         );
     }
 
+    #[test]
+    fn unreachable_response_protocol_test() {
+        let response = "UNREACHABLE;";
+
+        assert_eq!(
+            ChordResponse::parse(response).unwrap(),
+            ChordResponse::Unreachable
+        );
+        assert_eq!(ChordResponse::Unreachable.to_protocol_text(), response);
+    }
+
+    #[test]
+    fn acknowledged_response_parse_test() {
+        let response = "ACKNOWLEDGED;";
+
+        assert_eq!(
+            ChordResponse::Acknowledged,
+            ChordResponse::parse(response).unwrap()
+        );
+    }
+
+    #[test]
+    fn finger_table_response_parse_test() {
+        let response = "FINGER_TABLE={[0][6e4bfa7e2180a1cf55db0e38c12b9979][[2001:db8::1]:4040],[127][b2c7f1a82d3452f0a8577f7d3b9e38f5][172.16.8.53:9876]};";
+
+        let chord_response = ChordResponse::parse(response).unwrap();
+
+        if let ChordResponse::FingerTable(fingers) = chord_response {
+            assert_eq!(fingers.len(), 2);
+            assert_eq!(fingers[0].0, 0);
+            assert_eq!(
+                fingers[0].1.get_hash_id(),
+                "6e4bfa7e2180a1cf55db0e38c12b9979"
+            );
+            assert_eq!(
+                fingers[0].1.get_public_addr(),
+                "[2001:db8::1]:4040".parse::<SocketAddr>().unwrap()
+            );
+            assert_eq!(fingers[1].0, 127);
+            assert_eq!(
+                fingers[1].1.get_public_addr(),
+                "172.16.8.53:9876".parse::<SocketAddr>().unwrap()
+            );
+        } else {
+            panic!("parsing error");
+        }
+
+        // Empty finger table
+        assert_eq!(
+            ChordResponse::parse("FINGER_TABLE={};").unwrap(),
+            ChordResponse::FingerTable(Vec::new())
+        );
+
+        // Out of range finger index
+        assert!(ChordResponse::parse(
+            "FINGER_TABLE={[128][6e4bfa7e2180a1cf55db0e38c12b9979][10.0.0.33:443]};"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn redirect_response_protocol_test() {
+        let response = "REDIRECT=[cf4b19e32ce29fef04468ac9d2a6787d][17.5.7.3:1450];";
+
+        let chord_response = ChordResponse::parse(response).unwrap();
+
+        if let ChordResponse::Redirect(ref node) = chord_response {
+            assert_eq!(node.get_hash_id(), "cf4b19e32ce29fef04468ac9d2a6787d");
+            assert_eq!(
+                node.get_public_addr(),
+                "17.5.7.3:1450".parse::<SocketAddr>().unwrap()
+            );
+        } else {
+            panic!("parsing error");
+        }
+
+        assert_eq!(chord_response.to_protocol_text(), response);
+    }
+
+    #[test]
+    fn neighborhood_response_protocol_test() {
+        let successor_list = "{[6e4bfa7e2180a1cf55db0e38c12b9979][[2001:db8::1]:4040],[b2c7f1a82d3452f0a8577f7d3b9e38f5][172.16.8.53:9876#2],[98e317b512a1391a9e0eabf8e3f1c6b4][10.0.0.33:443],[98e317b512a1391a9e0eabf8e3f1c6b4][10.0.0.33:443],[98e317b512a1391a9e0eabf8e3f1c6b4][10.0.0.33:443]}";
+
+        // With a predecessor
+        let response = format!(
+            "NEIGHBORHOOD=[cf4b19e32ce29fef04468ac9d2a6787d][17.5.7.3:1450]{};",
+            successor_list
+        );
+
+        let chord_response = ChordResponse::parse(&response).unwrap();
+
+        if let ChordResponse::Neighborhood(Some(ref predecessor), ref successors) = chord_response {
+            assert_eq!(
+                predecessor.get_hash_id(),
+                "cf4b19e32ce29fef04468ac9d2a6787d"
+            );
+            assert_eq!(
+                predecessor.get_public_addr(),
+                "17.5.7.3:1450".parse::<SocketAddr>().unwrap()
+            );
+            assert_eq!(
+                successors[1].get_hash_id(),
+                "b2c7f1a82d3452f0a8577f7d3b9e38f5"
+            );
+            assert_eq!(
+                successors[1].get_node_addr().to_string(),
+                "172.16.8.53:9876#2"
+            );
+        } else {
+            panic!("parsing error");
+        }
+
+        assert_eq!(chord_response.to_protocol_text(), response);
+
+        // Without a predecessor
+        let response = format!("NEIGHBORHOOD=NONE{};", successor_list);
+        let chord_response = ChordResponse::parse(&response).unwrap();
+
+        assert!(matches!(
+            chord_response,
+            ChordResponse::Neighborhood(None, _)
+        ));
+        assert_eq!(chord_response.to_protocol_text(), response);
+
+        // Truncated successor list
+        assert!(ChordResponse::parse(
+            "NEIGHBORHOOD=NONE{[6e4bfa7e2180a1cf55db0e38c12b9979][10.0.0.33:443]};"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn owned_range_response_protocol_test() {
+        let response =
+            "OWNED_RANGE=[cf4b19e32ce29fef04468ac9d2a6787d][080501321f1d3ab94c90052a1938e7dc];";
+
+        let chord_response = ChordResponse::parse(response).unwrap();
+
+        assert_eq!(
+            chord_response,
+            ChordResponse::OwnedRange(RingRange::new(
+                0xcf4b19e32ce29fef04468ac9d2a6787d,
+                0x080501321f1d3ab94c90052a1938e7dc
+            ))
+        );
+        assert_eq!(chord_response.to_protocol_text(), response);
+    }
+
+    #[test]
+    fn node_info_response_protocol_test() {
+        let response = "NODE_INFO=[VERSION=0.2.0-rc.1][PROTOCOL=1][UPTIME=3600][RING_BITS=32][SUCCESSORS=5][HASH=MD5];";
+
+        let chord_response = ChordResponse::parse(response).unwrap();
+
+        assert_eq!(
+            chord_response,
+            ChordResponse::NodeInfo(NodeInfo {
+                version: "0.2.0-rc.1".to_string(),
+                protocol_version: 1,
+                uptime: Duration::from_secs(3600),
+                ring_bit_length: 32,
+                successor_list_length: 5,
+                hash_function: "MD5".to_string(),
+            })
+        );
+        assert_eq!(chord_response.to_protocol_text(), response);
+
+        // Fields are not optional
+        assert!(ChordResponse::parse(
+            "NODE_INFO=[VERSION=0.2.0][PROTOCOL=1][RING_BITS=32][SUCCESSORS=5][HASH=MD5];"
+        )
+        .is_err());
+    }
+
     #[test]
     fn error_response_parse_test() {
-        let response = "ERROR=[Some error message ...];";
+        let response = "ERROR=[RETRYABLE][Some error message ...];";
 
         let chord_response = ChordResponse::parse(response).unwrap();
 
-        if let ChordResponse::Error(err) = chord_response {
+        if let ChordResponse::Error(error_kind, err) = chord_response {
+            assert_eq!(error_kind, ErrorKind::Retryable);
             assert_eq!(err, "Some error message ...");
         } else {
             panic!("parsing error");
         }
+
+        assert_eq!(
+            ChordResponse::parse("ERROR=[FATAL][bad [request]];").unwrap(),
+            ChordResponse::Error(ErrorKind::Fatal, "bad [request]".to_string())
+        );
+
+        let response = "ERROR=[TIMEOUT][resource temporarily unavailable];";
+        assert_eq!(
+            ChordResponse::parse(response).unwrap(),
+            ChordResponse::Error(
+                ErrorKind::Timeout,
+                "resource temporarily unavailable".to_string()
+            )
+        );
+        assert_eq!(
+            ChordResponse::parse(response).unwrap().to_protocol_text(),
+            response
+        );
+
+        // The kind of the error is required
+        assert!(ChordResponse::parse("ERROR=[Some error message ...];").is_err());
+    }
+
+    #[test]
+    fn error_message_escaping_test() {
+        for message in [
+            "bad [request]; expected ];",
+            "100% [done]",
+            "line 1\r\nline 2",
+            "%5B is not escaped twice",
+            "",
+        ] {
+            let response = ChordResponse::Error(ErrorKind::Fatal, message.to_string());
+            let response_msg = response.to_protocol_text();
+
+            assert_eq!(
+                response_msg.matches(['[', ']', ';']).count(),
+                "ERROR=[FATAL][];".matches(['[', ']', ';']).count()
+            );
+            assert_eq!(ChordResponse::parse(&response_msg).unwrap(), response);
+        }
+
+        assert_eq!(
+            ChordResponse::Error(ErrorKind::Retryable, "a];b".to_string()).to_protocol_text(),
+            "ERROR=[RETRYABLE][a%5D%3Bb];"
+        );
+
+        // Unknown sequences are kept as is
+        assert_eq!(
+            ChordResponse::parse("ERROR=[FATAL][50%];").unwrap(),
+            ChordResponse::Error(ErrorKind::Fatal, "50%".to_string())
+        );
     }
 
     #[test]
@@ -420,15 +993,34 @@ mod chord_response_protocol_test {
         // to text-based protocol
         assert_eq!(ChordResponse::Active.to_protocol_text(), "ACTIVE;");
 
+        // ACKNOWLEDGED response abstraction
+        // to text-based protocol
+        assert_eq!(
+            ChordResponse::Acknowledged.to_protocol_text(),
+            "ACKNOWLEDGED;"
+        );
+
+        // FINGER TABLE response abstraction
+        // to text-based protocol
+        let fingers = vec![(3, successor.clone()), (90, predecessor.clone())];
+
+        assert_eq!(
+            ChordResponse::parse(&ChordResponse::FingerTable(fingers.clone()).to_protocol_text())
+                .unwrap(),
+            ChordResponse::FingerTable(fingers)
+        );
+
         // ERROR response abstraction
         // to text-based protocol
         let error_msg = String::from("some error ...");
 
-        let chord_response =
-            ChordResponse::parse(&ChordResponse::Error(error_msg.clone()).to_protocol_text())
-                .unwrap();
+        let chord_response = ChordResponse::parse(
+            &ChordResponse::Error(ErrorKind::Fatal, error_msg.clone()).to_protocol_text(),
+        )
+        .unwrap();
 
-        if let ChordResponse::Error(result_error_msg) = chord_response {
+        if let ChordResponse::Error(error_kind, result_error_msg) = chord_response {
+            assert_eq!(error_kind, ErrorKind::Fatal);
             assert_eq!(result_error_msg, error_msg);
         } else {
             panic!("parsing error");
@@ -438,13 +1030,40 @@ mod chord_response_protocol_test {
 
 /// Represents a request for the
 /// protocol used in the Chord Network.
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub(crate) enum ChordRequest {
-    FindSuccessorOfNode(Node),
+    /// Locates the successor of a joining node,
+    /// with the trace ID of the lookup, if any.
+    FindSuccessorOfNode(Node, Option<TraceId>),
+    /// Locates the successor of a key (a ring position),
+    /// e.g. the node targeted by a finger, with
+    /// the trace ID of the lookup, if any.
+    FindSuccessorOfKey(u128, Option<TraceId>),
     GetSuccessorList,
     GetPredecessor,
+    /// Asks for the predecessor and the successor list
+    /// of the node, as a consistent snapshot.
+    GetNeighborhood,
     NotificationBy(Node),
     CheckNode,
+    Stabilize,
+    Leaving(Node),
+    Shutdown(String),
+    /// Drains the node before its removal: it rejects new data
+    /// updates, hands off its gossip state, then leaves the
+    /// network. Carries the admin secret.
+    Drain(String),
+    GetFingerTable,
+    /// Asks for the range of keys owned by the node.
+    GetOwnedRange,
+    /// Asks for the version, uptime and ring parameters of the node.
+    GetNodeInfo,
+    /// Overwrites the predecessor of the node, for recovery
+    /// tooling. Carries the admin secret, then the new predecessor.
+    SetPredecessor(String, Node),
+    /// Asks the node to check whether it can reach the
+    /// requester, at the given address, in turn.
+    ProbeBack(NodeAddr),
 }
 
 impl ChordRequest {
@@ -456,6 +1075,11 @@ impl ChordRequest {
             return Ok(chord_request);
         }
 
+        // FIND_SUCCESSOR_OF_KEY text protocol parsing
+        if let Some(chord_request) = Self::parse_find_successor_of_key_request_protocol(request)? {
+            return Ok(chord_request);
+        }
+
         // GET_SUCCESSOR_LIST text protocol parsing
         if let Some(chord_request) = Self::parse_get_successor_list_request_protocol(request) {
             return Ok(chord_request);
@@ -466,6 +1090,11 @@ impl ChordRequest {
             return Ok(chord_request);
         }
 
+        // GET_NEIGHBORHOOD text protocol parsing
+        if request == "GET_NEIGHBORHOOD;" {
+            return Ok(Self::GetNeighborhood);
+        }
+
         // NOTIFICATION_BY text protocol parsing
         if let Some(chord_request) = Self::parse_notification_by_request_protocol(request)? {
             return Ok(chord_request);
@@ -476,30 +1105,99 @@ impl ChordRequest {
             return Ok(chord_request);
         }
 
-        Err("invalid request (protocol error)")
-    }
+        // STABILIZE text protocol parsing
+        if let Some(chord_request) = Self::parse_stabilize_request_protocol(request) {
+            return Ok(chord_request);
+        }
 
-    fn parse_find_successor_of_node_request_protocol(
-        request: &str,
-    ) -> Result<Option<Self>, &'static str> {
-        let find_successor_of_node_regex =
-            Regex::new(r"^FIND_SUCCESSOR_OF_NODE=\[([0-9a-f]{32})\]\[([0-9a-f:.\[\]]+)\];$")
-                .unwrap();
+        // LEAVING text protocol parsing
+        if let Some(chord_request) = Self::parse_leaving_request_protocol(request)? {
+            return Ok(chord_request);
+        }
 
-        if find_successor_of_node_regex.is_match(request) {
-            let request_datas = find_successor_of_node_regex.captures(request).unwrap();
-            let node_id = request_datas[1].to_string();
-            let node_public_addr = request_datas[2]
-                .parse::<SocketAddr>()
-                .map_err(|_| "invalid request (invalid socket address)")?;
-            return Ok(Some(ChordRequest::FindSuccessorOfNode(Node::create_from(
-                hex::decode(node_id).unwrap().try_into().unwrap(),
-                node_public_addr,
-            ))));
+        // SHUTDOWN text protocol parsing
+        if let Some(chord_request) = Self::parse_shutdown_request_protocol(request) {
+            return Ok(chord_request);
         }
 
-        Ok(None)
-    }
+        // DRAIN text protocol parsing
+        if let Some(chord_request) = Self::parse_drain_request_protocol(request) {
+            return Ok(chord_request);
+        }
+
+        // GET_FINGER_TABLE text protocol parsing
+        if let Some(chord_request) = Self::parse_get_finger_table_request_protocol(request) {
+            return Ok(chord_request);
+        }
+
+        // GET_OWNED_RANGE text protocol parsing
+        if let Some(chord_request) = Self::parse_get_owned_range_request_protocol(request) {
+            return Ok(chord_request);
+        }
+
+        // GET_NODE_INFO text protocol parsing
+        if request == "GET_NODE_INFO;" {
+            return Ok(Self::GetNodeInfo);
+        }
+
+        // SET_PREDECESSOR text protocol parsing
+        if let Some(chord_request) = Self::parse_set_predecessor_request_protocol(request)? {
+            return Ok(chord_request);
+        }
+
+        // PROBE_BACK text protocol parsing
+        if let Some(chord_request) = Self::parse_probe_back_request_protocol(request)? {
+            return Ok(chord_request);
+        }
+
+        Err("invalid request (protocol error)")
+    }
+
+    fn parse_find_successor_of_node_request_protocol(
+        request: &str,
+    ) -> Result<Option<Self>, &'static str> {
+        static FIND_SUCCESSOR_OF_NODE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+            r"^FIND_SUCCESSOR_OF_NODE=\[({id})\]\[([0-9a-f:.\[\]#]+)\](?:\[TRACE=([0-9a-f]+)\])?;$",
+            id = id_pattern()
+        )).unwrap()
+        });
+
+        if FIND_SUCCESSOR_OF_NODE_REGEX.is_match(request) {
+            let request_datas = FIND_SUCCESSOR_OF_NODE_REGEX.captures(request).unwrap();
+            let node_id = request_datas[1].to_string();
+            let node_addr = NodeAddr::parse(&request_datas[2])
+                .ok_or("invalid request (invalid socket address)")?;
+            let trace_id = parse_trace_id(request_datas.get(3))?;
+            return Ok(Some(ChordRequest::FindSuccessorOfNode(
+                decode_node(&node_id, node_addr),
+                trace_id,
+            )));
+        }
+
+        Ok(None)
+    }
+
+    fn parse_find_successor_of_key_request_protocol(
+        request: &str,
+    ) -> Result<Option<Self>, &'static str> {
+        static FIND_SUCCESSOR_OF_KEY_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+                r"^FIND_SUCCESSOR_OF_KEY=\[({id})\](?:\[TRACE=([0-9a-f]+)\])?;$",
+                id = id_pattern()
+            ))
+            .unwrap()
+        });
+
+        if FIND_SUCCESSOR_OF_KEY_REGEX.is_match(request) {
+            let request_datas = FIND_SUCCESSOR_OF_KEY_REGEX.captures(request).unwrap();
+            let key = u128::from_be_bytes(decode_id(&request_datas[1]));
+            let trace_id = parse_trace_id(request_datas.get(2))?;
+            return Ok(Some(Self::FindSuccessorOfKey(key, trace_id)));
+        }
+
+        Ok(None)
+    }
 
     fn parse_get_successor_list_request_protocol(request: &str) -> Option<Self> {
         if request == "GET_SUCCESSOR_LIST;" {
@@ -518,20 +1216,21 @@ impl ChordRequest {
     }
 
     fn parse_notification_by_request_protocol(request: &str) -> Result<Option<Self>, &'static str> {
-        let notification_by_regex =
-            Regex::new(r"^NOTIFICATION_BY=\[([0-9a-f]{32})\]\[([0-9a-f:.\[\]]+)\];$").unwrap();
-
-        if notification_by_regex.is_match(request) {
-            let request_datas = notification_by_regex.captures(request).unwrap();
+        static NOTIFICATION_BY_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+                r"^NOTIFICATION_BY=\[({id})\]\[([0-9a-f:.\[\]#]+)\];$",
+                id = id_pattern()
+            ))
+            .unwrap()
+        });
+
+        if NOTIFICATION_BY_REGEX.is_match(request) {
+            let request_datas = NOTIFICATION_BY_REGEX.captures(request).unwrap();
             let node_id = request_datas[1].to_string();
-            let node_public_addr = request_datas[2]
-                .parse::<SocketAddr>()
-                .map_err(|_| "invalid request (invalid socket address)")?;
+            let node_addr = NodeAddr::parse(&request_datas[2])
+                .ok_or("invalid request (invalid socket address)")?;
 
-            return Ok(Some(Self::NotificationBy(Node::create_from(
-                hex::decode(node_id).unwrap().try_into().unwrap(),
-                node_public_addr,
-            ))));
+            return Ok(Some(Self::NotificationBy(decode_node(&node_id, node_addr))));
         }
 
         Ok(None)
@@ -545,39 +1244,300 @@ impl ChordRequest {
         None
     }
 
+    fn parse_stabilize_request_protocol(request: &str) -> Option<Self> {
+        if request == "STABILIZE;" {
+            return Some(ChordRequest::Stabilize);
+        }
+
+        None
+    }
+
+    fn parse_leaving_request_protocol(request: &str) -> Result<Option<Self>, &'static str> {
+        static LEAVING_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+                r"^LEAVING=\[({id})\]\[([0-9a-f:.\[\]#]+)\];$",
+                id = id_pattern()
+            ))
+            .unwrap()
+        });
+
+        if LEAVING_REGEX.is_match(request) {
+            let request_datas = LEAVING_REGEX.captures(request).unwrap();
+            let node_id = request_datas[1].to_string();
+            let node_addr = NodeAddr::parse(&request_datas[2])
+                .ok_or("invalid request (invalid socket address)")?;
+
+            return Ok(Some(Self::Leaving(decode_node(&node_id, node_addr))));
+        }
+
+        Ok(None)
+    }
+
+    fn parse_shutdown_request_protocol(request: &str) -> Option<Self> {
+        static SHUTDOWN_REGEX: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^SHUTDOWN=\[([^\]]+)\];$").unwrap());
+
+        if SHUTDOWN_REGEX.is_match(request) {
+            let request_datas = SHUTDOWN_REGEX.captures(request).unwrap();
+            return Some(Self::Shutdown(request_datas[1].to_string()));
+        }
+
+        None
+    }
+
+    fn parse_drain_request_protocol(request: &str) -> Option<Self> {
+        static DRAIN_REGEX: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^DRAIN=\[([^\]]+)\];$").unwrap());
+
+        if DRAIN_REGEX.is_match(request) {
+            let request_datas = DRAIN_REGEX.captures(request).unwrap();
+            return Some(Self::Drain(request_datas[1].to_string()));
+        }
+
+        None
+    }
+
+    fn parse_get_finger_table_request_protocol(request: &str) -> Option<Self> {
+        if request == "GET_FINGER_TABLE;" {
+            return Some(Self::GetFingerTable);
+        }
+
+        None
+    }
+
+    fn parse_get_owned_range_request_protocol(request: &str) -> Option<Self> {
+        if request == "GET_OWNED_RANGE;" {
+            return Some(Self::GetOwnedRange);
+        }
+
+        None
+    }
+
+    fn parse_set_predecessor_request_protocol(request: &str) -> Result<Option<Self>, &'static str> {
+        static SET_PREDECESSOR_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+                r"^SET_PREDECESSOR=\[([^\]]+)\]\[({id})\]\[([0-9a-f:.\[\]#]+)\];$",
+                id = id_pattern()
+            ))
+            .unwrap()
+        });
+
+        if SET_PREDECESSOR_REGEX.is_match(request) {
+            let request_datas = SET_PREDECESSOR_REGEX.captures(request).unwrap();
+            let node_id = request_datas[2].to_string();
+            let node_addr = NodeAddr::parse(&request_datas[3])
+                .ok_or("invalid request (invalid socket address)")?;
+
+            return Ok(Some(Self::SetPredecessor(
+                request_datas[1].to_string(),
+                decode_node(&node_id, node_addr),
+            )));
+        }
+
+        Ok(None)
+    }
+
+    fn parse_probe_back_request_protocol(request: &str) -> Result<Option<Self>, &'static str> {
+        static PROBE_BACK_REGEX: LazyLock<Regex> =
+            LazyLock::new(|| Regex::new(r"^PROBE_BACK=\[([0-9a-f:.\[\]#]+)\];$").unwrap());
+
+        if PROBE_BACK_REGEX.is_match(request) {
+            let request_datas = PROBE_BACK_REGEX.captures(request).unwrap();
+            let node_addr = NodeAddr::parse(&request_datas[1])
+                .ok_or("invalid request (invalid socket address)")?;
+
+            return Ok(Some(Self::ProbeBack(node_addr)));
+        }
+
+        Ok(None)
+    }
+
+    /// Returns the node carried by the request, whose
+    /// identifier is compared with the receiver's.
+    pub(crate) fn get_carried_node(&self) -> Option<&Node> {
+        match self {
+            Self::FindSuccessorOfNode(node, _)
+            | Self::NotificationBy(node)
+            | Self::Leaving(node)
+            | Self::SetPredecessor(_, node) => Some(node),
+            _ => None,
+        }
+    }
+
     /// Converts the current `ChordRequest` abstraction
     /// into a text-based representation,
     /// according to the protocol specification.
     pub(crate) fn to_protocol_text(&self) -> String {
         match *self {
-            Self::FindSuccessorOfNode(ref target_node) => {
+            Self::FindSuccessorOfNode(ref target_node, trace_id) => {
                 format!(
-                    "FIND_SUCCESSOR_OF_NODE=[{}][{:?}];",
+                    "FIND_SUCCESSOR_OF_NODE=[{}][{}]{};",
                     target_node.get_hash_id(),
-                    target_node.get_public_addr()
+                    target_node.get_node_addr(),
+                    trace_id_to_protocol_text(trace_id)
                 )
             }
+            Self::FindSuccessorOfKey(key, trace_id) => format!(
+                "FIND_SUCCESSOR_OF_KEY=[{}]{};",
+                position_to_protocol_text(key),
+                trace_id_to_protocol_text(trace_id)
+            ),
             Self::GetSuccessorList => "GET_SUCCESSOR_LIST;".to_string(),
             Self::GetPredecessor => "GET_PREDECESSOR;".to_string(),
+            Self::GetNeighborhood => "GET_NEIGHBORHOOD;".to_string(),
             Self::NotificationBy(ref node) => {
                 format!(
-                    "NOTIFICATION_BY=[{}][{:?}];",
+                    "NOTIFICATION_BY=[{}][{}];",
                     node.get_hash_id(),
-                    node.get_public_addr()
+                    node.get_node_addr()
                 )
             }
             Self::CheckNode => "CHECK_NODE;".to_string(),
+            Self::Stabilize => "STABILIZE;".to_string(),
+            Self::Leaving(ref node) => {
+                format!(
+                    "LEAVING=[{}][{}];",
+                    node.get_hash_id(),
+                    node.get_node_addr()
+                )
+            }
+            Self::Shutdown(ref secret) => format!("SHUTDOWN=[{}];", secret),
+            Self::Drain(ref secret) => format!("DRAIN=[{}];", secret),
+            Self::GetFingerTable => "GET_FINGER_TABLE;".to_string(),
+            Self::GetOwnedRange => "GET_OWNED_RANGE;".to_string(),
+            Self::GetNodeInfo => "GET_NODE_INFO;".to_string(),
+            Self::SetPredecessor(ref secret, ref node) => {
+                format!(
+                    "SET_PREDECESSOR=[{}][{}][{}];",
+                    secret,
+                    node.get_hash_id(),
+                    node.get_node_addr()
+                )
+            }
+            Self::ProbeBack(ref node_addr) => format!("PROBE_BACK=[{}];", node_addr),
+        }
+    }
+}
+
+/// Returns the pattern of an identifier in the protocol:
+/// one hexadecimal digit per 4 bits of the ring, whose bit
+/// length is a multiple of 8, up to `MAX_RING_BIT_LENGTH`.
+fn id_pattern() -> String {
+    format!("(?:[0-9a-f]{{2}}){{1,{}}}", RING_BYTE_LENGTH)
+}
+
+/// Decodes the node of identifier `hash_id` (matched by
+/// `id_pattern`), on the ring of the identifier's bit length.
+fn decode_node(hash_id: &str, node_addr: NodeAddr) -> Node {
+    Node::create_at(decode_id(hash_id), node_addr).on_ring(hash_id.len() * 4)
+}
+
+/// Decodes an identifier matched by `id_pattern`
+/// into raw bytes format, zero-padded.
+fn decode_id(hash_id: &str) -> [u8; RING_BYTE_LENGTH] {
+    let mut id = [0; RING_BYTE_LENGTH];
+    hex::decode_to_slice(hash_id, &mut id[..hash_id.len() / 2]).unwrap(); // Safe unwrap, hex digits
+
+    id
+}
+
+/// Encodes `position` as an identifier of the protocol, on its
+/// significant leading bytes: the trailing zero bytes, which
+/// include the bits unused by a ring, are dropped.
+fn position_to_protocol_text(position: u128) -> String {
+    let significant_bytes = RING_BYTE_LENGTH - (position.trailing_zeros() as usize / 8);
+
+    hex::encode(&position.to_be_bytes()[..significant_bytes.max(1)])
+}
+
+/// Characters of an error message escaped on the wire (as
+/// `%XX`, their hexadecimal code), so that the message cannot
+/// be mistaken for the delimiters of the response.
+const ERROR_MESSAGE_ESCAPES: [(char, &str); 6] = [
+    ('%', "%25"),
+    ('[', "%5B"),
+    (']', "%5D"),
+    (';', "%3B"),
+    ('\n', "%0A"),
+    ('\r', "%0D"),
+];
+
+/// Escapes the delimiters and line breaks of an error message.
+fn escape_error_message(message: &str) -> String {
+    escape_text(message, &ERROR_MESSAGE_ESCAPES)
+}
+
+/// Reverts `escape_error_message`. Other `%` sequences are
+/// kept as is, as in the messages of older nodes.
+fn unescape_error_message(message: &str) -> String {
+    unescape_text(message, &ERROR_MESSAGE_ESCAPES)
+}
+
+/// Replaces the characters of `text` listed in
+/// `escapes` by their escape sequence.
+pub(crate) fn escape_text(text: &str, escapes: &[(char, &str)]) -> String {
+    text.chars().fold(String::new(), |mut escaped, c| {
+        match escapes.iter().find(|(escaped_char, _)| *escaped_char == c) {
+            Some((_, escape)) => escaped.push_str(escape),
+            None => escaped.push(c),
+        }
+
+        escaped
+    })
+}
+
+/// Reverts `escape_text`. The `%` sequences
+/// missing from `escapes` are kept as is.
+pub(crate) fn unescape_text(text: &str, escapes: &[(char, &str)]) -> String {
+    let mut unescaped = String::new();
+    let mut rest = text;
+
+    while let Some(escape_start) = rest.find('%') {
+        unescaped.push_str(&rest[..escape_start]);
+        rest = &rest[escape_start..];
+
+        match escapes.iter().find(|(_, escape)| rest.starts_with(escape)) {
+            Some((escaped_char, escape)) => {
+                unescaped.push(*escaped_char);
+                rest = &rest[escape.len()..];
+            }
+            None => {
+                unescaped.push('%');
+                rest = &rest[1..];
+            }
         }
     }
+
+    unescaped.push_str(rest);
+    unescaped
+}
+
+/// Parses the optional trace ID of a lookup request.
+fn parse_trace_id(trace_id: Option<regex::Match>) -> Result<Option<TraceId>, &'static str> {
+    match trace_id {
+        Some(trace_id) => TraceId::parse(trace_id.as_str())
+            .map(Some)
+            .ok_or("invalid request (invalid trace ID)"),
+        None => Ok(None),
+    }
+}
+
+/// Converts the optional trace ID of a lookup request into
+/// its text-based representation: `[TRACE=<id>]`, if any.
+fn trace_id_to_protocol_text(trace_id: Option<TraceId>) -> String {
+    match trace_id {
+        Some(trace_id) => format!("[TRACE={}]", trace_id),
+        None => String::new(),
+    }
 }
 
 #[cfg(test)]
 mod chord_request_protocol_test {
     use std::net::SocketAddr;
 
-    use crate::chord::Node;
+    use crate::chord::{trace::TraceId, Node, NodeAddr};
 
-    use super::ChordRequest;
+    use super::{decode_id, ChordRequest};
 
     #[test]
     fn find_successor_of_node_request_parse_test() {
@@ -585,7 +1545,8 @@ mod chord_request_protocol_test {
 
         let chord_request = ChordRequest::parse(request).unwrap();
 
-        if let ChordRequest::FindSuccessorOfNode(target_node) = chord_request {
+        if let ChordRequest::FindSuccessorOfNode(target_node, trace_id) = chord_request {
+            assert_eq!(trace_id, None);
             assert_eq!(
                 target_node.get_hash_id(),
                 "080501321f1d3ab94c90052a1938e7dc"
@@ -601,6 +1562,73 @@ mod chord_request_protocol_test {
         }
     }
 
+    #[test]
+    fn find_successor_of_key_request_parse_test() {
+        let request = "FIND_SUCCESSOR_OF_KEY=[080501321f1d3ab94c90052a1938e7dc];";
+
+        assert_eq!(
+            ChordRequest::parse(request).unwrap(),
+            ChordRequest::FindSuccessorOfKey(0x080501321f1d3ab94c90052a1938e7dc, None)
+        );
+
+        // Keys are written on their significant leading bytes
+        let request = "FIND_SUCCESSOR_OF_KEY=[0805];";
+        let chord_request = ChordRequest::parse(request).unwrap();
+
+        assert_eq!(
+            chord_request,
+            ChordRequest::FindSuccessorOfKey(0x0805 << 112, None)
+        );
+        assert_eq!(chord_request.to_protocol_text(), request);
+        assert_eq!(
+            ChordRequest::FindSuccessorOfKey(0, None).to_protocol_text(),
+            "FIND_SUCCESSOR_OF_KEY=[00];"
+        );
+
+        // Keys are whole bytes, of at most 128 bits
+        assert!(ChordRequest::parse("FIND_SUCCESSOR_OF_KEY=[080];").is_err());
+        assert!(
+            ChordRequest::parse(&format!("FIND_SUCCESSOR_OF_KEY=[{}];", "08".repeat(17))).is_err()
+        );
+    }
+
+    #[test]
+    fn traced_lookup_request_protocol_test() {
+        let trace_id = TraceId::parse("00000000deadbeef");
+
+        let request =
+            "FIND_SUCCESSOR_OF_KEY=[080501321f1d3ab94c90052a1938e7dc][TRACE=00000000deadbeef];";
+        let chord_request = ChordRequest::parse(request).unwrap();
+
+        assert_eq!(
+            chord_request,
+            ChordRequest::FindSuccessorOfKey(0x080501321f1d3ab94c90052a1938e7dc, trace_id)
+        );
+        assert_eq!(chord_request.to_protocol_text(), request);
+
+        let request = "FIND_SUCCESSOR_OF_NODE=[080501321f1d3ab94c90052a1938e7dc][[2001:db8::1]:8080][TRACE=00000000deadbeef];";
+        let chord_request = ChordRequest::parse(request).unwrap();
+
+        if let ChordRequest::FindSuccessorOfNode(ref target_node, request_trace_id) = chord_request
+        {
+            assert_eq!(
+                target_node.get_public_addr(),
+                "[2001:db8::1]:8080".parse::<SocketAddr>().unwrap()
+            );
+            assert_eq!(request_trace_id, trace_id);
+        } else {
+            panic!("parsing error");
+        }
+
+        assert_eq!(chord_request.to_protocol_text(), request);
+
+        // Trace IDs are 64-bit hexadecimal values
+        assert!(ChordRequest::parse(
+            "FIND_SUCCESSOR_OF_KEY=[080501321f1d3ab94c90052a1938e7dc][TRACE=beef];"
+        )
+        .is_err());
+    }
+
     #[test]
     fn get_successor_list_request_parse_test() {
         let request = "GET_SUCCESSOR_LIST;";
@@ -650,6 +1678,150 @@ mod chord_request_protocol_test {
         );
     }
 
+    #[test]
+    fn stabilize_request_parse_test() {
+        let request = "STABILIZE;";
+
+        assert_eq!(
+            ChordRequest::parse(request).unwrap(),
+            ChordRequest::Stabilize
+        );
+    }
+
+    #[test]
+    fn leaving_request_parse_test() {
+        let request = "LEAVING=[080501321f1d3ab94c90052a1938e7dc][10.0.0.33:443];";
+
+        let chord_request = ChordRequest::parse(request).unwrap();
+
+        if let ChordRequest::Leaving(node) = chord_request {
+            assert_eq!(node.get_hash_id(), "080501321f1d3ab94c90052a1938e7dc");
+            assert_eq!(
+                node.get_public_addr(),
+                "10.0.0.33:443".parse::<SocketAddr>().unwrap()
+            )
+        } else {
+            panic!("parsing error");
+        }
+    }
+
+    #[test]
+    fn shutdown_request_parse_test() {
+        let request = "SHUTDOWN=[s3cr3t-value];";
+
+        assert_eq!(
+            ChordRequest::parse(request).unwrap(),
+            ChordRequest::Shutdown("s3cr3t-value".to_string())
+        );
+
+        // A secret is required
+        assert!(ChordRequest::parse("SHUTDOWN=[];").is_err());
+        assert!(ChordRequest::parse("SHUTDOWN;").is_err());
+    }
+
+    #[test]
+    fn drain_request_parse_test() {
+        let request = "DRAIN=[s3cr3t-value];";
+
+        assert_eq!(
+            ChordRequest::parse(request).unwrap(),
+            ChordRequest::Drain("s3cr3t-value".to_string())
+        );
+
+        // A secret is required
+        assert!(ChordRequest::parse("DRAIN=[];").is_err());
+        assert!(ChordRequest::parse("DRAIN;").is_err());
+    }
+
+    #[test]
+    fn set_predecessor_request_parse_test() {
+        let request =
+            "SET_PREDECESSOR=[s3cr3t-value][cf4b19e32ce29fef04468ac9d2a6787d][10.0.0.33:443];";
+
+        assert_eq!(
+            ChordRequest::parse(request).unwrap(),
+            ChordRequest::SetPredecessor(
+                "s3cr3t-value".to_string(),
+                Node::create_from(
+                    hex::decode("cf4b19e32ce29fef04468ac9d2a6787d")
+                        .unwrap()
+                        .try_into()
+                        .unwrap(),
+                    "10.0.0.33:443".parse::<SocketAddr>().unwrap()
+                )
+            )
+        );
+
+        // A secret is required
+        assert!(ChordRequest::parse(
+            "SET_PREDECESSOR=[][cf4b19e32ce29fef04468ac9d2a6787d][10.0.0.33:443];"
+        )
+        .is_err());
+        assert!(ChordRequest::parse(
+            "SET_PREDECESSOR=[cf4b19e32ce29fef04468ac9d2a6787d][10.0.0.33:443];"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn get_finger_table_request_parse_test() {
+        let request = "GET_FINGER_TABLE;";
+
+        assert_eq!(
+            ChordRequest::parse(request).unwrap(),
+            ChordRequest::GetFingerTable
+        );
+    }
+
+    #[test]
+    fn get_neighborhood_request_protocol_test() {
+        let request = "GET_NEIGHBORHOOD;";
+
+        assert_eq!(
+            ChordRequest::parse(request).unwrap(),
+            ChordRequest::GetNeighborhood
+        );
+        assert_eq!(ChordRequest::GetNeighborhood.to_protocol_text(), request);
+    }
+
+    #[test]
+    fn get_owned_range_request_protocol_test() {
+        let request = "GET_OWNED_RANGE;";
+
+        assert_eq!(
+            ChordRequest::parse(request).unwrap(),
+            ChordRequest::GetOwnedRange
+        );
+        assert_eq!(ChordRequest::GetOwnedRange.to_protocol_text(), request);
+    }
+
+    #[test]
+    fn get_node_info_request_protocol_test() {
+        let request = "GET_NODE_INFO;";
+
+        assert_eq!(
+            ChordRequest::parse(request).unwrap(),
+            ChordRequest::GetNodeInfo
+        );
+        assert_eq!(ChordRequest::GetNodeInfo.to_protocol_text(), request);
+    }
+
+    #[test]
+    fn probe_back_request_protocol_test() {
+        let node_addr = NodeAddr::new("[2001:db8::1]:4040".parse().unwrap(), 2);
+        let request = "PROBE_BACK=[[2001:db8::1]:4040#2];";
+
+        assert_eq!(
+            ChordRequest::parse(request).unwrap(),
+            ChordRequest::ProbeBack(node_addr)
+        );
+        assert_eq!(
+            ChordRequest::ProbeBack(node_addr).to_protocol_text(),
+            request
+        );
+        assert!(ChordRequest::parse("PROBE_BACK=[localhost];").is_err());
+    }
+
     #[test]
     fn chord_request_to_protocol_text_test() {
         let node = Node::create_from(
@@ -665,11 +1837,11 @@ mod chord_request_protocol_test {
         // FIND_SUCCESSOR_OF_NODE request abstraction
         // to text-based protocol
         let chord_request = ChordRequest::parse(
-            &ChordRequest::FindSuccessorOfNode(node.clone()).to_protocol_text(),
+            &ChordRequest::FindSuccessorOfNode(node.clone(), None).to_protocol_text(),
         )
         .unwrap();
 
-        if let ChordRequest::FindSuccessorOfNode(target_node) = chord_request {
+        if let ChordRequest::FindSuccessorOfNode(target_node, _) = chord_request {
             assert_eq!(target_node, node)
         } else {
             panic!("parsing error");
@@ -704,5 +1876,100 @@ mod chord_request_protocol_test {
         // CHECK_NODE request abstraction
         // to text-based protocol
         assert_eq!(ChordRequest::CheckNode.to_protocol_text(), "CHECK_NODE;");
+
+        // STABILIZE request abstraction
+        // to text-based protocol
+        assert_eq!(ChordRequest::Stabilize.to_protocol_text(), "STABILIZE;");
+
+        // LEAVING request abstraction
+        // to text-based protocol
+        assert_eq!(
+            ChordRequest::parse(&ChordRequest::Leaving(node.clone()).to_protocol_text()).unwrap(),
+            ChordRequest::Leaving(node.clone())
+        );
+
+        // SHUTDOWN request abstraction
+        // to text-based protocol
+        assert_eq!(
+            ChordRequest::Shutdown("secret".to_string()).to_protocol_text(),
+            "SHUTDOWN=[secret];"
+        );
+
+        // DRAIN request abstraction
+        // to text-based protocol
+        assert_eq!(
+            ChordRequest::Drain("secret".to_string()).to_protocol_text(),
+            "DRAIN=[secret];"
+        );
+
+        // SET_PREDECESSOR request abstraction
+        // to text-based protocol
+        assert_eq!(
+            ChordRequest::parse(
+                &ChordRequest::SetPredecessor("secret".to_string(), node.clone())
+                    .to_protocol_text()
+            )
+            .unwrap(),
+            ChordRequest::SetPredecessor("secret".to_string(), node)
+        );
+
+        // FIND_SUCCESSOR_OF_KEY request abstraction
+        // to text-based protocol
+        assert_eq!(
+            ChordRequest::FindSuccessorOfKey(42, None).to_protocol_text(),
+            "FIND_SUCCESSOR_OF_KEY=[0000000000000000000000000000002a];"
+        );
+
+        // GET_FINGER_TABLE request abstraction
+        // to text-based protocol
+        assert_eq!(
+            ChordRequest::GetFingerTable.to_protocol_text(),
+            "GET_FINGER_TABLE;"
+        );
+    }
+
+    #[test]
+    fn virtual_node_request_protocol_test() {
+        let request = "NOTIFICATION_BY=[f41fec42855bc4c2fa9dd3e504a846d8][127.0.0.1:8000#3];";
+
+        let chord_request = ChordRequest::parse(request).unwrap();
+        let node = Node::create_at(
+            decode_id("f41fec42855bc4c2fa9dd3e504a846d8"),
+            NodeAddr::new("127.0.0.1:8000".parse::<SocketAddr>().unwrap(), 3),
+        );
+
+        assert_eq!(chord_request, ChordRequest::NotificationBy(node.clone()));
+        assert_eq!(chord_request.to_protocol_text(), request);
+
+        // Virtual node 0 is written without index
+        assert!(ChordRequest::parse(
+            "NOTIFICATION_BY=[f41fec42855bc4c2fa9dd3e504a846d8][127.0.0.1:8000#0];"
+        )
+        .is_err());
+
+        // Requests are addressed to a virtual node by index
+        let addressed_request = node.get_node_addr().address_request("CHECK_NODE;");
+        assert_eq!(addressed_request, "VNODE=[3];CHECK_NODE;");
+        assert_eq!(
+            NodeAddr::split_request(&addressed_request),
+            Some((3, "CHECK_NODE;"))
+        );
+        assert_eq!(
+            NodeAddr::split_request("CHECK_NODE;"),
+            Some((0, "CHECK_NODE;"))
+        );
+        assert_eq!(NodeAddr::split_request("VNODE=[0];CHECK_NODE;"), None);
+        assert_eq!(NodeAddr::split_request("VNODE=[x];CHECK_NODE;"), None);
+    }
+
+    #[test]
+    fn short_identifier_decode_test() {
+        // Identifiers of a ring of fewer bits
+        // occupy the leading bytes
+        assert_eq!(u128::from_be_bytes(decode_id("f41fec42")), 0xf41fec42 << 96);
+        assert_eq!(
+            u128::from_be_bytes(decode_id("f41fec42855bc4c2fa9dd3e504a846d8")),
+            0xf41fec42855bc4c2fa9dd3e504a846d8
+        );
     }
 }
@@ -0,0 +1,224 @@
+//! Reachability audit of the link between a node and its
+//! successor (`reachability-audit`).
+//!
+//! Liveness is only probed from a node to its successor list.
+//! If the successor cannot reach the node back (e.g. a one-way
+//! firewall rule), the successor drops it as a dead predecessor
+//! while it keeps being notified by it: the ring flaps without
+//! any visible failure. The audit asks the successor to probe
+//! the node back, and reports such an asymmetric link.
+
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use crate::transport::Transport;
+
+use super::{protocol::ChordResponse, request_initiator, Node};
+
+/// Periodic audit of the link between a node and its successor.
+#[derive(Debug)]
+pub(crate) struct ReachabilityAudit {
+    interval: Duration,
+    next_audit: Instant,
+    /// Successor which cannot reach the node back, if
+    /// any, shared with the status output of the node.
+    asymmetric_successor: Arc<RwLock<Option<Node>>>,
+}
+
+impl ReachabilityAudit {
+    /// Creates an audit running every `interval`, from `now`,
+    /// which records the successor of an asymmetric link
+    /// in `asymmetric_successor`.
+    pub(crate) fn new(
+        interval: Duration,
+        asymmetric_successor: Arc<RwLock<Option<Node>>>,
+        now: Instant,
+    ) -> Self {
+        Self {
+            interval,
+            next_audit: now,
+            asymmetric_successor,
+        }
+    }
+
+    /// Asks `successor` to probe `self_node` back, if an audit is
+    /// due at `now`, and logs the link when it becomes asymmetric
+    /// or symmetric again.
+    ///
+    /// A successor which does not answer (or does not support the
+    /// audit) leaves the last outcome as is: its failure is
+    /// handled by the stabilization.
+    pub(crate) fn run_if_due(
+        &mut self,
+        transport: &dyn Transport,
+        self_node: &Node,
+        successor: &Node,
+        now: Instant,
+    ) {
+        if now < self.next_audit || successor == self_node {
+            return;
+        }
+
+        self.next_audit = now + self.interval;
+
+        let is_asymmetric =
+            match request_initiator::probe_back(transport, self_node, successor.get_node_addr()) {
+                ChordResponse::Active => false,
+                ChordResponse::Unreachable => true,
+                _ => return,
+            };
+
+        let mut asymmetric_successor = self.asymmetric_successor.write().unwrap();
+
+        match *asymmetric_successor {
+            Some(ref known_successor) if is_asymmetric && known_successor == successor => (),
+            _ if is_asymmetric => {
+                eprintln!(
+                    "asymmetric link: [{:?}] reaches its successor [{:?}], which cannot reach it back",
+                    self_node.get_public_addr(),
+                    successor.get_public_addr()
+                );
+                *asymmetric_successor = Some(successor.clone());
+            }
+            Some(ref known_successor) => {
+                eprintln!(
+                    "asymmetric link with [{:?}] resolved",
+                    known_successor.get_public_addr()
+                );
+                *asymmetric_successor = None;
+            }
+            None => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod reachability_audit_test {
+    use std::{
+        io,
+        sync::{Arc, RwLock},
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        chord::{
+            protocol::{ChordRequest, ChordResponse, ErrorKind},
+            request_handler::probe_back_request_handler,
+            Node,
+        },
+        transport::mock::MockTransport,
+    };
+
+    use super::ReachabilityAudit;
+
+    /// Mock transport of node 100, whose successor 200
+    /// probes it back, reaching it only if `is_reachable_back`.
+    fn transport(is_reachable_back: bool) -> MockTransport {
+        MockTransport::new(move |_, request_msg| {
+            let successor_transport = MockTransport::new(move |_, _| {
+                if is_reachable_back {
+                    Ok(ChordResponse::Active.to_protocol_text())
+                } else {
+                    Err(io::Error::from(io::ErrorKind::ConnectionRefused))
+                }
+            });
+
+            let response = match ChordRequest::parse(request_msg) {
+                Ok(ChordRequest::ProbeBack(requester_addr)) => {
+                    probe_back_request_handler(&successor_transport, requester_addr, None)
+                }
+                _ => ChordResponse::Error(ErrorKind::Fatal, "unexpected request".to_string()),
+            };
+
+            Ok(response.to_protocol_text())
+        })
+    }
+
+    #[test]
+    fn one_way_link_is_reported_test() {
+        let asymmetric_successor = Arc::new(RwLock::new(None));
+        let now = Instant::now();
+        let mut audit = ReachabilityAudit::new(
+            Duration::from_secs(60),
+            Arc::clone(&asymmetric_successor),
+            now,
+        );
+
+        audit.run_if_due(
+            &transport(false),
+            &Node::for_test_at(100),
+            &Node::for_test_at(200),
+            now,
+        );
+        assert_eq!(
+            *asymmetric_successor.read().unwrap(),
+            Some(Node::for_test_at(200))
+        );
+
+        // Not audited again before the interval
+        let transport_before_due = transport(true);
+        audit.run_if_due(
+            &transport_before_due,
+            &Node::for_test_at(100),
+            &Node::for_test_at(200),
+            now + Duration::from_secs(30),
+        );
+        assert!(transport_before_due.sent_requests().is_empty());
+        assert_eq!(
+            *asymmetric_successor.read().unwrap(),
+            Some(Node::for_test_at(200))
+        );
+
+        audit.run_if_due(
+            &transport(true),
+            &Node::for_test_at(100),
+            &Node::for_test_at(200),
+            now + Duration::from_secs(60),
+        );
+        assert_eq!(*asymmetric_successor.read().unwrap(), None);
+    }
+
+    #[test]
+    fn unreachable_successor_keeps_last_outcome_test() {
+        let asymmetric_successor = Arc::new(RwLock::new(Some(Node::for_test_at(200))));
+        let now = Instant::now();
+        let mut audit = ReachabilityAudit::new(
+            Duration::from_secs(60),
+            Arc::clone(&asymmetric_successor),
+            now,
+        );
+
+        let unreachable_transport =
+            MockTransport::new(|_, _| Err(io::Error::from(io::ErrorKind::ConnectionRefused)));
+
+        audit.run_if_due(
+            &unreachable_transport,
+            &Node::for_test_at(100),
+            &Node::for_test_at(200),
+            now,
+        );
+        assert_eq!(
+            *asymmetric_successor.read().unwrap(),
+            Some(Node::for_test_at(200))
+        );
+    }
+
+    #[test]
+    fn third_parties_are_not_probed_test() {
+        let transport = MockTransport::new(|_, _| Ok(ChordResponse::Active.to_protocol_text()));
+
+        let response = probe_back_request_handler(
+            &transport,
+            Node::for_test_at(100).get_node_addr(),
+            Some("10.0.0.2".parse().unwrap()),
+        );
+
+        assert!(matches!(
+            response,
+            ChordResponse::Error(ErrorKind::Fatal, _)
+        ));
+        assert!(transport.sent_requests().is_empty());
+    }
+}
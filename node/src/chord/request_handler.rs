@@ -1,56 +1,428 @@
 //! Responsible for processing and handling various types
 //! of requests in the Chord network.
 
-use std::sync::{Arc, RwLock};
+use std::{
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
 
-use super::{protocol::ChordResponse, request_initiator, Node, SUCCESSOR_LIST_LENGTH};
+use subtle::ConstantTimeEq;
 
+use crate::{
+    chord,
+    cli::{AddressFamily, IdentityMode, RoutingMode},
+    context::NodeContext,
+    gossip::limiter::{InFlightLimiter, InFlightPermit},
+    observer::NodeObserver,
+    transport::Transport,
+};
+
+use super::{
+    finger_table::FingerTable,
+    node_info::NodeInfo,
+    protocol::{ChordResponse, ErrorKind},
+    request_initiator,
+    ring_range::RingRange,
+    trace::{self, TraceId},
+    utils, Node, NodeAddr, SUCCESSOR_LIST_LENGTH,
+};
+
+/// Locates the successor of `target_node`, which is joining
+/// the network, at the node running in `context`, forwarding
+/// the lookup if necessary.
+///
+/// With a lookup cache (`lookup-cache-ttl`), the owner of a
+/// recently resolved position is returned without forwarding
+/// the lookup. In iterative routing (`routing-mode`), the
+/// initiator is redirected to the next node instead.
+///
+/// A `target_node` identical to the successor of the node
+/// (same identifier and address) is restarting: it resumes its
+/// place in the ring, and is answered with the next successor.
+/// Any other identifier collision with the node, its successor
+/// list or its predecessor is an error, so that the join is
+/// rejected before it breaks the pointers of the ring. A join
+/// is also rejected by the membership policy (`max-nodes`).
+///
+/// If no entry of the successor list is a live next hop,
+/// a `target_node` between the predecessor and the node
+/// is routed through the predecessor, which still
+/// knows its successor.
+///
+/// The worker is not blocked on a forwarded lookup once
+/// too many are in flight (`max-forwarded-lookups`): the lookup
+/// is then answered with a retryable error (see `forward_permit`).
+///
+/// The outcome is logged if the lookup is traced by `trace_id`,
+/// which is propagated to the forwarded lookup. The successor
+/// of a traced lookup is answered with its hop count (see
+/// `count_hop`).
 pub(crate) fn find_successor_of_node_request_handler(
-    self_node: Node,
-    self_node_successor_list: [Node; SUCCESSOR_LIST_LENGTH],
+    context: &NodeContext,
     target_node: Node,
+    trace_id: Option<TraceId>,
 ) -> ChordResponse {
+    let transport = context.transport.as_ref();
+    let self_node = &context.self_node;
+    let self_node_successor_list = context.self_node_successor_list.read().unwrap().clone();
+    let self_node_predecessor = context.self_node_predecessor.read().unwrap().clone();
+    let self_node_lookup_cache = context.self_node_lookup_cache.as_deref();
+    let observer = context.observer.as_ref();
+
     let self_node_successor = self_node_successor_list[0].clone();
 
-    if target_node.get_ring_position() == self_node.get_ring_position()
+    let lookup = format!(
+        "lookup of node [{:?}] at [{:?}]",
+        target_node.get_public_addr(),
+        self_node.get_public_addr()
+    );
+
+    // A node restarting with the same identifier and address
+    // resumes its place: its successor is the next one.
+    if target_node == self_node_successor && target_node != *self_node {
+        let resumed_successor = self_node_successor_list[1].clone();
+
+        trace::log(trace_id, || {
+            format!(
+                "{}: restarting node, resumed with successor [{:?}]",
+                lookup,
+                resumed_successor.get_public_addr()
+            )
+        });
+        observer.lookup_completed(&target_node, &resumed_successor);
+        return count_hop(ChordResponse::Successor(resumed_successor), trace_id);
+    }
+
+    // Deeper successors and the predecessor are not resumed
+    // here: a node identical to one of them is left to the
+    // lookup, which reaches the node it directly follows.
+    let is_duplicate = target_node.get_ring_position() == self_node.get_ring_position()
         || target_node.get_ring_position() == self_node_successor.get_ring_position()
-    {
+        || self_node_successor_list[1..]
+            .iter()
+            .chain(self_node_predecessor.iter())
+            .any(|node| {
+                node.get_ring_position() == target_node.get_ring_position() && *node != target_node
+            });
+
+    if is_duplicate {
+        trace::log(trace_id, || format!("{}: duplicate identifier", lookup));
         return ChordResponse::Error(
+            ErrorKind::Fatal,
             "the node's identifier already exists in the network".to_string(),
         );
     }
 
-    if self_node.get_ring_position() == self_node_successor.get_ring_position() {
-        return ChordResponse::Successor(self_node_successor);
+    if let Err(err) = context
+        .membership_policy()
+        .admit(self_node, &self_node_successor_list)
+    {
+        trace::log(trace_id, || format!("{}: rejected, {}", lookup, err));
+        return ChordResponse::Error(ErrorKind::Fatal, err);
     }
 
-    if target_node.is_position_stictly_between(
+    if let Some(lookup_cache) = self_node_lookup_cache {
+        let cached_successor = lookup_cache
+            .read()
+            .unwrap()
+            .get(target_node.get_ring_position(), Instant::now());
+
+        if let Some(successor) = cached_successor {
+            trace::log(trace_id, || {
+                format!(
+                    "{}: successor [{:?}] (cached)",
+                    lookup,
+                    successor.get_public_addr()
+                )
+            });
+            observer.lookup_completed(&target_node, &successor);
+            return count_hop(ChordResponse::Successor(successor), trace_id);
+        }
+    }
+
+    // The successor of a single-node ring is `self_node`,
+    // and the range then covers the whole ring.
+    if RingRange::new(
         self_node.get_ring_position(),
         self_node_successor.get_ring_position(),
-    ) {
-        return ChordResponse::Successor(self_node_successor);
+    )
+    .contains(target_node.get_ring_position())
+    {
+        trace::log(trace_id, || {
+            format!(
+                "{}: successor [{:?}]",
+                lookup,
+                self_node_successor.get_public_addr()
+            )
+        });
+        observer.lookup_completed(&target_node, &self_node_successor);
+        return count_hop(ChordResponse::Successor(self_node_successor), trace_id);
     }
 
-    let mut closest_preceding_node_to_target: Option<Node> = None;
-
-    for entry in self_node_successor_list.into_iter().rev() {
-        if entry.is_position_stictly_between(
-            self_node.get_ring_position(),
+    let closest_preceding_node_to_target = match closest_preceding_active_node(
+        transport,
+        self_node,
+        self_node_successor_list,
+        target_node.get_ring_position(),
+    )
+    .or_else(|| {
+        active_predecessor_behind(
+            transport,
+            self_node,
+            self_node_predecessor,
             target_node.get_ring_position(),
-        ) {
-            if let ChordResponse::Active =
-                request_initiator::check_remote_node(entry.get_public_addr())
-            {
-                closest_preceding_node_to_target = Some(entry);
-                break;
-            }
+        )
+    }) {
+        Some(next_hop) => next_hop,
+        None => {
+            trace::log(trace_id, || format!("{}: no active next hop", lookup));
+            return ChordResponse::Error(
+                ErrorKind::Retryable,
+                "no active node precedes the target in the successor list".to_string(),
+            );
         }
+    };
+
+    if context.options.routing_mode == RoutingMode::Iterative {
+        trace::log(trace_id, || {
+            format!(
+                "{}: redirected to [{:?}]",
+                lookup,
+                closest_preceding_node_to_target.get_public_addr()
+            )
+        });
+        return ChordResponse::Redirect(closest_preceding_node_to_target);
     }
 
-    request_initiator::find_successor_of_node(
+    let _permit = match forward_permit(Some(&context.forward_limiter)) {
+        Ok(permit) => permit,
+        Err(err) => {
+            trace::log(trace_id, || format!("{}: shed, {}", lookup, err));
+            return ChordResponse::Error(ErrorKind::Retryable, err);
+        }
+    };
+
+    trace::log(trace_id, || {
+        format!(
+            "{}: forwarded to [{:?}]",
+            lookup,
+            closest_preceding_node_to_target.get_public_addr()
+        )
+    });
+
+    let response = request_initiator::find_successor_of_node(
+        transport,
         &target_node,
-        closest_preceding_node_to_target.unwrap().get_public_addr(),
+        closest_preceding_node_to_target.get_node_addr(),
+        trace_id,
+    );
+
+    if let ChordResponse::Successor(ref successor)
+    | ChordResponse::TracedSuccessor(ref successor, _) = response
+    {
+        if let Some(lookup_cache) = self_node_lookup_cache {
+            lookup_cache.write().unwrap().insert(
+                target_node.get_ring_position(),
+                successor.clone(),
+                Instant::now(),
+            );
+        }
+
+        observer.lookup_completed(&target_node, successor);
+    }
+
+    count_hop(response, trace_id)
+}
+
+/// Locates the successor of `key`, the node owning this
+/// ring position, forwarding the lookup if necessary.
+///
+/// Unlike `find_successor_of_node_request_handler`, no node
+/// is joining: membership checks do not apply. Forwards are
+/// limited by `forward_limiter` and traced lookups count their
+/// hops the same way.
+pub(crate) fn find_successor_of_key_request_handler(
+    transport: &dyn Transport,
+    self_node: Node,
+    self_node_successor_list: [Node; SUCCESSOR_LIST_LENGTH],
+    key: u128,
+    trace_id: Option<TraceId>,
+    routing_mode: RoutingMode,
+    forward_limiter: Option<&InFlightLimiter>,
+) -> ChordResponse {
+    let self_node_successor = self_node_successor_list[0].clone();
+
+    let lookup = format!(
+        "lookup of key [{:032x}] at [{:?}]",
+        key,
+        self_node.get_public_addr()
+    );
+
+    let owner = if key == self_node.get_ring_position() {
+        Some(self_node.clone())
+    } else if RingRange::new(
+        self_node.get_ring_position(),
+        self_node_successor.get_ring_position(),
     )
+    .contains(key)
+    {
+        Some(self_node_successor)
+    } else {
+        None
+    };
+
+    if let Some(owner) = owner {
+        trace::log(trace_id, || {
+            format!("{}: successor [{:?}]", lookup, owner.get_public_addr())
+        });
+        return count_hop(ChordResponse::Successor(owner), trace_id);
+    }
+
+    match closest_preceding_active_node(transport, &self_node, self_node_successor_list, key) {
+        Some(next_hop) if routing_mode == RoutingMode::Iterative => {
+            trace::log(trace_id, || {
+                format!(
+                    "{}: redirected to [{:?}]",
+                    lookup,
+                    next_hop.get_public_addr()
+                )
+            });
+            ChordResponse::Redirect(next_hop)
+        }
+        Some(next_hop) => {
+            let _permit = match forward_permit(forward_limiter) {
+                Ok(permit) => permit,
+                Err(err) => {
+                    trace::log(trace_id, || format!("{}: shed, {}", lookup, err));
+                    return ChordResponse::Error(ErrorKind::Retryable, err);
+                }
+            };
+
+            trace::log(trace_id, || {
+                format!(
+                    "{}: forwarded to [{:?}]",
+                    lookup,
+                    next_hop.get_public_addr()
+                )
+            });
+            let response = request_initiator::find_successor_of_key(
+                transport,
+                key,
+                next_hop.get_node_addr(),
+                trace_id,
+            );
+
+            count_hop(response, trace_id)
+        }
+        None => {
+            trace::log(trace_id, || format!("{}: no active next hop", lookup));
+            ChordResponse::Error(
+                ErrorKind::Retryable,
+                "no active node precedes the key in the successor list".to_string(),
+            )
+        }
+    }
+}
+
+/// Counts `self_node` as a hop of the lookup traced by `trace_id`
+/// (if any): the successor it located itself is answered with a
+/// hop count of 1, and the hop count of the successor located by
+/// a forwarded lookup is incremented.
+fn count_hop(response: ChordResponse, trace_id: Option<TraceId>) -> ChordResponse {
+    match response {
+        ChordResponse::Successor(successor) if trace_id.is_some() => {
+            ChordResponse::TracedSuccessor(successor, 1)
+        }
+        ChordResponse::TracedSuccessor(successor, hops) => {
+            ChordResponse::TracedSuccessor(successor, hops.saturating_add(1))
+        }
+        response => response,
+    }
+}
+
+/// Takes a slot of `forward_limiter` (if any) for a lookup
+/// to forward, held until the forwarded lookup is answered.
+///
+/// A forwarding worker blocks until the whole remaining path
+/// answers: without a cap, a lookup storm would hold the
+/// workers of every node on the path. Past the cap, the lookup
+/// is shed with a retryable error, which its initiator retries.
+fn forward_permit(
+    forward_limiter: Option<&InFlightLimiter>,
+) -> Result<Option<InFlightPermit>, String> {
+    match forward_limiter {
+        Some(forward_limiter) => forward_limiter
+            .try_acquire()
+            .map(Some)
+            .ok_or_else(|| "too many lookups are forwarded at once, retry later".to_string()),
+        None => Ok(None),
+    }
+}
+
+/// Returns the active entry of `self_node_successor_list`
+/// to forward a lookup of `target_position` to.
+///
+/// Candidates are the entries strictly between `self_node` and
+/// `target_position`, closest preceding node first. Any of them
+/// routes the lookup correctly, so lower-latency candidates are
+/// preferred when their latency is known.
+///
+/// Entries at the address of `self_node` are skipped, whatever
+/// their identifier (e.g. stale after a restart), so that a node
+/// never forwards a lookup to itself.
+fn closest_preceding_active_node(
+    transport: &dyn Transport,
+    self_node: &Node,
+    self_node_successor_list: [Node; SUCCESSOR_LIST_LENGTH],
+    target_position: u128,
+) -> Option<Node> {
+    let mut candidates: Vec<Node> = Vec::new();
+
+    for entry in self_node_successor_list.into_iter().rev() {
+        if entry.is_position_stictly_between(self_node.get_ring_position(), target_position)
+            && entry.get_node_addr() != self_node.get_node_addr()
+            && !candidates.contains(&entry)
+        {
+            candidates.push(entry);
+        }
+    }
+
+    candidates.sort_by_key(|entry| {
+        transport
+            .latency(entry.get_public_addr())
+            .unwrap_or(Duration::MAX)
+    });
+
+    candidates.into_iter().find(|entry| {
+        request_initiator::check_remote_node(transport, entry.get_node_addr())
+            == ChordResponse::Active
+    })
+}
+
+/// Returns `self_node_predecessor`, to forward a lookup of
+/// `target_position` to, if it is active and the target lies
+/// between it and `self_node`: the predecessor then answers
+/// the lookup itself, without forwarding it back.
+fn active_predecessor_behind(
+    transport: &dyn Transport,
+    self_node: &Node,
+    self_node_predecessor: Option<Node>,
+    target_position: u128,
+) -> Option<Node> {
+    self_node_predecessor.filter(|predecessor| {
+        predecessor.get_node_addr() != self_node.get_node_addr()
+            && RingRange::new(
+                predecessor.get_ring_position(),
+                self_node.get_ring_position(),
+            )
+            .contains(target_position)
+            && request_initiator::check_remote_node(transport, predecessor.get_node_addr())
+                == ChordResponse::Active
+    })
 }
 
 pub(crate) fn get_successor_list_request_handler(
@@ -65,29 +437,100 @@ pub(crate) fn get_predecessor_request_handler(
     ChordResponse::Predecessor(self_node_predecessor)
 }
 
+/// Returns the predecessor and the successor list of a node,
+/// read together so that no update is seen in between.
+///
+/// The locks are taken in this order: the predecessor,
+/// then the successor list, and none is written while
+/// holding the other.
+pub(crate) fn get_neighborhood_request_handler(
+    self_node_predecessor: &RwLock<Option<Node>>,
+    self_node_successor_list: &RwLock<[Node; SUCCESSOR_LIST_LENGTH]>,
+) -> ChordResponse {
+    let self_node_predecessor_lock = self_node_predecessor.read().unwrap();
+    let self_node_successor_list_lock = self_node_successor_list.read().unwrap();
+
+    ChordResponse::Neighborhood(
+        self_node_predecessor_lock.clone(),
+        self_node_successor_list_lock.clone(),
+    )
+}
+
+/// Considers `external_node`, which claims to be the
+/// predecessor of `self_node`, as its new predecessor.
+///
+/// With hashed identifiers, the notification is rejected if the
+/// claimed identifier is not the hash of the claimed public socket
+/// address, so that a node cannot pick its position in the ring.
 pub(crate) fn node_notification_request_handler(
     self_node: Node,
     self_node_predecessor: Arc<RwLock<Option<Node>>>,
     self_node_successor_list: [Node; SUCCESSOR_LIST_LENGTH],
     external_node: Node,
+    identity_mode: IdentityMode,
+    observer: &dyn NodeObserver,
 ) -> ChordResponse {
-    let self_node_predecessor_value = self_node_predecessor.read().unwrap().clone();
+    // A node alone in the network notifies itself during
+    // stabilization. It keeps no predecessor until
+    // a second node joins and notifies it.
+    if external_node == self_node {
+        return ChordResponse::SuccessorList(self_node_successor_list);
+    }
+
+    if identity_mode == IdentityMode::Hashed && !external_node.has_hashed_identifier() {
+        eprintln!(
+            "rejected notification from [{:?}]: identifier [{}] does not match its public address",
+            external_node.get_public_addr(),
+            external_node.get_hash_id()
+        );
+        return ChordResponse::Error(
+            ErrorKind::Fatal,
+            "the node's identifier does not match its public address".to_string(),
+        );
+    }
+
+    // The predecessor is checked and replaced under a single
+    // guard: concurrent notifications are decided one at a
+    // time, each against the predecessor left by the previous one.
     let mut self_node_predecessor_lock = self_node_predecessor.write().unwrap();
 
-    match self_node_predecessor_value {
-        Some(predecessor)
-            if predecessor.get_ring_position() == self_node.get_ring_position()
+    // Two nodes with the same identifier joining at once both
+    // pass the collision check of the lookup: the one notifying
+    // second learns here that its identifier is taken.
+    let is_duplicate = external_node.get_ring_position() == self_node.get_ring_position()
+        || self_node_predecessor_lock
+            .as_ref()
+            .is_some_and(|predecessor| {
+                predecessor.get_ring_position() == external_node.get_ring_position()
+                    && *predecessor != external_node
+            });
+
+    if is_duplicate {
+        eprintln!(
+            "rejected notification from [{:?}]: identifier [{}] already exists in the network",
+            external_node.get_public_addr(),
+            external_node.get_hash_id()
+        );
+        return ChordResponse::Error(
+            ErrorKind::Fatal,
+            "the node's identifier already exists in the network".to_string(),
+        );
+    }
+
+    let is_accepted = match self_node_predecessor_lock.as_ref() {
+        Some(predecessor) => {
+            predecessor.get_ring_position() == self_node.get_ring_position()
                 || external_node.is_position_stictly_between(
                     predecessor.get_ring_position(),
                     self_node.get_ring_position(),
-                ) =>
-        {
-            *self_node_predecessor_lock = Some(external_node);
+                )
         }
-        None => {
-            *self_node_predecessor_lock = Some(external_node);
-        }
-        _ => (),
+        None => true,
+    };
+
+    if is_accepted {
+        observer.predecessor_accepted(&external_node);
+        *self_node_predecessor_lock = Some(external_node);
     }
 
     ChordResponse::SuccessorList(self_node_successor_list)
@@ -96,3 +539,1417 @@ pub(crate) fn node_notification_request_handler(
 pub(crate) fn check_node_request_handler() -> ChordResponse {
     ChordResponse::Active
 }
+
+/// Checks whether the requester of a reachability audit,
+/// at `requester_addr`, can be reached from this node.
+///
+/// Only the host the request comes from (`requester_ip`, if
+/// known) is probed, so that nodes cannot be made to probe
+/// third parties.
+pub(crate) fn probe_back_request_handler(
+    transport: &dyn Transport,
+    requester_addr: NodeAddr,
+    requester_ip: Option<IpAddr>,
+) -> ChordResponse {
+    if requester_ip.is_some_and(|ip| ip != requester_addr.get_socket_addr().ip()) {
+        return ChordResponse::Error(
+            ErrorKind::Fatal,
+            "only the requesting host can be probed back".to_string(),
+        );
+    }
+
+    match request_initiator::check_remote_node(transport, requester_addr) {
+        ChordResponse::Active => ChordResponse::Active,
+        _ => ChordResponse::Unreachable,
+    }
+}
+
+/// Returns the range of keys owned by `self_node`, from
+/// its predecessor (excluded) to itself (included).
+///
+/// Without a predecessor, the range covers the whole ring.
+pub(crate) fn get_owned_range_request_handler(
+    self_node: &Node,
+    self_node_predecessor: Option<Node>,
+) -> ChordResponse {
+    let self_position = self_node.get_ring_position();
+    let start = self_node_predecessor
+        .map(|predecessor| predecessor.get_ring_position())
+        .unwrap_or(self_position);
+
+    ChordResponse::OwnedRange(RingRange::new(start, self_position))
+}
+
+/// Returns the version, uptime and ring parameters
+/// of `self_node`, whose process started at `started_at`.
+pub(crate) fn get_node_info_request_handler(
+    self_node: &Node,
+    started_at: Instant,
+) -> ChordResponse {
+    ChordResponse::NodeInfo(NodeInfo::current(
+        started_at.elapsed(),
+        self_node.get_ring_bit_length(),
+    ))
+}
+
+/// Returns the located fingers of `self_node_finger_table`.
+pub(crate) fn get_finger_table_request_handler(
+    self_node_finger_table: &RwLock<FingerTable>,
+) -> ChordResponse {
+    ChordResponse::FingerTable(self_node_finger_table.read().unwrap().entries())
+}
+
+/// Runs one network stabilization pass immediately,
+/// instead of waiting for the periodic one.
+pub(crate) fn stabilize_request_handler(
+    transport: &dyn Transport,
+    self_node: Node,
+    self_node_predecessor: Arc<RwLock<Option<Node>>>,
+    self_node_successor_list: Arc<RwLock<[Node; SUCCESSOR_LIST_LENGTH]>>,
+    address_family: AddressFamily,
+    observer: &dyn NodeObserver,
+) -> ChordResponse {
+    match chord::stabilize(
+        transport,
+        &self_node,
+        &self_node_predecessor,
+        &self_node_successor_list,
+        address_family,
+        observer,
+    ) {
+        Ok(successor_list) => ChordResponse::SuccessorList(successor_list),
+        Err(err) => ChordResponse::Error(ErrorKind::Retryable, err),
+    }
+}
+
+/// Forgets `leaving_node`, which announced
+/// its departure from the network.
+pub(crate) fn leaving_node_request_handler(
+    self_node: Node,
+    self_node_predecessor: Arc<RwLock<Option<Node>>>,
+    self_node_successor_list: Arc<RwLock<[Node; SUCCESSOR_LIST_LENGTH]>>,
+    leaving_node: Node,
+    observer: &dyn NodeObserver,
+) -> ChordResponse {
+    let mut is_neighbor = false;
+
+    {
+        let mut self_node_predecessor_lock = self_node_predecessor.write().unwrap();
+
+        if self_node_predecessor_lock.as_ref() == Some(&leaving_node) {
+            *self_node_predecessor_lock = None;
+            is_neighbor = true;
+        }
+    }
+
+    {
+        let mut self_node_successor_list_lock = self_node_successor_list.write().unwrap();
+
+        if self_node_successor_list_lock.contains(&leaving_node) {
+            *self_node_successor_list_lock = utils::remove_from_successor_list(
+                &self_node,
+                &self_node_successor_list_lock,
+                &leaving_node,
+            );
+            is_neighbor = true;
+        }
+    }
+
+    if is_neighbor {
+        observer.neighbor_left(&leaving_node);
+    }
+
+    ChordResponse::Acknowledged
+}
+
+/// Authorizes a remote shutdown of the node.
+///
+/// The request is only accepted if an admin secret is configured
+/// and `received_secret` matches it.
+pub(crate) fn shutdown_request_handler(
+    admin_secret: Option<&str>,
+    received_secret: &str,
+) -> ChordResponse {
+    if !is_admin_secret_valid(admin_secret, received_secret) {
+        return ChordResponse::Error(ErrorKind::Fatal, "invalid secret".to_string());
+    }
+
+    ChordResponse::Acknowledged
+}
+
+/// Authorizes the drain of the node, which is then
+/// flagged as draining (`is_draining`).
+///
+/// The request is only accepted if an admin secret is configured
+/// and `received_secret` matches it, and only once.
+pub(crate) fn drain_request_handler(
+    admin_secret: Option<&str>,
+    received_secret: &str,
+    is_draining: &AtomicBool,
+) -> ChordResponse {
+    if !is_admin_secret_valid(admin_secret, received_secret) {
+        return ChordResponse::Error(ErrorKind::Fatal, "invalid secret".to_string());
+    }
+
+    if is_draining.swap(true, Ordering::SeqCst) {
+        return ChordResponse::Error(ErrorKind::Fatal, "node is already draining".to_string());
+    }
+
+    ChordResponse::Acknowledged
+}
+
+/// Overwrites `self_node_predecessor` with `predecessor`, to
+/// repair a node whose stabilization does not converge.
+///
+/// The request is only accepted if an admin secret is configured
+/// and `received_secret` matches it. `self_node` is never set as
+/// its own predecessor, as a node alone in the network keeps none.
+pub(crate) fn set_predecessor_request_handler(
+    admin_secret: Option<&str>,
+    received_secret: &str,
+    self_node: Node,
+    self_node_predecessor: Arc<RwLock<Option<Node>>>,
+    predecessor: Node,
+) -> ChordResponse {
+    if !is_admin_secret_valid(admin_secret, received_secret) {
+        return ChordResponse::Error(ErrorKind::Fatal, "invalid secret".to_string());
+    }
+
+    if predecessor == self_node {
+        return ChordResponse::Error(
+            ErrorKind::Fatal,
+            "the node cannot be its own predecessor".to_string(),
+        );
+    }
+
+    *self_node_predecessor.write().unwrap() = Some(predecessor);
+
+    ChordResponse::Acknowledged
+}
+
+/// Checks that an admin secret is
+/// configured and matches `received_secret`.
+///
+/// The secrets are compared in constant time, so that the
+/// response time does not tell how much of a guess matches.
+fn is_admin_secret_valid(admin_secret: Option<&str>, received_secret: &str) -> bool {
+    admin_secret.is_some_and(|admin_secret| {
+        bool::from(admin_secret.as_bytes().ct_eq(received_secret.as_bytes()))
+    })
+}
+
+#[cfg(test)]
+mod find_successor_of_node_request_handler_test {
+    use std::{
+        io,
+        net::SocketAddr,
+        sync::{mpsc, Arc, RwLock},
+        thread,
+        time::Duration,
+    };
+
+    use crate::{
+        chord::{
+            lookup_cache::LookupCache,
+            protocol::{ChordRequest, ChordResponse, ErrorKind},
+            trace::TraceId,
+            Node, MAX_RING_BIT_LENGTH, SUCCESSOR_LIST_LENGTH,
+        },
+        cli::{Options, RoutingMode},
+        context::NodeContext,
+        gossip::limiter::InFlightLimiter,
+        transport::mock::MockTransport,
+    };
+
+    use super::{find_successor_of_key_request_handler, find_successor_of_node_request_handler};
+
+    /// Mock transport where every node is active, and answers
+    /// a forwarded lookup with itself, revealing the chosen hop.
+    fn all_active_transport() -> MockTransport {
+        MockTransport::new(|remote_addr, request_msg| {
+            let response = match ChordRequest::parse(request_msg) {
+                Ok(ChordRequest::CheckNode) => ChordResponse::Active,
+                Ok(
+                    ChordRequest::FindSuccessorOfNode(..) | ChordRequest::FindSuccessorOfKey(..),
+                ) => ChordResponse::Successor(Node::for_test_at(remote_addr.port() as u128)),
+                _ => ChordResponse::Error(ErrorKind::Fatal, "unexpected request".to_string()),
+            };
+
+            Ok(response.to_protocol_text())
+        })
+    }
+
+    /// Number of `FIND_SUCCESSOR_OF_NODE`
+    /// requests sent through `transport`.
+    fn forwarded_lookups(transport: &MockTransport) -> usize {
+        transport
+            .sent_requests()
+            .iter()
+            .filter(|(_, request_msg)| {
+                matches!(
+                    ChordRequest::parse(request_msg),
+                    Ok(ChordRequest::FindSuccessorOfNode(..))
+                )
+            })
+            .count()
+    }
+
+    /// Context of `self_node`, with `successor_list` and `predecessor`,
+    /// reaching the other nodes through `transport`.
+    fn lookup_context(
+        transport: &Arc<MockTransport>,
+        self_node: Node,
+        successor_list: [Node; SUCCESSOR_LIST_LENGTH],
+        predecessor: Option<Node>,
+    ) -> NodeContext {
+        NodeContext {
+            self_node: self_node.clone(),
+            self_node_successor_list: Arc::new(RwLock::new(successor_list)),
+            self_node_predecessor: Arc::new(RwLock::new(predecessor)),
+            transport: transport.clone(),
+            ..NodeContext::for_test(self_node.get_public_addr())
+        }
+    }
+
+    fn successor_list() -> [Node; SUCCESSOR_LIST_LENGTH] {
+        [
+            Node::for_test_at(100),
+            Node::for_test_at(200),
+            Node::for_test_at(300),
+            Node::for_test_at(400),
+            Node::for_test_at(500),
+        ]
+    }
+
+    /// Mock transport serving a ring of ten nodes at positions
+    /// 100, 200, ..., 1000, routing lookups recursively, where
+    /// the node at `dead_position` is unreachable.
+    fn ring_transport(dead_position: u128) -> MockTransport {
+        MockTransport::new(move |remote_addr, request_msg| {
+            let position = remote_addr.port() as u128;
+
+            if position == dead_position {
+                return Err(io::Error::from(io::ErrorKind::ConnectionRefused));
+            }
+
+            let response = match ChordRequest::parse(request_msg) {
+                Ok(ChordRequest::CheckNode) => ChordResponse::Active,
+                Ok(ChordRequest::FindSuccessorOfNode(target_node, trace_id)) => {
+                    find_successor_of_node_request_handler(
+                        &lookup_context(
+                            &Arc::new(ring_transport(dead_position)),
+                            Node::for_test_at(position),
+                            std::array::from_fn(|i| {
+                                Node::for_test_at((position + 100 * i as u128) % 1000 + 100)
+                            }),
+                            None,
+                        ),
+                        target_node,
+                        trace_id,
+                    )
+                }
+                _ => ChordResponse::Error(ErrorKind::Fatal, "unexpected request".to_string()),
+            };
+
+            Ok(response.to_protocol_text())
+        })
+    }
+
+    /// Successor lists of `self_node` in a
+    /// single-node ring and in a 2-node ring.
+    fn small_ring_successor_lists() -> [[Node; SUCCESSOR_LIST_LENGTH]; 2] {
+        [
+            std::array::from_fn(|_| Node::for_test_at(10)),
+            std::array::from_fn(|i| {
+                if i == 0 {
+                    Node::for_test_at(100)
+                } else {
+                    Node::for_test_at(10)
+                }
+            }),
+        ]
+    }
+
+    #[test]
+    fn single_node_ring_owns_every_target_test() {
+        let transport = Arc::new(all_active_transport());
+        let [successor_list, _] = small_ring_successor_lists();
+
+        for target_node in [
+            Node::for_test_at(5),
+            Node::for_test_at(100),
+            Node::for_test_at(1000),
+        ] {
+            let response = find_successor_of_node_request_handler(
+                &lookup_context(
+                    &transport,
+                    Node::for_test_at(10),
+                    successor_list.clone(),
+                    None,
+                ),
+                target_node,
+                None,
+            );
+
+            assert_eq!(response, ChordResponse::Successor(Node::for_test_at(10)));
+        }
+
+        assert!(transport.sent_requests().is_empty());
+    }
+
+    #[test]
+    fn target_before_successor_is_answered_directly_test() {
+        let transport = Arc::new(all_active_transport());
+        let [_, two_node_successor_list] = small_ring_successor_lists();
+
+        for successor_list in [successor_list(), two_node_successor_list] {
+            let response = find_successor_of_node_request_handler(
+                &lookup_context(&transport, Node::for_test_at(10), successor_list, None),
+                Node::for_test_at(50),
+                None,
+            );
+
+            assert_eq!(response, ChordResponse::Successor(Node::for_test_at(100)));
+        }
+
+        assert!(transport.sent_requests().is_empty());
+    }
+
+    #[test]
+    fn full_network_rejects_joining_node_test() {
+        let response = find_successor_of_node_request_handler(
+            &NodeContext {
+                options: Options {
+                    max_nodes: Some(3),
+                    ..Options::default()
+                },
+                ..lookup_context(
+                    &Arc::new(all_active_transport()),
+                    Node::for_test_at(10),
+                    successor_list(),
+                    None,
+                )
+            },
+            Node::for_test_at(50),
+            None,
+        );
+
+        assert!(matches!(
+            response,
+            ChordResponse::Error(ErrorKind::Fatal, ref err) if err.contains("maximum capacity")
+        ));
+    }
+
+    #[test]
+    fn multi_hop_forwarding_skips_dead_candidate_test() {
+        // The node at 600, closest preceding node to
+        // the target in the successor list, is dead
+        let transport = Arc::new(ring_transport(600));
+
+        let response = find_successor_of_node_request_handler(
+            &lookup_context(
+                &transport,
+                Node::for_test_at(100),
+                std::array::from_fn(|i| Node::for_test_at(200 + 100 * i as u128)),
+                None,
+            ),
+            Node::for_test_at(950),
+            None,
+        );
+
+        assert_eq!(response, ChordResponse::Successor(Node::for_test_at(1000)));
+
+        // The dead candidate is checked, then skipped
+        // for the next preceding node, which forwards
+        // the lookup in turn
+        let sent_requests = transport
+            .sent_requests()
+            .into_iter()
+            .map(|(remote_addr, request_msg)| {
+                (
+                    remote_addr.port() as u128,
+                    ChordRequest::parse(&request_msg).unwrap(),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            sent_requests,
+            [
+                (600, ChordRequest::CheckNode),
+                (500, ChordRequest::CheckNode),
+                (
+                    500,
+                    ChordRequest::FindSuccessorOfNode(Node::for_test_at(950), None)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn no_active_candidate_is_a_retryable_error_test() {
+        let transport = Arc::new(MockTransport::new(|_, _| {
+            Err(io::Error::from(io::ErrorKind::ConnectionRefused))
+        }));
+
+        let response = find_successor_of_node_request_handler(
+            &lookup_context(&transport, Node::for_test_at(10), successor_list(), None),
+            Node::for_test_at(1000),
+            None,
+        );
+
+        assert_eq!(
+            response,
+            ChordResponse::Error(
+                ErrorKind::Retryable,
+                "no active node precedes the target in the successor list".to_string()
+            )
+        );
+        // Every candidate was checked
+        assert_eq!(transport.sent_requests().len(), SUCCESSOR_LIST_LENGTH);
+    }
+
+    #[test]
+    fn self_node_address_is_never_a_next_hop_test() {
+        let transport = Arc::new(all_active_transport());
+        let self_addr = Node::for_test_at(10).get_public_addr();
+
+        // Entries at the address of the node, under other
+        // identifiers, precede the target more closely than 200
+        let stale_self_node = |position: u128| Node::create_from(position.to_be_bytes(), self_addr);
+
+        let response = find_successor_of_node_request_handler(
+            &NodeContext {
+                options: Options {
+                    routing_mode: RoutingMode::Iterative,
+                    ..Options::default()
+                },
+                ..lookup_context(
+                    &transport,
+                    Node::for_test_at(10),
+                    [
+                        Node::for_test_at(100),
+                        Node::for_test_at(200),
+                        stale_self_node(300),
+                        stale_self_node(400),
+                        Node::for_test_at(10),
+                    ],
+                    None,
+                )
+            },
+            Node::for_test_at(950),
+            None,
+        );
+
+        assert_eq!(response, ChordResponse::Redirect(Node::for_test_at(200)));
+
+        // Without other nodes, the lookup fails, even behind the node
+        let response = find_successor_of_node_request_handler(
+            &lookup_context(
+                &transport,
+                Node::for_test_at(10),
+                std::array::from_fn(|_| stale_self_node(300)),
+                Some(stale_self_node(5)),
+            ),
+            Node::for_test_at(7),
+            None,
+        );
+
+        assert!(matches!(
+            response,
+            ChordResponse::Error(ErrorKind::Retryable, _)
+        ));
+        assert!(transport
+            .sent_requests()
+            .iter()
+            .all(|(remote_addr, _)| *remote_addr != self_addr));
+    }
+
+    #[test]
+    fn predecessor_routes_target_behind_self_node_test() {
+        // Only the predecessor, at 1000, of the node
+        // at 10 is alive: its successor list is dead
+        let transport = Arc::new(MockTransport::new(|remote_addr, request_msg| {
+            if remote_addr != Node::for_test_at(1000).get_public_addr() {
+                return Err(io::Error::from(io::ErrorKind::ConnectionRefused));
+            }
+
+            let response = match ChordRequest::parse(request_msg) {
+                Ok(ChordRequest::CheckNode) => ChordResponse::Active,
+                Ok(ChordRequest::FindSuccessorOfNode(target_node, trace_id)) => {
+                    find_successor_of_node_request_handler(
+                        &lookup_context(
+                            &Arc::new(MockTransport::new(|_, _| {
+                                unreachable!("the lookup is not forwarded")
+                            })),
+                            Node::for_test_at(1000),
+                            std::array::from_fn(|_| Node::for_test_at(10)),
+                            None,
+                        ),
+                        target_node,
+                        trace_id,
+                    )
+                }
+                _ => ChordResponse::Error(ErrorKind::Fatal, "unexpected request".to_string()),
+            };
+
+            Ok(response.to_protocol_text())
+        }));
+
+        let find_successor = |target_node, routing_mode| {
+            find_successor_of_node_request_handler(
+                &NodeContext {
+                    options: Options {
+                        routing_mode,
+                        ..Options::default()
+                    },
+                    ..lookup_context(
+                        &transport,
+                        Node::for_test_at(10),
+                        successor_list(),
+                        Some(Node::for_test_at(1000)),
+                    )
+                },
+                target_node,
+                None,
+            )
+        };
+
+        // A target between the predecessor and the node
+        assert_eq!(
+            find_successor(Node::for_test_at(5), RoutingMode::Recursive),
+            ChordResponse::Successor(Node::for_test_at(10))
+        );
+        assert_eq!(
+            find_successor(Node::for_test_at(1005), RoutingMode::Iterative),
+            ChordResponse::Redirect(Node::for_test_at(1000))
+        );
+
+        // A target farther behind is not routed backwards
+        assert!(matches!(
+            find_successor(Node::for_test_at(950), RoutingMode::Recursive),
+            ChordResponse::Error(ErrorKind::Retryable, _)
+        ));
+    }
+
+    #[test]
+    fn forwards_to_closest_preceding_node_without_latency_test() {
+        let transport = Arc::new(all_active_transport());
+
+        let response = find_successor_of_node_request_handler(
+            &lookup_context(&transport, Node::for_test_at(10), successor_list(), None),
+            Node::for_test_at(1000),
+            None,
+        );
+
+        assert_eq!(response, ChordResponse::Successor(Node::for_test_at(500)));
+    }
+
+    #[test]
+    fn cached_lookup_is_not_forwarded_test() {
+        let transport = Arc::new(all_active_transport());
+        let lookup_cache = Arc::new(RwLock::new(LookupCache::new(Duration::from_secs(60))));
+
+        for _ in 0..2 {
+            let response = find_successor_of_node_request_handler(
+                &NodeContext {
+                    self_node_lookup_cache: Some(Arc::clone(&lookup_cache)),
+                    ..lookup_context(&transport, Node::for_test_at(10), successor_list(), None)
+                },
+                Node::for_test_at(1000),
+                None,
+            );
+
+            assert_eq!(response, ChordResponse::Successor(Node::for_test_at(500)));
+        }
+
+        assert_eq!(forwarded_lookups(&transport), 1);
+
+        // Once the neighborhood changes, lookups are forwarded again
+        lookup_cache.write().unwrap().clear();
+
+        find_successor_of_node_request_handler(
+            &NodeContext {
+                self_node_lookup_cache: Some(Arc::clone(&lookup_cache)),
+                ..lookup_context(&transport, Node::for_test_at(10), successor_list(), None)
+            },
+            Node::for_test_at(1000),
+            None,
+        );
+
+        assert_eq!(forwarded_lookups(&transport), 2);
+    }
+
+    #[test]
+    fn iterative_routing_redirects_test() {
+        let transport = Arc::new(all_active_transport());
+
+        let response = find_successor_of_node_request_handler(
+            &NodeContext {
+                options: Options {
+                    routing_mode: RoutingMode::Iterative,
+                    ..Options::default()
+                },
+                ..lookup_context(&transport, Node::for_test_at(10), successor_list(), None)
+            },
+            Node::for_test_at(1000),
+            None,
+        );
+
+        assert_eq!(response, ChordResponse::Redirect(Node::for_test_at(500)));
+        assert_eq!(forwarded_lookups(&transport), 0);
+
+        let response = find_successor_of_key_request_handler(
+            transport.as_ref(),
+            Node::for_test_at(10),
+            successor_list(),
+            250,
+            None,
+            RoutingMode::Iterative,
+            None,
+        );
+
+        assert_eq!(response, ChordResponse::Redirect(Node::for_test_at(200)));
+
+        // Keys owned by the successor are answered directly
+        let response = find_successor_of_key_request_handler(
+            transport.as_ref(),
+            Node::for_test_at(10),
+            successor_list(),
+            50,
+            None,
+            RoutingMode::Iterative,
+            None,
+        );
+
+        assert_eq!(response, ChordResponse::Successor(Node::for_test_at(100)));
+    }
+
+    #[test]
+    fn expired_lookup_is_forwarded_test() {
+        let transport = Arc::new(all_active_transport());
+        let lookup_cache = Arc::new(RwLock::new(LookupCache::new(Duration::ZERO)));
+
+        for _ in 0..2 {
+            find_successor_of_node_request_handler(
+                &NodeContext {
+                    self_node_lookup_cache: Some(Arc::clone(&lookup_cache)),
+                    ..lookup_context(&transport, Node::for_test_at(10), successor_list(), None)
+                },
+                Node::for_test_at(1000),
+                None,
+            );
+        }
+
+        assert_eq!(forwarded_lookups(&transport), 2);
+    }
+
+    #[test]
+    fn trace_id_is_propagated_test() {
+        let transport = Arc::new(all_active_transport());
+        let trace_id = Some(TraceId::generate());
+
+        find_successor_of_node_request_handler(
+            &lookup_context(&transport, Node::for_test_at(10), successor_list(), None),
+            Node::for_test_at(1000),
+            trace_id,
+        );
+
+        find_successor_of_key_request_handler(
+            transport.as_ref(),
+            Node::for_test_at(10),
+            successor_list(),
+            1000,
+            trace_id,
+            RoutingMode::Recursive,
+            None,
+        );
+
+        let forwarded_requests = transport
+            .sent_requests()
+            .into_iter()
+            .filter(|(remote_addr, _)| *remote_addr == Node::for_test_at(500).get_public_addr())
+            .filter_map(|(_, request_msg)| match ChordRequest::parse(&request_msg) {
+                Ok(ChordRequest::CheckNode) => None,
+                request => Some(request.unwrap()),
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            forwarded_requests,
+            [
+                ChordRequest::FindSuccessorOfNode(Node::for_test_at(1000), trace_id),
+                ChordRequest::FindSuccessorOfKey(1000, trace_id),
+            ]
+        );
+    }
+
+    #[test]
+    fn key_lookup_test() {
+        let transport = Arc::new(all_active_transport());
+        let lookup = |key: u128| {
+            find_successor_of_key_request_handler(
+                transport.as_ref(),
+                Node::for_test_at(10),
+                successor_list(),
+                key,
+                None,
+                RoutingMode::Recursive,
+                None,
+            )
+        };
+
+        // Keys owned by `self_node` or its successor
+        assert_eq!(lookup(10), ChordResponse::Successor(Node::for_test_at(10)));
+        assert_eq!(lookup(11), ChordResponse::Successor(Node::for_test_at(100)));
+        assert_eq!(
+            lookup(100),
+            ChordResponse::Successor(Node::for_test_at(100))
+        );
+
+        // Forwarded keys, including keys wrapping around the ring
+        assert_eq!(
+            lookup(250),
+            ChordResponse::Successor(Node::for_test_at(200))
+        );
+        assert_eq!(
+            lookup(1000),
+            ChordResponse::Successor(Node::for_test_at(500))
+        );
+        assert_eq!(lookup(5), ChordResponse::Successor(Node::for_test_at(500)));
+    }
+
+    #[test]
+    fn duplicate_identifier_is_rejected_test() {
+        // Sequential identifiers are operator-supplied,
+        // so collisions are expected mistakes.
+        for duplicate_node in [Node::for_test_at(10), Node::for_test_at(100)] {
+            let response = find_successor_of_node_request_handler(
+                &lookup_context(
+                    &Arc::new(all_active_transport()),
+                    Node::for_test_at(10),
+                    successor_list(),
+                    None,
+                ),
+                Node::with_sequential_id(
+                    duplicate_node.get_ring_position(),
+                    SocketAddr::from(([10, 0, 0, 2], 9000)),
+                    MAX_RING_BIT_LENGTH,
+                ),
+                None,
+            );
+
+            assert_eq!(
+                response,
+                ChordResponse::Error(
+                    ErrorKind::Fatal,
+                    "the node's identifier already exists in the network".to_string()
+                )
+            );
+        }
+    }
+
+    #[test]
+    fn deeper_duplicate_identifier_is_rejected_test() {
+        let lookup = |target_node| {
+            find_successor_of_node_request_handler(
+                &lookup_context(
+                    &Arc::new(all_active_transport()),
+                    Node::for_test_at(10),
+                    successor_list(),
+                    Some(Node::for_test_at(5)),
+                ),
+                target_node,
+                None,
+            )
+        };
+
+        for duplicate_node in [
+            Node::for_test_at(300),
+            Node::for_test_at(500),
+            Node::for_test_at(5),
+        ] {
+            assert_eq!(
+                lookup(Node::with_sequential_id(
+                    duplicate_node.get_ring_position(),
+                    SocketAddr::from(([10, 0, 0, 2], 9000)),
+                    MAX_RING_BIT_LENGTH,
+                )),
+                ChordResponse::Error(
+                    ErrorKind::Fatal,
+                    "the node's identifier already exists in the network".to_string()
+                )
+            );
+        }
+
+        // The same node restarting is forwarded as usual
+        assert!(matches!(
+            lookup(Node::for_test_at(300)),
+            ChordResponse::Successor(_)
+        ));
+    }
+
+    #[test]
+    fn restarting_node_resumes_its_place_test() {
+        let transport = Arc::new(all_active_transport());
+        let lookup = |successor_list, target_node| {
+            find_successor_of_node_request_handler(
+                &lookup_context(&transport, Node::for_test_at(10), successor_list, None),
+                target_node,
+                None,
+            )
+        };
+
+        assert_eq!(
+            lookup(successor_list(), Node::for_test_at(100)),
+            ChordResponse::Successor(Node::for_test_at(200))
+        );
+
+        // 2-node ring, the successor list is padded with self_node
+        let two_node_successor_list = std::array::from_fn(|i| {
+            if i == 0 {
+                Node::for_test_at(100)
+            } else {
+                Node::for_test_at(10)
+            }
+        });
+
+        assert_eq!(
+            lookup(two_node_successor_list, Node::for_test_at(100)),
+            ChordResponse::Successor(Node::for_test_at(10))
+        );
+
+        assert!(transport.sent_requests().is_empty());
+    }
+
+    #[test]
+    fn forwards_to_lowest_latency_node_test() {
+        let transport = Arc::new(
+            all_active_transport()
+                .with_latency(
+                    Node::for_test_at(500).get_public_addr(),
+                    Duration::from_millis(200),
+                )
+                .with_latency(
+                    Node::for_test_at(400).get_public_addr(),
+                    Duration::from_millis(150),
+                )
+                .with_latency(
+                    Node::for_test_at(300).get_public_addr(),
+                    Duration::from_millis(5),
+                ),
+        );
+
+        let response = find_successor_of_node_request_handler(
+            &lookup_context(&transport, Node::for_test_at(10), successor_list(), None),
+            Node::for_test_at(1000),
+            None,
+        );
+
+        assert_eq!(response, ChordResponse::Successor(Node::for_test_at(300)));
+
+        // Entries past the target are never candidates,
+        // whatever their latency.
+        let transport = Arc::new(
+            all_active_transport()
+                .with_latency(
+                    Node::for_test_at(500).get_public_addr(),
+                    Duration::from_millis(1),
+                )
+                .with_latency(
+                    Node::for_test_at(200).get_public_addr(),
+                    Duration::from_millis(50),
+                ),
+        );
+
+        let response = find_successor_of_node_request_handler(
+            &lookup_context(&transport, Node::for_test_at(10), successor_list(), None),
+            Node::for_test_at(350),
+            None,
+        );
+
+        assert_eq!(response, ChordResponse::Successor(Node::for_test_at(200)));
+        assert!(transport
+            .sent_requests()
+            .iter()
+            .all(|(remote_addr, _)| *remote_addr != Node::for_test_at(500).get_public_addr()));
+    }
+
+    #[test]
+    fn forwarding_storm_is_shed_test() {
+        // Forwarded lookups are held until the gate is released
+        let gate = Arc::new(RwLock::new(()));
+        let gate_lock = gate.write().unwrap();
+
+        let transport = Arc::new(MockTransport::new({
+            let gate = Arc::clone(&gate);
+
+            move |remote_addr, request_msg| {
+                let response = match ChordRequest::parse(request_msg) {
+                    Ok(ChordRequest::CheckNode) => ChordResponse::Active,
+                    Ok(ChordRequest::FindSuccessorOfNode(..)) => {
+                        let _gate = gate.read().unwrap();
+                        ChordResponse::Successor(Node::for_test_at(remote_addr.port() as u128))
+                    }
+                    _ => ChordResponse::Error(ErrorKind::Fatal, "unexpected request".to_string()),
+                };
+
+                Ok(response.to_protocol_text())
+            }
+        }));
+        let forward_limiter = InFlightLimiter::new(2);
+        let (response_sender, response_receiver) = mpsc::channel();
+
+        for _ in 0..6 {
+            let transport = Arc::clone(&transport);
+            let forward_limiter = forward_limiter.clone();
+            let response_sender = response_sender.clone();
+
+            thread::spawn(move || {
+                let response = find_successor_of_node_request_handler(
+                    &NodeContext {
+                        forward_limiter,
+                        ..lookup_context(&transport, Node::for_test_at(10), successor_list(), None)
+                    },
+                    Node::for_test_at(450),
+                    None,
+                );
+                response_sender.send(response).unwrap();
+            });
+        }
+
+        // The lookups past the cap are shed while two are held
+        for _ in 0..4 {
+            assert!(matches!(
+                response_receiver.recv().unwrap(),
+                ChordResponse::Error(ErrorKind::Retryable, _)
+            ));
+        }
+        assert_eq!(forward_limiter.in_flight(), 2);
+
+        drop(gate_lock);
+
+        for _ in 0..2 {
+            assert_eq!(
+                response_receiver.recv().unwrap(),
+                ChordResponse::Successor(Node::for_test_at(400))
+            );
+        }
+        assert_eq!(forward_limiter.in_flight(), 0);
+    }
+}
+
+#[cfg(test)]
+mod leave_request_handler_test {
+    use std::sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, RwLock,
+    };
+
+    use crate::{
+        chord::{
+            protocol::{ChordResponse, ErrorKind},
+            Node,
+        },
+        observer::NoopObserver,
+    };
+
+    use super::{
+        drain_request_handler, leaving_node_request_handler, set_predecessor_request_handler,
+        shutdown_request_handler,
+    };
+
+    #[test]
+    fn leaving_node_is_forgotten_test() {
+        let self_node = Node::for_test_at(100);
+        let self_node_predecessor = Arc::new(RwLock::new(Some(Node::for_test_at(50))));
+        let self_node_successor_list = Arc::new(RwLock::new([
+            Node::for_test_at(200),
+            Node::for_test_at(300),
+            Node::for_test_at(400),
+            Node::for_test_at(500),
+            Node::for_test_at(600),
+        ]));
+
+        // Leaving successor
+        let response = leaving_node_request_handler(
+            self_node.clone(),
+            Arc::clone(&self_node_predecessor),
+            Arc::clone(&self_node_successor_list),
+            Node::for_test_at(200),
+            &NoopObserver,
+        );
+
+        assert_eq!(response, ChordResponse::Acknowledged);
+        assert_eq!(
+            *self_node_predecessor.read().unwrap(),
+            Some(Node::for_test_at(50))
+        );
+        assert_eq!(
+            *self_node_successor_list.read().unwrap(),
+            [
+                Node::for_test_at(300),
+                Node::for_test_at(400),
+                Node::for_test_at(500),
+                Node::for_test_at(600),
+                Node::for_test_at(600),
+            ]
+        );
+
+        // Leaving predecessor
+        leaving_node_request_handler(
+            self_node,
+            Arc::clone(&self_node_predecessor),
+            Arc::clone(&self_node_successor_list),
+            Node::for_test_at(50),
+            &NoopObserver,
+        );
+
+        assert_eq!(*self_node_predecessor.read().unwrap(), None);
+    }
+
+    #[test]
+    fn shutdown_requires_the_admin_secret_test() {
+        assert_eq!(
+            shutdown_request_handler(Some("secret"), "secret"),
+            ChordResponse::Acknowledged
+        );
+        for guess in ["guess", "secre", "secrets", ""] {
+            assert_eq!(
+                shutdown_request_handler(Some("secret"), guess),
+                ChordResponse::Error(ErrorKind::Fatal, "invalid secret".to_string()),
+                "{}",
+                guess
+            );
+        }
+
+        // Without configured secret, remote shutdown is disabled
+        assert_eq!(
+            shutdown_request_handler(None, "secret"),
+            ChordResponse::Error(ErrorKind::Fatal, "invalid secret".to_string())
+        );
+    }
+
+    #[test]
+    fn drain_is_accepted_once_with_the_admin_secret_test() {
+        let is_draining = AtomicBool::new(false);
+
+        for (admin_secret, received_secret) in [(Some("secret"), "guess"), (None, "secret")] {
+            assert_eq!(
+                drain_request_handler(admin_secret, received_secret, &is_draining),
+                ChordResponse::Error(ErrorKind::Fatal, "invalid secret".to_string())
+            );
+            assert!(!is_draining.load(Ordering::SeqCst));
+        }
+
+        assert_eq!(
+            drain_request_handler(Some("secret"), "secret", &is_draining),
+            ChordResponse::Acknowledged
+        );
+        assert!(is_draining.load(Ordering::SeqCst));
+
+        assert_eq!(
+            drain_request_handler(Some("secret"), "secret", &is_draining),
+            ChordResponse::Error(ErrorKind::Fatal, "node is already draining".to_string())
+        );
+    }
+
+    #[test]
+    fn set_predecessor_requires_the_admin_secret_test() {
+        let self_node = Node::for_test_at(100);
+        let self_node_predecessor = Arc::new(RwLock::new(Some(Node::for_test_at(50))));
+
+        for (admin_secret, received_secret) in [(Some("secret"), "guess"), (None, "secret")] {
+            assert_eq!(
+                set_predecessor_request_handler(
+                    admin_secret,
+                    received_secret,
+                    self_node.clone(),
+                    Arc::clone(&self_node_predecessor),
+                    Node::for_test_at(80),
+                ),
+                ChordResponse::Error(ErrorKind::Fatal, "invalid secret".to_string())
+            );
+            assert_eq!(
+                *self_node_predecessor.read().unwrap(),
+                Some(Node::for_test_at(50))
+            );
+        }
+
+        assert_eq!(
+            set_predecessor_request_handler(
+                Some("secret"),
+                "secret",
+                self_node.clone(),
+                Arc::clone(&self_node_predecessor),
+                self_node.clone(),
+            ),
+            ChordResponse::Error(
+                ErrorKind::Fatal,
+                "the node cannot be its own predecessor".to_string(),
+            )
+        );
+
+        // The new predecessor is set, even
+        // if the current one is closer
+        assert_eq!(
+            set_predecessor_request_handler(
+                Some("secret"),
+                "secret",
+                self_node,
+                Arc::clone(&self_node_predecessor),
+                Node::for_test_at(20),
+            ),
+            ChordResponse::Acknowledged
+        );
+        assert_eq!(
+            *self_node_predecessor.read().unwrap(),
+            Some(Node::for_test_at(20))
+        );
+    }
+}
+
+#[cfg(test)]
+mod node_notification_request_handler_test {
+    use std::{
+        net::SocketAddr,
+        sync::{mpsc, Arc, RwLock},
+        thread,
+        time::Duration,
+    };
+
+    use crate::{
+        chord::{
+            protocol::{ChordResponse, ErrorKind},
+            Node, MAX_RING_BIT_LENGTH,
+        },
+        cli::IdentityMode,
+        observer::NoopObserver,
+    };
+
+    use super::node_notification_request_handler;
+
+    fn notify(
+        self_node: &Node,
+        self_node_predecessor: &Arc<RwLock<Option<Node>>>,
+        external_node: Node,
+        identity_mode: IdentityMode,
+    ) -> ChordResponse {
+        node_notification_request_handler(
+            self_node.clone(),
+            Arc::clone(self_node_predecessor),
+            std::array::from_fn(|_| self_node.clone()),
+            external_node,
+            identity_mode,
+            &NoopObserver,
+        )
+    }
+
+    #[test]
+    fn spoofed_identifier_is_rejected_test() {
+        let self_node = Node::new(SocketAddr::from(([10, 0, 0, 1], 8000)));
+        let self_node_predecessor = Arc::new(RwLock::new(None));
+
+        let notifier_addr = SocketAddr::from(([10, 0, 0, 2], 8000));
+        // Claims the position right before `self_node`
+        let spoofed_node = Node::create_from(
+            (self_node.get_ring_position().wrapping_sub(1)).to_be_bytes(),
+            notifier_addr,
+        );
+
+        assert_eq!(
+            notify(
+                &self_node,
+                &self_node_predecessor,
+                spoofed_node.clone(),
+                IdentityMode::Hashed
+            ),
+            ChordResponse::Error(
+                ErrorKind::Fatal,
+                "the node's identifier does not match its public address".to_string()
+            )
+        );
+        assert_eq!(*self_node_predecessor.read().unwrap(), None);
+
+        // The same address, with its hashed identifier
+        let notifier_node = Node::new(notifier_addr);
+
+        assert!(matches!(
+            notify(
+                &self_node,
+                &self_node_predecessor,
+                notifier_node.clone(),
+                IdentityMode::Hashed
+            ),
+            ChordResponse::SuccessorList(_)
+        ));
+        assert_eq!(*self_node_predecessor.read().unwrap(), Some(notifier_node));
+
+        // Sequential identifiers are not hashes
+        assert!(matches!(
+            notify(
+                &self_node,
+                &self_node_predecessor,
+                spoofed_node.clone(),
+                IdentityMode::Sequential(1)
+            ),
+            ChordResponse::SuccessorList(_)
+        ));
+        assert_eq!(*self_node_predecessor.read().unwrap(), Some(spoofed_node));
+    }
+
+    #[test]
+    fn duplicate_identifier_is_rejected_test() {
+        let self_node = Node::with_sequential_id(
+            1000,
+            SocketAddr::from(([10, 0, 0, 1], 8000)),
+            MAX_RING_BIT_LENGTH,
+        );
+        let self_node_predecessor = Arc::new(RwLock::new(None));
+
+        // Two nodes which joined at once with the same identifier
+        let first_node = Node::with_sequential_id(
+            10,
+            SocketAddr::from(([10, 0, 0, 2], 8000)),
+            MAX_RING_BIT_LENGTH,
+        );
+        let second_node = Node::with_sequential_id(
+            10,
+            SocketAddr::from(([10, 0, 0, 3], 8000)),
+            MAX_RING_BIT_LENGTH,
+        );
+        let duplicate_error = ChordResponse::Error(
+            ErrorKind::Fatal,
+            "the node's identifier already exists in the network".to_string(),
+        );
+
+        assert!(matches!(
+            notify(
+                &self_node,
+                &self_node_predecessor,
+                first_node.clone(),
+                IdentityMode::Sequential(1000)
+            ),
+            ChordResponse::SuccessorList(_)
+        ));
+        assert_eq!(
+            notify(
+                &self_node,
+                &self_node_predecessor,
+                second_node,
+                IdentityMode::Sequential(1000)
+            ),
+            duplicate_error
+        );
+        assert_eq!(
+            *self_node_predecessor.read().unwrap(),
+            Some(first_node.clone())
+        );
+
+        // The predecessor keeps notifying
+        assert!(matches!(
+            notify(
+                &self_node,
+                &self_node_predecessor,
+                first_node,
+                IdentityMode::Sequential(1000)
+            ),
+            ChordResponse::SuccessorList(_)
+        ));
+
+        // A node with the identifier of `self_node`
+        let self_duplicate = Node::with_sequential_id(
+            1000,
+            SocketAddr::from(([10, 0, 0, 4], 8000)),
+            MAX_RING_BIT_LENGTH,
+        );
+
+        assert_eq!(
+            notify(
+                &self_node,
+                &self_node_predecessor,
+                self_duplicate,
+                IdentityMode::Sequential(1000)
+            ),
+            duplicate_error
+        );
+    }
+
+    #[test]
+    fn concurrent_notifications_keep_closest_predecessor_test() {
+        let self_node = Node::for_test_at(1000);
+        let self_node_predecessor = Arc::new(RwLock::new(None));
+        let (done_sender, done_receiver) = mpsc::channel();
+
+        for thread_index in 0..8 {
+            let self_node = self_node.clone();
+            let self_node_predecessor = Arc::clone(&self_node_predecessor);
+            let done_sender = done_sender.clone();
+
+            thread::spawn(move || {
+                for round in 0..100u128 {
+                    for offset in 0..64 {
+                        // Each thread and round notifies in a different order
+                        let position = (offset + round + thread_index) % 64 + 1;
+
+                        notify(
+                            &self_node,
+                            &self_node_predecessor,
+                            Node::for_test_at(position),
+                            IdentityMode::Sequential(1000),
+                        );
+                    }
+                }
+
+                done_sender.send(()).unwrap();
+            });
+        }
+
+        for _ in 0..8 {
+            done_receiver
+                .recv_timeout(Duration::from_secs(30))
+                .expect("concurrent notifications deadlocked");
+        }
+
+        // Whatever the interleaving, the closest
+        // preceding notifier ends up as predecessor
+        assert_eq!(
+            *self_node_predecessor.read().unwrap(),
+            Some(Node::for_test_at(64))
+        );
+    }
+}
+
+#[cfg(test)]
+mod get_neighborhood_request_handler_test {
+    use std::sync::RwLock;
+
+    use crate::chord::{protocol::ChordResponse, Node};
+
+    use super::get_neighborhood_request_handler;
+
+    #[test]
+    fn neighborhood_is_read_together_test() {
+        let successor_list = [
+            Node::for_test_at(200),
+            Node::for_test_at(300),
+            Node::for_test_at(400),
+            Node::for_test_at(500),
+            Node::for_test_at(600),
+        ];
+        let self_node_predecessor = RwLock::new(Some(Node::for_test_at(50)));
+        let self_node_successor_list = RwLock::new(successor_list.clone());
+
+        assert_eq!(
+            get_neighborhood_request_handler(&self_node_predecessor, &self_node_successor_list),
+            ChordResponse::Neighborhood(Some(Node::for_test_at(50)), successor_list.clone())
+        );
+
+        *self_node_predecessor.write().unwrap() = None;
+
+        assert_eq!(
+            get_neighborhood_request_handler(&self_node_predecessor, &self_node_successor_list),
+            ChordResponse::Neighborhood(None, successor_list)
+        );
+    }
+}
@@ -1,57 +1,117 @@
 //! Responsible for initiating requests
 //! in the Chord network.
 
-use std::{
-    io::{Read, Write},
-    net::{Shutdown, SocketAddr, TcpStream},
-    time::Duration,
-};
+use std::io;
+
+use crate::transport::Transport;
 
 use super::{
-    protocol::{ChordRequest, ChordResponse},
-    Node,
+    protocol::{ChordRequest, ChordResponse, ErrorKind},
+    trace::{self, TraceId},
+    Node, NodeAddr,
 };
 
-fn init_chord_request(remote_addr: SocketAddr, request: ChordRequest) -> ChordResponse {
-    let mut request_stream = match TcpStream::connect(remote_addr) {
-        Ok(stream) => stream,
-        Err(err) => return ChordResponse::Error(err.to_string()),
-    };
+/// Maximum number of redirects followed by a lookup,
+/// beyond which the ring is considered inconsistent.
+const MAX_LOOKUP_REDIRECTS: usize = 128;
 
-    let request_msg = request.to_protocol_text();
-
-    if let Err(err) = request_stream.write(request_msg.as_bytes()) {
-        return ChordResponse::Error(err.to_string());
-    }
+fn init_chord_request(
+    transport: &dyn Transport,
+    remote_addr: NodeAddr,
+    request: ChordRequest,
+) -> ChordResponse {
+    let response_msg = match transport.send(
+        remote_addr.get_socket_addr(),
+        &remote_addr.address_request(&request.to_protocol_text()),
+    ) {
+        Ok(response_msg) => response_msg,
+        // A read timeout is reported as `WouldBlock` on Unix
+        Err(err)
+            if matches!(
+                err.kind(),
+                io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock
+            ) =>
+        {
+            return ChordResponse::Error(ErrorKind::Timeout, err.to_string())
+        }
+        Err(err) => return ChordResponse::Error(ErrorKind::Retryable, err.to_string()),
+    };
 
-    if let Err(err) = request_stream.shutdown(Shutdown::Write) {
-        return ChordResponse::Error(err.to_string());
+    match ChordResponse::parse(&response_msg) {
+        Ok(response) => response,
+        Err(err) => ChordResponse::Error(ErrorKind::Fatal, err.to_string()),
     }
+}
 
-    request_stream
-        .set_read_timeout(Some(Duration::from_secs(5)))
-        .unwrap();
+/// Sends the lookup `request` to `remote_addr`, then to
+/// each node it is redirected to (in iterative routing),
+/// and returns the first response which is not a redirect.
+///
+/// Each hop is logged if the lookup is traced by `trace_id`,
+/// and the redirects are added to the hop count of its successor.
+fn init_lookup_request(
+    transport: &dyn Transport,
+    remote_addr: NodeAddr,
+    request: ChordRequest,
+    trace_id: Option<TraceId>,
+) -> ChordResponse {
+    let mut remote_addr = remote_addr;
 
-    let mut response_msg = String::new();
+    for redirects in 0..=MAX_LOOKUP_REDIRECTS as u32 {
+        trace::log(trace_id, || format!("lookup sent to [{}]", remote_addr));
 
-    if let Err(err) = request_stream.read_to_string(&mut response_msg) {
-        return ChordResponse::Error(err.to_string());
+        match init_chord_request(transport, remote_addr, request.clone()) {
+            ChordResponse::Redirect(next_hop) => remote_addr = next_hop.get_node_addr(),
+            ChordResponse::TracedSuccessor(successor, hops) => {
+                return ChordResponse::TracedSuccessor(successor, hops.saturating_add(redirects))
+            }
+            response => return response,
+        }
     }
 
-    match ChordResponse::parse(&response_msg) {
-        Ok(response) => response,
-        Err(err) => ChordResponse::Error(err.to_string()),
-    }
+    ChordResponse::Error(
+        ErrorKind::Retryable,
+        "too many redirects during the lookup".to_string(),
+    )
 }
 
 /// Sends a request to `remote_addr`
 /// (a Chord node) to locate the successor
 /// of the `target_node` in the network
 /// and returns a `ChordResponse`.
-pub(crate) fn find_successor_of_node(target_node: &Node, remote_addr: SocketAddr) -> ChordResponse {
-    init_chord_request(
+///
+/// Redirects are followed until the successor is located.
+pub(crate) fn find_successor_of_node(
+    transport: &dyn Transport,
+    target_node: &Node,
+    remote_addr: NodeAddr,
+    trace_id: Option<TraceId>,
+) -> ChordResponse {
+    init_lookup_request(
+        transport,
         remote_addr,
-        ChordRequest::FindSuccessorOfNode(target_node.clone()),
+        ChordRequest::FindSuccessorOfNode(target_node.clone(), trace_id),
+        trace_id,
+    )
+}
+
+/// Sends a request to `remote_addr`
+/// (a Chord node) to locate the successor
+/// of `key` in the network
+/// and returns a `ChordResponse`.
+///
+/// Redirects are followed until the successor is located.
+pub(crate) fn find_successor_of_key(
+    transport: &dyn Transport,
+    key: u128,
+    remote_addr: NodeAddr,
+    trace_id: Option<TraceId>,
+) -> ChordResponse {
+    init_lookup_request(
+        transport,
+        remote_addr,
+        ChordRequest::FindSuccessorOfKey(key, trace_id),
+        trace_id,
     )
 }
 
@@ -59,27 +119,287 @@ pub(crate) fn find_successor_of_node(target_node: &Node, remote_addr: SocketAddr
 /// to retrieve the successor list
 /// of this remote node (a Chord node)
 /// and returns a `ChordResponse`.
-pub(crate) fn get_successor_list(remote_addr: SocketAddr) -> ChordResponse {
-    init_chord_request(remote_addr, ChordRequest::GetSuccessorList)
+pub(crate) fn get_successor_list(
+    transport: &dyn Transport,
+    remote_addr: NodeAddr,
+) -> ChordResponse {
+    init_chord_request(transport, remote_addr, ChordRequest::GetSuccessorList)
 }
 
 /// Sends a request to `remote_addr`
 /// to retrieve the predecessor
 /// of this remote node (a Chord node)
 /// and returns a `ChordResponse`.
-pub(crate) fn get_predecessor(remote_addr: SocketAddr) -> ChordResponse {
-    init_chord_request(remote_addr, ChordRequest::GetPredecessor)
+pub(crate) fn get_predecessor(transport: &dyn Transport, remote_addr: NodeAddr) -> ChordResponse {
+    init_chord_request(transport, remote_addr, ChordRequest::GetPredecessor)
 }
 
 /// Notifies a remote node about
 /// the existence of `self_node` in the network,
 /// and returns a `ChordResponse`.
-pub(crate) fn notify_remote_node(self_node: &Node, remote_addr: SocketAddr) -> ChordResponse {
-    init_chord_request(remote_addr, ChordRequest::NotificationBy(self_node.clone()))
+pub(crate) fn notify_remote_node(
+    transport: &dyn Transport,
+    self_node: &Node,
+    remote_addr: NodeAddr,
+) -> ChordResponse {
+    init_chord_request(
+        transport,
+        remote_addr,
+        ChordRequest::NotificationBy(self_node.clone()),
+    )
 }
 
 /// Sends a request to `remote_addr` to ckeck
 /// if this remote node (a Chord node) is active.
-pub(crate) fn check_remote_node(remote_addr: SocketAddr) -> ChordResponse {
-    init_chord_request(remote_addr, ChordRequest::CheckNode)
+pub(crate) fn check_remote_node(transport: &dyn Transport, remote_addr: NodeAddr) -> ChordResponse {
+    init_chord_request(transport, remote_addr, ChordRequest::CheckNode)
+}
+
+/// Asks the node at `remote_addr` whether it can reach
+/// `self_node` in turn, and returns a `ChordResponse`.
+pub(crate) fn probe_back(
+    transport: &dyn Transport,
+    self_node: &Node,
+    remote_addr: NodeAddr,
+) -> ChordResponse {
+    init_chord_request(
+        transport,
+        remote_addr,
+        ChordRequest::ProbeBack(self_node.get_node_addr()),
+    )
+}
+
+/// Notifies a remote node that `self_node`
+/// is leaving the network, and returns a `ChordResponse`.
+pub(crate) fn notify_leaving(
+    transport: &dyn Transport,
+    self_node: &Node,
+    remote_addr: NodeAddr,
+) -> ChordResponse {
+    init_chord_request(
+        transport,
+        remote_addr,
+        ChordRequest::Leaving(self_node.clone()),
+    )
+}
+
+#[cfg(test)]
+mod iterative_lookup_test {
+    use crate::{
+        chord::{
+            protocol::{ChordRequest, ChordResponse, ErrorKind},
+            request_handler::find_successor_of_key_request_handler,
+            trace::TraceId,
+            Node, SUCCESSOR_LIST_LENGTH,
+        },
+        cli::RoutingMode,
+        transport::mock::MockTransport,
+    };
+
+    use super::{find_successor_of_key, MAX_LOOKUP_REDIRECTS};
+
+    /// Successor list of the node at `position`, in a ring
+    /// of ten nodes at positions 100, 200, ..., 1000.
+    fn successor_list_at(position: u128) -> [Node; SUCCESSOR_LIST_LENGTH] {
+        std::array::from_fn(|i| Node::for_test_at((position + 100 * i as u128) % 1000 + 100))
+    }
+
+    /// Mock transport where every node of the ring
+    /// routes lookups iteratively.
+    fn iterative_ring_transport() -> MockTransport {
+        MockTransport::new(|remote_addr, request_msg| {
+            let node = Node::for_test_at(remote_addr.port() as u128);
+            let all_active_transport =
+                MockTransport::new(|_, _| Ok(ChordResponse::Active.to_protocol_text()));
+
+            let response = match ChordRequest::parse(request_msg) {
+                Ok(ChordRequest::FindSuccessorOfKey(key, trace_id)) => {
+                    find_successor_of_key_request_handler(
+                        &all_active_transport,
+                        node.clone(),
+                        successor_list_at(node.get_ring_position()),
+                        key,
+                        trace_id,
+                        RoutingMode::Iterative,
+                        None,
+                    )
+                }
+                _ => ChordResponse::Error(ErrorKind::Fatal, "unexpected request".to_string()),
+            };
+
+            Ok(response.to_protocol_text())
+        })
+    }
+
+    #[test]
+    fn initiator_follows_redirects_test() {
+        let transport = iterative_ring_transport();
+        let trace_id = TraceId::generate();
+
+        let response = find_successor_of_key(
+            &transport,
+            950,
+            Node::for_test_at(100).get_node_addr(),
+            Some(trace_id),
+        );
+
+        assert_eq!(
+            response,
+            ChordResponse::TracedSuccessor(Node::for_test_at(1000), 3)
+        );
+
+        // The initiator contacts each hop itself,
+        // with the trace ID of the lookup
+        let hops = transport
+            .sent_requests()
+            .into_iter()
+            .map(|(remote_addr, request_msg)| {
+                assert_eq!(
+                    ChordRequest::parse(&request_msg).unwrap(),
+                    ChordRequest::FindSuccessorOfKey(950, Some(trace_id))
+                );
+                remote_addr
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            hops,
+            [
+                Node::for_test_at(100),
+                Node::for_test_at(600),
+                Node::for_test_at(900)
+            ]
+            .map(|node| node.get_public_addr())
+        );
+    }
+
+    /// Mock transport where every node of the ring
+    /// forwards lookups recursively.
+    fn recursive_ring_transport() -> MockTransport {
+        MockTransport::new(|remote_addr, request_msg| {
+            let node = Node::for_test_at(remote_addr.port() as u128);
+
+            let response = match ChordRequest::parse(request_msg) {
+                Ok(ChordRequest::FindSuccessorOfKey(key, trace_id)) => {
+                    find_successor_of_key_request_handler(
+                        &recursive_ring_transport(),
+                        node.clone(),
+                        successor_list_at(node.get_ring_position()),
+                        key,
+                        trace_id,
+                        RoutingMode::Recursive,
+                        None,
+                    )
+                }
+                Ok(ChordRequest::CheckNode) => ChordResponse::Active,
+                _ => ChordResponse::Error(ErrorKind::Fatal, "unexpected request".to_string()),
+            };
+
+            Ok(response.to_protocol_text())
+        })
+    }
+
+    #[test]
+    fn traced_lookup_counts_hops_test() {
+        let transport = recursive_ring_transport();
+
+        // The lookup goes through the nodes at 100, 600 and 900
+        assert_eq!(
+            find_successor_of_key(
+                &transport,
+                950,
+                Node::for_test_at(100).get_node_addr(),
+                Some(TraceId::generate())
+            ),
+            ChordResponse::TracedSuccessor(Node::for_test_at(1000), 3)
+        );
+
+        assert_eq!(
+            find_successor_of_key(
+                &transport,
+                150,
+                Node::for_test_at(100).get_node_addr(),
+                Some(TraceId::generate())
+            ),
+            ChordResponse::TracedSuccessor(Node::for_test_at(200), 1)
+        );
+
+        // Untraced lookups are answered without hop count
+        assert_eq!(
+            find_successor_of_key(
+                &transport,
+                950,
+                Node::for_test_at(100).get_node_addr(),
+                None
+            ),
+            ChordResponse::Successor(Node::for_test_at(1000))
+        );
+    }
+
+    #[test]
+    fn redirect_loop_is_bounded_test() {
+        let transport = MockTransport::new(|remote_addr, _| {
+            Ok(
+                ChordResponse::Redirect(Node::for_test_at(remote_addr.port() as u128))
+                    .to_protocol_text(),
+            )
+        });
+
+        let response = find_successor_of_key(
+            &transport,
+            950,
+            Node::for_test_at(100).get_node_addr(),
+            None,
+        );
+
+        assert_eq!(
+            response,
+            ChordResponse::Error(
+                ErrorKind::Retryable,
+                "too many redirects during the lookup".to_string()
+            )
+        );
+        assert_eq!(transport.sent_requests().len(), MAX_LOOKUP_REDIRECTS + 1);
+    }
+}
+
+#[cfg(test)]
+mod transport_failure_test {
+    use std::{io, net::SocketAddr};
+
+    use crate::{
+        chord::{
+            protocol::{ChordResponse, ErrorKind},
+            NodeAddr,
+        },
+        transport::mock::MockTransport,
+    };
+
+    use super::check_remote_node;
+
+    /// Kind of the error response to a request
+    /// failing with the transport error `kind`.
+    fn error_kind_of(kind: io::ErrorKind) -> ErrorKind {
+        let transport = MockTransport::new(move |_, _| Err(io::Error::from(kind)));
+        let remote_addr = NodeAddr::new(SocketAddr::from(([10, 0, 0, 1], 8000)), 0);
+
+        match check_remote_node(&transport, remote_addr) {
+            ChordResponse::Error(error_kind, _) => error_kind,
+            response => panic!("unexpected response: {:?}", response),
+        }
+    }
+
+    #[test]
+    fn timeouts_are_distinguished_from_refusals_test() {
+        assert_eq!(error_kind_of(io::ErrorKind::TimedOut), ErrorKind::Timeout);
+        assert_eq!(error_kind_of(io::ErrorKind::WouldBlock), ErrorKind::Timeout);
+        assert_eq!(
+            error_kind_of(io::ErrorKind::ConnectionRefused),
+            ErrorKind::Retryable
+        );
+        assert_eq!(
+            error_kind_of(io::ErrorKind::ConnectionReset),
+            ErrorKind::Retryable
+        );
+    }
 }
@@ -0,0 +1,146 @@
+//! Ranges of positions on the Chord ring.
+//!
+//! Ranges may wrap past `u128::MAX` back to `0`. All the wrap-around
+//! logic of the crate lives here, instead of being reimplemented
+//! (with its subtle edge cases) by each feature.
+
+/// Half-open range `(start, end]` of ring positions, going
+/// clockwise from `start` (excluded) to `end` (included).
+///
+/// This is the range of keys owned by the node at `end`, whose
+/// predecessor is at `start`. If `start` equals `end`, the range
+/// covers the whole ring (a single node owns every key).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct RingRange {
+    start: u128,
+    end: u128,
+}
+
+impl RingRange {
+    pub(crate) fn new(start: u128, end: u128) -> Self {
+        Self { start, end }
+    }
+
+    /// Returns the excluded start of the range.
+    pub(crate) fn start(&self) -> u128 {
+        self.start
+    }
+
+    /// Returns the included end of the range.
+    pub(crate) fn end(&self) -> u128 {
+        self.end
+    }
+
+    /// Returns `true` if `position` is in `(start, end]`.
+    pub(crate) fn contains(&self, position: u128) -> bool {
+        // Distances are measured clockwise from `start`,
+        // so that the range never wraps around.
+        let range_length = self.end.wrapping_sub(self.start);
+        let distance = position.wrapping_sub(self.start);
+
+        range_length == 0 || (distance != 0 && distance <= range_length)
+    }
+
+    /// Returns `true` if `position` is in `(start, end)`,
+    /// i.e. in the range, but not `end` itself.
+    ///
+    /// If `start` equals `end`, every position except `end` qualifies.
+    pub(crate) fn contains_strictly(&self, position: u128) -> bool {
+        position != self.end && self.contains(position)
+    }
+}
+
+#[cfg(test)]
+mod ring_range_test {
+    use super::RingRange;
+
+    /// Positions around the edges of the ring, and around
+    /// arbitrary positions, where wrap-around bugs hide.
+    fn sample_positions() -> Vec<u128> {
+        let mut positions = vec![
+            0,
+            1,
+            2,
+            3,
+            u128::MAX / 2 - 1,
+            u128::MAX / 2,
+            u128::MAX / 2 + 1,
+            u128::MAX / 2 + 2,
+            u128::MAX - 2,
+            u128::MAX - 1,
+            u128::MAX,
+        ];
+        positions.extend([100, 101, 102, 1 << 64, (1 << 64) + 1]);
+        positions
+    }
+
+    /// Reference definition of `(start, end]`, by case analysis
+    /// on whether the range wraps around the ring.
+    fn reference_contains(start: u128, end: u128, position: u128) -> bool {
+        if start < end {
+            start < position && position <= end
+        } else if start > end {
+            position > start || position <= end
+        } else {
+            true
+        }
+    }
+
+    #[test]
+    fn contains_matches_reference_test() {
+        let positions = sample_positions();
+
+        for &start in &positions {
+            for &end in &positions {
+                let range = RingRange::new(start, end);
+
+                for &position in &positions {
+                    assert_eq!(
+                        range.contains(position),
+                        reference_contains(start, end, position),
+                        "({}, {}] contains {}",
+                        start,
+                        end,
+                        position
+                    );
+
+                    assert_eq!(
+                        range.contains_strictly(position),
+                        reference_contains(start, end, position) && position != end,
+                        "({}, {}) contains {}",
+                        start,
+                        end,
+                        position
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn wrapping_range_test() {
+        let range = RingRange::new(u128::MAX - 1, 1);
+
+        assert!(!range.contains(u128::MAX - 1));
+        assert!(range.contains(u128::MAX));
+        assert!(range.contains(0));
+        assert!(range.contains(1));
+        assert!(!range.contains(2));
+        assert!(!range.contains(u128::MAX / 2));
+
+        assert!(range.contains_strictly(0));
+        assert!(!range.contains_strictly(1));
+    }
+
+    #[test]
+    fn whole_ring_range_test() {
+        let range = RingRange::new(42, 42);
+
+        for position in [0, 41, 42, 43, u128::MAX] {
+            assert!(range.contains(position));
+        }
+
+        assert!(!range.contains_strictly(42));
+        assert!(range.contains_strictly(43));
+    }
+}
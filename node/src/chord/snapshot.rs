@@ -0,0 +1,169 @@
+//! Bootstrap of a node from a membership snapshot: the
+//! public socket addresses of the nodes of a ring
+//! (`membership-snapshot`).
+//!
+//! Identifiers being hashed, each node derives the whole ring from
+//! the snapshot, and starts with the successor list and the fingers
+//! it would have in this ring, instead of joining through a seed
+//! node. Stabilization then corrects the pointers to the members
+//! which are dead, or not started yet.
+
+use std::net::SocketAddr;
+
+use super::{finger_table::FingerTable, utils, Node, SUCCESSOR_LIST_LENGTH};
+
+/// Ring described by a membership snapshot,
+/// as seen by the node bootstrapped from it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct MembershipSnapshot {
+    self_node: Node,
+    /// The other members, clockwise from `self_node`.
+    successors: Vec<Node>,
+    /// Number of members skipped by the successor
+    /// list, after attempts to reach them failed.
+    skipped: usize,
+}
+
+impl MembershipSnapshot {
+    /// Creates the ring of the members at `addrs`, seen
+    /// by `self_node`. The address of `self_node` and
+    /// repeated addresses are ignored.
+    pub(crate) fn new(self_node: &Node, addrs: &[SocketAddr]) -> Self {
+        let mut successors = addrs
+            .iter()
+            .filter(|addr| **addr != self_node.get_public_addr())
+            .map(|addr| Node::new(*addr))
+            .collect::<Vec<_>>();
+
+        successors.sort_by_key(|node| {
+            node.get_ring_position()
+                .wrapping_sub(self_node.get_ring_position())
+        });
+        successors.dedup();
+
+        Self {
+            self_node: self_node.clone(),
+            successors,
+            skipped: 0,
+        }
+    }
+
+    /// Returns the successor list of `self_node` in the ring:
+    /// the members following the skipped ones, padded with
+    /// `self_node` if there are too few of them.
+    pub(crate) fn successor_list(&self) -> [Node; SUCCESSOR_LIST_LENGTH] {
+        let mut successors = self
+            .successors
+            .iter()
+            .cycle()
+            .skip(self.skipped)
+            .take(self.successors.len().min(SUCCESSOR_LIST_LENGTH));
+
+        std::array::from_fn(|_| successors.next().unwrap_or(&self.self_node).clone())
+    }
+
+    /// Skips the members of the current successor list, found
+    /// unreachable, and returns the successor list made of the
+    /// next ones (wrapping around to the first ones eventually,
+    /// as members may only be starting).
+    pub(crate) fn skip_successor_list(&mut self) -> [Node; SUCCESSOR_LIST_LENGTH] {
+        if !self.successors.is_empty() {
+            self.skipped = (self.skipped + SUCCESSOR_LIST_LENGTH) % self.successors.len();
+        }
+
+        self.successor_list()
+    }
+
+    /// Returns the finger table of `self_node` in the ring.
+    pub(crate) fn finger_table(&self) -> FingerTable {
+        let mut ring = self.successors.clone();
+        ring.push(self.self_node.clone());
+
+        let bit_length = self.self_node.get_ring_bit_length();
+        let mut finger_table = FingerTable::new(bit_length);
+
+        for index in 0..bit_length {
+            let target_position = FingerTable::target_position(&self.self_node, index);
+            finger_table.set(index, Some(utils::owner_of(target_position, &ring).clone()));
+        }
+
+        finger_table
+    }
+}
+
+#[cfg(test)]
+mod snapshot_test {
+    use std::net::SocketAddr;
+
+    use crate::chord::{finger_table::FingerTable, utils, Node, SUCCESSOR_LIST_LENGTH};
+
+    use super::MembershipSnapshot;
+
+    fn addrs(ports: std::ops::Range<u16>) -> Vec<SocketAddr> {
+        ports
+            .map(|port| SocketAddr::from(([10, 0, 0, 1], port)))
+            .collect()
+    }
+
+    #[test]
+    fn snapshot_successor_list_test() {
+        let addrs = addrs(8000..8010);
+        let self_node = Node::new(addrs[3]);
+
+        let mut ring = addrs
+            .iter()
+            .map(|addr| Node::new(*addr))
+            .collect::<Vec<_>>();
+        ring.sort_by_key(|node| node.get_ring_position());
+        let position = ring.iter().position(|node| *node == self_node).unwrap();
+        // The 9 other members, clockwise from the node
+        let successor = |i: usize| ring[(position + 1 + i % 9) % ring.len()].clone();
+
+        // Repeated addresses are ignored
+        let mut snapshot =
+            MembershipSnapshot::new(&self_node, &[addrs.clone(), addrs.clone()].concat());
+
+        assert_eq!(snapshot.successor_list(), std::array::from_fn(successor));
+
+        // Members are skipped by lists of 5, wrapping around
+        assert_eq!(
+            snapshot.skip_successor_list(),
+            std::array::from_fn(|i| successor(SUCCESSOR_LIST_LENGTH + i))
+        );
+        assert_eq!(
+            snapshot.skip_successor_list(),
+            std::array::from_fn(|i| successor(2 * SUCCESSOR_LIST_LENGTH + i))
+        );
+
+        // Fingers are the owners of their targets
+        for (index, finger) in snapshot.finger_table().entries() {
+            let target_position = FingerTable::target_position(&self_node, index);
+            assert_eq!(finger, *utils::owner_of(target_position, &ring));
+        }
+    }
+
+    #[test]
+    fn small_snapshot_successor_list_test() {
+        let addrs = addrs(8000..8002);
+        let self_node = Node::new(addrs[0]);
+        let other_node = Node::new(addrs[1]);
+
+        let mut snapshot = MembershipSnapshot::new(&self_node, &addrs);
+
+        let expected_successor_list: [Node; SUCCESSOR_LIST_LENGTH] = std::array::from_fn(|i| {
+            if i == 0 {
+                other_node.clone()
+            } else {
+                self_node.clone()
+            }
+        });
+        assert_eq!(snapshot.successor_list(), expected_successor_list);
+        assert_eq!(snapshot.skip_successor_list(), expected_successor_list);
+
+        // A snapshot made of the node alone
+        let mut snapshot = MembershipSnapshot::new(&self_node, &addrs[..1]);
+        let alone: [Node; SUCCESSOR_LIST_LENGTH] = std::array::from_fn(|_| self_node.clone());
+        assert_eq!(snapshot.successor_list(), alone);
+        assert_eq!(snapshot.skip_successor_list(), alone);
+    }
+}
@@ -0,0 +1,496 @@
+//! Pure decision logic of the network stabilization.
+//!
+//! The stabilization driver performs the network requests,
+//! then relies on these functions to decide what to do next,
+//! on `StabilizationPacer` to decide when to run again, and on
+//! `IsolationTracker` to decide how to handle an isolated node.
+
+use std::time::Duration;
+
+use crate::cli::IsolationPolicy;
+
+use super::Node;
+
+/// Number of passes in a row without change in the neighborhood
+/// of a node, after which its stabilization interval is doubled.
+pub(crate) const STABLE_PASSES_BEFORE_SLOWDOWN: u32 = 3;
+
+/// Result of asking a successor list
+/// entry for its predecessor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SuccessorProbe {
+    /// The entry did not answer.
+    Unreachable,
+    /// The entry answered with its predecessor.
+    Predecessor(Option<Node>),
+}
+
+/// Decision on the successor to notify
+/// during a stabilization pass.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum SuccessorDecision {
+    /// All probed successor list entries are unreachable.
+    Isolated,
+    /// Notifies the given active successor.
+    Notify(Node),
+    /// The active successor's predecessor sits between `self_node`
+    /// and the active successor: adopts `candidate` if it is active,
+    /// otherwise notifies `fallback` (the active successor).
+    AdoptIfActive { candidate: Node, fallback: Node },
+}
+
+/// Decision on `self_node`'s predecessor
+/// during a stabilization pass.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum PredecessorAction {
+    /// Keeps the current predecessor.
+    Keep,
+    /// Clears the predecessor, which is no longer active.
+    Clear,
+}
+
+/// Decides which successor to notify, given the probes
+/// of the successor list entries, in successor list order.
+///
+/// The first reachable entry is the active successor.
+/// Its predecessor becomes a candidate successor if it sits
+/// strictly between `self_node` and the active successor, or if
+/// the active successor is `self_node` itself (single-node ring).
+pub(crate) fn decide_successor(
+    self_node: &Node,
+    probes: &[(Node, SuccessorProbe)],
+) -> SuccessorDecision {
+    let (active_successor, potential_successor) =
+        match probes.iter().find_map(|probe| match probe {
+            (successor, SuccessorProbe::Predecessor(predecessor)) => Some((successor, predecessor)),
+            (_, SuccessorProbe::Unreachable) => None,
+        }) {
+            Some(probe) => probe,
+            None => return SuccessorDecision::Isolated,
+        };
+
+    match potential_successor {
+        Some(potential_successor)
+            if self_node.get_ring_position() == active_successor.get_ring_position()
+                || potential_successor.is_position_stictly_between(
+                    self_node.get_ring_position(),
+                    active_successor.get_ring_position(),
+                ) =>
+        {
+            SuccessorDecision::AdoptIfActive {
+                candidate: potential_successor.clone(),
+                fallback: active_successor.clone(),
+            }
+        }
+        _ => SuccessorDecision::Notify(active_successor.clone()),
+    }
+}
+
+/// Decides whether `self_node`'s predecessor
+/// is kept, given its liveness.
+///
+/// `is_predecessor_active` is ignored without predecessor.
+pub(crate) fn decide_predecessor(
+    predecessor: Option<&Node>,
+    is_predecessor_active: bool,
+) -> PredecessorAction {
+    match predecessor {
+        Some(_) if !is_predecessor_active => PredecessorAction::Clear,
+        _ => PredecessorAction::Keep,
+    }
+}
+
+/// Decides whether `self_node` is part of the ring, after a
+/// successful stabilization pass: it has a successor other than
+/// itself, and is the successor of its predecessor.
+///
+/// The first node of the ring (`is_first_node`) is part of it
+/// as soon as it stabilizes, being alone in the ring.
+pub(crate) fn is_part_of_ring(
+    self_node: &Node,
+    is_first_node: bool,
+    predecessor: Option<&Node>,
+    successor: &Node,
+) -> bool {
+    is_first_node
+        || (successor != self_node
+            && predecessor.is_some_and(|predecessor| predecessor != self_node))
+}
+
+/// Interval between the stabilization passes of a node,
+/// adapting to the churn seen in its neighborhood.
+///
+/// The interval drops to `min_interval` as soon as a pass sees
+/// a change (new predecessor, changed successor list), and doubles
+/// after each `STABLE_PASSES_BEFORE_SLOWDOWN` passes without change,
+/// up to `max_interval`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct StabilizationPacer {
+    min_interval: Duration,
+    max_interval: Duration,
+    interval: Duration,
+    stable_passes: u32,
+}
+
+impl StabilizationPacer {
+    /// Creates a pacer starting at `min_interval`,
+    /// as a starting node has yet to converge.
+    pub(crate) fn new(min_interval: Duration, max_interval: Duration) -> Self {
+        Self {
+            min_interval,
+            max_interval,
+            interval: min_interval,
+            stable_passes: 0,
+        }
+    }
+
+    /// Returns the interval before the next pass,
+    /// given whether the last one saw a change.
+    pub(crate) fn next_interval(&mut self, is_changed: bool) -> Duration {
+        if is_changed {
+            self.interval = self.min_interval;
+            self.stable_passes = 0;
+        } else {
+            self.stable_passes += 1;
+
+            if self.stable_passes >= STABLE_PASSES_BEFORE_SLOWDOWN {
+                self.interval = (self.interval * 2).min(self.max_interval);
+                self.stable_passes = 0;
+            }
+        }
+
+        self.interval
+    }
+}
+
+/// Action of a node after a failed stabilization
+/// pass it could not recover from through a known peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum IsolationAction {
+    /// Retries its successors and known peers on the next pass.
+    Retry,
+    /// Joins the network again through its bootstrap node.
+    Rejoin,
+    /// Exits.
+    Exit,
+}
+
+/// Counts the stabilization passes in a row a node could not recover
+/// from, and applies its `IsolationPolicy` once they reach
+/// `max_failures`: a single failed pass (e.g. a transient network
+/// failure) is retried.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct IsolationTracker {
+    max_failures: u32,
+    policy: IsolationPolicy,
+    consecutive_failures: u32,
+}
+
+impl IsolationTracker {
+    pub(crate) fn new(max_failures: u32, policy: IsolationPolicy) -> Self {
+        Self {
+            max_failures,
+            policy,
+            consecutive_failures: 0,
+        }
+    }
+
+    /// Counts a failed pass, and returns the action to take.
+    pub(crate) fn record_failure(&mut self) -> IsolationAction {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+
+        if self.consecutive_failures < self.max_failures {
+            return IsolationAction::Retry;
+        }
+
+        match self.policy {
+            IsolationPolicy::Rejoin => IsolationAction::Rejoin,
+            IsolationPolicy::Retry => IsolationAction::Retry,
+            IsolationPolicy::Exit => IsolationAction::Exit,
+        }
+    }
+
+    /// Restarts the count, once the node recovered or attempted to
+    /// rejoin the network: a failed rejoin is attempted again after
+    /// `max_failures` failed passes.
+    pub(crate) fn reset(&mut self) {
+        self.consecutive_failures = 0;
+    }
+
+    pub(crate) fn consecutive_failures(&self) -> u32 {
+        self.consecutive_failures
+    }
+}
+
+#[cfg(test)]
+mod isolation_tracker_test {
+    use crate::cli::IsolationPolicy;
+
+    use super::{IsolationAction, IsolationTracker};
+
+    #[test]
+    fn transient_failure_is_retried_test() {
+        let mut tracker = IsolationTracker::new(3, IsolationPolicy::Exit);
+
+        assert_eq!(tracker.record_failure(), IsolationAction::Retry);
+        assert_eq!(tracker.record_failure(), IsolationAction::Retry);
+        tracker.reset();
+
+        assert_eq!(tracker.record_failure(), IsolationAction::Retry);
+        assert_eq!(tracker.record_failure(), IsolationAction::Retry);
+        assert_eq!(tracker.record_failure(), IsolationAction::Exit);
+    }
+
+    #[test]
+    fn policy_applies_after_max_failures_test() {
+        let mut tracker = IsolationTracker::new(2, IsolationPolicy::Rejoin);
+        assert_eq!(tracker.record_failure(), IsolationAction::Retry);
+        assert_eq!(tracker.record_failure(), IsolationAction::Rejoin);
+
+        // A failed rejoin is attempted again after as many failures
+        tracker.reset();
+        assert_eq!(tracker.record_failure(), IsolationAction::Retry);
+        assert_eq!(tracker.record_failure(), IsolationAction::Rejoin);
+
+        let mut tracker = IsolationTracker::new(1, IsolationPolicy::Retry);
+        assert!((0..10).all(|_| tracker.record_failure() == IsolationAction::Retry));
+        assert_eq!(tracker.consecutive_failures(), 10);
+    }
+}
+
+#[cfg(test)]
+mod stabilization_pacer_test {
+    use std::time::Duration;
+
+    use super::{StabilizationPacer, STABLE_PASSES_BEFORE_SLOWDOWN};
+
+    /// Counts the passes run over `period`,
+    /// each seeing a change if `is_changed` says so.
+    fn count_passes(
+        pacer: &mut StabilizationPacer,
+        period: Duration,
+        is_changed: impl Fn(usize) -> bool,
+    ) -> usize {
+        let mut elapsed = Duration::ZERO;
+        let mut passes = 0;
+
+        while elapsed < period {
+            elapsed += pacer.next_interval(is_changed(passes));
+            passes += 1;
+        }
+
+        passes
+    }
+
+    #[test]
+    fn quiescent_ring_slows_down_test() {
+        let mut pacer = StabilizationPacer::new(Duration::from_secs(1), Duration::from_secs(8));
+
+        // Doubles after each series of stable passes, up to the maximum
+        let intervals = (0..4 * STABLE_PASSES_BEFORE_SLOWDOWN + 2)
+            .map(|_| pacer.next_interval(false).as_secs())
+            .collect::<Vec<_>>();
+        assert_eq!(intervals, [1, 1, 2, 2, 2, 4, 4, 4, 8, 8, 8, 8, 8, 8]);
+
+        // A quiescent ring runs far fewer passes than a fixed 1s interval
+        let mut pacer = StabilizationPacer::new(Duration::from_secs(1), Duration::from_secs(8));
+        let passes = count_passes(&mut pacer, Duration::from_secs(120), |_| false);
+        assert!(passes < 30, "{} passes", passes);
+    }
+
+    #[test]
+    fn churn_speeds_up_test() {
+        let mut pacer = StabilizationPacer::new(Duration::from_secs(1), Duration::from_secs(8));
+
+        // Slowed down by a stable period
+        count_passes(&mut pacer, Duration::from_secs(60), |_| false);
+        assert_eq!(pacer.next_interval(false), Duration::from_secs(8));
+
+        // A join or a leave is handled at the minimum interval
+        assert_eq!(pacer.next_interval(true), Duration::from_secs(1));
+
+        // Converging over 5 passes in 10s, which
+        // takes 40s at the maximum interval
+        let passes = count_passes(&mut pacer, Duration::from_secs(10), |pass| pass < 5);
+        assert!(passes >= 8, "{} passes", passes);
+
+        // A fixed interval, with equal bounds
+        let mut pacer = StabilizationPacer::new(Duration::from_secs(2), Duration::from_secs(2));
+        assert_eq!(
+            count_passes(&mut pacer, Duration::from_secs(20), |_| false),
+            10
+        );
+    }
+}
+
+#[cfg(test)]
+mod stabilization_decision_test {
+    use crate::chord::Node;
+
+    use super::{
+        decide_predecessor, decide_successor, is_part_of_ring, PredecessorAction,
+        SuccessorDecision, SuccessorProbe,
+    };
+
+    #[test]
+    fn decide_successor_test() {
+        let self_node = Node::for_test_at(100);
+
+        let cases = vec![
+            (
+                "all entries unreachable",
+                vec![
+                    (Node::for_test_at(200), SuccessorProbe::Unreachable),
+                    (Node::for_test_at(300), SuccessorProbe::Unreachable),
+                ],
+                SuccessorDecision::Isolated,
+            ),
+            (
+                "successor without predecessor",
+                vec![(Node::for_test_at(200), SuccessorProbe::Predecessor(None))],
+                SuccessorDecision::Notify(Node::for_test_at(200)),
+            ),
+            (
+                "successor's predecessor is self",
+                vec![(
+                    Node::for_test_at(200),
+                    SuccessorProbe::Predecessor(Some(Node::for_test_at(100))),
+                )],
+                SuccessorDecision::Notify(Node::for_test_at(200)),
+            ),
+            (
+                "successor's predecessor between self and successor",
+                vec![(
+                    Node::for_test_at(200),
+                    SuccessorProbe::Predecessor(Some(Node::for_test_at(150))),
+                )],
+                SuccessorDecision::AdoptIfActive {
+                    candidate: Node::for_test_at(150),
+                    fallback: Node::for_test_at(200),
+                },
+            ),
+            (
+                "successor's predecessor behind self",
+                vec![(
+                    Node::for_test_at(200),
+                    SuccessorProbe::Predecessor(Some(Node::for_test_at(50))),
+                )],
+                SuccessorDecision::Notify(Node::for_test_at(200)),
+            ),
+            (
+                "wrapping interval, predecessor between self and successor",
+                vec![(
+                    Node::for_test_at(50),
+                    SuccessorProbe::Predecessor(Some(Node::for_test_at(u128::MAX - 1))),
+                )],
+                SuccessorDecision::AdoptIfActive {
+                    candidate: Node::for_test_at(u128::MAX - 1),
+                    fallback: Node::for_test_at(50),
+                },
+            ),
+            (
+                "first entry dead, second entry adopted as active",
+                vec![
+                    (Node::for_test_at(200), SuccessorProbe::Unreachable),
+                    (
+                        Node::for_test_at(300),
+                        SuccessorProbe::Predecessor(Some(Node::for_test_at(100))),
+                    ),
+                ],
+                SuccessorDecision::Notify(Node::for_test_at(300)),
+            ),
+            (
+                "first entry dead, second entry's predecessor adopted",
+                vec![
+                    (Node::for_test_at(200), SuccessorProbe::Unreachable),
+                    (
+                        Node::for_test_at(300),
+                        SuccessorProbe::Predecessor(Some(Node::for_test_at(250))),
+                    ),
+                ],
+                SuccessorDecision::AdoptIfActive {
+                    candidate: Node::for_test_at(250),
+                    fallback: Node::for_test_at(300),
+                },
+            ),
+            (
+                "single-node ring, self's predecessor adopted",
+                vec![(
+                    Node::for_test_at(100),
+                    SuccessorProbe::Predecessor(Some(Node::for_test_at(400))),
+                )],
+                SuccessorDecision::AdoptIfActive {
+                    candidate: Node::for_test_at(400),
+                    fallback: Node::for_test_at(100),
+                },
+            ),
+            (
+                "single-node ring without predecessor",
+                vec![(Node::for_test_at(100), SuccessorProbe::Predecessor(None))],
+                SuccessorDecision::Notify(Node::for_test_at(100)),
+            ),
+        ];
+
+        for (name, probes, expected) in cases {
+            assert_eq!(decide_successor(&self_node, &probes), expected, "{}", name);
+        }
+    }
+
+    #[test]
+    fn decide_predecessor_test() {
+        let predecessor = Node::for_test_at(50);
+
+        let cases = [
+            ("no predecessor", None, false, PredecessorAction::Keep),
+            (
+                "active predecessor",
+                Some(&predecessor),
+                true,
+                PredecessorAction::Keep,
+            ),
+            (
+                "expired predecessor",
+                Some(&predecessor),
+                false,
+                PredecessorAction::Clear,
+            ),
+        ];
+
+        for (name, predecessor, is_predecessor_active, expected) in cases {
+            assert_eq!(
+                decide_predecessor(predecessor, is_predecessor_active),
+                expected,
+                "{}",
+                name
+            );
+        }
+    }
+
+    #[test]
+    fn is_part_of_ring_test() {
+        let self_node = Node::for_test_at(100);
+        let peer = Node::for_test_at(200);
+
+        let cases = [
+            ("first node, alone", true, None, &self_node, true),
+            ("joining node, alone", false, None, &self_node, false),
+            ("joining node, no predecessor", false, None, &peer, false),
+            (
+                "joining node, with predecessor",
+                false,
+                Some(&peer),
+                &peer,
+                true,
+            ),
+        ];
+
+        for (name, is_first_node, predecessor, successor, expected) in cases {
+            assert_eq!(
+                is_part_of_ring(&self_node, is_first_node, predecessor, successor),
+                expected,
+                "{}",
+                name
+            );
+        }
+    }
+}
@@ -0,0 +1,64 @@
+//! Correlation of the log lines of a lookup
+//! across the nodes it goes through.
+//!
+//! The node initiating a lookup generates a trace ID, sent along
+//! with the lookup and with every forwarded copy of it. Each node
+//! prefixes its log lines about the lookup with this ID, so that
+//! the hop path can be rebuilt by searching the logs for it.
+
+use std::fmt;
+
+/// Identifier of a traced lookup, displayed
+/// as 16 hexadecimal digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct TraceId(u64);
+
+impl TraceId {
+    /// Generates a random trace ID.
+    pub(crate) fn generate() -> Self {
+        Self(rand::random())
+    }
+
+    /// Parses the 16 hexadecimal digits of a trace ID.
+    pub(crate) fn parse(trace_id: &str) -> Option<Self> {
+        if trace_id.len() != 16 || !trace_id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+
+        u64::from_str_radix(trace_id, 16).ok().map(Self)
+    }
+}
+
+impl fmt::Display for TraceId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+/// Logs the line built by `message` for the
+/// lookup traced by `trace_id`, if it is traced.
+pub(crate) fn log(trace_id: Option<TraceId>, message: impl FnOnce() -> String) {
+    if let Some(trace_id) = trace_id {
+        eprintln!("[trace={}] {}", trace_id, message());
+    }
+}
+
+#[cfg(test)]
+mod trace_id_test {
+    use super::TraceId;
+
+    #[test]
+    fn trace_id_text_test() {
+        let trace_id = TraceId(0x2a);
+
+        assert_eq!(trace_id.to_string(), "000000000000002a");
+        assert_eq!(TraceId::parse("000000000000002a"), Some(trace_id));
+
+        let trace_id = TraceId::generate();
+        assert_eq!(TraceId::parse(&trace_id.to_string()), Some(trace_id));
+
+        assert_eq!(TraceId::parse("2a"), None);
+        assert_eq!(TraceId::parse("+00000000000002a"), None);
+        assert_eq!(TraceId::parse("zzzzzzzzzzzzzzzz"), None);
+    }
+}
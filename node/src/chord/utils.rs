@@ -0,0 +1,631 @@
+//! Pure helpers operating on a known view
+//! of the Chord ring, without any network request.
+
+use crate::cli::AddressFamily;
+
+use super::{Node, MAX_RING_BIT_LENGTH, SUCCESSOR_LIST_LENGTH};
+
+/// Returns the node owning `key_position`, given a full
+/// snapshot of the ring's nodes.
+///
+/// The owner is the successor of the key: the first node
+/// whose position is equal to or follows `key_position`
+/// clockwise, wrapping around the ring if necessary.
+///
+/// `nodes` is expected to be sorted by ring position,
+/// but the result does not depend on the order.
+///
+/// # Panics
+///
+/// Panics if `nodes` is empty.
+pub fn owner_of(key_position: u128, nodes: &[Node]) -> &Node {
+    nodes
+        .iter()
+        .min_by_key(|node| node.get_ring_position().wrapping_sub(key_position))
+        .expect("the ring snapshot must contain at least one node")
+}
+
+/// Builds `self_node`'s successor list, made of `successor`,
+/// followed by the entries of `successor`'s own successor
+/// list (`remote_successor_list`).
+///
+/// Only the borrowed entries between `successor` and `self_node`
+/// (clockwise) are kept, each of them once. This leaves out
+/// `self_node`, which shows up in the successor list of its own
+/// successor in small rings (e.g. a 2-node ring), the repeated
+/// entries, as small or changing rings repeat nodes, and the stale
+/// entries past a wrap around the ring.
+///
+/// The kept entries are sorted by ring position from `self_node`,
+/// so that a misordered remote list (see `is_clockwise_ordered`)
+/// cannot corrupt the order of the list, nor push out the
+/// entries it lists after a misplaced one.
+///
+/// The borrowed entries which `self_node` cannot contact, given the
+/// `address_family` it reaches, are filtered out as well, and so are
+/// the `unreachable_nodes` (entries found dead by the caller).
+///
+/// The freed tail is padded with `self_node`, so that the list
+/// holds distinct live nodes followed by `self_node` when the
+/// ring shrinks below `SUCCESSOR_LIST_LENGTH + 1` nodes, instead
+/// of repeating (possibly dead) entries.
+///
+/// Returns an error, instead of panicking, if the
+/// remote successor list is too short.
+pub(crate) fn build_successor_list(
+    self_node: &Node,
+    successor: &Node,
+    remote_successor_list: &[Node],
+    unreachable_nodes: &[Node],
+    address_family: AddressFamily,
+) -> Result<[Node; SUCCESSOR_LIST_LENGTH], String> {
+    if remote_successor_list.len() < SUCCESSOR_LIST_LENGTH - 1 {
+        return Err(format!(
+            "the successor list of node [{:?}] is too short: expected at least {} entries, got {}",
+            successor.get_public_addr(),
+            SUCCESSOR_LIST_LENGTH - 1,
+            remote_successor_list.len()
+        ));
+    }
+
+    let distance_from_self_node = |node: &Node| {
+        node.get_ring_position()
+            .wrapping_sub(self_node.get_ring_position())
+    };
+
+    let mut borrowed_entries = remote_successor_list
+        .iter()
+        .filter(|entry| {
+            distance_from_self_node(entry) > distance_from_self_node(successor)
+                && !unreachable_nodes.contains(entry)
+                && address_family.can_reach(entry.get_public_addr())
+        })
+        .collect::<Vec<_>>();
+
+    borrowed_entries.sort_by_key(|entry| distance_from_self_node(entry));
+    borrowed_entries.dedup();
+
+    // Entries past the list length only fill
+    // the slots freed by the filtered entries
+    let mut successor_list = Vec::with_capacity(SUCCESSOR_LIST_LENGTH);
+    successor_list.push(successor);
+    successor_list.extend(borrowed_entries);
+    successor_list.truncate(SUCCESSOR_LIST_LENGTH);
+
+    Ok(std::array::from_fn(|i| {
+        successor_list.get(i).copied().unwrap_or(self_node).clone()
+    }))
+}
+
+/// Checks that `remote_successor_list`, the successor list of
+/// `successor`, is in clockwise order from `successor`, as the
+/// list of a consistent node is.
+///
+/// The entries following a wrap back to `successor` (e.g. the
+/// padding of a small ring) are not checked, and repeated entries
+/// (e.g. stale ones, in a changing ring) are tolerated.
+pub(crate) fn is_clockwise_ordered(successor: &Node, remote_successor_list: &[Node]) -> bool {
+    let distances_from_successor = remote_successor_list
+        .iter()
+        .take_while(|entry| *entry != successor)
+        .map(|entry| {
+            entry
+                .get_ring_position()
+                .wrapping_sub(successor.get_ring_position())
+        })
+        .collect::<Vec<_>>();
+
+    distances_from_successor
+        .windows(2)
+        .all(|distances| distances[0] <= distances[1])
+}
+
+/// Removes `departed_node` from `successor_list`, shifting the
+/// following entries forward.
+///
+/// The freed tail is padded with the last remaining entry,
+/// or with `self_node` if no entry remains.
+pub(crate) fn remove_from_successor_list(
+    self_node: &Node,
+    successor_list: &[Node; SUCCESSOR_LIST_LENGTH],
+    departed_node: &Node,
+) -> [Node; SUCCESSOR_LIST_LENGTH] {
+    let remaining_entries = successor_list
+        .iter()
+        .filter(|entry| *entry != departed_node)
+        .collect::<Vec<_>>();
+
+    let padding = remaining_entries.last().copied().unwrap_or(self_node);
+
+    std::array::from_fn(|i| remaining_entries.get(i).copied().unwrap_or(padding).clone())
+}
+
+/// Estimates the number of nodes in the ring,
+/// from the spacing of the distinct nodes
+/// in `self_node`'s successor list.
+///
+/// With `k` distinct successors spanning a clockwise
+/// distance `d` from `self_node`, the estimate is `k * 2^MAX_RING_BIT_LENGTH / d`.
+pub(crate) fn estimate_ring_size(self_node: &Node, successor_list: &[Node]) -> u128 {
+    let mut distinct_successors: Vec<&Node> = Vec::new();
+
+    for successor in successor_list {
+        if successor != self_node && !distinct_successors.contains(&successor) {
+            distinct_successors.push(successor);
+        }
+    }
+
+    let farthest_successor = match distinct_successors.last() {
+        Some(node) => node,
+        None => return 1,
+    };
+
+    let span = farthest_successor
+        .get_ring_position()
+        .wrapping_sub(self_node.get_ring_position());
+
+    if span == 0 {
+        return 1;
+    }
+
+    let ring_length = 2f64.powi(MAX_RING_BIT_LENGTH as i32);
+
+    (distinct_successors.len() as f64 * ring_length / span as f64).round() as u128
+}
+
+#[cfg(test)]
+mod owner_of_test {
+    use crate::chord::Node;
+
+    use super::owner_of;
+
+    #[test]
+    fn four_node_ring_owner_test() {
+        let ring = [
+            Node::for_test_at(100),
+            Node::for_test_at(200),
+            Node::for_test_at(u128::MAX / 2),
+            Node::for_test_at(u128::MAX - 10),
+        ];
+
+        // Key equal to a node position
+        assert_eq!(owner_of(100, &ring), &ring[0]);
+
+        // Key strictly between two nodes
+        assert_eq!(owner_of(101, &ring), &ring[1]);
+        assert_eq!(owner_of(150, &ring), &ring[1]);
+        assert_eq!(owner_of(201, &ring), &ring[2]);
+        assert_eq!(owner_of(u128::MAX / 2 + 1, &ring), &ring[3]);
+
+        // Keys wrapping around the end of the ring
+        assert_eq!(owner_of(u128::MAX - 9, &ring), &ring[0]);
+        assert_eq!(owner_of(u128::MAX, &ring), &ring[0]);
+        assert_eq!(owner_of(0, &ring), &ring[0]);
+    }
+
+    #[test]
+    fn single_node_ring_owner_test() {
+        let ring = [Node::for_test_at(42)];
+
+        assert_eq!(owner_of(0, &ring), &ring[0]);
+        assert_eq!(owner_of(42, &ring), &ring[0]);
+        assert_eq!(owner_of(u128::MAX, &ring), &ring[0]);
+    }
+}
+
+#[cfg(test)]
+mod successor_list_utils_test {
+    use crate::{
+        chord::{Node, SUCCESSOR_LIST_LENGTH},
+        cli::AddressFamily,
+    };
+
+    use super::{
+        build_successor_list, estimate_ring_size, is_clockwise_ordered, remove_from_successor_list,
+    };
+
+    #[test]
+    fn build_successor_list_test() {
+        let self_node = Node::for_test_at(0);
+        let successor = Node::for_test_at(10);
+        let remote_successor_list = [
+            Node::for_test_at(20),
+            Node::for_test_at(30),
+            Node::for_test_at(40),
+            Node::for_test_at(50),
+            Node::for_test_at(60),
+        ];
+
+        let successor_list = build_successor_list(
+            &self_node,
+            &successor,
+            &remote_successor_list,
+            &[],
+            AddressFamily::Any,
+        )
+        .unwrap();
+
+        assert_eq!(successor_list[0], successor);
+        assert_eq!(
+            successor_list[1..],
+            remote_successor_list[0..(SUCCESSOR_LIST_LENGTH - 1)]
+        );
+    }
+
+    #[test]
+    fn build_successor_list_sorts_misordered_entries_test() {
+        let self_node = Node::for_test_at(0);
+        let successor = Node::for_test_at(10);
+
+        // Misplaced entries do not push out the following ones,
+        // and self_node, listed in the middle, is left out
+        let remote_successor_list = [
+            Node::for_test_at(40),
+            Node::for_test_at(20),
+            self_node.clone(),
+            Node::for_test_at(50),
+            Node::for_test_at(30),
+        ];
+
+        assert_eq!(
+            build_successor_list(
+                &self_node,
+                &successor,
+                &remote_successor_list,
+                &[],
+                AddressFamily::Any
+            )
+            .unwrap(),
+            [
+                successor.clone(),
+                Node::for_test_at(20),
+                Node::for_test_at(30),
+                Node::for_test_at(40),
+                Node::for_test_at(50),
+            ]
+        );
+    }
+
+    #[test]
+    fn is_clockwise_ordered_test() {
+        let successor = Node::for_test_at(10);
+
+        assert!(is_clockwise_ordered(
+            &successor,
+            &[
+                Node::for_test_at(20),
+                Node::for_test_at(30),
+                Node::for_test_at(40),
+                Node::for_test_at(50),
+                Node::for_test_at(60),
+            ]
+        ));
+
+        // Wrapping around a small ring, then padded
+        assert!(is_clockwise_ordered(
+            &successor,
+            &[
+                Node::for_test_at(20),
+                Node::for_test_at(0),
+                successor.clone(),
+                Node::for_test_at(20),
+                successor.clone(),
+            ]
+        ));
+
+        // Stale repeated entries
+        assert!(is_clockwise_ordered(
+            &successor,
+            &[
+                Node::for_test_at(20),
+                Node::for_test_at(20),
+                Node::for_test_at(30),
+                Node::for_test_at(40),
+                Node::for_test_at(50),
+            ]
+        ));
+
+        assert!(!is_clockwise_ordered(
+            &successor,
+            &[
+                Node::for_test_at(40),
+                Node::for_test_at(20),
+                Node::for_test_at(30),
+                Node::for_test_at(50),
+                Node::for_test_at(60),
+            ]
+        ));
+    }
+
+    #[test]
+    fn build_successor_list_deduplicates_entries_test() {
+        let self_node = Node::for_test_at(0);
+        let successor = Node::for_test_at(10);
+
+        // Stale remote list, repeating nodes: the entries
+        // after the repeated ones fill the freed slots
+        let remote_successor_list = [
+            Node::for_test_at(20),
+            Node::for_test_at(20),
+            Node::for_test_at(30),
+            Node::for_test_at(40),
+            Node::for_test_at(50),
+        ];
+
+        assert_eq!(
+            build_successor_list(
+                &self_node,
+                &successor,
+                &remote_successor_list,
+                &[],
+                AddressFamily::Any
+            )
+            .unwrap(),
+            [
+                successor.clone(),
+                Node::for_test_at(20),
+                Node::for_test_at(30),
+                Node::for_test_at(40),
+                Node::for_test_at(50),
+            ]
+        );
+
+        // The successor itself is not repeated
+        let remote_successor_list = [
+            Node::for_test_at(20),
+            successor.clone(),
+            Node::for_test_at(30),
+            Node::for_test_at(30),
+            Node::for_test_at(30),
+        ];
+
+        assert_eq!(
+            build_successor_list(
+                &self_node,
+                &successor,
+                &remote_successor_list,
+                &[],
+                AddressFamily::Any
+            )
+            .unwrap(),
+            [
+                successor.clone(),
+                Node::for_test_at(20),
+                Node::for_test_at(30),
+                self_node.clone(),
+                self_node.clone(),
+            ]
+        );
+    }
+
+    #[test]
+    fn build_successor_list_with_short_remote_list_test() {
+        let self_node = Node::for_test_at(0);
+        let successor = Node::for_test_at(10);
+        let remote_successor_list = [Node::for_test_at(20), Node::for_test_at(30)];
+
+        let err = build_successor_list(
+            &self_node,
+            &successor,
+            &remote_successor_list,
+            &[],
+            AddressFamily::Any,
+        )
+        .unwrap_err();
+
+        assert!(err.contains("too short"));
+    }
+
+    #[test]
+    fn build_successor_list_in_small_ring_test() {
+        let self_node = Node::for_test_at(0);
+        let successor = Node::for_test_at(10);
+
+        // 2-node ring: the successor's list alternates
+        // between self_node and the successor.
+        let remote_successor_list = [
+            self_node.clone(),
+            successor.clone(),
+            self_node.clone(),
+            successor.clone(),
+            self_node.clone(),
+        ];
+
+        let successor_list = build_successor_list(
+            &self_node,
+            &successor,
+            &remote_successor_list,
+            &[],
+            AddressFamily::Any,
+        )
+        .unwrap();
+
+        let two_node_successor_list: [Node; SUCCESSOR_LIST_LENGTH] = std::array::from_fn(|i| {
+            if i == 0 {
+                successor.clone()
+            } else {
+                self_node.clone()
+            }
+        });
+
+        assert_eq!(successor_list, two_node_successor_list);
+
+        // Successive stabilization passes, once the
+        // successor's list is padded the same way
+        let remote_successor_list: [Node; SUCCESSOR_LIST_LENGTH] = std::array::from_fn(|i| {
+            if i == 0 {
+                self_node.clone()
+            } else {
+                successor.clone()
+            }
+        });
+
+        assert_eq!(
+            build_successor_list(
+                &self_node,
+                &successor,
+                &remote_successor_list,
+                &[],
+                AddressFamily::Any
+            )
+            .unwrap(),
+            two_node_successor_list
+        );
+
+        // 3-node ring
+        let other_node = Node::for_test_at(20);
+        let remote_successor_list = [
+            other_node.clone(),
+            self_node.clone(),
+            successor.clone(),
+            other_node.clone(),
+            self_node.clone(),
+        ];
+
+        assert_eq!(
+            build_successor_list(
+                &self_node,
+                &successor,
+                &remote_successor_list,
+                &[],
+                AddressFamily::Any
+            )
+            .unwrap(),
+            [
+                successor.clone(),
+                other_node,
+                self_node.clone(),
+                self_node.clone(),
+                self_node.clone(),
+            ]
+        );
+
+        // Single-node ring: self_node is its own successor
+        let successor_list: [Node; SUCCESSOR_LIST_LENGTH] =
+            std::array::from_fn(|_| self_node.clone());
+
+        assert_eq!(
+            build_successor_list(
+                &self_node,
+                &self_node,
+                &successor_list,
+                &[],
+                AddressFamily::Any
+            )
+            .unwrap(),
+            successor_list
+        );
+    }
+
+    #[test]
+    fn build_successor_list_skips_unreachable_nodes_test() {
+        let self_node = Node::for_test_at(0);
+        let successor = Node::for_test_at(10);
+        let remote_successor_list = [
+            Node::for_test_at(20),
+            Node::for_test_at(30),
+            Node::for_test_at(40),
+            Node::for_test_at(50),
+            Node::for_test_at(60),
+        ];
+
+        // Nodes found dead are left out, instead
+        // of being borrowed from a stale remote list
+        assert_eq!(
+            build_successor_list(
+                &self_node,
+                &successor,
+                &remote_successor_list,
+                &[Node::for_test_at(30), Node::for_test_at(50)],
+                AddressFamily::Any
+            )
+            .unwrap(),
+            [
+                successor.clone(),
+                Node::for_test_at(20),
+                Node::for_test_at(40),
+                Node::for_test_at(60),
+                self_node.clone(),
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_from_successor_list_test() {
+        let self_node = Node::for_test_at(0);
+        let successor_list = [
+            Node::for_test_at(10),
+            Node::for_test_at(20),
+            Node::for_test_at(30),
+            Node::for_test_at(40),
+            Node::for_test_at(50),
+        ];
+
+        // Following entries are shifted, the tail is padded
+        assert_eq!(
+            remove_from_successor_list(&self_node, &successor_list, &Node::for_test_at(20)),
+            [
+                Node::for_test_at(10),
+                Node::for_test_at(30),
+                Node::for_test_at(40),
+                Node::for_test_at(50),
+                Node::for_test_at(50),
+            ]
+        );
+
+        // Unknown node, the list is unchanged
+        assert_eq!(
+            remove_from_successor_list(&self_node, &successor_list, &Node::for_test_at(25)),
+            successor_list
+        );
+
+        // Two-node ring, only self_node remains
+        let successor = Node::for_test_at(10);
+        let successor_list = [
+            successor.clone(),
+            self_node.clone(),
+            successor.clone(),
+            self_node.clone(),
+            successor.clone(),
+        ];
+        assert_eq!(
+            remove_from_successor_list(&self_node, &successor_list, &successor),
+            std::array::from_fn(|_| self_node.clone())
+        );
+
+        // No entry remains, padded with self_node
+        let successor_list: [Node; SUCCESSOR_LIST_LENGTH] =
+            std::array::from_fn(|_| successor.clone());
+        assert_eq!(
+            remove_from_successor_list(&self_node, &successor_list, &successor),
+            std::array::from_fn(|_| self_node.clone())
+        );
+    }
+
+    #[test]
+    fn estimate_ring_size_test() {
+        let self_node = Node::for_test_at(0);
+
+        // Single-node ring
+        let successor_list: [Node; SUCCESSOR_LIST_LENGTH] =
+            std::array::from_fn(|_| self_node.clone());
+        assert_eq!(estimate_ring_size(&self_node, &successor_list), 1);
+
+        // Evenly spaced ring of 16 nodes
+        let step = u128::MAX / 16 + 1;
+        let successor_list: [Node; SUCCESSOR_LIST_LENGTH] =
+            std::array::from_fn(|i| Node::for_test_at(step * (i as u128 + 1)));
+        assert_eq!(estimate_ring_size(&self_node, &successor_list), 16);
+
+        // Two-node ring
+        let successor = Node::for_test_at(u128::MAX / 2);
+        let successor_list = [
+            successor.clone(),
+            self_node.clone(),
+            successor.clone(),
+            self_node.clone(),
+            successor,
+        ];
+        assert_eq!(estimate_ring_size(&self_node, &successor_list), 2);
+    }
+}
@@ -1,7 +1,232 @@
 //! Utilities for the Command Line Interface (CLI)
 //! that represents a Chord node.
 
-use std::{env, error::Error, net::SocketAddr};
+use std::{
+    env,
+    error::Error,
+    fmt, fs,
+    net::{SocketAddr, ToSocketAddrs},
+    path::PathBuf,
+    time::Duration,
+};
+
+pub mod sources;
+
+/// Default maximum number of concurrent
+/// outbound gossip exchanges.
+pub const DEFAULT_GOSSIP_MAX_IN_FLIGHT: usize = 4;
+
+/// Default connect timeout, in milliseconds,
+/// of the gossip exchanges with a peer.
+pub const DEFAULT_GOSSIP_CONNECT_TIMEOUT_MS: u64 = 1000;
+
+/// Default read timeout, in milliseconds, when
+/// waiting for the response of a remote node.
+pub const DEFAULT_RESPONSE_TIMEOUT_MS: u64 = 5000;
+
+/// Default clock skew, in milliseconds, beyond
+/// which a gossip peer is reported.
+pub const DEFAULT_CLOCK_SKEW_THRESHOLD_MS: u64 = 1000;
+
+/// Default name the TLS certificates
+/// of the nodes are issued for.
+pub const DEFAULT_TLS_SERVER_NAME: &str = "chord-node";
+
+/// Default read timeout, in seconds,
+/// on each accepted connection.
+pub const DEFAULT_READ_TIMEOUT_SECS: u64 = 5;
+
+/// Default number of retries after a refused
+/// or reset connection to a remote node.
+pub const DEFAULT_CONNECT_RETRIES: u32 = 2;
+
+/// Default number of stabilization passes in a row a node
+/// cannot recover from before it applies `on-isolation`.
+pub const DEFAULT_MAX_STABILIZATION_FAILURES: u32 = 3;
+
+/// Default bit length of the node identifiers,
+/// which is the length of an MD5 hash.
+pub const DEFAULT_RING_BIT_LENGTH: usize = 128;
+
+/// Maximum number of virtual nodes run by a process.
+pub const MAX_VNODES: u16 = 64;
+
+/// Default maximum number of pending
+/// connections queued by the listener.
+pub const DEFAULT_LISTEN_BACKLOG: u32 = 128;
+
+/// Default maximum number of
+/// connections served at once.
+pub const DEFAULT_MAX_CONNECTIONS: usize = 256;
+
+/// Default maximum number of lookups forwarded at once,
+/// below the number of server threads, so that a lookup
+/// storm leaves threads to the other requests.
+pub const DEFAULT_MAX_FORWARDED_LOOKUPS: usize = 8;
+
+/// Default minimum interval, in milliseconds,
+/// between two stabilization passes.
+pub const DEFAULT_STABILIZATION_MIN_INTERVAL_MS: u64 = 1000;
+
+/// Default maximum interval, in milliseconds,
+/// between two stabilization passes.
+pub const DEFAULT_STABILIZATION_MAX_INTERVAL_MS: u64 = 8000;
+
+/// Default interval, in seconds, between two
+/// dumps of the node status on the standard output.
+pub const DEFAULT_STATUS_INTERVAL_SECS: u64 = 1;
+
+/// Environment variable providing the message authentication
+/// key (`hmac-key`, see `sources::env_var_name`), also read
+/// by the client tools.
+pub const HMAC_KEY_ENV_VAR: &str = "CHORD_HMAC_KEY";
+
+/// Keys of all the settings, required ones first.
+///
+/// Settings can be passed as `key=value` arguments, as
+/// environment variables (see `sources::env_var_name`),
+/// or in a config file.
+const SETTING_KEYS: [&str; 42] = [
+    "self-port",
+    "public-addr",
+    "remote-addr",
+    "gossip-max-in-flight",
+    "max-nodes",
+    "read-timeout",
+    "admin-secret",
+    "hmac-key",
+    "identity-mode",
+    "node-id",
+    "connect-retries",
+    "lookup-cache-ttl",
+    "listen-backlog",
+    "max-connections",
+    "routing",
+    "address-family",
+    "timestamp-source",
+    "gossip-compression",
+    "gossip-mode",
+    "ring-bits",
+    "vnodes",
+    "startup-report",
+    "membership-snapshot",
+    "data-port",
+    "stabilization-interval",
+    "gossip-journal",
+    "max-forwarded-lookups",
+    "admin-http-addr",
+    "reachability-audit",
+    "status-interval",
+    "gossip-connect-timeout",
+    "ring-integrity-check",
+    "chord-response-timeout",
+    "gossip-response-timeout",
+    "public-addr-verification",
+    "tls-cert",
+    "tls-key",
+    "tls-ca",
+    "tls-server-name",
+    "max-stabilization-failures",
+    "on-isolation",
+    "clock-skew-threshold",
+];
+
+/// Keys of the settings which are not `Options`.
+const REQUIRED_SETTING_KEYS: [&str; 3] = ["self-port", "public-addr", "remote-addr"];
+
+/// Action checking that every protocol message round-trips
+/// through its text representation, then exiting.
+pub const SELF_TEST_ACTION: &str = "self-test";
+
+/// Action printing the identifier the node would be
+/// started with, without starting it, then exiting.
+pub const PRINT_ID_ACTION: &str = "print-id";
+
+/// Key of the argument giving the config file path.
+const CONFIG_KEY: &str = "config";
+
+/// Usage message of the command line.
+pub const USAGE: &str = "\
+usage:
+  node init self-port=<port> public-addr=<ip:port> [options]
+  node join self-port=<port> public-addr=<ip:port> remote-addr=<ip:port> [options]
+  node [init|join] config=<path> [key=value ...]
+  node self-test                      check the protocol round-trips, then exit
+  node print-id public-addr=<ip:port> [options]
+                                      print the node's identifier, then exit
+
+public-addr and remote-addr also accept a host:port, resolved
+once at startup. The node's identifier is derived from the
+resolved address, not from the host name.
+
+Each key=value setting can also be provided by a CHORD_<KEY>
+environment variable (e.g. CHORD_SELF_PORT), or by a config file
+(config=<path> or CHORD_CONFIG) made of `key = value` lines.
+The command line takes precedence over the environment,
+which takes precedence over the config file.
+
+options (key=value):
+  gossip-max-in-flight=<n>            concurrent outbound gossip exchanges
+  max-nodes=<n>                       maximum number of nodes in the network
+  read-timeout=<seconds>              read timeout on accepted connections
+  admin-secret=<secret>               secret authorizing remote shutdown (without `]`)
+  hmac-key=<key>                      message authentication key
+  identity-mode=hashed|sequential     node identifier assignment
+  node-id=<n>                         identifier, with identity-mode=sequential
+  connect-retries=<n>                 retries after a refused or reset connection
+  lookup-cache-ttl=<seconds>          cache resolved lookups for this long
+  listen-backlog=<n>                  pending connections queued by the listener
+  max-connections=<n>                 connections served at once, others are rejected
+  routing=recursive|iterative         forward lookups, or redirect their initiator
+  address-family=any|ipv4|ipv6        address families the node can reach
+  timestamp-source=wall-clock|hybrid  clock timestamping gossip updates
+  gossip-compression=none|deflate     compression of large gossip data on the wire
+  gossip-mode=push-pull|push|pull     direction of the periodic gossip exchanges
+  ring-bits=<n>                       identifier bit length (multiple of 8, up to 128)
+  vnodes=<n>                          ring positions taken by the node (up to 64), by capacity
+  startup-report=<path>               also write the JSON startup report to this file
+  membership-snapshot=<path>          start (init) from the ring of the listed addresses
+  data-port=<port>                    serve client data updates on this port only
+  stabilization-interval=<min>..<max> bounds of the adaptive stabilization interval (ms)
+  gossip-journal=<path>               append the accepted gossip state changes to this file
+  max-forwarded-lookups=<n>           lookups forwarded at once, others get a retryable error
+  admin-http-addr=<ip:port>           serve the admin HTTP API (GET /status, POST /data) here
+  reachability-audit=<seconds>        check this often that the successor can reach the node back
+  status-interval=<seconds>|off       period of the status dump on the standard output
+  gossip-connect-timeout=<ms>         give up connecting to a gossip peer after this long
+  ring-integrity-check=<seconds>      walk the successors around the ring this often, repair forks
+  chord-response-timeout=<ms>         give up waiting for the response to a Chord request after this long
+  gossip-response-timeout=<ms>        give up waiting for the response to a gossip request after this long
+  public-addr-verification=on|off     check at startup that public-addr reaches the node itself
+  tls-cert=<path>                     encrypt the connections with TLS, with this PEM certificate chain
+  tls-key=<path>                      PEM private key of tls-cert
+  tls-ca=<path>                       PEM certificates trusted for the other nodes (default: tls-cert)
+  tls-server-name=<name>              name the certificates of the nodes are issued for (default: chord-node)
+  max-stabilization-failures=<n>      failed stabilization passes in a row before on-isolation applies
+  on-isolation=rejoin|retry|exit      behavior of an isolated node: rejoin through remote-addr, retry, or exit
+  clock-skew-threshold=<ms>|off       report the gossip peers whose clock is skewed beyond this";
+
+/// Every problem found in the command line arguments.
+///
+/// Displayed with the usage message.
+#[derive(Debug, PartialEq, Eq)]
+pub struct ArgsError {
+    problems: Vec<String>,
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "invalid argument(s):")?;
+
+        for problem in self.problems.iter() {
+            writeln!(f, "  - {}", problem)?;
+        }
+
+        write!(f, "\n{}", USAGE)
+    }
+}
+
+impl Error for ArgsError {}
 
 /// Contains differents arguments,
 /// required to run a Chord node.
@@ -11,56 +236,1012 @@ pub enum Args {
     Init {
         self_port: u16,
         public_addr: SocketAddr,
+        options: Options,
     },
     /// Used to join an existing Chord network.
     Join {
         self_port: u16,
         public_addr: SocketAddr,
         remote_addr: SocketAddr,
+        options: Options,
     },
 }
 
+/// How the identifier of a node is assigned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IdentityMode {
+    /// MD5 hash of the public socket address.
+    #[default]
+    Hashed,
+    /// Operator-supplied identifier (`node-id`),
+    /// for small debugging deployments only.
+    Sequential(u128),
+}
+
+/// How a node routes the lookups it cannot answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoutingMode {
+    /// The node forwards the lookup and
+    /// relays the answer to the initiator.
+    #[default]
+    Recursive,
+    /// The node redirects the initiator to the next
+    /// node to contact, without forwarding the lookup.
+    Iterative,
+}
+
+/// Address families a node can reach.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AddressFamily {
+    /// Both IPv4 and IPv6 addresses.
+    #[default]
+    Any,
+    /// IPv4 addresses only.
+    Ipv4,
+    /// IPv6 addresses only.
+    Ipv6,
+}
+
+impl AddressFamily {
+    /// Checks if a node reaching this
+    /// address family can contact `addr`.
+    pub fn can_reach(self, addr: SocketAddr) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Ipv4 => addr.is_ipv4(),
+            Self::Ipv6 => addr.is_ipv6(),
+        }
+    }
+}
+
+/// Source of the timestamps of local gossip updates,
+/// which decide the winner when gossip states are merged.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TimestampSource {
+    /// The local wall clock. Updates made on a
+    /// node whose clock is ahead win over later ones.
+    #[default]
+    WallClock,
+    /// A hybrid logical clock: the wall clock, moved past
+    /// the latest timestamp seen, so that an update wins over
+    /// every state known when it is made, whatever the clock skew.
+    Hybrid,
+}
+
+/// Compression of the gossip data disseminated between nodes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GossipCompression {
+    /// The data is sent as is.
+    #[default]
+    None,
+    /// Large data is sent deflated, when it shrinks.
+    Deflate,
+}
+
+/// Direction of the periodic gossip exchanges with a random peer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum GossipMode {
+    /// The node sends its data, and merges the peer's
+    /// data sent back if it is fresher.
+    #[default]
+    PushPull,
+    /// The node sends its data, ignoring the reply.
+    /// Fast to spread a few updates.
+    Push,
+    /// The node asks for the peer's data, without sending
+    /// its own. Robust once most nodes hold the data.
+    Pull,
+}
+
+/// Behavior of a node isolated from the network: whose successors
+/// and known peers all failed for `max-stabilization-failures`
+/// stabilization passes in a row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IsolationPolicy {
+    /// The node joins the network again through its bootstrap
+    /// node (`remote-addr`), or restarts the ring alone if it
+    /// initiated it. A failed rejoin is attempted again after
+    /// as many failed passes.
+    #[default]
+    Rejoin,
+    /// The node keeps retrying its successors and known peers.
+    Retry,
+    /// The node exits.
+    Exit,
+}
+
+/// Contains optional arguments, tuning
+/// the behavior of a Chord node.
+///
+/// Each option is passed as a `key=value` argument,
+/// after the required arguments.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    /// Maximum number of concurrent outbound
+    /// gossip exchanges (`gossip-max-in-flight`).
+    pub gossip_max_in_flight: usize,
+    /// Maximum number of nodes allowed
+    /// in the network (`max-nodes`).
+    pub max_nodes: Option<u128>,
+    /// Read timeout on each accepted
+    /// connection (`read-timeout`, in seconds).
+    pub read_timeout: Duration,
+    /// Shared secret authorizing remote shutdown
+    /// requests (`admin-secret`), without `]` as it is
+    /// sent in a request field. Without it, remote
+    /// shutdown is disabled.
+    pub admin_secret: Option<String>,
+    /// Pre-shared key authenticating every
+    /// protocol message (`hmac-key`).
+    pub hmac_key: Option<String>,
+    /// How the node's identifier is assigned
+    /// (`identity-mode=hashed|sequential`, with
+    /// `node-id` in sequential mode).
+    pub identity_mode: IdentityMode,
+    /// Number of retries after a refused or reset
+    /// connection to a remote node (`connect-retries`).
+    pub connect_retries: u32,
+    /// Lifetime of the cached lookups (`lookup-cache-ttl`,
+    /// in seconds). Without it, lookups are not cached.
+    pub lookup_cache_ttl: Option<Duration>,
+    /// Maximum number of pending connections
+    /// queued by the listener (`listen-backlog`).
+    pub listen_backlog: u32,
+    /// Maximum number of connections served at once
+    /// (`max-connections`). Connections accepted
+    /// beyond it are rejected with an error response.
+    pub max_connections: usize,
+    /// How lookups are routed
+    /// (`routing=recursive|iterative`).
+    pub routing_mode: RoutingMode,
+    /// Address families the node can reach
+    /// (`address-family=any|ipv4|ipv6`). Successors
+    /// of another family are left out of its successor list.
+    pub address_family: AddressFamily,
+    /// Source of the timestamps of local gossip
+    /// updates (`timestamp-source=wall-clock|hybrid`).
+    pub timestamp_source: TimestampSource,
+    /// Compression of the disseminated gossip data
+    /// (`gossip-compression=none|deflate`). Compressed
+    /// data is decompressed on receipt in any case.
+    pub gossip_compression: GossipCompression,
+    /// Direction of the periodic gossip exchanges
+    /// (`gossip-mode=push-pull|push|pull`).
+    pub gossip_mode: GossipMode,
+    /// Bit length of the node identifiers (`ring-bits`),
+    /// which are a prefix of the MD5 hash. Every node
+    /// of the network must use the same bit length.
+    pub ring_bit_length: usize,
+    /// Number of virtual nodes run by the process
+    /// (`vnodes`), each with its own ring position
+    /// and pointers, sharing the listener.
+    ///
+    /// Processes of a ring may run different numbers of
+    /// virtual nodes: each owns a share of the keys about
+    /// proportional to its count, which weights the load
+    /// of a process by its capacity.
+    pub vnodes: u16,
+    /// File the startup report is written to
+    /// (`startup-report`), besides the standard output.
+    pub startup_report_path: Option<PathBuf>,
+    /// Public socket addresses of the ring members, read from the
+    /// `membership-snapshot` file (one `ip:port` per line), which the
+    /// node derives its initial pointers from, instead of joining.
+    pub membership_snapshot: Option<Vec<SocketAddr>>,
+    /// Port of a second listener (`data-port`), with its own
+    /// threads, serving the gossip requests only. Client data
+    /// updates are then rejected on `self-port`, so that their
+    /// load does not delay the Chord maintenance requests.
+    pub data_port: Option<u16>,
+    /// Bounds of the interval between two stabilization passes
+    /// (`stabilization-interval=<min>..<max>`, in milliseconds).
+    /// The interval is shortened when the neighborhood of the
+    /// node changes, and lengthened while it is stable.
+    pub stabilization_interval: (Duration, Duration),
+    /// File the accepted gossip state changes are
+    /// appended to (`gossip-journal`), with their source.
+    pub gossip_journal_path: Option<PathBuf>,
+    /// Maximum number of lookups forwarded at once, each
+    /// blocking a server thread until it is answered
+    /// (`max-forwarded-lookups`). Lookups to forward beyond
+    /// it are answered with a retryable error.
+    pub max_forwarded_lookups: usize,
+    /// Address the admin HTTP API is served on
+    /// (`admin-http-addr`), if any. The API is not
+    /// authenticated: it should be a loopback or private address.
+    pub admin_http_addr: Option<SocketAddr>,
+    /// Interval between two audits of the link with the successor
+    /// (`reachability-audit`), which is asked to probe the node
+    /// back, so that one-way links are reported. Disabled if `None`.
+    pub reachability_audit_interval: Option<Duration>,
+    /// Interval between two dumps of the node status on the
+    /// standard output (`status-interval=<seconds>|off`).
+    /// Disabled if `None`.
+    pub status_interval: Option<Duration>,
+    /// Bound of the connection attempts of the gossip exchanges
+    /// (`gossip-connect-timeout`, in milliseconds). A peer not
+    /// reached in time is skipped until the next round.
+    pub gossip_connect_timeout: Duration,
+    /// Interval between two walks of the successors around
+    /// the ring (`ring-integrity-check`), which report and
+    /// repair a forked ring. Disabled if `None`.
+    pub ring_integrity_check_interval: Option<Duration>,
+    /// Read timeout when waiting for the response to a Chord
+    /// request (`chord-response-timeout`, in milliseconds).
+    /// Short, so that dead nodes are detected quickly.
+    pub chord_response_timeout: Duration,
+    /// Read timeout when waiting for the response to a gossip
+    /// request (`gossip-response-timeout`, in milliseconds).
+    /// Long enough for the transfer of large gossip data.
+    pub gossip_response_timeout: Duration,
+    /// Whether the node checks at startup that its public socket
+    /// address reaches it (`public-addr-verification=on|off`).
+    /// Turned off behind a NAT without hairpinning, which the
+    /// node cannot traverse to reach itself.
+    pub verify_public_addr: bool,
+    /// PEM certificate chain of the node (`tls-cert`). If set,
+    /// the connections are encrypted with TLS, and the nodes
+    /// of the ring must all be configured for it.
+    pub tls_cert_path: Option<PathBuf>,
+    /// PEM private key of the certificate (`tls-key`).
+    pub tls_key_path: Option<PathBuf>,
+    /// PEM certificates trusted for the other nodes (`tls-ca`),
+    /// the node's certificate chain if `None`, for a ring
+    /// sharing a self-signed certificate.
+    pub tls_ca_path: Option<PathBuf>,
+    /// Name the certificates of the nodes are issued for
+    /// (`tls-server-name`), `DEFAULT_TLS_SERVER_NAME` if `None`.
+    pub tls_server_name: Option<String>,
+    /// Number of stabilization passes in a row the node cannot
+    /// recover from through a known peer, after which it applies
+    /// `on_isolation` (`max-stabilization-failures`).
+    pub max_stabilization_failures: u32,
+    /// Behavior of the node once isolated from the
+    /// network (`on-isolation=rejoin|retry|exit`).
+    pub on_isolation: IsolationPolicy,
+    /// Clock skew beyond which a gossip peer is reported
+    /// (`clock-skew-threshold=<ms>|off`), estimated over
+    /// the gossip exchanges. Not checked if `None`.
+    pub clock_skew_threshold: Option<Duration>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            gossip_max_in_flight: DEFAULT_GOSSIP_MAX_IN_FLIGHT,
+            max_nodes: None,
+            read_timeout: Duration::from_secs(DEFAULT_READ_TIMEOUT_SECS),
+            admin_secret: None,
+            hmac_key: None,
+            identity_mode: IdentityMode::Hashed,
+            connect_retries: DEFAULT_CONNECT_RETRIES,
+            lookup_cache_ttl: None,
+            listen_backlog: DEFAULT_LISTEN_BACKLOG,
+            max_connections: DEFAULT_MAX_CONNECTIONS,
+            routing_mode: RoutingMode::Recursive,
+            address_family: AddressFamily::Any,
+            timestamp_source: TimestampSource::WallClock,
+            gossip_compression: GossipCompression::None,
+            gossip_mode: GossipMode::PushPull,
+            ring_bit_length: DEFAULT_RING_BIT_LENGTH,
+            vnodes: 1,
+            startup_report_path: None,
+            membership_snapshot: None,
+            data_port: None,
+            stabilization_interval: (
+                Duration::from_millis(DEFAULT_STABILIZATION_MIN_INTERVAL_MS),
+                Duration::from_millis(DEFAULT_STABILIZATION_MAX_INTERVAL_MS),
+            ),
+            gossip_journal_path: None,
+            max_forwarded_lookups: DEFAULT_MAX_FORWARDED_LOOKUPS,
+            admin_http_addr: None,
+            reachability_audit_interval: None,
+            status_interval: Some(Duration::from_secs(DEFAULT_STATUS_INTERVAL_SECS)),
+            gossip_connect_timeout: Duration::from_millis(DEFAULT_GOSSIP_CONNECT_TIMEOUT_MS),
+            ring_integrity_check_interval: None,
+            chord_response_timeout: Duration::from_millis(DEFAULT_RESPONSE_TIMEOUT_MS),
+            gossip_response_timeout: Duration::from_millis(DEFAULT_RESPONSE_TIMEOUT_MS),
+            verify_public_addr: true,
+            tls_cert_path: None,
+            tls_key_path: None,
+            tls_ca_path: None,
+            tls_server_name: None,
+            max_stabilization_failures: DEFAULT_MAX_STABILIZATION_FAILURES,
+            on_isolation: IsolationPolicy::Rejoin,
+            clock_skew_threshold: Some(Duration::from_millis(DEFAULT_CLOCK_SKEW_THRESHOLD_MS)),
+        }
+    }
+}
+
+impl Options {
+    /// Parses the remaining optional `key=value` arguments.
+    ///
+    /// Returns every invalid argument found, not only the first one.
+    fn parse(args: impl Iterator<Item = String>) -> Result<Self, Vec<String>> {
+        let mut options = Self::default();
+
+        let mut problems = Vec::new();
+        let mut is_sequential_identity = false;
+        let mut node_id: Option<u128> = None;
+
+        for arg in args {
+            let (key, value) = match arg.split_once('=') {
+                Some(key_value) => key_value,
+                None => {
+                    problems.push(format!(
+                        "malformed optional argument `{}`: expected key=value",
+                        arg
+                    ));
+                    continue;
+                }
+            };
+
+            let result: Result<(), String> = match key {
+                "gossip-max-in-flight" => value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.gossip_max_in_flight = n)
+                    .ok_or(format!(
+                        "invalid gossip-max-in-flight `{}`: expected a positive integer",
+                        value
+                    )),
+                "max-nodes" => value
+                    .parse::<u128>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.max_nodes = Some(n))
+                    .ok_or(format!(
+                        "invalid max-nodes `{}`: expected a positive integer",
+                        value
+                    )),
+                "read-timeout" => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.read_timeout = Duration::from_secs(n))
+                    .ok_or(format!(
+                        "invalid read-timeout `{}`: expected a positive number of seconds",
+                        value
+                    )),
+                "admin-secret" => {
+                    if value.is_empty() || value.contains(']') {
+                        Err(
+                            "invalid admin-secret: expected a non-empty secret without `]`"
+                                .to_string(),
+                        )
+                    } else {
+                        options.admin_secret = Some(value.to_string());
+                        Ok(())
+                    }
+                }
+                "hmac-key" => {
+                    if value.is_empty() {
+                        Err("invalid hmac-key: expected a non-empty key".to_string())
+                    } else {
+                        options.hmac_key = Some(value.to_string());
+                        Ok(())
+                    }
+                }
+                "identity-mode" => match value {
+                    "hashed" => {
+                        is_sequential_identity = false;
+                        Ok(())
+                    }
+                    // Debug builds only, production nodes keep hashed identifiers
+                    "sequential" if cfg!(debug_assertions) => {
+                        is_sequential_identity = true;
+                        Ok(())
+                    }
+                    "sequential" => {
+                        Err("identity-mode=sequential is only available in debug builds"
+                            .to_string())
+                    }
+                    _ => Err(format!(
+                        "invalid identity-mode `{}`: expected hashed or sequential",
+                        value
+                    )),
+                },
+                "connect-retries" => value
+                    .parse::<u32>()
+                    .map(|n| options.connect_retries = n)
+                    .map_err(|_| {
+                        format!(
+                            "invalid connect-retries `{}`: expected a non-negative integer",
+                            value
+                        )
+                    }),
+                "lookup-cache-ttl" => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.lookup_cache_ttl = Some(Duration::from_secs(n)))
+                    .ok_or(format!(
+                        "invalid lookup-cache-ttl `{}`: expected a positive number of seconds",
+                        value
+                    )),
+                "listen-backlog" => value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.listen_backlog = n)
+                    .ok_or(format!(
+                        "invalid listen-backlog `{}`: expected a positive integer",
+                        value
+                    )),
+                "max-connections" => value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.max_connections = n)
+                    .ok_or(format!(
+                        "invalid max-connections `{}`: expected a positive integer",
+                        value
+                    )),
+                "routing" => match value {
+                    "recursive" => {
+                        options.routing_mode = RoutingMode::Recursive;
+                        Ok(())
+                    }
+                    "iterative" => {
+                        options.routing_mode = RoutingMode::Iterative;
+                        Ok(())
+                    }
+                    _ => Err(format!(
+                        "invalid routing `{}`: expected recursive or iterative",
+                        value
+                    )),
+                },
+                "address-family" => match value {
+                    "any" => {
+                        options.address_family = AddressFamily::Any;
+                        Ok(())
+                    }
+                    "ipv4" => {
+                        options.address_family = AddressFamily::Ipv4;
+                        Ok(())
+                    }
+                    "ipv6" => {
+                        options.address_family = AddressFamily::Ipv6;
+                        Ok(())
+                    }
+                    _ => Err(format!(
+                        "invalid address-family `{}`: expected any, ipv4 or ipv6",
+                        value
+                    )),
+                },
+                "timestamp-source" => match value {
+                    "wall-clock" => {
+                        options.timestamp_source = TimestampSource::WallClock;
+                        Ok(())
+                    }
+                    "hybrid" => {
+                        options.timestamp_source = TimestampSource::Hybrid;
+                        Ok(())
+                    }
+                    _ => Err(format!(
+                        "invalid timestamp-source `{}`: expected wall-clock or hybrid",
+                        value
+                    )),
+                },
+                "gossip-compression" => match value {
+                    "none" => {
+                        options.gossip_compression = GossipCompression::None;
+                        Ok(())
+                    }
+                    "deflate" => {
+                        options.gossip_compression = GossipCompression::Deflate;
+                        Ok(())
+                    }
+                    _ => Err(format!(
+                        "invalid gossip-compression `{}`: expected none or deflate",
+                        value
+                    )),
+                },
+                "gossip-mode" => match value {
+                    "push-pull" => {
+                        options.gossip_mode = GossipMode::PushPull;
+                        Ok(())
+                    }
+                    "push" => {
+                        options.gossip_mode = GossipMode::Push;
+                        Ok(())
+                    }
+                    "pull" => {
+                        options.gossip_mode = GossipMode::Pull;
+                        Ok(())
+                    }
+                    _ => Err(format!(
+                        "invalid gossip-mode `{}`: expected push-pull, push or pull",
+                        value
+                    )),
+                },
+                "ring-bits" => value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| *n > 0 && *n <= DEFAULT_RING_BIT_LENGTH && n.is_multiple_of(8))
+                    .map(|n| options.ring_bit_length = n)
+                    .ok_or(format!(
+                        "invalid ring-bits `{}`: expected a multiple of 8, up to {}",
+                        value, DEFAULT_RING_BIT_LENGTH
+                    )),
+                "vnodes" => value
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|n| *n > 0 && *n <= MAX_VNODES)
+                    .map(|n| options.vnodes = n)
+                    .ok_or(format!(
+                        "invalid vnodes `{}`: expected a positive integer, up to {}",
+                        value, MAX_VNODES
+                    )),
+                "startup-report" => {
+                    if value.is_empty() {
+                        Err("invalid startup-report: expected a file path".to_string())
+                    } else {
+                        options.startup_report_path = Some(PathBuf::from(value));
+                        Ok(())
+                    }
+                }
+                "membership-snapshot" => fs::read_to_string(value)
+                    .map_err(|err| format!("cannot read membership-snapshot `{}`: {}", value, err))
+                    .and_then(|content| parse_membership_snapshot(&content))
+                    .map(|addrs| options.membership_snapshot = Some(addrs)),
+                "data-port" => value
+                    .parse::<u16>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.data_port = Some(n))
+                    .ok_or(format!(
+                        "invalid data-port `{}`: expected a port number",
+                        value
+                    )),
+                "stabilization-interval" => value
+                    .split_once("..")
+                    .and_then(|(min, max)| Some((min.parse::<u64>().ok()?, max.parse::<u64>().ok()?)))
+                    .filter(|(min, max)| *min > 0 && min <= max)
+                    .map(|(min, max)| {
+                        options.stabilization_interval =
+                            (Duration::from_millis(min), Duration::from_millis(max))
+                    })
+                    .ok_or(format!(
+                        "invalid stabilization-interval `{}`: expected <min>..<max> milliseconds, with 0 < min <= max",
+                        value
+                    )),
+                "gossip-journal" => {
+                    if value.is_empty() {
+                        Err("invalid gossip-journal: expected a file path".to_string())
+                    } else {
+                        options.gossip_journal_path = Some(PathBuf::from(value));
+                        Ok(())
+                    }
+                }
+                "max-forwarded-lookups" => value
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.max_forwarded_lookups = n)
+                    .ok_or(format!(
+                        "invalid max-forwarded-lookups `{}`: expected a positive integer",
+                        value
+                    )),
+                "admin-http-addr" => value
+                    .parse::<SocketAddr>()
+                    .map(|addr| options.admin_http_addr = Some(addr))
+                    .map_err(|_| {
+                        format!(
+                            "invalid admin-http-addr `{}`: expected a socket address (ip:port)",
+                            value
+                        )
+                    }),
+                "reachability-audit" => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.reachability_audit_interval = Some(Duration::from_secs(n)))
+                    .ok_or(format!(
+                        "invalid reachability-audit `{}`: expected a positive number of seconds",
+                        value
+                    )),
+                "ring-integrity-check" => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.ring_integrity_check_interval = Some(Duration::from_secs(n)))
+                    .ok_or(format!(
+                        "invalid ring-integrity-check `{}`: expected a positive number of seconds",
+                        value
+                    )),
+                "status-interval" if value == "off" => {
+                    options.status_interval = None;
+                    Ok(())
+                }
+                "status-interval" => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.status_interval = Some(Duration::from_secs(n)))
+                    .ok_or(format!(
+                        "invalid status-interval `{}`: expected a positive number of seconds, or off",
+                        value
+                    )),
+                "gossip-connect-timeout" => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.gossip_connect_timeout = Duration::from_millis(n))
+                    .ok_or(format!(
+                        "invalid gossip-connect-timeout `{}`: expected a positive number of milliseconds",
+                        value
+                    )),
+                "chord-response-timeout" => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.chord_response_timeout = Duration::from_millis(n))
+                    .ok_or(format!(
+                        "invalid chord-response-timeout `{}`: expected a positive number of milliseconds",
+                        value
+                    )),
+                "gossip-response-timeout" => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.gossip_response_timeout = Duration::from_millis(n))
+                    .ok_or(format!(
+                        "invalid gossip-response-timeout `{}`: expected a positive number of milliseconds",
+                        value
+                    )),
+                "public-addr-verification" => match value {
+                    "on" => {
+                        options.verify_public_addr = true;
+                        Ok(())
+                    }
+                    "off" => {
+                        options.verify_public_addr = false;
+                        Ok(())
+                    }
+                    _ => Err(format!(
+                        "invalid public-addr-verification `{}`: expected on or off",
+                        value
+                    )),
+                },
+                "tls-cert" | "tls-key" | "tls-ca" if value.is_empty() => {
+                    Err(format!("invalid {}: expected a file path", key))
+                }
+                "tls-cert" => {
+                    options.tls_cert_path = Some(PathBuf::from(value));
+                    Ok(())
+                }
+                "tls-key" => {
+                    options.tls_key_path = Some(PathBuf::from(value));
+                    Ok(())
+                }
+                "tls-ca" => {
+                    options.tls_ca_path = Some(PathBuf::from(value));
+                    Ok(())
+                }
+                "tls-server-name" => {
+                    if value.is_empty() {
+                        Err("invalid tls-server-name: expected a non-empty name".to_string())
+                    } else {
+                        options.tls_server_name = Some(value.to_string());
+                        Ok(())
+                    }
+                }
+                "max-stabilization-failures" => value
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.max_stabilization_failures = n)
+                    .ok_or(format!(
+                        "invalid max-stabilization-failures `{}`: expected a positive integer",
+                        value
+                    )),
+                "on-isolation" => match value {
+                    "rejoin" => {
+                        options.on_isolation = IsolationPolicy::Rejoin;
+                        Ok(())
+                    }
+                    "retry" => {
+                        options.on_isolation = IsolationPolicy::Retry;
+                        Ok(())
+                    }
+                    "exit" => {
+                        options.on_isolation = IsolationPolicy::Exit;
+                        Ok(())
+                    }
+                    _ => Err(format!(
+                        "invalid on-isolation `{}`: expected rejoin, retry or exit",
+                        value
+                    )),
+                },
+                "clock-skew-threshold" if value == "off" => {
+                    options.clock_skew_threshold = None;
+                    Ok(())
+                }
+                "clock-skew-threshold" => value
+                    .parse::<u64>()
+                    .ok()
+                    .filter(|n| *n > 0)
+                    .map(|n| options.clock_skew_threshold = Some(Duration::from_millis(n)))
+                    .ok_or(format!(
+                        "invalid clock-skew-threshold `{}`: expected a positive number of milliseconds, or off",
+                        value
+                    )),
+                "node-id" => value
+                    .parse::<u128>()
+                    .map(|n| node_id = Some(n))
+                    .map_err(|_| {
+                        format!(
+                            "invalid node-id `{}`: expected a non-negative integer",
+                            value
+                        )
+                    }),
+                _ => Err(format!("unknown optional argument `{}`", key)),
+            };
+
+            if let Err(problem) = result {
+                problems.push(problem);
+            }
+        }
+
+        options.identity_mode = match (is_sequential_identity, node_id) {
+            (true, Some(node_id))
+                if options.ring_bit_length < DEFAULT_RING_BIT_LENGTH
+                    && node_id >> options.ring_bit_length != 0 =>
+            {
+                problems.push(format!(
+                    "node-id {} does not fit in {} bits",
+                    node_id, options.ring_bit_length
+                ));
+                IdentityMode::Hashed
+            }
+            (true, Some(node_id)) => IdentityMode::Sequential(node_id),
+            (true, None) => {
+                problems
+                    .push("node-id argument is required with identity-mode=sequential".to_string());
+                IdentityMode::Hashed
+            }
+            (false, Some(_)) => {
+                problems.push("node-id argument requires identity-mode=sequential".to_string());
+                IdentityMode::Hashed
+            }
+            (false, None) => IdentityMode::Hashed,
+        };
+
+        if options.vnodes > 1 && options.identity_mode != IdentityMode::Hashed {
+            problems.push("vnodes argument requires identity-mode=hashed".to_string());
+        }
+
+        if options.membership_snapshot.is_some() && options.identity_mode != IdentityMode::Hashed {
+            problems.push("membership-snapshot argument requires identity-mode=hashed".to_string());
+        }
+
+        match (&options.tls_cert_path, &options.tls_key_path) {
+            (Some(_), None) => problems.push("tls-cert argument requires tls-key".to_string()),
+            (None, Some(_)) => problems.push("tls-key argument requires tls-cert".to_string()),
+            _ => (),
+        }
+
+        if options.tls_cert_path.is_none() && options.tls_ca_path.is_some() {
+            problems.push("tls-ca argument requires tls-cert".to_string());
+        }
+
+        if options.tls_cert_path.is_none() && options.tls_server_name.is_some() {
+            problems.push("tls-server-name argument requires tls-cert".to_string());
+        }
+
+        if !problems.is_empty() {
+            return Err(problems);
+        }
+
+        Ok(options)
+    }
+}
+
 impl Args {
     /// Parses all received arguments, performs types
     /// verification and build `Args` instance.
-    pub fn parse() -> Result<Self, Box<dyn Error>> {
-        let mut args = env::args().skip(1);
-        let action = args.next().ok_or("invalid argument(s)")?;
+    ///
+    /// Settings missing from the command line are read from
+    /// the environment, then from the config file (if any).
+    pub fn parse() -> Result<Self, ArgsError> {
+        Self::parse_from(env::args().skip(1), |name| env::var(name).ok())
+    }
 
-        if action != "init" && action != "join" {
-            return Err(From::from("invalid argument(s)"));
+    /// Parses `args` (without the program name), reading
+    /// the environment variables through `env_var`.
+    ///
+    /// The action (`init` or `join`) is optional: without it,
+    /// the node joins the network if `remote-addr` is set.
+    ///
+    /// Every invalid argument is reported, not only the first one.
+    pub fn parse_from(
+        args: impl Iterator<Item = String>,
+        env_var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, ArgsError> {
+        let mut args = args.peekable();
+        let mut problems = Vec::new();
+
+        let action = match args.peek() {
+            Some(arg) if arg == "init" || arg == "join" => args.next(),
+            Some(arg) if !arg.contains('=') => {
+                return Err(ArgsError {
+                    problems: vec![format!("unknown action `{}`: expected init or join", arg)],
+                })
+            }
+            _ => None,
+        };
+
+        // Command line settings
+        let mut cli_settings: Vec<(String, String)> = Vec::new();
+
+        for arg in args {
+            match arg.split_once('=') {
+                Some((key, _)) if key != CONFIG_KEY && !SETTING_KEYS.contains(&key) => {
+                    problems.push(format!("unknown argument `{}`", key))
+                }
+                Some((key, value)) => cli_settings.push((key.to_string(), value.to_string())),
+                None => problems.push(format!("malformed argument `{}`: expected key=value", arg)),
+            }
         }
 
-        let self_port_arg = args.next().ok_or("self-port argument is missing")?;
-        let self_port_value = self_port_arg.split("self-port=").last().unwrap(); // Safe unwrap
-        let self_port = self_port_value
-            .parse::<u16>()
-            .map_err(|_| "self-port argument is missing or invalid")?;
+        let cli_setting = |key: &str| {
+            cli_settings
+                .iter()
+                .rev()
+                .find(|(cli_key, _)| cli_key == key)
+                .map(|(_, value)| value.clone())
+        };
 
-        let public_addr_arg = args.next().ok_or("public-addr argument is missing")?;
-        let public_addr_value = public_addr_arg.split("public-addr=").last().unwrap(); // Safe unwrap
-        let public_addr = public_addr_value
-            .parse::<SocketAddr>()
-            .map_err(|_| "public-addr argument is missing or invalid")?;
+        // Config file settings
+        let mut file_settings = Vec::new();
 
-        if action == "init" {
-            return Ok(Self::Init {
-                self_port,
-                public_addr,
+        let config_path = cli_setting(CONFIG_KEY).or_else(|| env_var(sources::CONFIG_ENV_VAR));
+
+        if let Some(config_path) = config_path {
+            match fs::read_to_string(&config_path)
+                .map_err(|err| {
+                    vec![format!(
+                        "cannot read config file `{}`: {}",
+                        config_path, err
+                    )]
+                })
+                .and_then(|content| sources::parse_config_file(&content))
+            {
+                Ok(settings) => {
+                    for setting in settings {
+                        if SETTING_KEYS.contains(&setting.key.as_str()) {
+                            file_settings.push(setting);
+                        } else {
+                            problems.push(format!(
+                                "unknown setting `{}` in config file line {}",
+                                setting.key, setting.line
+                            ));
+                        }
+                    }
+                }
+                Err(config_problems) => problems.extend(config_problems),
+            }
+        }
+
+        // Resolves each setting, by order of precedence,
+        // with its origin for diagnostics
+        let setting = |key: &str| -> Option<(String, String)> {
+            if let Some(value) = cli_setting(key) {
+                return Some((value, String::new()));
+            }
+
+            let env_var_name = sources::env_var_name(key);
+
+            if let Some(value) = env_var(&env_var_name) {
+                return Some((value, format!(" (from {})", env_var_name)));
+            }
+
+            file_settings
+                .iter()
+                .rev()
+                .find(|setting| setting.key == key)
+                .map(|setting| {
+                    (
+                        setting.value.clone(),
+                        format!(" (from config file line {})", setting.line),
+                    )
+                })
+        };
+
+        let is_join = match action.as_deref() {
+            Some("join") => true,
+            Some(_) => {
+                // Ignores a network-wide remote address
+                // provided by the environment or the config file
+                if cli_setting("remote-addr").is_some() {
+                    problems.push("remote-addr argument is only valid with join".to_string());
+                }
+
+                false
+            }
+            None => setting("remote-addr").is_some(),
+        };
+
+        let option_args = SETTING_KEYS
+            .iter()
+            .filter(|key| !REQUIRED_SETTING_KEYS.contains(key))
+            .filter_map(|key| Some(format!("{}={}", key, setting(key)?.0)))
+            .collect::<Vec<_>>();
+
+        // Parsed first, as host names resolve to
+        // an address of a reachable family
+        let options = Options::parse(option_args.into_iter());
+        let address_family = options
+            .as_ref()
+            .map(|options| options.address_family)
+            .unwrap_or_default();
+
+        let self_port =
+            parse_required_setting(setting("self-port"), "self-port", &mut problems, |value| {
+                value
+                    .parse()
+                    .map_err(|_| "expected a port number".to_string())
             });
+        let public_addr = parse_required_setting(
+            setting("public-addr"),
+            "public-addr",
+            &mut problems,
+            |value| resolve_addr(value, address_family),
+        );
+        let remote_addr = if is_join {
+            parse_required_setting(
+                setting("remote-addr"),
+                "remote-addr",
+                &mut problems,
+                |value| resolve_addr(value, address_family),
+            )
+        } else {
+            None
+        };
+
+        let options = options
+            .map_err(|option_problems| problems.extend(option_problems))
+            .ok();
+
+        // The snapshot replaces the join through a remote node
+        if is_join
+            && options
+                .as_ref()
+                .is_some_and(|options| options.membership_snapshot.is_some())
+        {
+            problems.push("membership-snapshot argument is only valid with init".to_string());
         }
 
-        let remote_addr_arg = args.next().ok_or("remote-addr argument is missing")?;
-        let remote_addr_value = remote_addr_arg.split("remote-addr=").last().unwrap(); // Safe unwrap
-        let remote_addr = remote_addr_value
-            .parse::<SocketAddr>()
-            .map_err(|_| "remote-addr argument is missing or invalid")?;
+        if let (Some(self_port), Some(options)) = (self_port, options.as_ref()) {
+            if options.data_port == Some(self_port) {
+                problems.push("data-port argument must differ from self-port".to_string());
+            }
+        }
 
-        Ok(Self::Join {
-            self_port,
-            public_addr,
-            remote_addr,
-        })
+        if !problems.is_empty() {
+            return Err(ArgsError { problems });
+        }
+
+        match (self_port, public_addr, remote_addr, options) {
+            (Some(self_port), Some(public_addr), None, Some(options)) if !is_join => {
+                Ok(Self::Init {
+                    self_port,
+                    public_addr,
+                    options,
+                })
+            }
+            (Some(self_port), Some(public_addr), Some(remote_addr), Some(options)) => {
+                Ok(Self::Join {
+                    self_port,
+                    public_addr,
+                    remote_addr,
+                    options,
+                })
+            }
+            _ => Err(ArgsError { problems }),
+        }
     }
 
     /// Gets the value of the `self-port` argument.
@@ -69,11 +1250,13 @@ impl Args {
             Self::Init {
                 self_port,
                 public_addr: _,
+                options: _,
             } => self_port,
             Self::Join {
                 self_port,
                 public_addr: _,
                 remote_addr: _,
+                options: _,
             } => self_port,
         }
     }
@@ -84,11 +1267,13 @@ impl Args {
             Self::Init {
                 self_port: _,
                 public_addr,
+                options: _,
             } => public_addr,
             Self::Join {
                 self_port: _,
                 public_addr,
                 remote_addr: _,
+                options: _,
             } => public_addr,
         }
     }
@@ -100,12 +1285,973 @@ impl Args {
             Self::Init {
                 self_port: _,
                 public_addr: _,
+                options: _,
             } => None,
             Self::Join {
                 self_port: _,
                 public_addr: _,
                 remote_addr,
+                options: _,
             } => Some(remote_addr),
         }
     }
+
+    /// Gets the optional arguments.
+    pub fn get_options(&self) -> &Options {
+        match *self {
+            Self::Init {
+                self_port: _,
+                public_addr: _,
+                ref options,
+            } => options,
+            Self::Join {
+                self_port: _,
+                public_addr: _,
+                remote_addr: _,
+                ref options,
+            } => options,
+        }
+    }
+}
+
+/// Arguments of the `print-id` action: the settings
+/// the identifier of a node is derived from.
+#[derive(Debug, PartialEq, Eq)]
+pub struct PrintIdArgs {
+    pub public_addr: SocketAddr,
+    /// Only the identity options (`identity-mode`,
+    /// `node-id`, `ring-bits`, `vnodes`) are used.
+    pub options: Options,
+}
+
+impl PrintIdArgs {
+    /// Parses `args`, following the action: `public-addr`
+    /// and the options, on the command line only.
+    ///
+    /// Every invalid argument is reported, not only the first one.
+    pub fn parse_from(args: impl Iterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut problems = Vec::new();
+        let mut public_addr_value = None;
+        let mut option_args = Vec::new();
+
+        for arg in args {
+            match arg.split_once('=') {
+                Some(("public-addr", value)) => public_addr_value = Some(value.to_string()),
+                Some((key, _))
+                    if REQUIRED_SETTING_KEYS.contains(&key) || !SETTING_KEYS.contains(&key) =>
+                {
+                    problems.push(format!("unknown argument `{}`", key))
+                }
+                Some(_) => option_args.push(arg),
+                None => problems.push(format!("malformed argument `{}`: expected key=value", arg)),
+            }
+        }
+
+        let options = Options::parse(option_args.into_iter());
+        let address_family = options
+            .as_ref()
+            .map(|options| options.address_family)
+            .unwrap_or_default();
+
+        let public_addr = match public_addr_value {
+            Some(value) => resolve_addr(&value, address_family)
+                .map_err(|err| problems.push(format!("invalid public-addr `{}`: {}", value, err)))
+                .ok(),
+            None => {
+                problems.push("missing public-addr argument".to_string());
+                None
+            }
+        };
+
+        let options = options
+            .map_err(|option_problems| problems.extend(option_problems))
+            .ok();
+
+        match (public_addr, options) {
+            (Some(public_addr), Some(options)) if problems.is_empty() => Ok(Self {
+                public_addr,
+                options,
+            }),
+            _ => Err(ArgsError { problems }),
+        }
+    }
+}
+
+/// Parses the required `setting` named `key`, given as a value
+/// and its origin, with `parse` (which describes an invalid value).
+///
+/// Records a problem and returns `None` if the setting
+/// is missing or has an invalid value.
+fn parse_required_setting<T>(
+    setting: Option<(String, String)>,
+    key: &str,
+    problems: &mut Vec<String>,
+    parse: impl FnOnce(&str) -> Result<T, String>,
+) -> Option<T> {
+    let (value, origin) = match setting {
+        Some(setting) => setting,
+        None => {
+            problems.push(format!(
+                "missing {} argument (or {} environment variable)",
+                key,
+                sources::env_var_name(key)
+            ));
+            return None;
+        }
+    };
+
+    match parse(&value) {
+        Ok(parsed_value) => Some(parsed_value),
+        Err(err) => {
+            problems.push(format!("invalid {} `{}`{}: {}", key, value, origin, err));
+            None
+        }
+    }
+}
+
+/// Parses `value`, a socket address (`ip:port`) or a host name
+/// with a port (`host:port`), resolved to its first address
+/// of a family reachable through `address_family`.
+///
+/// A node identifier is derived from the resolved address: the
+/// ring only knows nodes by the address they are reached at, so
+/// a node whose host name moves to another address rejoins as
+/// a new node, like a node restarted on another address.
+fn resolve_addr(value: &str, address_family: AddressFamily) -> Result<SocketAddr, String> {
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Ok(addr);
+    }
+
+    let host = match value.rsplit_once(':') {
+        Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => host,
+        _ => return Err("expected a socket address (ip:port or host:port)".to_string()),
+    };
+
+    let addrs = value
+        .to_socket_addrs()
+        .map_err(|err| format!("cannot resolve host `{}`: {}", host, err))?;
+
+    addrs
+        .into_iter()
+        .find(|addr| address_family.can_reach(*addr))
+        .ok_or(format!(
+            "host `{}` has no address of the family set by address-family",
+            host
+        ))
+}
+
+/// Parses the content of a membership snapshot file: one
+/// public socket address (`ip:port`) per line. Blank lines
+/// and lines starting with `#` are ignored.
+///
+/// Returns the first malformed line, if any.
+fn parse_membership_snapshot(content: &str) -> Result<Vec<SocketAddr>, String> {
+    let mut addrs = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        match line.parse::<SocketAddr>() {
+            Ok(addr) => addrs.push(addr),
+            Err(_) => {
+                return Err(format!(
+                    "malformed membership-snapshot line {}: expected a socket address (ip:port)",
+                    i + 1
+                ))
+            }
+        }
+    }
+
+    if addrs.is_empty() {
+        return Err("invalid membership-snapshot: expected at least one address".to_string());
+    }
+
+    Ok(addrs)
+}
+
+#[cfg(test)]
+mod options_test {
+    use std::{net::SocketAddr, path::PathBuf, time::Duration};
+
+    use super::{
+        AddressFamily, GossipCompression, GossipMode, IdentityMode, IsolationPolicy, Options,
+        RoutingMode, TimestampSource, DEFAULT_MAX_FORWARDED_LOOKUPS,
+    };
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+            .map_err(|problems| problems.join("; "))
+    }
+
+    #[test]
+    fn identity_mode_parse_test() {
+        assert_eq!(parse(&[]).unwrap().identity_mode, IdentityMode::Hashed);
+        assert_eq!(
+            parse(&["identity-mode=hashed"]).unwrap().identity_mode,
+            IdentityMode::Hashed
+        );
+        assert_eq!(
+            parse(&["identity-mode=sequential", "node-id=3"])
+                .unwrap()
+                .identity_mode,
+            IdentityMode::Sequential(3)
+        );
+
+        assert!(parse(&["identity-mode=sequential"]).is_err());
+        assert!(parse(&["node-id=3"]).is_err());
+        assert!(parse(&["identity-mode=sequential", "node-id=-1"]).is_err());
+        assert!(parse(&["identity-mode=random"]).is_err());
+    }
+
+    #[test]
+    fn routing_mode_parse_test() {
+        assert_eq!(parse(&[]).unwrap().routing_mode, RoutingMode::Recursive);
+        assert_eq!(
+            parse(&["routing=iterative"]).unwrap().routing_mode,
+            RoutingMode::Iterative
+        );
+        assert_eq!(
+            parse(&["routing=recursive"]).unwrap().routing_mode,
+            RoutingMode::Recursive
+        );
+
+        assert!(parse(&["routing=direct"]).is_err());
+    }
+
+    #[test]
+    fn address_family_parse_test() {
+        assert_eq!(parse(&[]).unwrap().address_family, AddressFamily::Any);
+        assert_eq!(
+            parse(&["address-family=ipv4"]).unwrap().address_family,
+            AddressFamily::Ipv4
+        );
+        assert_eq!(
+            parse(&["address-family=ipv6"]).unwrap().address_family,
+            AddressFamily::Ipv6
+        );
+
+        assert!(parse(&["address-family=ipx"]).is_err());
+
+        let v4_addr = "10.0.0.1:8000".parse::<SocketAddr>().unwrap();
+        let v6_addr = "[2001:db8::1]:8000".parse::<SocketAddr>().unwrap();
+
+        assert!(AddressFamily::Any.can_reach(v4_addr) && AddressFamily::Any.can_reach(v6_addr));
+        assert!(AddressFamily::Ipv4.can_reach(v4_addr) && !AddressFamily::Ipv4.can_reach(v6_addr));
+        assert!(!AddressFamily::Ipv6.can_reach(v4_addr) && AddressFamily::Ipv6.can_reach(v6_addr));
+    }
+
+    #[test]
+    fn timestamp_source_parse_test() {
+        assert_eq!(
+            parse(&[]).unwrap().timestamp_source,
+            TimestampSource::WallClock
+        );
+        assert_eq!(
+            parse(&["timestamp-source=hybrid"])
+                .unwrap()
+                .timestamp_source,
+            TimestampSource::Hybrid
+        );
+        assert_eq!(
+            parse(&["timestamp-source=wall-clock"])
+                .unwrap()
+                .timestamp_source,
+            TimestampSource::WallClock
+        );
+
+        assert!(parse(&["timestamp-source=ntp"]).is_err());
+    }
+
+    #[test]
+    fn gossip_compression_parse_test() {
+        assert_eq!(
+            parse(&[]).unwrap().gossip_compression,
+            GossipCompression::None
+        );
+        assert_eq!(
+            parse(&["gossip-compression=deflate"])
+                .unwrap()
+                .gossip_compression,
+            GossipCompression::Deflate
+        );
+        assert!(parse(&["gossip-compression=zstd"]).is_err());
+    }
+
+    #[test]
+    fn gossip_mode_parse_test() {
+        assert_eq!(parse(&[]).unwrap().gossip_mode, GossipMode::PushPull);
+        assert_eq!(
+            parse(&["gossip-mode=push"]).unwrap().gossip_mode,
+            GossipMode::Push
+        );
+        assert_eq!(
+            parse(&["gossip-mode=pull"]).unwrap().gossip_mode,
+            GossipMode::Pull
+        );
+        assert!(parse(&["gossip-mode=flood"]).is_err());
+    }
+
+    #[test]
+    fn vnodes_parse_test() {
+        assert_eq!(parse(&[]).unwrap().vnodes, 1);
+        assert_eq!(parse(&["vnodes=8"]).unwrap().vnodes, 8);
+        assert!(parse(&["vnodes=0"]).is_err());
+        assert!(parse(&["vnodes=65"]).is_err());
+        assert!(parse(&["vnodes=8", "identity-mode=sequential", "node-id=3"]).is_err());
+    }
+
+    #[test]
+    fn startup_report_parse_test() {
+        assert_eq!(parse(&[]).unwrap().startup_report_path, None);
+        assert_eq!(
+            parse(&["startup-report=/tmp/node.json"])
+                .unwrap()
+                .startup_report_path,
+            Some("/tmp/node.json".into())
+        );
+        assert!(parse(&["startup-report="]).is_err());
+    }
+
+    #[test]
+    fn data_port_parse_test() {
+        assert_eq!(parse(&[]).unwrap().data_port, None);
+        assert_eq!(parse(&["data-port=7100"]).unwrap().data_port, Some(7100));
+        assert!(parse(&["data-port=0"]).is_err());
+        assert!(parse(&["data-port=70000"]).is_err());
+    }
+
+    #[test]
+    fn stabilization_interval_parse_test() {
+        assert_eq!(
+            parse(&[]).unwrap().stabilization_interval,
+            (Duration::from_secs(1), Duration::from_secs(8))
+        );
+        assert_eq!(
+            parse(&["stabilization-interval=250..4000"])
+                .unwrap()
+                .stabilization_interval,
+            (Duration::from_millis(250), Duration::from_secs(4))
+        );
+        // A fixed interval
+        assert_eq!(
+            parse(&["stabilization-interval=2000..2000"])
+                .unwrap()
+                .stabilization_interval,
+            (Duration::from_secs(2), Duration::from_secs(2))
+        );
+        assert!(parse(&["stabilization-interval=2000"]).is_err());
+        assert!(parse(&["stabilization-interval=0..2000"]).is_err());
+        assert!(parse(&["stabilization-interval=4000..2000"]).is_err());
+    }
+
+    #[test]
+    fn gossip_journal_parse_test() {
+        assert_eq!(parse(&[]).unwrap().gossip_journal_path, None);
+        assert_eq!(
+            parse(&["gossip-journal=/var/log/chord/gossip.log"])
+                .unwrap()
+                .gossip_journal_path,
+            Some("/var/log/chord/gossip.log".into())
+        );
+        assert!(parse(&["gossip-journal="]).is_err());
+    }
+
+    #[test]
+    fn max_forwarded_lookups_parse_test() {
+        assert_eq!(
+            parse(&[]).unwrap().max_forwarded_lookups,
+            DEFAULT_MAX_FORWARDED_LOOKUPS
+        );
+        assert_eq!(
+            parse(&["max-forwarded-lookups=2"])
+                .unwrap()
+                .max_forwarded_lookups,
+            2
+        );
+        assert!(parse(&["max-forwarded-lookups=0"]).is_err());
+        assert!(parse(&["max-forwarded-lookups=many"]).is_err());
+    }
+
+    #[test]
+    fn admin_http_addr_parse_test() {
+        assert_eq!(parse(&[]).unwrap().admin_http_addr, None);
+        assert_eq!(
+            parse(&["admin-http-addr=127.0.0.1:9090"])
+                .unwrap()
+                .admin_http_addr,
+            Some("127.0.0.1:9090".parse::<SocketAddr>().unwrap())
+        );
+        assert!(parse(&["admin-http-addr=9090"]).is_err());
+    }
+
+    #[test]
+    fn reachability_audit_parse_test() {
+        assert_eq!(parse(&[]).unwrap().reachability_audit_interval, None);
+        assert_eq!(
+            parse(&["reachability-audit=30"])
+                .unwrap()
+                .reachability_audit_interval,
+            Some(Duration::from_secs(30))
+        );
+        assert!(parse(&["reachability-audit=0"]).is_err());
+    }
+
+    #[test]
+    fn response_timeouts_parse_test() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(options.chord_response_timeout, Duration::from_secs(5));
+        assert_eq!(options.gossip_response_timeout, Duration::from_secs(5));
+
+        let options = parse(&[
+            "chord-response-timeout=1000",
+            "gossip-response-timeout=30000",
+        ])
+        .unwrap();
+        assert_eq!(options.chord_response_timeout, Duration::from_secs(1));
+        assert_eq!(options.gossip_response_timeout, Duration::from_secs(30));
+
+        assert!(parse(&["chord-response-timeout=0"]).is_err());
+        assert!(parse(&["gossip-response-timeout=-1"]).is_err());
+    }
+
+    #[test]
+    fn public_addr_verification_parse_test() {
+        assert!(parse(&[]).unwrap().verify_public_addr);
+        assert!(
+            parse(&["public-addr-verification=on"])
+                .unwrap()
+                .verify_public_addr
+        );
+        assert!(
+            !parse(&["public-addr-verification=off"])
+                .unwrap()
+                .verify_public_addr
+        );
+        assert!(parse(&["public-addr-verification=no"]).is_err());
+    }
+
+    #[test]
+    fn tls_parse_test() {
+        assert_eq!(parse(&[]).unwrap().tls_cert_path, None);
+
+        let options = parse(&[
+            "tls-cert=node.pem",
+            "tls-key=node.key",
+            "tls-ca=ring.pem",
+            "tls-server-name=ring.example",
+        ])
+        .unwrap();
+        assert_eq!(options.tls_cert_path, Some(PathBuf::from("node.pem")));
+        assert_eq!(options.tls_key_path, Some(PathBuf::from("node.key")));
+        assert_eq!(options.tls_ca_path, Some(PathBuf::from("ring.pem")));
+        assert_eq!(options.tls_server_name, Some("ring.example".to_string()));
+
+        assert_eq!(
+            parse(&["tls-cert=node.pem"]).unwrap_err(),
+            "tls-cert argument requires tls-key"
+        );
+        assert_eq!(
+            parse(&["tls-ca=ring.pem", "tls-server-name=ring.example"]).unwrap_err(),
+            "tls-ca argument requires tls-cert; tls-server-name argument requires tls-cert"
+        );
+        assert!(parse(&["tls-cert=", "tls-key=node.key"]).is_err());
+    }
+
+    #[test]
+    fn admin_secret_parse_test() {
+        assert_eq!(parse(&[]).unwrap().admin_secret, None);
+        assert_eq!(
+            parse(&["admin-secret=s3cr[et"]).unwrap().admin_secret,
+            Some("s3cr[et".to_string())
+        );
+
+        for secret in ["", "s3cr]et", "]"] {
+            assert_eq!(
+                parse(&[&format!("admin-secret={}", secret)]),
+                Err("invalid admin-secret: expected a non-empty secret without `]`".to_string()),
+                "{}",
+                secret
+            );
+        }
+    }
+
+    #[test]
+    fn isolation_parse_test() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(options.max_stabilization_failures, 3);
+        assert_eq!(options.on_isolation, IsolationPolicy::Rejoin);
+
+        let options = parse(&["max-stabilization-failures=1", "on-isolation=exit"]).unwrap();
+        assert_eq!(options.max_stabilization_failures, 1);
+        assert_eq!(options.on_isolation, IsolationPolicy::Exit);
+
+        assert_eq!(
+            parse(&["on-isolation=retry"]).unwrap().on_isolation,
+            IsolationPolicy::Retry
+        );
+        assert!(parse(&["max-stabilization-failures=0"]).is_err());
+        assert!(parse(&["on-isolation=wait"]).is_err());
+    }
+
+    #[test]
+    fn clock_skew_threshold_parse_test() {
+        assert_eq!(
+            parse(&[]).unwrap().clock_skew_threshold,
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            parse(&["clock-skew-threshold=250"])
+                .unwrap()
+                .clock_skew_threshold,
+            Some(Duration::from_millis(250))
+        );
+        assert_eq!(
+            parse(&["clock-skew-threshold=off"])
+                .unwrap()
+                .clock_skew_threshold,
+            None
+        );
+        assert!(parse(&["clock-skew-threshold=0"]).is_err());
+    }
+
+    #[test]
+    fn ring_integrity_check_parse_test() {
+        assert_eq!(parse(&[]).unwrap().ring_integrity_check_interval, None);
+        assert_eq!(
+            parse(&["ring-integrity-check=60"])
+                .unwrap()
+                .ring_integrity_check_interval,
+            Some(Duration::from_secs(60))
+        );
+        assert!(parse(&["ring-integrity-check=0"]).is_err());
+    }
+
+    #[test]
+    fn status_interval_parse_test() {
+        assert_eq!(
+            parse(&[]).unwrap().status_interval,
+            Some(Duration::from_secs(1))
+        );
+        assert_eq!(
+            parse(&["status-interval=30"]).unwrap().status_interval,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            parse(&["status-interval=off"]).unwrap().status_interval,
+            None
+        );
+        assert!(parse(&["status-interval=0"]).is_err());
+    }
+
+    #[test]
+    fn gossip_connect_timeout_parse_test() {
+        assert_eq!(
+            parse(&[]).unwrap().gossip_connect_timeout,
+            Duration::from_millis(1000)
+        );
+        assert_eq!(
+            parse(&["gossip-connect-timeout=250"])
+                .unwrap()
+                .gossip_connect_timeout,
+            Duration::from_millis(250)
+        );
+        assert!(parse(&["gossip-connect-timeout=0"]).is_err());
+    }
+
+    #[test]
+    fn ring_bits_parse_test() {
+        assert_eq!(parse(&[]).unwrap().ring_bit_length, 128);
+        assert_eq!(parse(&["ring-bits=32"]).unwrap().ring_bit_length, 32);
+        assert!(parse(&["ring-bits=0"]).is_err());
+        assert!(parse(&["ring-bits=12"]).is_err());
+        assert!(parse(&["ring-bits=136"]).is_err());
+
+        // Sequential identifiers must fit in the ring
+        assert!(parse(&["ring-bits=8", "identity-mode=sequential", "node-id=255"]).is_ok());
+        assert!(parse(&["ring-bits=8", "identity-mode=sequential", "node-id=256"]).is_err());
+    }
+}
+
+#[cfg(test)]
+mod args_test {
+    use std::{collections::HashMap, env, fs, net::SocketAddr, time::Duration};
+
+    use super::{Args, ArgsError, IdentityMode, Options, PrintIdArgs, HMAC_KEY_ENV_VAR};
+
+    fn parse(args: &[&str]) -> Result<Args, ArgsError> {
+        parse_with_env(args, &[])
+    }
+
+    fn parse_with_env(args: &[&str], env_vars: &[(&str, &str)]) -> Result<Args, ArgsError> {
+        let env_vars = env_vars
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect::<HashMap<_, _>>();
+
+        Args::parse_from(args.iter().map(|arg| arg.to_string()), |name| {
+            env_vars.get(name).cloned()
+        })
+    }
+
+    #[test]
+    fn valid_args_parse_test() {
+        assert_eq!(
+            parse(&["init", "self-port=8000", "public-addr=127.0.0.1:8000"]).unwrap(),
+            Args::Init {
+                self_port: 8000,
+                public_addr: "127.0.0.1:8000".parse::<SocketAddr>().unwrap(),
+                options: Options::default(),
+            }
+        );
+
+        let args = parse(&[
+            "join",
+            "self-port=8001",
+            "public-addr=127.0.0.1:8001",
+            "remote-addr=127.0.0.1:8000",
+            "max-nodes=16",
+            "lookup-cache-ttl=30",
+            "max-connections=64",
+        ])
+        .unwrap();
+
+        assert_eq!(
+            args.get_remote_addr(),
+            Some("127.0.0.1:8000".parse::<SocketAddr>().unwrap())
+        );
+        assert_eq!(args.get_options().max_nodes, Some(16));
+        assert_eq!(
+            args.get_options().lookup_cache_ttl,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(args.get_options().max_connections, 64);
+    }
+
+    #[test]
+    fn malformed_keys_are_rejected_test() {
+        for (self_port_arg, problem) in [
+            ("selfport=5", "unknown argument `selfport`"),
+            ("self-portXYZ=5", "unknown argument `self-portXYZ`"),
+            (
+                "garbage",
+                "malformed argument `garbage`: expected key=value",
+            ),
+        ] {
+            let err = parse(&["init", self_port_arg, "public-addr=127.0.0.1:8000"]).unwrap_err();
+
+            assert_eq!(
+                err.problems,
+                [
+                    problem,
+                    "missing self-port argument (or CHORD_SELF_PORT environment variable)"
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn all_problems_are_reported_test() {
+        let err = parse(&[
+            "join",
+            "self-port=99999",
+            "public-addr=localhost",
+            "max-nodes=0",
+            "colour=blue",
+            "verbose",
+        ])
+        .unwrap_err();
+
+        assert_eq!(
+            err.problems,
+            [
+                "unknown argument `colour`",
+                "malformed argument `verbose`: expected key=value",
+                "invalid self-port `99999`: expected a port number",
+                "invalid public-addr `localhost`: expected a socket address (ip:port or host:port)",
+                "missing remote-addr argument (or CHORD_REMOTE_ADDR environment variable)",
+                "invalid max-nodes `0`: expected a positive integer",
+            ]
+        );
+
+        // The usage message follows the problems
+        let message = err.to_string();
+        assert!(message.contains("  - invalid self-port `99999`"));
+        assert!(message.contains("usage:"));
+    }
+
+    #[test]
+    fn missing_args_are_reported_test() {
+        assert_eq!(
+            parse(&["start"]).unwrap_err().problems,
+            ["unknown action `start`: expected init or join"]
+        );
+        assert_eq!(
+            parse(&["join", "self-port=8001"]).unwrap_err().problems,
+            [
+                "missing public-addr argument (or CHORD_PUBLIC_ADDR environment variable)",
+                "missing remote-addr argument (or CHORD_REMOTE_ADDR environment variable)"
+            ]
+        );
+    }
+
+    #[test]
+    fn env_vars_only_parse_test() {
+        let env_vars = [
+            ("CHORD_SELF_PORT", "8001"),
+            ("CHORD_PUBLIC_ADDR", "127.0.0.1:8001"),
+            ("CHORD_REMOTE_ADDR", "127.0.0.1:8000"),
+            ("CHORD_MAX_NODES", "16"),
+        ];
+
+        // Joins the network, since a remote address is set
+        let args = parse_with_env(&[], &env_vars).unwrap();
+
+        assert_eq!(args.get_self_port(), 8001);
+        assert_eq!(
+            args.get_remote_addr(),
+            Some("127.0.0.1:8000".parse::<SocketAddr>().unwrap())
+        );
+        assert_eq!(args.get_options().max_nodes, Some(16));
+
+        // The command line takes precedence, and an explicit
+        // init ignores the network-wide remote address
+        let args = parse_with_env(&["init", "self-port=9000"], &env_vars).unwrap();
+
+        assert_eq!(args.get_self_port(), 9000);
+        assert_eq!(args.get_remote_addr(), None);
+
+        // Invalid values name their source
+        let err = parse_with_env(&[], &[("CHORD_SELF_PORT", "http"), env_vars[1]]).unwrap_err();
+
+        assert_eq!(
+            err.problems,
+            ["invalid self-port `http` (from CHORD_SELF_PORT): expected a port number"]
+        );
+    }
+
+    #[test]
+    fn hmac_key_env_var_parse_test() {
+        let public_addr = ("CHORD_PUBLIC_ADDR", "127.0.0.1:8001");
+        let args = parse_with_env(
+            &["init", "self-port=8001"],
+            &[public_addr, (HMAC_KEY_ENV_VAR, "ring-key")],
+        )
+        .unwrap();
+
+        assert_eq!(args.get_options().hmac_key.as_deref(), Some("ring-key"));
+
+        // The former name of the variable is not read
+        let args = parse_with_env(
+            &["init", "self-port=8001"],
+            &[public_addr, ("NODE_HMAC_KEY", "ring-key")],
+        )
+        .unwrap();
+
+        assert_eq!(args.get_options().hmac_key, None);
+    }
+
+    #[test]
+    fn process_env_vars_parse_test() {
+        // Names specific to this test, which runs
+        // concurrently with others in the same process
+        env::set_var("CHORD_SELF_PORT", "8002");
+        env::set_var("CHORD_PUBLIC_ADDR", "127.0.0.1:8002");
+
+        let args = Args::parse_from(std::iter::empty(), |name| env::var(name).ok());
+
+        env::remove_var("CHORD_SELF_PORT");
+        env::remove_var("CHORD_PUBLIC_ADDR");
+
+        let args = args.unwrap();
+
+        assert_eq!(args.get_self_port(), 8002);
+        assert_eq!(args.get_remote_addr(), None);
+    }
+
+    #[test]
+    fn config_file_parse_test() {
+        let config_path = env::temp_dir().join(format!("chord-node-{}.toml", std::process::id()));
+
+        fs::write(
+            &config_path,
+            "self-port = 8003\npublic-addr = \"127.0.0.1:8003\"\nmax-nodes = 8\n",
+        )
+        .unwrap();
+
+        let config_arg = format!("config={}", config_path.display());
+
+        // Environment variables take precedence
+        // over the config file
+        let args = parse_with_env(&[&config_arg], &[("CHORD_MAX_NODES", "32")]).unwrap();
+
+        assert_eq!(args.get_self_port(), 8003);
+        assert_eq!(args.get_options().max_nodes, Some(32));
+
+        // The config file path can come from the environment
+        let config_path_value = config_path.display().to_string();
+        let args = parse_with_env(&[], &[("CHORD_CONFIG", &config_path_value)]).unwrap();
+
+        assert_eq!(args.get_self_port(), 8003);
+        assert_eq!(args.get_options().max_nodes, Some(8));
+
+        fs::remove_file(&config_path).unwrap();
+
+        let err = parse(&[&config_arg]).unwrap_err();
+        assert!(err.problems[0].starts_with("cannot read config file"));
+    }
+
+    #[test]
+    fn membership_snapshot_parse_test() {
+        let snapshot_path =
+            env::temp_dir().join(format!("chord-snapshot-{}.txt", std::process::id()));
+
+        fs::write(
+            &snapshot_path,
+            "# ring members\n127.0.0.1:8000\n\n127.0.0.1:8001\n",
+        )
+        .unwrap();
+
+        let snapshot_arg = format!("membership-snapshot={}", snapshot_path.display());
+
+        let args = parse(&[
+            "init",
+            "self-port=8000",
+            "public-addr=127.0.0.1:8000",
+            &snapshot_arg,
+        ])
+        .unwrap();
+
+        assert_eq!(
+            args.get_options().membership_snapshot,
+            Some(vec![
+                "127.0.0.1:8000".parse::<SocketAddr>().unwrap(),
+                "127.0.0.1:8001".parse::<SocketAddr>().unwrap(),
+            ])
+        );
+
+        // The snapshot replaces the join
+        let err = parse(&[
+            "join",
+            "self-port=8000",
+            "public-addr=127.0.0.1:8000",
+            "remote-addr=127.0.0.1:8001",
+            &snapshot_arg,
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.problems,
+            ["membership-snapshot argument is only valid with init"]
+        );
+
+        fs::write(&snapshot_path, "127.0.0.1:8000\nlocalhost\n").unwrap();
+
+        let err = parse(&[
+            "init",
+            "self-port=8000",
+            "public-addr=127.0.0.1:8000",
+            &snapshot_arg,
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.problems,
+            ["malformed membership-snapshot line 2: expected a socket address (ip:port)"]
+        );
+
+        fs::remove_file(&snapshot_path).unwrap();
+    }
+
+    #[test]
+    fn data_port_must_differ_from_self_port_test() {
+        let args = parse(&[
+            "self-port=8000",
+            "public-addr=127.0.0.1:8000",
+            "data-port=8100",
+        ])
+        .unwrap();
+        assert_eq!(args.get_options().data_port, Some(8100));
+
+        let err = parse(&[
+            "self-port=8000",
+            "public-addr=127.0.0.1:8000",
+            "data-port=8000",
+        ])
+        .unwrap_err();
+        assert_eq!(
+            err.problems,
+            ["data-port argument must differ from self-port"]
+        );
+    }
+
+    #[test]
+    fn host_names_are_resolved_test() {
+        let args = parse(&[
+            "join",
+            "self-port=8001",
+            "public-addr=127.0.0.1:8001",
+            "remote-addr=localhost:8000",
+            "address-family=ipv4",
+        ])
+        .unwrap();
+        assert_eq!(
+            args.get_remote_addr(),
+            Some("127.0.0.1:8000".parse::<SocketAddr>().unwrap())
+        );
+
+        // The .invalid domain never resolves
+        let err = parse(&[
+            "join",
+            "self-port=8001",
+            "public-addr=127.0.0.1:8001",
+            "remote-addr=seed.invalid:8000",
+        ])
+        .unwrap_err();
+        assert_eq!(err.problems.len(), 1);
+        assert!(err.problems[0].starts_with(
+            "invalid remote-addr `seed.invalid:8000`: cannot resolve host `seed.invalid`"
+        ));
+
+        let err = parse(&["init", "self-port=8000", "public-addr=localhost:http"]).unwrap_err();
+        assert_eq!(
+            err.problems,
+            ["invalid public-addr `localhost:http`: expected a socket address (ip:port or host:port)"]
+        );
+    }
+
+    #[test]
+    fn print_id_args_parse_test() {
+        let parse_print_id =
+            |args: &[&str]| PrintIdArgs::parse_from(args.iter().map(|arg| arg.to_string()));
+
+        let print_id_args = parse_print_id(&[
+            "public-addr=1.2.3.4:9000",
+            "identity-mode=sequential",
+            "node-id=7",
+            "ring-bits=8",
+        ])
+        .unwrap();
+        assert_eq!(
+            print_id_args.public_addr,
+            "1.2.3.4:9000".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            print_id_args.options.identity_mode,
+            IdentityMode::Sequential(7)
+        );
+        assert_eq!(print_id_args.options.ring_bit_length, 8);
+
+        assert_eq!(
+            parse_print_id(&["self-port=8000", "vnodes=0"])
+                .unwrap_err()
+                .problems,
+            [
+                "unknown argument `self-port`",
+                "missing public-addr argument",
+                "invalid vnodes `0`: expected a positive integer, up to 64"
+            ]
+        );
+    }
 }
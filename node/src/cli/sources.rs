@@ -0,0 +1,146 @@
+//! Sources of the `key=value` settings of a node, besides
+//! the command line: environment variables and a config file.
+//!
+//! When a setting comes from several sources, the command line
+//! takes precedence over the environment, which takes precedence
+//! over the config file.
+
+/// Prefix of the environment variables providing settings.
+pub const ENV_VAR_PREFIX: &str = "CHORD_";
+
+/// Environment variable providing the config file path,
+/// if the `config` argument is not set.
+pub const CONFIG_ENV_VAR: &str = "CHORD_CONFIG";
+
+/// Returns the name of the environment variable
+/// providing the setting `key` (e.g. `CHORD_SELF_PORT`
+/// for `self-port`).
+pub fn env_var_name(key: &str) -> String {
+    format!("{}{}", ENV_VAR_PREFIX, key.to_uppercase().replace('-', "_"))
+}
+
+/// A setting read from a config file, with its line number.
+#[derive(Debug, PartialEq, Eq)]
+pub struct FileSetting {
+    pub key: String,
+    pub value: String,
+    pub line: usize,
+}
+
+/// Parses the content of a config file: a flat TOML subset made
+/// of `key = value` lines, using the command line keys.
+///
+/// Values may be double-quoted (without escape sequences).
+/// Blank lines and lines starting with `#` are ignored.
+///
+/// Returns every malformed line, not only the first one.
+pub fn parse_config_file(content: &str) -> Result<Vec<FileSetting>, Vec<String>> {
+    let mut settings = Vec::new();
+    let mut problems = Vec::new();
+
+    for (i, line) in content.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.trim();
+
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let (key, value) = match line.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => (key.trim(), value.trim()),
+            _ => {
+                problems.push(format!(
+                    "malformed config file line {}: expected key = value",
+                    line_number
+                ));
+                continue;
+            }
+        };
+
+        let value = match value.strip_prefix('"') {
+            Some(quoted_value) => match quoted_value.strip_suffix('"') {
+                Some(value) => value,
+                None => {
+                    problems.push(format!(
+                        "malformed config file line {}: unterminated string",
+                        line_number
+                    ));
+                    continue;
+                }
+            },
+            None => value,
+        };
+
+        settings.push(FileSetting {
+            key: key.to_string(),
+            value: value.to_string(),
+            line: line_number,
+        });
+    }
+
+    if !problems.is_empty() {
+        return Err(problems);
+    }
+
+    Ok(settings)
+}
+
+#[cfg(test)]
+mod config_file_test {
+    use super::{env_var_name, parse_config_file, FileSetting};
+
+    #[test]
+    fn env_var_name_test() {
+        assert_eq!(env_var_name("self-port"), "CHORD_SELF_PORT");
+        assert_eq!(
+            env_var_name("gossip-max-in-flight"),
+            "CHORD_GOSSIP_MAX_IN_FLIGHT"
+        );
+    }
+
+    #[test]
+    fn config_file_parse_test() {
+        let content = "\
+# Seed node
+self-port = 8000
+public-addr = \"127.0.0.1:8000\"
+
+max-nodes=16
+";
+
+        assert_eq!(
+            parse_config_file(content).unwrap(),
+            vec![
+                FileSetting {
+                    key: "self-port".to_string(),
+                    value: "8000".to_string(),
+                    line: 2,
+                },
+                FileSetting {
+                    key: "public-addr".to_string(),
+                    value: "127.0.0.1:8000".to_string(),
+                    line: 3,
+                },
+                FileSetting {
+                    key: "max-nodes".to_string(),
+                    value: "16".to_string(),
+                    line: 5,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn malformed_config_file_test() {
+        let content = "[node]\nself-port = 8000\npublic-addr = \"127.0.0.1:8000\n= 3\n";
+
+        assert_eq!(
+            parse_config_file(content).unwrap_err(),
+            vec![
+                "malformed config file line 1: expected key = value",
+                "malformed config file line 3: unterminated string",
+                "malformed config file line 4: expected key = value",
+            ]
+        );
+    }
+}
@@ -0,0 +1,237 @@
+//! Requests of external tools (clients, admin tools) to
+//! the nodes of a ring, signed with the key shared by the
+//! nodes (`hmac-key`) if they authenticate their messages,
+//! and encrypted with TLS if the nodes require it.
+//!
+//! Each request is sent over a new connection, as between
+//! nodes, so that the tools reproduce their message format.
+
+use std::{env, io, net::SocketAddr, path::Path, sync::Arc, time::Duration};
+
+use crate::{
+    gossip::QUORUM_WRITE_TIMEOUT,
+    transport::{
+        auth::MessageAuthenticator, tls::TlsConfig, TcpTransport, Transport,
+        DEFAULT_RESPONSE_TIMEOUT,
+    },
+};
+
+pub use crate::cli::{DEFAULT_TLS_SERVER_NAME, HMAC_KEY_ENV_VAR};
+
+/// Read timeout when waiting for a response, long enough
+/// for a write waiting for acknowledgments.
+const RESPONSE_TIMEOUT: Duration = QUORUM_WRITE_TIMEOUT.saturating_add(DEFAULT_RESPONSE_TIMEOUT);
+
+/// Sends the requests of an external tool to the nodes of a ring.
+#[derive(Debug, Clone, Default)]
+pub struct Client {
+    authenticator: Option<MessageAuthenticator>,
+    tls: Option<Arc<TlsConfig>>,
+}
+
+impl Client {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the client described by the connection options of
+    /// a tool, removed from its arguments `args`, wherever they are:
+    /// - `--hmac-key <key>` (the `CHORD_HMAC_KEY` environment
+    ///   variable if not set, as for the nodes),
+    /// - `--tls-ca <path>`, encrypting the connections with TLS (see
+    ///   `with_tls`), and `--tls-server-name <name>` (`chord-node` if
+    ///   not set).
+    pub fn from_args(args: &mut Vec<String>) -> Result<Self, String> {
+        let mut client = Self::new();
+
+        let hmac_key = match take_option(args, "--hmac-key")? {
+            Some(hmac_key) if hmac_key.is_empty() => {
+                return Err("invalid --hmac-key: expected a non-empty key".to_string())
+            }
+            Some(hmac_key) => Some(hmac_key),
+            None => env::var(HMAC_KEY_ENV_VAR)
+                .ok()
+                .filter(|hmac_key| !hmac_key.is_empty()),
+        };
+
+        if let Some(hmac_key) = hmac_key {
+            client = client.with_hmac_key(&hmac_key);
+        }
+
+        let server_name = take_option(args, "--tls-server-name")?;
+
+        match take_option(args, "--tls-ca")? {
+            Some(ca_path) => {
+                client = client.with_tls(
+                    Path::new(&ca_path),
+                    server_name.as_deref().unwrap_or(DEFAULT_TLS_SERVER_NAME),
+                )?
+            }
+            None if server_name.is_some() => {
+                return Err("--tls-server-name requires --tls-ca".to_string())
+            }
+            None => (),
+        }
+
+        Ok(client)
+    }
+
+    /// Signs the requests with `hmac_key`, the key shared by the
+    /// nodes, and rejects the responses failing verification.
+    pub fn with_hmac_key(mut self, hmac_key: &str) -> Self {
+        self.authenticator = Some(MessageAuthenticator::new(hmac_key.as_bytes()));
+        self
+    }
+
+    /// Encrypts the connections with TLS, trusting the PEM
+    /// certificates of `ca_path` (`tls-ca` of the nodes, or
+    /// their `tls-cert` if they share a self-signed one) for
+    /// the nodes, issued for `server_name` (`tls-server-name`).
+    pub fn with_tls(mut self, ca_path: &Path, server_name: &str) -> Result<Self, String> {
+        let tls = TlsConfig::load_client(ca_path, server_name)
+            .map_err(|err| format!("cannot load the TLS configuration: {}", err))?;
+
+        self.tls = Some(Arc::new(tls));
+        Ok(self)
+    }
+
+    /// Sends `request_msg` to the node at `remote_addr`,
+    /// and returns its response message, without its HMAC.
+    ///
+    /// The response is empty if the node dropped the
+    /// request (e.g. signed with another key).
+    pub fn send(&self, remote_addr: SocketAddr, request_msg: &str) -> io::Result<String> {
+        let transport = match self.authenticator {
+            Some(ref authenticator) => TcpTransport::with_authenticator(authenticator.clone()),
+            None => TcpTransport::new(),
+        };
+
+        let transport = match self.tls {
+            Some(ref tls) => transport.with_tls(Arc::clone(tls)),
+            None => transport,
+        };
+
+        transport
+            .with_response_timeout(RESPONSE_TIMEOUT)
+            .send(remote_addr, request_msg)
+    }
+}
+
+/// Removes the option `name` and its value from
+/// `args`, and returns the value, if the option is set.
+fn take_option(args: &mut Vec<String>, name: &str) -> Result<Option<String>, String> {
+    let Some(index) = args.iter().position(|arg| arg == name) else {
+        return Ok(None);
+    };
+
+    if index + 1 >= args.len() {
+        return Err(format!("missing value for [{}]", name));
+    }
+
+    let value = args.remove(index + 1);
+    args.remove(index);
+
+    Ok(Some(value))
+}
+
+#[cfg(test)]
+mod client_test {
+    use std::{fs, sync::Arc, time::Duration};
+
+    use crate::{observer::NoopObserver, transport::tls, RunningNode};
+
+    use super::{take_option, Client};
+
+    #[test]
+    fn take_option_test() {
+        let mut args = ["127.0.0.1:8000", "--hmac-key", "ring-key", "members"]
+            .map(String::from)
+            .to_vec();
+
+        assert_eq!(
+            take_option(&mut args, "--hmac-key"),
+            Ok(Some("ring-key".to_string()))
+        );
+        assert_eq!(args, ["127.0.0.1:8000", "members"]);
+        assert_eq!(take_option(&mut args, "--hmac-key"), Ok(None));
+
+        let mut args = ["127.0.0.1:8000", "--hmac-key"].map(String::from).to_vec();
+
+        assert_eq!(
+            take_option(&mut args, "--hmac-key"),
+            Err("missing value for [--hmac-key]".to_string())
+        );
+        assert!(Client::from_args(&mut args).is_err());
+    }
+
+    #[test]
+    fn signed_update_is_accepted_test() {
+        let node = RunningNode::init_for_test(&["hmac-key=ring-key"], Arc::new(NoopObserver));
+        let public_addr = node.self_node().get_public_addr();
+
+        let client = Client::new().with_hmac_key("ring-key");
+
+        assert_eq!(
+            client.send(public_addr, "UPDATE_DATA=[signed];").unwrap(),
+            "RESPONSE=IGNORE;"
+        );
+        assert!(client
+            .send(public_addr, "GET_DATA;")
+            .unwrap()
+            .starts_with("RESPONSE=[signed]"));
+
+        // Unsigned, or signed with another key
+        for client in [Client::new(), Client::new().with_hmac_key("other-key")] {
+            assert_eq!(
+                client.send(public_addr, "UPDATE_DATA=[forged];").unwrap(),
+                ""
+            );
+        }
+
+        assert!(client
+            .send(public_addr, "GET_DATA;")
+            .unwrap()
+            .starts_with("RESPONSE=[signed]"));
+
+        node.shutdown(Duration::from_secs(10)).unwrap();
+    }
+
+    #[test]
+    fn tls_client_is_accepted_test() {
+        let (cert_path, key_path) = tls::self_signed_files("chord-client-tls");
+
+        let node = RunningNode::init_for_test(
+            &[
+                &format!("tls-cert={}", cert_path.display()),
+                &format!("tls-key={}", key_path.display()),
+            ],
+            Arc::new(NoopObserver),
+        );
+        let public_addr = node.self_node().get_public_addr();
+
+        let client = Client::new().with_tls(&cert_path, "chord-node").unwrap();
+
+        let _ = fs::remove_file(cert_path);
+        let _ = fs::remove_file(key_path);
+
+        assert_eq!(
+            client
+                .send(public_addr, "UPDATE_DATA=[encrypted];")
+                .unwrap(),
+            "RESPONSE=IGNORE;"
+        );
+
+        // The plaintext request fails the handshake,
+        // answered by a TLS alert at most
+        assert!(Client::new()
+            .send(public_addr, "UPDATE_DATA=[plaintext];")
+            .map_or(true, |response_msg| !response_msg.starts_with("RESPONSE=")));
+
+        assert!(client
+            .send(public_addr, "GET_DATA;")
+            .unwrap()
+            .starts_with("RESPONSE=[encrypted]"));
+
+        node.shutdown(Duration::from_secs(10)).unwrap();
+    }
+}
@@ -0,0 +1,171 @@
+//! Contains the state shared by the
+//! different components of a running node.
+
+use std::{
+    sync::{atomic::AtomicBool, Arc, RwLock},
+    time::Instant,
+};
+
+use crate::{
+    chord::{
+        finger_table::FingerTable, lookup_cache::LookupCache, MembershipPolicy, Node,
+        SUCCESSOR_LIST_LENGTH,
+    },
+    cli::Options,
+    gossip::{
+        clock::GossipClock, idempotency::IdempotencyKeys, journal::Journal,
+        limiter::InFlightLimiter, skew::SkewMonitor, State,
+    },
+    observer::NodeObserver,
+    runtime::shutdown::Shutdown,
+    transport::{auth::MessageAuthenticator, tls::TlsConfig, Transport},
+};
+
+/// State of a running node, shared between the
+/// server, the request handlers and the background threads.
+///
+/// Cloning a `NodeContext` is cheap: the mutable
+/// components are shared behind `Arc`s.
+#[derive(Clone)]
+pub(crate) struct NodeContext {
+    pub self_node: Node,
+    pub self_node_successor_list: Arc<RwLock<[Node; SUCCESSOR_LIST_LENGTH]>>,
+    pub self_node_predecessor: Arc<RwLock<Option<Node>>>,
+    pub self_node_finger_table: Arc<RwLock<FingerTable>>,
+    /// Successor which cannot reach the node back, as
+    /// found by the last reachability audit (`reachability-audit`).
+    pub self_node_asymmetric_successor: Arc<RwLock<Option<Node>>>,
+    /// Set once the node is part of the ring, after
+    /// a successful stabilization pass (see `node_ready`).
+    pub self_node_is_ready: Arc<AtomicBool>,
+    /// Set once the process drains before its removal (`DRAIN`
+    /// request), shared by the virtual nodes. New data updates
+    /// are then rejected.
+    pub is_draining: Arc<AtomicBool>,
+    /// Present if lookups are cached (`lookup-cache-ttl`).
+    pub self_node_lookup_cache: Option<Arc<RwLock<LookupCache>>>,
+    pub self_node_gossip_data: Arc<RwLock<Option<State>>>,
+    /// Timestamps the local gossip updates.
+    pub gossip_clock: Arc<GossipClock>,
+    /// Present if the clocks of the gossip peers
+    /// are checked (`clock-skew-threshold`).
+    pub skew_monitor: Option<Arc<SkewMonitor>>,
+    /// Idempotency keys of the recent client
+    /// updates, shared by the virtual nodes.
+    pub idempotency_keys: Arc<IdempotencyKeys>,
+    /// Present if the gossip state changes
+    /// are journaled (`gossip-journal`).
+    pub gossip_journal: Option<Arc<Journal>>,
+    pub transport: Arc<dyn Transport>,
+    /// Transport of the gossip exchanges, giving up the
+    /// connection attempts after `gossip-connect-timeout`.
+    pub gossip_transport: Arc<dyn Transport>,
+    /// Limits the lookups forwarded at once
+    /// (`max-forwarded-lookups`), shared by the virtual nodes.
+    pub forward_limiter: InFlightLimiter,
+    pub authenticator: Option<MessageAuthenticator>,
+    /// Present if the connections are encrypted (`tls-cert`).
+    pub tls: Option<Arc<TlsConfig>>,
+    pub observer: Arc<dyn NodeObserver>,
+    /// Stops the background threads of the process,
+    /// shared by the virtual nodes.
+    pub shutdown: Shutdown,
+    /// Start of the process, for the uptime of the node.
+    pub started_at: Instant,
+    pub options: Options,
+}
+
+/// Contexts of the virtual nodes run by a process (`vnodes`),
+/// sharing its listener, indexed by virtual node.
+///
+/// Virtual node 0 is the node started first: it serves the
+/// requests without virtual node index. The others are
+/// added once they joined the network through it.
+#[derive(Clone)]
+pub(crate) struct VirtualNodes {
+    contexts: Arc<RwLock<Vec<NodeContext>>>,
+}
+
+impl VirtualNodes {
+    /// Creates the virtual nodes of a process
+    /// whose first node runs in `context`.
+    pub(crate) fn new(context: NodeContext) -> Self {
+        Self {
+            contexts: Arc::new(RwLock::new(vec![context])),
+        }
+    }
+
+    /// Adds the context of the next virtual node.
+    pub(crate) fn push(&self, context: NodeContext) {
+        self.contexts.write().unwrap().push(context);
+    }
+
+    /// Returns the context of virtual node `vnode`,
+    /// if it is already started.
+    pub(crate) fn get(&self, vnode: u16) -> Option<NodeContext> {
+        self.contexts.read().unwrap().get(vnode as usize).cloned()
+    }
+
+    /// Returns the context of virtual node 0.
+    pub(crate) fn first(&self) -> NodeContext {
+        self.contexts.read().unwrap()[0].clone()
+    }
+
+    /// Returns the contexts of all the started virtual nodes.
+    pub(crate) fn all(&self) -> Vec<NodeContext> {
+        self.contexts.read().unwrap().clone()
+    }
+}
+
+impl NodeContext {
+    /// Returns the context of a node alone in its ring, reachable at
+    /// `public_addr`, with the default options and without background
+    /// tasks. Tests override its fields as needed.
+    #[cfg(test)]
+    pub(crate) fn for_test(public_addr: std::net::SocketAddr) -> Self {
+        use crate::{
+            gossip::idempotency::IdempotencyKeys, observer::NoopObserver, transport::TcpTransport,
+        };
+
+        let self_node = Node::new(public_addr);
+        let options = Options::default();
+
+        Self {
+            self_node: self_node.clone(),
+            self_node_successor_list: Arc::new(RwLock::new(std::array::from_fn(|_| {
+                self_node.clone()
+            }))),
+            self_node_predecessor: Arc::new(RwLock::new(None)),
+            self_node_finger_table: Arc::new(RwLock::new(FingerTable::new(
+                self_node.get_ring_bit_length(),
+            ))),
+            self_node_asymmetric_successor: Arc::new(RwLock::new(None)),
+            self_node_is_ready: Arc::new(AtomicBool::new(false)),
+            is_draining: Arc::new(AtomicBool::new(false)),
+            self_node_lookup_cache: None,
+            self_node_gossip_data: Arc::new(RwLock::new(None)),
+            gossip_clock: Arc::new(GossipClock::new(options.timestamp_source)),
+            skew_monitor: None,
+            idempotency_keys: Arc::new(IdempotencyKeys::default()),
+            gossip_journal: None,
+            transport: Arc::new(TcpTransport::new()),
+            gossip_transport: Arc::new(TcpTransport::new()),
+            forward_limiter: InFlightLimiter::new(options.max_forwarded_lookups),
+            authenticator: None,
+            tls: None,
+            observer: Arc::new(NoopObserver),
+            shutdown: Shutdown::default(),
+            started_at: Instant::now(),
+            options,
+        }
+    }
+
+    /// Returns the membership policy
+    /// configured for this node.
+    pub(crate) fn membership_policy(&self) -> MembershipPolicy {
+        match self.options.max_nodes {
+            Some(max_nodes) => MembershipPolicy::MaxNodes(max_nodes),
+            None => MembershipPolicy::Open,
+        }
+    }
+}
@@ -1,14 +1,29 @@
 use std::{
-    io::{Read, Write},
-    net::TcpStream,
-    sync::{Arc, RwLock},
+    io::{self, BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpStream},
+    sync::atomic::Ordering,
+    time::{Duration, Instant},
 };
 
 use crate::{
-    chord::{self, protocol::ChordRequest, Node, SUCCESSOR_LIST_LENGTH},
-    gossip::{self, protocol::GossipRequest, State},
+    chord::{
+        self,
+        protocol::{ChordRequest, ChordResponse, ErrorKind},
+        NodeAddr,
+    },
+    cli::GossipCompression,
+    context::{NodeContext, VirtualNodes},
+    gossip::{self, journal::JournalSource, protocol::GossipRequest, skew},
+    transport::{framing, tls::Stream},
 };
 
+/// Time given to a rejected connection to complete its request.
+const REJECTION_READ_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// Time given to the successors of a draining
+/// node to accept its gossip state.
+const DRAIN_HANDOFF_TIMEOUT: Duration = Duration::from_secs(10);
+
 enum Request {
     ChordRequest(ChordRequest),
     GossipRequest(GossipRequest),
@@ -28,80 +43,1146 @@ impl Request {
     }
 }
 
+/// Listener a connection was accepted on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Listener {
+    /// The listener of `self-port`. It serves every request,
+    /// except the client data updates if `data-port` is set.
+    Main,
+    /// The listener of `data-port`, serving the gossip requests only.
+    Data,
+}
+
+impl Listener {
+    /// Returns the error response to `request`, if it is not served
+    /// on this listener, the data listener being at `data_port`.
+    fn reject(self, request: &Request, data_port: Option<u16>) -> Option<ChordResponse> {
+        match (self, request, data_port) {
+            (Self::Data, Request::ChordRequest(_), _) => Some(ChordResponse::Error(
+                ErrorKind::Fatal,
+                "chord requests are not served on the data port".to_string(),
+            )),
+            (
+                Self::Main,
+                Request::GossipRequest(
+                    GossipRequest::UpdateData(..) | GossipRequest::UpdateDataWithAck(..),
+                ),
+                Some(data_port),
+            ) => Some(ChordResponse::Error(
+                ErrorKind::Fatal,
+                format!("data updates are served on the data port {}", data_port),
+            )),
+            _ => None,
+        }
+    }
+}
+
+/// Outcome of the processing of a single request.
+enum RequestOutcome {
+    /// Sends back the response message.
+    Respond(String),
+    /// Drops the request without response.
+    Drop,
+    /// Sends back the response message,
+    /// then leaves the network.
+    Shutdown(String),
+    /// Sends back the response message, then hands
+    /// off the gossip state and leaves the network.
+    Drain(String),
+}
+
+/// Builds the task handling the requests received on `stream`,
+/// accepted on `listener`, each of them by the virtual node
+/// it is addressed to.
+///
+/// A connection carries either a single unframed request, read until
+/// the peer shuts down its write half, or a sequence of framed requests,
+/// answered in order until the peer closes the connection.
+///
+/// The connection is dropped, freeing the worker thread, if the
+/// peer does not complete its request within the configured read timeout.
+///
+/// With TLS, a connection failing the handshake (e.g. a plaintext
+/// one) is logged and dropped.
 pub(crate) fn build_request_handler(
-    mut stream: TcpStream,
-    self_node: Node,
-    self_node_successor_list: Arc<RwLock<[Node; SUCCESSOR_LIST_LENGTH]>>,
-    self_node_predecessor: Arc<RwLock<Option<Node>>>,
-    self_node_gossip_data: Arc<RwLock<Option<State>>>,
+    stream: TcpStream,
+    virtual_nodes: VirtualNodes,
+    listener: Listener,
 ) -> impl FnOnce() + Send + 'static {
     move || {
-        let mut request_msg = String::new();
+        // The virtual nodes share the options, the
+        // authenticator and the TLS configuration
+        let context = virtual_nodes.first();
 
-        if stream.read_to_string(&mut request_msg).is_err() {
+        if stream
+            .set_read_timeout(Some(context.options.read_timeout))
+            .is_err()
+        {
             return;
         }
 
-        let request = if let Some(request) = Request::parse(&request_msg) {
-            request
-        } else {
-            return;
+        let peer_addr = stream.peer_addr();
+
+        let stream = match Stream::accept(stream, context.tls.as_deref()) {
+            Ok(stream) => stream,
+            // Closed without a handshake (e.g. by a port probe)
+            Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return,
+            Err(err) => {
+                eprintln!(
+                    "rejected connection from [{}]: TLS handshake failed: {}",
+                    peer_addr
+                        .map(|addr| addr.to_string())
+                        .unwrap_or("unknown".to_string()),
+                    err
+                );
+                return;
+            }
         };
 
-        match request {
-            Request::ChordRequest(chord_request) => {
-                let self_node_successor_list = self_node_successor_list.read().unwrap().clone();
-
-                let response = match chord_request {
-                    ChordRequest::FindSuccessorOfNode(target_node) => {
-                        chord::request_handler::find_successor_of_node_request_handler(
-                            self_node,
-                            self_node_successor_list,
-                            target_node,
-                        )
-                    }
-                    ChordRequest::GetSuccessorList => {
-                        chord::request_handler::get_successor_list_request_handler(
-                            self_node_successor_list,
-                        )
+        let mut reader = BufReader::new(stream);
+
+        let first_byte = match reader.fill_buf() {
+            Ok([first_byte, ..]) => *first_byte,
+            // Closed without a request (e.g. by a port probe),
+            // which is benign and not logged, or failed
+            _ => return,
+        };
+
+        if !first_byte.is_ascii_digit() {
+            let mut request_bytes = Vec::new();
+
+            if reader.read_to_end(&mut request_bytes).is_err() {
+                return;
+            }
+
+            let stream = reader.get_mut();
+
+            match process_request(request_bytes, &virtual_nodes, listener, stream) {
+                RequestOutcome::Respond(response_msg) => {
+                    write_response(stream, &context, &response_msg, false);
+                }
+                RequestOutcome::Drop => (),
+                RequestOutcome::Shutdown(response_msg) => {
+                    write_response(stream, &context, &response_msg, false);
+                    leave_network(&virtual_nodes);
+                }
+                RequestOutcome::Drain(response_msg) => {
+                    write_response(stream, &context, &response_msg, false);
+                    drain_network(&virtual_nodes);
+                }
+            }
+
+            return;
+        }
+
+        loop {
+            let request_bytes = match framing::read_frame(&mut reader) {
+                Ok(Some(request_bytes)) => request_bytes,
+                _ => return,
+            };
+
+            let stream = reader.get_mut();
+
+            match process_request(request_bytes, &virtual_nodes, listener, stream) {
+                RequestOutcome::Respond(response_msg) => {
+                    if !write_response(stream, &context, &response_msg, true) {
+                        return;
                     }
-                    ChordRequest::GetPredecessor => {
-                        let self_node_predecessor = self_node_predecessor.read().unwrap().clone();
-                        chord::request_handler::get_predecessor_request_handler(
-                            self_node_predecessor,
-                        )
+                }
+                RequestOutcome::Drop => return,
+                RequestOutcome::Shutdown(response_msg) => {
+                    write_response(stream, &context, &response_msg, true);
+                    leave_network(&virtual_nodes);
+                    return;
+                }
+                RequestOutcome::Drain(response_msg) => {
+                    write_response(stream, &context, &response_msg, true);
+                    drain_network(&virtual_nodes);
+                    return;
+                }
+            }
+        }
+    }
+}
+
+/// Rejects the connection `stream`, accepted while the node
+/// already serves its maximum number of connections.
+///
+/// The pending request is discarded, for at most `REJECTION_READ_TIMEOUT`,
+/// before the error response is written. Closing the connection with
+/// unread data would reset it before the peer gets the response.
+pub(crate) fn reject_connection(stream: TcpStream, context: &NodeContext) {
+    if stream
+        .set_read_timeout(Some(REJECTION_READ_TIMEOUT))
+        .is_err()
+    {
+        return;
+    }
+
+    let Ok(mut stream) = Stream::accept(stream, context.tls.as_deref()) else {
+        return;
+    };
+
+    let _ = io::copy(
+        &mut (&mut stream).take(framing::MAX_FRAME_LENGTH as u64),
+        &mut io::sink(),
+    );
+
+    let response = ChordResponse::Error(ErrorKind::Retryable, "too many connections".to_string());
+    write_response(&mut stream, context, &response.to_protocol_text(), false);
+
+    let _ = stream.shutdown_write();
+}
+
+/// Authenticates, parses and handles a single request
+/// received on `stream`, accepted on `listener`, by the
+/// virtual node it is addressed to.
+///
+/// An empty request is dropped silently, while a request
+/// which cannot be parsed is logged, and answered with
+/// an error response.
+fn process_request(
+    request_bytes: Vec<u8>,
+    virtual_nodes: &VirtualNodes,
+    listener: Listener,
+    stream: &Stream,
+) -> RequestOutcome {
+    let context = &virtual_nodes.first();
+
+    // Empty request, benign as an empty connection
+    if request_bytes.is_empty() {
+        return RequestOutcome::Drop;
+    }
+
+    let request_msg = match (String::from_utf8(request_bytes), &context.authenticator) {
+        (Ok(request_msg), _) => request_msg,
+        (Err(_), None) => {
+            let response = ChordResponse::Error(ErrorKind::Fatal, "malformed encoding".to_string());
+            return RequestOutcome::Respond(response.to_protocol_text());
+        }
+        (Err(_), Some(_)) => {
+            log_unauthenticated_request(stream);
+            return RequestOutcome::Drop;
+        }
+    };
+
+    let request_msg = match context.authenticator {
+        Some(ref authenticator) => match authenticator.verify(&request_msg) {
+            Some(request_msg) => request_msg,
+            None => {
+                log_unauthenticated_request(stream);
+                return RequestOutcome::Drop;
+            }
+        },
+        None => &request_msg,
+    };
+
+    // Wall time of the initiator of a gossip exchange, which
+    // expects the node's own in the response (see `gossip::skew`)
+    let (initiator_clock, request_msg) = skew::split_clock(request_msg);
+
+    let request = NodeAddr::split_request(request_msg)
+        .and_then(|(vnode, request_msg)| Some((vnode, Request::parse(request_msg)?)));
+
+    let (vnode, request) = match request {
+        Some(addressed_request) => addressed_request,
+        None => {
+            log_malformed_request(stream);
+            let response = ChordResponse::Error(ErrorKind::Fatal, "malformed request".to_string());
+            return RequestOutcome::Respond(response.to_protocol_text());
+        }
+    };
+
+    if let Some(response) = listener.reject(&request, context.options.data_port) {
+        return RequestOutcome::Respond(response.to_protocol_text());
+    }
+
+    // Updates accepted now would not be handed off
+    if context.is_draining.load(Ordering::SeqCst)
+        && matches!(
+            request,
+            Request::GossipRequest(
+                GossipRequest::UpdateData(..) | GossipRequest::UpdateDataWithAck(..)
+            )
+        )
+    {
+        let response = ChordResponse::Error(ErrorKind::Retryable, "node is draining".to_string());
+        return RequestOutcome::Respond(response.to_protocol_text());
+    }
+
+    // Virtual nodes not started yet are
+    // reached by the next attempt
+    let context = match virtual_nodes.get(vnode) {
+        Some(context) => context,
+        None => {
+            let response = ChordResponse::Error(
+                ErrorKind::Retryable,
+                format!("virtual node {} is not started", vnode),
+            );
+            return RequestOutcome::Respond(response.to_protocol_text());
+        }
+    };
+
+    // Positions on rings of different bit lengths are not comparable
+    if let Request::ChordRequest(ref chord_request) = request {
+        let ring_bit_length = context.self_node.get_ring_bit_length();
+
+        if let Some(node) = chord_request
+            .get_carried_node()
+            .filter(|node| node.get_ring_bit_length() != ring_bit_length)
+        {
+            let response = ChordResponse::Error(
+                ErrorKind::Fatal,
+                format!(
+                    "node on a ring of {} bits, expected {}",
+                    node.get_ring_bit_length(),
+                    ring_bit_length
+                ),
+            );
+            return RequestOutcome::Respond(response.to_protocol_text());
+        }
+    }
+
+    let NodeContext {
+        self_node,
+        self_node_successor_list,
+        self_node_predecessor,
+        self_node_finger_table,
+        self_node_asymmetric_successor: _,
+        self_node_is_ready: _,
+        is_draining,
+        self_node_lookup_cache: _,
+        self_node_gossip_data,
+        gossip_clock,
+        skew_monitor: _,
+        idempotency_keys,
+        gossip_journal,
+        transport,
+        gossip_transport: _,
+        forward_limiter,
+        authenticator: _,
+        tls: _,
+        observer,
+        shutdown: _,
+        started_at,
+        options,
+    } = context.clone();
+
+    match request {
+        Request::ChordRequest(chord_request) => {
+            let self_node_successor_list_value = self_node_successor_list.read().unwrap().clone();
+
+            let response = match chord_request {
+                ChordRequest::FindSuccessorOfNode(target_node, trace_id) => {
+                    chord::request_handler::find_successor_of_node_request_handler(
+                        &context,
+                        target_node,
+                        trace_id,
+                    )
+                }
+                ChordRequest::FindSuccessorOfKey(key, trace_id) => {
+                    chord::request_handler::find_successor_of_key_request_handler(
+                        transport.as_ref(),
+                        self_node,
+                        self_node_successor_list_value,
+                        key,
+                        trace_id,
+                        options.routing_mode,
+                        Some(&forward_limiter),
+                    )
+                }
+                ChordRequest::GetSuccessorList => {
+                    chord::request_handler::get_successor_list_request_handler(
+                        self_node_successor_list_value,
+                    )
+                }
+                ChordRequest::GetPredecessor => {
+                    let self_node_predecessor = self_node_predecessor.read().unwrap().clone();
+                    chord::request_handler::get_predecessor_request_handler(self_node_predecessor)
+                }
+                ChordRequest::GetNeighborhood => {
+                    chord::request_handler::get_neighborhood_request_handler(
+                        &self_node_predecessor,
+                        &self_node_successor_list,
+                    )
+                }
+                ChordRequest::CheckNode => chord::request_handler::check_node_request_handler(),
+                ChordRequest::ProbeBack(requester_addr) => {
+                    chord::request_handler::probe_back_request_handler(
+                        transport.as_ref(),
+                        requester_addr,
+                        stream.peer_addr().ok().map(|addr| addr.ip()),
+                    )
+                }
+                ChordRequest::NotificationBy(external_node) => {
+                    chord::request_handler::node_notification_request_handler(
+                        self_node,
+                        self_node_predecessor,
+                        self_node_successor_list_value,
+                        external_node,
+                        options.identity_mode,
+                        observer.as_ref(),
+                    )
+                }
+                ChordRequest::Stabilize => chord::request_handler::stabilize_request_handler(
+                    transport.as_ref(),
+                    self_node,
+                    self_node_predecessor,
+                    self_node_successor_list,
+                    options.address_family,
+                    observer.as_ref(),
+                ),
+                ChordRequest::Leaving(leaving_node) => {
+                    chord::request_handler::leaving_node_request_handler(
+                        self_node,
+                        self_node_predecessor,
+                        self_node_successor_list,
+                        leaving_node,
+                        observer.as_ref(),
+                    )
+                }
+                ChordRequest::GetOwnedRange => {
+                    let self_node_predecessor = self_node_predecessor.read().unwrap().clone();
+                    chord::request_handler::get_owned_range_request_handler(
+                        &self_node,
+                        self_node_predecessor,
+                    )
+                }
+                ChordRequest::GetNodeInfo => {
+                    chord::request_handler::get_node_info_request_handler(&self_node, started_at)
+                }
+                ChordRequest::GetFingerTable => {
+                    chord::request_handler::get_finger_table_request_handler(
+                        &self_node_finger_table,
+                    )
+                }
+                ChordRequest::Shutdown(received_secret) => {
+                    let response = chord::request_handler::shutdown_request_handler(
+                        options.admin_secret.as_deref(),
+                        &received_secret,
+                    );
+
+                    if response == ChordResponse::Acknowledged {
+                        return RequestOutcome::Shutdown(response.to_protocol_text());
                     }
-                    ChordRequest::CheckNode => chord::request_handler::check_node_request_handler(),
-                    ChordRequest::NotificationBy(external_node) => {
-                        chord::request_handler::node_notification_request_handler(
-                            self_node,
-                            self_node_predecessor,
-                            self_node_successor_list,
-                            external_node,
-                        )
+
+                    eprintln!(
+                        "rejected shutdown request from [{}]: invalid secret",
+                        stream
+                            .peer_addr()
+                            .map(|addr| addr.to_string())
+                            .unwrap_or("unknown".to_string())
+                    );
+
+                    response
+                }
+                ChordRequest::Drain(received_secret) => {
+                    let response = chord::request_handler::drain_request_handler(
+                        options.admin_secret.as_deref(),
+                        &received_secret,
+                        &is_draining,
+                    );
+
+                    if response == ChordResponse::Acknowledged {
+                        return RequestOutcome::Drain(response.to_protocol_text());
                     }
-                };
 
-                let _ = stream.write(response.to_protocol_text().as_bytes());
-            }
-            Request::GossipRequest(gossip_request) => {
-                let response = match gossip_request {
-                    GossipRequest::UpdateData(received_data) => {
-                        gossip::request_handler::update_data_request_handler(
-                            self_node_gossip_data,
-                            received_data,
-                        )
+                    if let ChordResponse::Error(_, ref err) = response {
+                        eprintln!(
+                            "rejected drain request from [{}]: {}",
+                            stream
+                                .peer_addr()
+                                .map(|addr| addr.to_string())
+                                .unwrap_or("unknown".to_string()),
+                            err
+                        );
                     }
-                    GossipRequest::ShareData(received_data) => {
-                        gossip::request_handler::share_data_request_handler(
-                            self_node_gossip_data,
-                            received_data,
-                        )
+
+                    response
+                }
+                ChordRequest::SetPredecessor(received_secret, predecessor) => {
+                    let response = chord::request_handler::set_predecessor_request_handler(
+                        options.admin_secret.as_deref(),
+                        &received_secret,
+                        self_node,
+                        self_node_predecessor,
+                        predecessor.clone(),
+                    );
+
+                    let peer_addr = stream
+                        .peer_addr()
+                        .map(|addr| addr.to_string())
+                        .unwrap_or("unknown".to_string());
+
+                    match response {
+                        ChordResponse::Acknowledged => eprintln!(
+                            "predecessor force-set to [{:?}] by [{}]",
+                            predecessor.get_public_addr(),
+                            peer_addr
+                        ),
+                        ChordResponse::Error(_, ref err) => eprintln!(
+                            "rejected set-predecessor request from [{}]: {}",
+                            peer_addr, err
+                        ),
+                        _ => (),
                     }
-                };
 
-                let _ = stream.write(response.to_protocol_text().as_bytes());
+                    response
+                }
+            };
+
+            RequestOutcome::Respond(response.to_protocol_text())
+        }
+        Request::GossipRequest(gossip_request) => {
+            // Read by tools, which do not decompress data
+            let compression = match gossip_request {
+                GossipRequest::GetData => GossipCompression::None,
+                _ => options.gossip_compression,
+            };
+
+            let response = match gossip_request {
+                GossipRequest::UpdateData(received_data, ttl, key) => {
+                    gossip::request_handler::update_data_request_handler(
+                        self_node_gossip_data,
+                        &gossip_clock,
+                        received_data,
+                        ttl,
+                        key.as_deref().map(|key| (idempotency_keys.as_ref(), key)),
+                        gossip_journal.as_deref(),
+                        observer.as_ref(),
+                    )
+                }
+                GossipRequest::UpdateDataWithAck(received_data, required_acks, ttl, key) => {
+                    gossip::request_handler::update_data_with_ack_request_handler(
+                        &context,
+                        received_data,
+                        required_acks,
+                        ttl,
+                        key.as_deref(),
+                    )
+                }
+                GossipRequest::ShareData(received_data) => {
+                    Ok(gossip::request_handler::share_data_request_handler(
+                        self_node_gossip_data,
+                        received_data,
+                        gossip_journal
+                            .as_deref()
+                            .map(|journal| (journal, JournalSource::Peer(stream.peer_addr().ok()))),
+                        gossip_clock.now(),
+                        observer.as_ref(),
+                    ))
+                }
+                GossipRequest::GetData => Ok(gossip::request_handler::get_data_request_handler(
+                    self_node_gossip_data,
+                    gossip_clock.now(),
+                )),
+            };
+
+            // An idempotency key reused for a different update
+            let response = match response {
+                Ok(response) => response,
+                Err(err) => {
+                    let response = ChordResponse::Error(ErrorKind::Fatal, err);
+                    return RequestOutcome::Respond(response.to_protocol_text());
+                }
+            };
+
+            let response_msg = response.to_protocol_text(compression);
+
+            match initiator_clock {
+                Some(_) => {
+                    RequestOutcome::Respond(skew::with_clock(&response_msg, gossip_clock.now()))
+                }
+                None => RequestOutcome::Respond(response_msg),
+            }
+        }
+    }
+}
+
+/// Leaves the network gracefully, with every virtual node,
+/// then requests the shutdown of the node: its listeners
+/// return, and the threads are joined by whoever started it
+/// (the `node` binary exits once they are).
+pub(crate) fn leave_network(virtual_nodes: &VirtualNodes) {
+    println!("node is leaving the network");
+
+    for context in virtual_nodes.all() {
+        chord::leave(
+            context.transport.as_ref(),
+            &context.self_node,
+            &context.self_node_predecessor,
+            &context.self_node_successor_list,
+        );
+    }
+
+    virtual_nodes.first().shutdown.request();
+}
+
+/// Drains the node before its removal, then leaves the network
+/// gracefully. New data updates are already rejected, while
+/// lookups are answered until the node leaves.
+fn drain_network(virtual_nodes: &VirtualNodes) {
+    println!("node is draining");
+
+    match hand_off_gossip_state(&virtual_nodes.first()) {
+        Some(0) => eprintln!(
+            "no successor accepted the gossip state (unreachable, or holding a fresher one)"
+        ),
+        Some(_) => println!("gossip state handed off to the successor"),
+        None => (),
+    }
+
+    leave_network(virtual_nodes);
+}
+
+/// Pushes the live gossip state of the node running in `context`
+/// to its successors, one at a time, until one of them accepts
+/// it, so that the latest update is not lost if the node was
+/// its only holder.
+///
+/// Returns the number of successors which accepted the
+/// state, or `None` if the node holds no live state.
+fn hand_off_gossip_state(context: &NodeContext) -> Option<usize> {
+    let state = gossip::live_state(&context.self_node_gossip_data, context.gossip_clock.now())?;
+    let self_addr = context.self_node.get_public_addr();
+
+    let mut successors: Vec<SocketAddr> = Vec::new();
+
+    for successor in context.self_node_successor_list.read().unwrap().iter() {
+        // The other virtual nodes are leaving as well
+        if successor.get_public_addr() != self_addr
+            && !successors.contains(&successor.get_public_addr())
+        {
+            successors.push(successor.get_public_addr());
+        }
+    }
+
+    Some(gossip::request_initiator::push_until_acknowledged(
+        context.gossip_transport.as_ref(),
+        &state,
+        &successors,
+        1,
+        Instant::now() + DRAIN_HANDOFF_TIMEOUT,
+        context.options.gossip_compression,
+    ))
+}
+
+/// Writes `response_msg` on `stream`, signed with the
+/// node's authenticator if any, and framed if `is_framed`.
+///
+/// Returns `false` if the response could not be written.
+fn write_response(
+    stream: &mut Stream,
+    context: &NodeContext,
+    response_msg: &str,
+    is_framed: bool,
+) -> bool {
+    let response_msg = match context.authenticator {
+        Some(ref authenticator) => authenticator.sign(response_msg),
+        None => response_msg.to_string(),
+    };
+
+    if is_framed {
+        framing::write_frame(stream, response_msg.as_bytes()).is_ok()
+    } else {
+        stream.write_all(response_msg.as_bytes()).is_ok()
+    }
+}
+
+fn log_unauthenticated_request(stream: &Stream) {
+    eprintln!(
+        "dropped unauthenticated request from [{}]",
+        stream
+            .peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or("unknown".to_string())
+    );
+}
+
+fn log_malformed_request(stream: &Stream) {
+    eprintln!(
+        "rejected malformed request from [{}]",
+        stream
+            .peer_addr()
+            .map(|addr| addr.to_string())
+            .unwrap_or("unknown".to_string())
+    );
+}
+
+#[cfg(test)]
+mod build_request_handler_test {
+    use std::{
+        io::{BufReader, Read, Write},
+        net::{Shutdown, SocketAddr, TcpListener, TcpStream},
+        sync::{atomic::Ordering, mpsc, Arc},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        chord::{
+            protocol::{ChordResponse, ErrorKind},
+            Node,
+        },
+        cli::{GossipCompression, Options},
+        context::{NodeContext, VirtualNodes},
+        gossip::{
+            protocol::{GossipRequest, GossipResponse},
+            skew, State,
+        },
+        transport::{
+            auth::MessageAuthenticator, framing, mock::MockTransport, tls, TcpTransport, Transport,
+        },
+    };
+
+    use super::{build_request_handler, hand_off_gossip_state, Listener};
+
+    /// Builds a request handler for a single-node
+    /// ring, serving the connection `stream`.
+    fn single_node_request_handler(
+        stream: TcpStream,
+        read_timeout: Duration,
+        authenticator: Option<MessageAuthenticator>,
+    ) -> impl FnOnce() + Send + 'static {
+        let context = single_node_context(read_timeout, authenticator);
+
+        build_request_handler(stream, VirtualNodes::new(context), Listener::Main)
+    }
+
+    /// Builds the context of a single-node ring.
+    fn single_node_context(
+        read_timeout: Duration,
+        authenticator: Option<MessageAuthenticator>,
+    ) -> NodeContext {
+        NodeContext {
+            authenticator,
+            options: Options {
+                read_timeout,
+                ..Options::default()
+            },
+            ..NodeContext::for_test("127.0.0.1:8000".parse::<SocketAddr>().unwrap())
+        }
+    }
+
+    #[test]
+    fn idle_connection_is_dropped_after_read_timeout_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let listener_addr = listener.local_addr().unwrap();
+
+        // Slow-loris client: sends a partial request,
+        // then stalls without closing the connection.
+        let mut idle_client = TcpStream::connect(listener_addr).unwrap();
+        idle_client.write_all(b"GET_SUCC").unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        let request_handler = single_node_request_handler(stream, Duration::from_millis(200), None);
+
+        let (done_sender, done_receiver) = mpsc::channel();
+        let started_at = Instant::now();
+
+        thread::spawn(move || {
+            request_handler();
+            done_sender.send(()).unwrap();
+        });
+
+        // The worker is reclaimed shortly after the timeout.
+        done_receiver.recv_timeout(Duration::from_secs(2)).unwrap();
+        assert!(started_at.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn malformed_encoding_gets_error_response_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        client.write_all(b"CHECK_NODE\xff\xfe;").unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        single_node_request_handler(stream, Duration::from_secs(1), None)();
+
+        let mut response_msg = String::new();
+        client.read_to_string(&mut response_msg).unwrap();
+
+        assert_eq!(response_msg, "ERROR=[FATAL][malformed encoding];");
+        assert_eq!(
+            ChordResponse::parse(&response_msg).unwrap(),
+            ChordResponse::Error(ErrorKind::Fatal, "malformed encoding".to_string())
+        );
+    }
+
+    #[test]
+    fn node_of_another_ring_bit_length_is_rejected_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        // An identifier of 32 bits, sent to a node of 128 bits
+        client
+            .write_all(b"NOTIFICATION_BY=[f41fec42][127.0.0.1:8001];")
+            .unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        single_node_request_handler(stream, Duration::from_secs(1), None)();
+
+        let mut response_msg = String::new();
+        client.read_to_string(&mut response_msg).unwrap();
+
+        assert_eq!(
+            ChordResponse::parse(&response_msg).unwrap(),
+            ChordResponse::Error(
+                ErrorKind::Fatal,
+                "node on a ring of 32 bits, expected 128".to_string()
+            )
+        );
+    }
+
+    #[test]
+    fn tls_connection_is_served_and_plaintext_rejected_test() {
+        let tls_config = Arc::new(tls::self_signed_config("chord-handler-tls"));
+
+        let mut context = single_node_context(Duration::from_secs(1), None);
+        context.tls = Some(Arc::clone(&tls_config));
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let listener_addr = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().unwrap();
+                build_request_handler(stream, VirtualNodes::new(context.clone()), Listener::Main)();
+            }
+        });
+
+        let transport = TcpTransport::new().with_tls(tls_config);
+        assert_eq!(
+            transport.send(listener_addr, "GET_PREDECESSOR;").unwrap(),
+            "PREDECESSOR=NONE;"
+        );
+
+        let mut plaintext_client = TcpStream::connect(listener_addr).unwrap();
+        plaintext_client.write_all(b"GET_PREDECESSOR;").unwrap();
+        plaintext_client.shutdown(Shutdown::Write).unwrap();
+
+        // At most a TLS alert, never a response
+        let mut response_bytes = Vec::new();
+        let _ = plaintext_client.read_to_end(&mut response_bytes);
+        assert!(!response_bytes.starts_with(b"PREDECESSOR"));
+
+        server.join().unwrap();
+    }
+
+    #[test]
+    fn draining_node_rejects_data_updates_test() {
+        let context = single_node_context(Duration::from_secs(1), None);
+        context.is_draining.store(true, Ordering::SeqCst);
+        let self_node_gossip_data = Arc::clone(&context.self_node_gossip_data);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        client.write_all(b"UPDATE_DATA=[late write];").unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        build_request_handler(stream, VirtualNodes::new(context), Listener::Main)();
+
+        let mut response_msg = String::new();
+        client.read_to_string(&mut response_msg).unwrap();
+
+        assert_eq!(response_msg, "ERROR=[RETRYABLE][node is draining];");
+        assert_eq!(*self_node_gossip_data.read().unwrap(), None);
+    }
+
+    #[test]
+    fn clocked_gossip_exchange_gets_clocked_response_test() {
+        let context = single_node_context(Duration::from_secs(1), None);
+        let virtual_nodes = VirtualNodes::new(context);
+
+        let request = |request_msg: &str| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+            client.write_all(request_msg.as_bytes()).unwrap();
+            client.shutdown(Shutdown::Write).unwrap();
+
+            let (stream, _) = listener.accept().unwrap();
+            build_request_handler(stream, virtual_nodes.clone(), Listener::Main)();
+
+            let mut response_msg = String::new();
+            client.read_to_string(&mut response_msg).unwrap();
+
+            response_msg
+        };
+
+        let clocked_response_msg = request("CLOCK=[1000];SHARE_DATA=NONE;");
+        let (node_clock, response_msg) = skew::split_clock(&clocked_response_msg);
+        assert!(node_clock.is_some_and(|node_clock| node_clock > 1000));
+        assert_eq!(response_msg, "RESPONSE=IGNORE;");
+
+        // Older nodes get the response they expect
+        assert_eq!(request("SHARE_DATA=NONE;"), "RESPONSE=IGNORE;");
+    }
+
+    #[test]
+    fn gossip_state_is_handed_off_to_a_successor_test() {
+        let unreachable_successor = Node::new("127.0.0.1:8001".parse::<SocketAddr>().unwrap());
+        let successor = Node::new("127.0.0.1:8002".parse::<SocketAddr>().unwrap());
+        let successor_addr = successor.get_public_addr();
+
+        let transport = Arc::new(MockTransport::new(move |remote_addr, _| {
+            if remote_addr == successor_addr {
+                Ok(GossipResponse::Ignore.to_protocol_text(GossipCompression::None))
+            } else {
+                Err(std::io::Error::from(std::io::ErrorKind::ConnectionRefused))
             }
+        }));
+
+        let mut context = single_node_context(Duration::from_secs(1), None);
+        context.gossip_transport = transport.clone();
+
+        // Nothing to hand off
+        assert_eq!(hand_off_gossip_state(&context), None);
+
+        let state = State {
+            data: "latest update".to_string(),
+            timestamp: crate::gossip::now(),
+            ttl: None,
+        };
+        *context.self_node_gossip_data.write().unwrap() = Some(state.clone());
+        *context.self_node_successor_list.write().unwrap() = [
+            unreachable_successor.clone(),
+            successor.clone(),
+            context.self_node.clone(),
+            context.self_node.clone(),
+            context.self_node.clone(),
+        ];
+
+        assert_eq!(hand_off_gossip_state(&context), Some(1));
+
+        let sent_requests = transport.sent_requests();
+        assert_eq!(sent_requests.len(), 2);
+        assert_eq!(sent_requests[0].0, unreachable_successor.get_public_addr());
+        assert_eq!(sent_requests[1].0, successor_addr);
+        assert_eq!(
+            GossipRequest::parse(&sent_requests[1].1).unwrap(),
+            GossipRequest::ShareData(Some(state))
+        );
+    }
+
+    #[test]
+    fn empty_connection_is_ignored_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        single_node_request_handler(stream, Duration::from_secs(1), None)();
+
+        let mut response_msg = String::new();
+        client.read_to_string(&mut response_msg).unwrap();
+
+        assert_eq!(response_msg, "");
+    }
+
+    #[test]
+    fn oversized_timestamp_gets_error_response_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let request_msg = format!("SHARE_DATA=[Some data ...][{}];", "9".repeat(50));
+        client.write_all(request_msg.as_bytes()).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let context = single_node_context(Duration::from_secs(1), None);
+        let self_node_gossip_data = Arc::clone(&context.self_node_gossip_data);
+
+        let (stream, _) = listener.accept().unwrap();
+        build_request_handler(stream, VirtualNodes::new(context), Listener::Main)();
+
+        let mut response_msg = String::new();
+        client.read_to_string(&mut response_msg).unwrap();
+
+        assert_eq!(response_msg, "ERROR=[FATAL][malformed request];");
+        assert_eq!(*self_node_gossip_data.read().unwrap(), None);
+    }
+
+    /// Sends `request_msg` to a single-node ring
+    /// using `authenticator`, and returns the raw response.
+    fn request_authenticated_node(
+        request_msg: &str,
+        authenticator: MessageAuthenticator,
+    ) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        client.write_all(request_msg.as_bytes()).unwrap();
+        client.shutdown(Shutdown::Write).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        single_node_request_handler(stream, Duration::from_secs(1), Some(authenticator))();
+
+        let mut response_msg = String::new();
+        client.read_to_string(&mut response_msg).unwrap();
+
+        response_msg
+    }
+
+    #[test]
+    fn authenticated_request_gets_signed_response_test() {
+        let authenticator = MessageAuthenticator::new(b"shared-key");
+
+        let response_msg =
+            request_authenticated_node(&authenticator.sign("CHECK_NODE;"), authenticator.clone());
+
+        assert_eq!(authenticator.verify(&response_msg), Some("ACTIVE;"));
+    }
+
+    #[test]
+    fn unauthenticated_request_is_dropped_test() {
+        let authenticator = MessageAuthenticator::new(b"shared-key");
+
+        // Unsigned request
+        assert_eq!(
+            request_authenticated_node("CHECK_NODE;", authenticator.clone()),
+            ""
+        );
+
+        // Request signed with another key
+        let forged_msg = MessageAuthenticator::new(b"other-key").sign("CHECK_NODE;");
+        assert_eq!(request_authenticated_node(&forged_msg, authenticator), "");
+    }
+
+    #[test]
+    fn pipelined_requests_get_responses_in_order_test() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+
+        let (stream, _) = listener.accept().unwrap();
+        let request_handler = single_node_request_handler(stream, Duration::from_secs(1), None);
+        let request_handler = thread::spawn(request_handler);
+
+        for request_msg in [
+            "CHECK_NODE;",
+            "GET_PREDECESSOR;",
+            "GET_SUCCESSOR_LIST;",
+            "GET_NEIGHBORHOOD;",
+        ] {
+            framing::write_frame(&mut client, request_msg.as_bytes()).unwrap();
         }
+
+        let mut reader = BufReader::new(&client);
+        let mut responses = Vec::new();
+
+        for _ in 0..4 {
+            let response_bytes = framing::read_frame(&mut reader).unwrap().unwrap();
+            responses
+                .push(ChordResponse::parse(&String::from_utf8(response_bytes).unwrap()).unwrap());
+        }
+
+        assert_eq!(responses[0], ChordResponse::Active);
+        assert_eq!(responses[1], ChordResponse::Predecessor(None));
+        assert!(matches!(responses[2], ChordResponse::SuccessorList(_)));
+        assert!(matches!(responses[3], ChordResponse::Neighborhood(None, _)));
+
+        // The handler stops once the client closes the connection
+        client.shutdown(Shutdown::Both).unwrap();
+        request_handler.join().unwrap();
+    }
+
+    #[test]
+    fn data_port_isolates_client_updates_test() {
+        let mut context = single_node_context(Duration::from_secs(1), None);
+        context.options.data_port = Some(8100);
+        let virtual_nodes = VirtualNodes::new(context);
+
+        let request = |listener: Listener, request_msg: &str| {
+            let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let mut client = TcpStream::connect(tcp_listener.local_addr().unwrap()).unwrap();
+
+            client.write_all(request_msg.as_bytes()).unwrap();
+            client.shutdown(Shutdown::Write).unwrap();
+
+            let (stream, _) = tcp_listener.accept().unwrap();
+            build_request_handler(stream, virtual_nodes.clone(), listener)();
+
+            let mut response_msg = String::new();
+            client.read_to_string(&mut response_msg).unwrap();
+
+            response_msg
+        };
+
+        // Client updates are served on the data port only
+        assert_eq!(
+            request(Listener::Main, "UPDATE_DATA=[hello];"),
+            "ERROR=[FATAL][data updates are served on the data port 8100];"
+        );
+        assert_eq!(
+            request(Listener::Data, "UPDATE_DATA=[hello];"),
+            "RESPONSE=IGNORE;"
+        );
+
+        // Chord requests, on the main port only
+        assert_eq!(request(Listener::Main, "CHECK_NODE;"), "ACTIVE;");
+        assert_eq!(
+            request(Listener::Data, "CHECK_NODE;"),
+            "ERROR=[FATAL][chord requests are not served on the data port];"
+        );
+
+        // Gossip exchanges between nodes, and reads, on both
+        assert!(request(Listener::Main, "SHARE_DATA=NONE;").starts_with("RESPONSE=[hello]["));
+        assert!(request(Listener::Data, "SHARE_DATA=NONE;").starts_with("RESPONSE=[hello]["));
+        assert!(request(Listener::Main, "GET_DATA;").starts_with("RESPONSE=[hello]["));
+        assert!(request(Listener::Data, "GET_DATA;").starts_with("RESPONSE=[hello]["));
+    }
+
+    #[test]
+    fn retried_update_with_idempotency_key_is_written_once_test() {
+        let context = single_node_context(Duration::from_secs(1), None);
+        let self_node_gossip_data = Arc::clone(&context.self_node_gossip_data);
+        let virtual_nodes = VirtualNodes::new(context);
+
+        let request = |request_msg: &str| {
+            let tcp_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let mut client = TcpStream::connect(tcp_listener.local_addr().unwrap()).unwrap();
+
+            client.write_all(request_msg.as_bytes()).unwrap();
+            client.shutdown(Shutdown::Write).unwrap();
+
+            let (stream, _) = tcp_listener.accept().unwrap();
+            build_request_handler(stream, virtual_nodes.clone(), Listener::Main)();
+
+            let mut response_msg = String::new();
+            client.read_to_string(&mut response_msg).unwrap();
+
+            response_msg
+        };
+
+        let state = || self_node_gossip_data.read().unwrap().clone().unwrap();
+
+        assert_eq!(
+            request("UPDATE_DATA=[hello][KEY=retry-1];"),
+            "RESPONSE=IGNORE;"
+        );
+        let first_state = state();
+
+        // The retry keeps the timestamp of the first attempt
+        thread::sleep(Duration::from_millis(5));
+        assert_eq!(
+            request("UPDATE_DATA=[hello][KEY=retry-1];"),
+            "RESPONSE=IGNORE;"
+        );
+        assert_eq!(state(), first_state);
+
+        assert_eq!(
+            request("UPDATE_DATA=[bye][KEY=retry-1];"),
+            "ERROR=[FATAL][idempotency key `retry-1` already used for a different update];"
+        );
+        assert_eq!(state(), first_state);
+
+        // Without a key, each attempt is a new write
+        assert_eq!(request("UPDATE_DATA=[hello];"), "RESPONSE=IGNORE;");
+        assert!(state().timestamp > first_state.timestamp);
     }
 }
@@ -0,0 +1,172 @@
+//! Timestamps of the local gossip updates.
+//!
+//! Merged states are ordered by timestamp, so a node whose wall
+//! clock is ahead wins over the later updates of the other nodes.
+//! The hybrid clock never issues a timestamp below one it has seen,
+//! which keeps the merge order robust to clock skew and clock steps.
+
+use std::{fmt, sync::Mutex};
+
+use crate::{cli::TimestampSource, gossip};
+
+/// Reads the wall time, in milliseconds since the UNIX epoch.
+type WallClock = dyn Fn() -> u128 + Send + Sync;
+
+/// Clock timestamping the local gossip updates of a node.
+pub(crate) struct GossipClock {
+    source: TimestampSource,
+    /// `gossip::now`, unless a test injects its own wall time.
+    wall_clock: Box<WallClock>,
+    /// Latest timestamp issued by the hybrid clock.
+    latest: Mutex<u128>,
+}
+
+impl fmt::Debug for GossipClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GossipClock")
+            .field("source", &self.source)
+            .field("latest", &self.latest)
+            .finish_non_exhaustive()
+    }
+}
+
+impl GossipClock {
+    pub(crate) fn new(source: TimestampSource) -> Self {
+        Self {
+            source,
+            wall_clock: Box::new(gossip::now),
+            latest: Mutex::new(0),
+        }
+    }
+
+    /// Creates a clock reading the wall time from `wall_clock`
+    /// instead of the system clock, so that tests control
+    /// the timestamps of the updates.
+    #[cfg(test)]
+    pub(crate) fn with_wall_clock<F>(source: TimestampSource, wall_clock: F) -> Self
+    where
+        F: Fn() -> u128 + Send + Sync + 'static,
+    {
+        Self {
+            wall_clock: Box::new(wall_clock),
+            ..Self::new(source)
+        }
+    }
+
+    /// Returns the wall time, in milliseconds since the UNIX epoch.
+    pub(crate) fn now(&self) -> u128 {
+        (self.wall_clock)()
+    }
+
+    /// Returns the timestamp of a local update made now,
+    /// replacing a state timestamped `current`, if any
+    /// (see `timestamp`).
+    pub(crate) fn timestamp_now(&self, current: Option<u128>) -> u128 {
+        self.timestamp(self.now(), current)
+    }
+
+    /// Returns the timestamp of a local update made at `now`
+    /// (wall time, in milliseconds since the UNIX epoch),
+    /// replacing a state timestamped `current`, if any.
+    ///
+    /// The hybrid clock returns `now`, unless it does not follow
+    /// `current` or the latest issued timestamp: the logical part
+    /// then moves the timestamp one millisecond past them.
+    pub(crate) fn timestamp(&self, now: u128, current: Option<u128>) -> u128 {
+        match self.source {
+            TimestampSource::WallClock => now,
+            TimestampSource::Hybrid => {
+                let mut latest = self.latest.lock().unwrap();
+
+                let seen = current.map_or(*latest, |current| current.max(*latest));
+                let timestamp = now.max(seen.saturating_add(1));
+
+                *latest = timestamp;
+                timestamp
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod gossip_clock_test {
+    use std::sync::RwLock;
+
+    use crate::{
+        cli::TimestampSource,
+        gossip::{merge_state, State},
+    };
+
+    use super::GossipClock;
+
+    #[test]
+    fn hybrid_clock_never_goes_backwards_test() {
+        let clock = GossipClock::new(TimestampSource::Hybrid);
+
+        assert_eq!(clock.timestamp(10_000, None), 10_000);
+        // Wall clock stepped back
+        assert_eq!(clock.timestamp(5_000, None), 10_001);
+        assert_eq!(clock.timestamp(5_000, None), 10_002);
+        // Replaced state from a node whose clock is ahead
+        assert_eq!(clock.timestamp(11_000, Some(20_000)), 20_001);
+        // Wall clock caught up
+        assert_eq!(clock.timestamp(30_000, Some(20_001)), 30_000);
+
+        let clock = GossipClock::new(TimestampSource::WallClock);
+
+        assert_eq!(clock.timestamp(10_000, None), 10_000);
+        assert_eq!(clock.timestamp(5_000, Some(20_000)), 5_000);
+    }
+
+    /// Node A, whose clock is 10 seconds ahead, updates the data
+    /// first. Node B receives it, then updates the data a second
+    /// later. Returns the data both nodes hold once they gossiped.
+    fn converged_data_with_skew(source: TimestampSource) -> (String, String) {
+        let now = 1_700_000_000_000;
+        let (clock_a, clock_b) = (GossipClock::new(source), GossipClock::new(source));
+        let (data_a, data_b) = (RwLock::new(None), RwLock::new(None));
+
+        let state_a = State {
+            data: String::from("first"),
+            timestamp: clock_a.timestamp(now + 10_000, None),
+            ttl: None,
+        };
+        *data_a.write().unwrap() = Some(state_a.clone());
+        merge_state(&data_b, state_a, None, now);
+
+        let current_b = data_b.read().unwrap().as_ref().map(|state| state.timestamp);
+        let state_b = State {
+            data: String::from("second"),
+            timestamp: clock_b.timestamp(now + 1_000, current_b),
+            ttl: None,
+        };
+        *data_b.write().unwrap() = Some(state_b.clone());
+
+        // Gossip exchange between A and B
+        merge_state(&data_a, state_b, None, now + 2_000);
+        merge_state(
+            &data_b,
+            data_a.read().unwrap().clone().unwrap(),
+            None,
+            now + 2_000,
+        );
+
+        let data_of = |data: &RwLock<Option<State>>| data.read().unwrap().clone().unwrap().data;
+
+        (data_of(&data_a), data_of(&data_b))
+    }
+
+    #[test]
+    fn skewed_clocks_converge_to_latest_update_test() {
+        assert_eq!(
+            converged_data_with_skew(TimestampSource::Hybrid),
+            (String::from("second"), String::from("second"))
+        );
+
+        // The node whose clock is ahead wins with wall clocks
+        assert_eq!(
+            converged_data_with_skew(TimestampSource::WallClock),
+            (String::from("first"), String::from("first"))
+        );
+    }
+}
@@ -0,0 +1,94 @@
+//! Compression of the gossip data on the wire.
+//!
+//! Compressed data is deflated, then base64-encoded so that the
+//! protocol stays text-based, and flagged by an `[ENCODING=DEFLATE]`
+//! field. Data shorter than `MIN_COMPRESSED_LENGTH`, or which does
+//! not shrink, is sent as is.
+
+use std::io::{Read, Write};
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+
+use crate::{cli::GossipCompression, transport::framing};
+
+/// Minimum length of the data worth compressing, in bytes.
+pub(crate) const MIN_COMPRESSED_LENGTH: usize = 256;
+
+/// Returns `data` deflated and base64-encoded, if `compression`
+/// is enabled and the encoded data is shorter than `data`.
+pub(crate) fn compress(data: &str, compression: GossipCompression) -> Option<String> {
+    if compression == GossipCompression::None || data.len() < MIN_COMPRESSED_LENGTH {
+        return None;
+    }
+
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(data.as_bytes()).ok()?;
+    let compressed_data = STANDARD.encode(encoder.finish().ok()?);
+
+    if compressed_data.len() < data.len() {
+        Some(compressed_data)
+    } else {
+        None
+    }
+}
+
+/// Decodes and inflates data compressed by `compress`.
+///
+/// Returns `None` if the data is malformed, is not valid
+/// UTF-8 once inflated, or inflates beyond the maximum
+/// frame length (which no legitimate update exceeds).
+pub(crate) fn decompress(compressed_data: &str) -> Option<String> {
+    let deflated_data = STANDARD.decode(compressed_data).ok()?;
+
+    let mut data = Vec::new();
+    DeflateDecoder::new(deflated_data.as_slice())
+        .take(framing::MAX_FRAME_LENGTH as u64 + 1)
+        .read_to_end(&mut data)
+        .ok()?;
+
+    if data.len() > framing::MAX_FRAME_LENGTH {
+        return None;
+    }
+
+    String::from_utf8(data).ok()
+}
+
+#[cfg(test)]
+mod compression_test {
+    use crate::cli::GossipCompression;
+
+    use super::{compress, decompress, MIN_COMPRESSED_LENGTH};
+
+    #[test]
+    fn compression_round_trip_test() {
+        let data = "temperature=21.5;".repeat(1000);
+
+        let compressed_data = compress(&data, GossipCompression::Deflate).unwrap();
+
+        // A repetitive value shrinks by far
+        assert!(compressed_data.len() * 20 < data.len());
+        assert_eq!(decompress(&compressed_data), Some(data.clone()));
+
+        // Disabled compression
+        assert_eq!(compress(&data, GossipCompression::None), None);
+    }
+
+    #[test]
+    fn small_data_is_not_compressed_test() {
+        let data = "a".repeat(MIN_COMPRESSED_LENGTH - 1);
+        assert_eq!(compress(&data, GossipCompression::Deflate), None);
+
+        // Data which does not shrink once encoded
+        let data = (0..MIN_COMPRESSED_LENGTH)
+            .map(|_| char::from(rand::random_range(b'!'..=b'~')))
+            .collect::<String>();
+        assert_eq!(compress(&data, GossipCompression::Deflate), None);
+    }
+
+    #[test]
+    fn malformed_compressed_data_test() {
+        assert_eq!(decompress("not base64!"), None);
+        assert_eq!(decompress("bm90IGRlZmxhdGVk"), None);
+    }
+}
@@ -0,0 +1,200 @@
+//! Deduplication of the client updates retried with
+//! an idempotency key (`[KEY=<key>]`).
+//!
+//! A client retrying an update after a timeout cannot tell whether
+//! the first attempt was applied. Without a key, the retry stamps
+//! the data again, which wins over the updates made meanwhile and
+//! is disseminated anew. With a key, the node remembers the state
+//! written by the first attempt for `IDEMPOTENCY_KEY_RETENTION`:
+//! an identical update with the same key returns that state
+//! instead of writing a new one.
+
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use super::State;
+
+/// Duration an idempotency key is remembered for.
+pub(crate) const IDEMPOTENCY_KEY_RETENTION: Duration = Duration::from_secs(300);
+
+/// Keys remembered at most. Beyond, the oldest key is forgotten.
+pub(crate) const MAX_IDEMPOTENCY_KEYS: usize = 1024;
+
+/// Update written with an idempotency key.
+#[derive(Debug)]
+struct KeyedUpdate {
+    data: String,
+    ttl: Option<u64>,
+    /// State written by the update.
+    state: State,
+    written_at: Instant,
+}
+
+/// Idempotency keys of the recent client updates of
+/// a node, shared by its virtual nodes.
+#[derive(Debug)]
+pub(crate) struct IdempotencyKeys {
+    retention: Duration,
+    updates: Mutex<HashMap<String, KeyedUpdate>>,
+}
+
+impl Default for IdempotencyKeys {
+    fn default() -> Self {
+        Self::new(IDEMPOTENCY_KEY_RETENTION)
+    }
+}
+
+impl IdempotencyKeys {
+    pub(crate) fn new(retention: Duration) -> Self {
+        Self {
+            retention,
+            updates: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Writes the update of `data` living for `ttl` seconds with
+    /// `write`, unless it retries an update made with `key`
+    /// before `now`: the state written then is returned instead.
+    ///
+    /// Concurrent attempts with the same key write at most once.
+    /// Returns an error if `key` was used for a different update.
+    pub(crate) fn write_once(
+        &self,
+        key: &str,
+        data: &str,
+        ttl: Option<u64>,
+        now: Instant,
+        write: impl FnOnce() -> State,
+    ) -> Result<State, String> {
+        let mut updates = self.updates.lock().unwrap();
+
+        updates
+            .retain(|_, update| now.saturating_duration_since(update.written_at) < self.retention);
+
+        if let Some(update) = updates.get(key) {
+            if update.data != data || update.ttl != ttl {
+                return Err(format!(
+                    "idempotency key `{}` already used for a different update",
+                    key
+                ));
+            }
+
+            return Ok(update.state.clone());
+        }
+
+        if updates.len() >= MAX_IDEMPOTENCY_KEYS {
+            let oldest_key = updates
+                .iter()
+                .min_by_key(|(_, update)| update.written_at)
+                .map(|(key, _)| key.clone());
+
+            if let Some(oldest_key) = oldest_key {
+                updates.remove(&oldest_key);
+            }
+        }
+
+        let state = write();
+
+        updates.insert(
+            key.to_string(),
+            KeyedUpdate {
+                data: data.to_string(),
+                ttl,
+                state: state.clone(),
+                written_at: now,
+            },
+        );
+
+        Ok(state)
+    }
+}
+
+#[cfg(test)]
+mod idempotency_test {
+    use std::time::{Duration, Instant};
+
+    use crate::gossip::State;
+
+    use super::{IdempotencyKeys, MAX_IDEMPOTENCY_KEYS};
+
+    fn state(data: &str, timestamp: u128) -> State {
+        State {
+            data: data.to_string(),
+            timestamp,
+            ttl: None,
+        }
+    }
+
+    #[test]
+    fn retried_update_is_written_once_test() {
+        let keys = IdempotencyKeys::new(Duration::from_secs(60));
+        let now = Instant::now();
+
+        assert_eq!(
+            keys.write_once("k1", "a", None, now, || state("a", 100)),
+            Ok(state("a", 100))
+        );
+
+        // The retry returns the first state, without writing
+        assert_eq!(
+            keys.write_once("k1", "a", None, now + Duration::from_secs(1), || {
+                panic!("written twice")
+            }),
+            Ok(state("a", 100))
+        );
+
+        // Another key is another update
+        assert_eq!(
+            keys.write_once("k2", "a", None, now, || state("a", 200)),
+            Ok(state("a", 200))
+        );
+    }
+
+    #[test]
+    fn key_reused_for_another_update_is_rejected_test() {
+        let keys = IdempotencyKeys::new(Duration::from_secs(60));
+        let now = Instant::now();
+
+        keys.write_once("k1", "a", None, now, || state("a", 100))
+            .unwrap();
+
+        assert!(keys
+            .write_once("k1", "b", None, now, || state("b", 200))
+            .is_err());
+        assert!(keys
+            .write_once("k1", "a", Some(10), now, || state("a", 200))
+            .is_err());
+    }
+
+    #[test]
+    fn keys_are_forgotten_test() {
+        let keys = IdempotencyKeys::new(Duration::from_secs(60));
+        let now = Instant::now();
+
+        keys.write_once("k1", "a", None, now, || state("a", 100))
+            .unwrap();
+
+        // After the retention
+        assert_eq!(
+            keys.write_once("k1", "a", None, now + Duration::from_secs(60), || {
+                state("a", 200)
+            }),
+            Ok(state("a", 200))
+        );
+
+        // Beyond the capacity, the oldest key first
+        for i in 0..MAX_IDEMPOTENCY_KEYS {
+            let key = format!("key-{}", i);
+            keys.write_once(&key, "a", None, now + Duration::from_secs(61), || {
+                state("a", 300)
+            })
+            .unwrap();
+        }
+
+        assert_eq!(keys.updates.lock().unwrap().len(), MAX_IDEMPOTENCY_KEYS);
+        assert!(!keys.updates.lock().unwrap().contains_key("k1"));
+    }
+}
@@ -0,0 +1,162 @@
+//! Append-only journal of the gossip state changes
+//! accepted by a node (`gossip-journal`).
+//!
+//! Each accepted state is written on its own line, in the order
+//! the node accepted them: the local time of the change (in
+//! milliseconds since the UNIX epoch), its source, then the
+//! state as shared on the wire (`[<data>][<timestamp>]`, the data
+//! being escaped, followed by `[TTL=<seconds>]` if any).
+//!
+//! A failure to write (e.g. a full disk) is logged, and the node
+//! keeps serving: the journal is an audit trail, not a
+//! requirement to accept updates.
+
+use std::{
+    fmt,
+    fs::{File, OpenOptions},
+    io::{self, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::cli::GossipCompression;
+
+use super::{protocol, State};
+
+/// Where an accepted state change comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum JournalSource {
+    /// A client update (`UPDATE_DATA`, `UPDATE_DATA_WITH_ACK`).
+    Client,
+    /// A gossip exchange with the node at the given
+    /// address (unknown if the connection was lost).
+    Peer(Option<SocketAddr>),
+}
+
+impl fmt::Display for JournalSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Client => write!(f, "client"),
+            Self::Peer(Some(addr)) => write!(f, "peer {}", addr),
+            Self::Peer(None) => write!(f, "peer unknown"),
+        }
+    }
+}
+
+/// Append-only journal file of the gossip state changes.
+#[derive(Debug)]
+pub(crate) struct Journal {
+    path: PathBuf,
+    /// The file, and whether the last write failed,
+    /// so that a failure is logged once until it recovers.
+    file: Mutex<(File, bool)>,
+}
+
+impl Journal {
+    /// Opens the journal at `path`, created if it does not exist.
+    /// The changes are appended to the existing ones.
+    pub(crate) fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: Mutex::new((file, false)),
+        })
+    }
+
+    /// Appends the change of the local state to `state`, coming
+    /// from `source`, at `now` (in milliseconds since the UNIX epoch).
+    ///
+    /// To keep the journal in the order of the changes, it must
+    /// be called while holding the lock of the local state.
+    pub(crate) fn append(&self, state: &State, source: JournalSource, now: u128) {
+        let entry = format!(
+            "{} {} {}\n",
+            now,
+            source,
+            protocol::state_to_protocol_text(state, GossipCompression::None)
+        );
+
+        let mut file = self.file.lock().unwrap();
+        let (ref mut journal_file, ref mut is_failing) = *file;
+
+        match journal_file.write_all(entry.as_bytes()) {
+            Ok(()) if *is_failing => {
+                eprintln!("gossip journal [{}] written again", self.path.display());
+                *is_failing = false;
+            }
+            Ok(()) => (),
+            Err(err) if !*is_failing => {
+                eprintln!(
+                    "cannot write the gossip journal [{}], changes are not journaled until it recovers: {}",
+                    self.path.display(),
+                    err
+                );
+                *is_failing = true;
+            }
+            Err(_) => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod journal_test {
+    use std::{env, fs, net::SocketAddr, path::Path};
+
+    use crate::gossip::State;
+
+    use super::{Journal, JournalSource};
+
+    fn state(data: &str, timestamp: u128, ttl: Option<u64>) -> State {
+        State {
+            data: data.to_string(),
+            timestamp,
+            ttl,
+        }
+    }
+
+    #[test]
+    fn changes_are_appended_in_order_test() {
+        let path = env::temp_dir().join(format!("chord-journal-{}.log", std::process::id()));
+        let peer_addr = "10.0.0.1:8000".parse::<SocketAddr>().unwrap();
+
+        let journal = Journal::open(&path).unwrap();
+        journal.append(&state("a", 1000, None), JournalSource::Client, 1001);
+        journal.append(
+            &state("[b]", 2000, Some(60)),
+            JournalSource::Peer(Some(peer_addr)),
+            2500,
+        );
+
+        // Reopened journals are appended to
+        let journal = Journal::open(&path).unwrap();
+        journal.append(&state("c", 3000, None), JournalSource::Peer(None), 3001);
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "1001 client [a][1000]\n\
+             2500 peer 10.0.0.1:8000 [%5Bb%5D][2000][TTL=60]\n\
+             3001 peer unknown [c][3000]\n"
+        );
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn write_failure_does_not_panic_test() {
+        // Writes to /dev/full fail as on a full disk
+        let path = Path::new("/dev/full");
+
+        if !path.exists() {
+            return;
+        }
+
+        let journal = Journal::open(path).unwrap();
+
+        journal.append(&state("a", 1000, None), JournalSource::Client, 1001);
+        journal.append(&state("b", 2000, None), JournalSource::Client, 2001);
+
+        assert!(journal.file.lock().unwrap().1);
+    }
+}
@@ -0,0 +1,167 @@
+//! Back-pressure utilities for outbound gossip
+//! exchanges, inbound connections and forwarded lookups.
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
+
+/// Semaphore-style limit on the number of concurrent
+/// outbound gossip exchanges, or served connections.
+#[derive(Debug, Clone)]
+pub(crate) struct InFlightLimiter {
+    in_flight: Arc<AtomicUsize>,
+    max_in_flight: usize,
+}
+
+/// A slot held by an in-flight gossip exchange or connection.
+///
+/// The slot is released when the permit is dropped.
+#[derive(Debug)]
+pub(crate) struct InFlightPermit {
+    in_flight: Arc<AtomicUsize>,
+}
+
+impl Drop for InFlightPermit {
+    fn drop(&mut self) {
+        self.in_flight.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+impl InFlightLimiter {
+    /// Creates a limiter allowing at most
+    /// `max_in_flight` concurrent exchanges.
+    pub(crate) fn new(max_in_flight: usize) -> Self {
+        Self {
+            in_flight: Arc::new(AtomicUsize::new(0)),
+            max_in_flight,
+        }
+    }
+
+    /// Returns the number of exchanges currently in flight.
+    pub(crate) fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+
+    /// Tries to take a free slot, without blocking.
+    ///
+    /// Returns `None` if the in-flight cap is reached.
+    pub(crate) fn try_acquire(&self) -> Option<InFlightPermit> {
+        self.in_flight
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+                (n < self.max_in_flight).then_some(n + 1)
+            })
+            .ok()?;
+
+        Some(InFlightPermit {
+            in_flight: Arc::clone(&self.in_flight),
+        })
+    }
+
+    /// Runs `exchange` in a separate thread if a slot is free,
+    /// holding the slot until `exchange` returns.
+    ///
+    /// Returns `false` if the exchange was skipped
+    /// because the in-flight cap is reached.
+    pub(crate) fn spawn<F>(&self, exchange: F) -> bool
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let permit = match self.try_acquire() {
+            Some(permit) => permit,
+            None => return false,
+        };
+
+        thread::spawn(move || {
+            exchange();
+            drop(permit);
+        });
+
+        true
+    }
+}
+
+#[cfg(test)]
+mod in_flight_limiter_test {
+    use std::{
+        net::SocketAddr,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc,
+        },
+        thread,
+        time::Duration,
+    };
+
+    use crate::transport::{mock::MockTransport, Transport};
+
+    use super::InFlightLimiter;
+
+    #[test]
+    fn try_acquire_respects_cap_test() {
+        let limiter = InFlightLimiter::new(2);
+
+        let first = limiter.try_acquire().unwrap();
+        let _second = limiter.try_acquire().unwrap();
+
+        assert!(limiter.try_acquire().is_none());
+        assert_eq!(limiter.in_flight(), 2);
+
+        drop(first);
+
+        assert!(limiter.try_acquire().is_some());
+    }
+
+    #[test]
+    fn spawn_never_exceeds_cap_with_slow_peers_test() {
+        let limiter = InFlightLimiter::new(3);
+
+        // Mock transport tracking the number
+        // of simultaneously open exchanges.
+        let open = Arc::new(AtomicUsize::new(0));
+        let max_open = Arc::new(AtomicUsize::new(0));
+
+        let transport = {
+            let open = Arc::clone(&open);
+            let max_open = Arc::clone(&max_open);
+
+            Arc::new(MockTransport::new(move |_, _| {
+                let current = open.fetch_add(1, Ordering::SeqCst) + 1;
+                max_open.fetch_max(current, Ordering::SeqCst);
+                // Artificial peer slowness
+                thread::sleep(Duration::from_millis(200));
+                open.fetch_sub(1, Ordering::SeqCst);
+
+                Ok("RESPONSE=IGNORE;".to_string())
+            }))
+        };
+
+        let peer_addr = SocketAddr::from(([127, 0, 0, 1], 8001));
+        let mut spawned = 0;
+
+        for _ in 0..20 {
+            let transport = Arc::clone(&transport);
+
+            let is_spawned = limiter.spawn(move || {
+                let _ = transport.send(peer_addr, "SHARE_DATA=NONE;");
+            });
+
+            if is_spawned {
+                spawned += 1;
+            }
+        }
+
+        assert_eq!(spawned, 3);
+
+        while limiter.in_flight() > 0 {
+            thread::sleep(Duration::from_millis(10));
+        }
+
+        assert!(max_open.load(Ordering::SeqCst) <= 3);
+        assert_eq!(transport.sent_requests().len(), 3);
+        assert!(limiter.spawn(|| ()));
+    }
+}
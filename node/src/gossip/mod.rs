@@ -1,14 +1,237 @@
 //! Contains utilities related
 //! to the Gossip protocol.
 
+use std::{
+    sync::RwLock,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use journal::{Journal, JournalSource};
+
+pub(crate) mod clock;
+pub(crate) mod compression;
+pub(crate) mod idempotency;
+pub(crate) mod journal;
+pub(crate) mod limiter;
+pub(crate) mod peer_selector;
 pub(crate) mod protocol;
 pub(crate) mod request_handler;
 pub(crate) mod request_initiator;
+pub(crate) mod skew;
+
+/// Maximum duration of a write waiting for
+/// acknowledgments (`UPDATE_DATA_WITH_ACK`).
+pub(crate) const QUORUM_WRITE_TIMEOUT: Duration = Duration::from_secs(10);
 
 /// Represents the data to disseminate
 /// using the Gossip protocol.
 #[derive(PartialEq, Eq, Debug, Clone)]
-pub(crate) struct State {
+pub struct State {
     pub data: String,
+    /// Time of the update at the origin node,
+    /// in milliseconds since the UNIX epoch.
     pub timestamp: u128,
+    /// Lifetime of the data in seconds,
+    /// counted from `timestamp`.
+    pub ttl: Option<u64>,
+}
+
+impl State {
+    /// Checks if the data outlived its TTL at `now`
+    /// (in milliseconds since the UNIX epoch).
+    ///
+    /// Expiry is based on the origin timestamp, so that every
+    /// node expires the data at the same time, regardless of
+    /// when it received it.
+    pub(crate) fn is_expired_at(&self, now: u128) -> bool {
+        match self.ttl {
+            Some(ttl) => now >= self.timestamp.saturating_add(u128::from(ttl) * 1000),
+            None => false,
+        }
+    }
+
+    /// Checks if this state wins over `other` when they are merged.
+    ///
+    /// The most recent state wins. States with equal timestamps
+    /// are ordered by their data, then by their TTL, so that every
+    /// node picks the same one instead of keeping its own.
+    pub(crate) fn supersedes(&self, other: &State) -> bool {
+        (self.timestamp, &self.data, self.ttl) > (other.timestamp, &other.data, other.ttl)
+    }
+}
+
+/// Returns the current time, in
+/// milliseconds since the UNIX epoch.
+pub(crate) fn now() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis()
+}
+
+/// Returns the local state, unless it has expired at `now`
+/// (in milliseconds since the UNIX epoch).
+pub(crate) fn live_state(
+    self_node_gossip_data: &RwLock<Option<State>>,
+    now: u128,
+) -> Option<State> {
+    self_node_gossip_data
+        .read()
+        .unwrap()
+        .clone()
+        .filter(|state| !state.is_expired_at(now))
+}
+
+/// Clears `self_node_gossip_data` if it has expired at `now`.
+///
+/// Returns `true` if the local state was cleared.
+pub(crate) fn remove_expired_state(
+    self_node_gossip_data: &RwLock<Option<State>>,
+    now: u128,
+) -> bool {
+    let mut self_node_gossip_data_lock = self_node_gossip_data.write().unwrap();
+
+    match *self_node_gossip_data_lock {
+        Some(ref state) if state.is_expired_at(now) => {
+            *self_node_gossip_data_lock = None;
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Merges `received_data` into `self_node_gossip_data`,
+/// keeping the state with the most recent timestamp
+/// (see `State::supersedes` for the equal timestamps).
+///
+/// Data expired at `now` (in milliseconds since the UNIX epoch)
+/// is never merged. The merged state is appended to the `journal`,
+/// if any, with its source, at `now`.
+///
+/// Returns `true` if the local state was updated.
+pub(crate) fn merge_state(
+    self_node_gossip_data: &RwLock<Option<State>>,
+    received_data: State,
+    journal: Option<(&Journal, JournalSource)>,
+    now: u128,
+) -> bool {
+    if received_data.is_expired_at(now) {
+        return false;
+    }
+
+    let mut self_node_gossip_data_lock = self_node_gossip_data.write().unwrap();
+
+    match *self_node_gossip_data_lock {
+        Some(ref self_node_gossip_data_content)
+            if !received_data.supersedes(self_node_gossip_data_content) =>
+        {
+            false
+        }
+        _ => {
+            if let Some((journal, source)) = journal {
+                journal.append(&received_data, source, now);
+            }
+
+            *self_node_gossip_data_lock = Some(received_data);
+            true
+        }
+    }
+}
+
+#[cfg(test)]
+mod state_expiry_test {
+    use std::sync::RwLock;
+
+    use super::{live_state, merge_state, remove_expired_state, State};
+
+    /// Wall time of the tests, in milliseconds since the UNIX epoch.
+    const NOW: u128 = 1_700_000_000_000;
+
+    fn state(timestamp: u128, ttl: Option<u64>) -> State {
+        State {
+            data: String::from("data"),
+            timestamp,
+            ttl,
+        }
+    }
+
+    #[test]
+    fn expiry_is_based_on_origin_timestamp_test() {
+        let state = state(10_000, Some(10));
+
+        assert!(!state.is_expired_at(10_000));
+        assert!(!state.is_expired_at(19_999));
+        assert!(state.is_expired_at(20_000));
+
+        // Without TTL, data never expires
+        assert!(!super::State { ttl: None, ..state }.is_expired_at(u128::MAX));
+    }
+
+    #[test]
+    fn expired_state_is_removed_test() {
+        let self_node_gossip_data = RwLock::new(Some(state(10_000, Some(10))));
+
+        assert!(!remove_expired_state(&self_node_gossip_data, 15_000));
+        assert!(self_node_gossip_data.read().unwrap().is_some());
+
+        assert!(remove_expired_state(&self_node_gossip_data, 20_000));
+        assert_eq!(*self_node_gossip_data.read().unwrap(), None);
+    }
+
+    #[test]
+    fn fresher_update_resets_expiry_test() {
+        let self_node_gossip_data = RwLock::new(Some(state(NOW - 5_000, Some(10))));
+
+        // Expired data is not merged, even if fresher
+        assert!(!merge_state(
+            &self_node_gossip_data,
+            state(NOW - 2_000, Some(1)),
+            None,
+            NOW
+        ));
+
+        // A fresher update carries its own TTL
+        let fresher_state = state(NOW - 1_000, Some(60));
+        assert!(merge_state(
+            &self_node_gossip_data,
+            fresher_state.clone(),
+            None,
+            NOW
+        ));
+        assert!(!remove_expired_state(&self_node_gossip_data, NOW + 10_000));
+        assert_eq!(*self_node_gossip_data.read().unwrap(), Some(fresher_state));
+    }
+
+    #[test]
+    fn merge_checks_expiry_at_given_time_test() {
+        let received_state = state(NOW, Some(10));
+
+        // The same state expired 10 seconds after its origin timestamp
+        assert!(!merge_state(
+            &RwLock::new(None),
+            received_state.clone(),
+            None,
+            NOW + 10_000
+        ));
+        assert!(merge_state(
+            &RwLock::new(None),
+            received_state.clone(),
+            None,
+            NOW + 9_999
+        ));
+    }
+
+    #[test]
+    fn live_state_checks_expiry_at_given_time_test() {
+        let self_node_gossip_data = RwLock::new(Some(state(NOW, Some(10))));
+
+        assert_eq!(
+            live_state(&self_node_gossip_data, NOW + 9_999),
+            Some(state(NOW, Some(10)))
+        );
+        assert_eq!(live_state(&self_node_gossip_data, NOW + 10_000), None);
+
+        // An expired state is only hidden, until the sweep clears it
+        assert!(self_node_gossip_data.read().unwrap().is_some());
+    }
 }
@@ -0,0 +1,111 @@
+//! Selection of the peer of each gossip round.
+//!
+//! Peers are drawn at random among the successors of the node.
+//! The generator can be seeded, so that tests can pin the
+//! sequence of peers contacted round after round.
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+use crate::chord::Node;
+
+/// Random selector of the peer of each gossip round.
+#[derive(Debug)]
+pub(crate) struct PeerSelector {
+    rng: StdRng,
+}
+
+impl PeerSelector {
+    /// Creates a selector seeded by the thread-local generator.
+    pub(crate) fn new() -> Self {
+        Self {
+            rng: StdRng::from_rng(&mut rand::rng()),
+        }
+    }
+
+    /// Creates a selector drawing the same
+    /// sequence of peers for the same `seed`.
+    #[cfg(test)]
+    pub(crate) fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Draws the peer of the next round among the
+    /// entries of `successor_list` other than `self_node`.
+    ///
+    /// Returns `None` if `self_node` is alone in its network.
+    pub(crate) fn select(&mut self, self_node: &Node, successor_list: &[Node]) -> Option<Node> {
+        let candidates = successor_list
+            .iter()
+            .filter(|node| *node != self_node)
+            .collect::<Vec<_>>();
+
+        if candidates.is_empty() {
+            return None;
+        }
+
+        Some(candidates[self.rng.random_range(0..candidates.len())].clone())
+    }
+}
+
+#[cfg(test)]
+mod peer_selector_test {
+    use crate::chord::Node;
+
+    use super::PeerSelector;
+
+    /// Ports of the peers drawn by `peer_selector`
+    /// over `rounds` rounds, for `self_node` at 1.
+    fn draw(peer_selector: &mut PeerSelector, successor_list: &[Node], rounds: usize) -> Vec<u16> {
+        (0..rounds)
+            .map(|_| {
+                peer_selector
+                    .select(&Node::for_test_at(1), successor_list)
+                    .unwrap()
+                    .get_public_addr()
+                    .port()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn seeded_selection_is_repeatable_test() {
+        let successor_list = [2, 3, 4, 5, 6].map(Node::for_test_at);
+
+        let targets = draw(&mut PeerSelector::with_seed(42), &successor_list, 10);
+
+        // The same sequence, run after run
+        assert_eq!(targets, [2, 4, 3, 4, 6, 5, 6, 4, 6, 2]);
+        assert_eq!(
+            draw(&mut PeerSelector::with_seed(42), &successor_list, 10),
+            targets
+        );
+        assert_ne!(
+            draw(&mut PeerSelector::with_seed(43), &successor_list, 10),
+            targets
+        );
+    }
+
+    #[test]
+    fn self_node_is_never_selected_test() {
+        let mut peer_selector = PeerSelector::with_seed(42);
+
+        // A node alone in its network
+        assert_eq!(
+            peer_selector.select(
+                &Node::for_test_at(1),
+                &[Node::for_test_at(1), Node::for_test_at(1)]
+            ),
+            None
+        );
+
+        // A network of two nodes, with a short successor list
+        let targets = draw(
+            &mut peer_selector,
+            &[Node::for_test_at(2), Node::for_test_at(1)],
+            10,
+        );
+        assert_eq!(targets, [2; 10]);
+    }
+}
@@ -3,14 +3,76 @@
 
 use regex::Regex;
 
-use super::State;
+use std::sync::LazyLock;
+
+use crate::{
+    chord::protocol::{escape_text, unescape_text},
+    cli::GossipCompression,
+};
+
+use super::{compression, State};
+
+/// Optional idempotency key of a data update (`[KEY=<key>]`),
+/// captured by the last group (see `gossip::idempotency`).
+const IDEMPOTENCY_KEY_PATTERN: &str = r"(?:\[KEY=([A-Za-z0-9_-]{1,64})\])?";
+
+/// Characters of the data escaped on the wire (as `%XX`, their
+/// hexadecimal code), so that the data cannot be mistaken for the
+/// delimiters or the metadata (e.g. `[TTL=<seconds>]`) following it.
+const DATA_ESCAPES: [(char, &str); 3] = [('%', "%25"), ('[', "%5B"), (']', "%5D")];
 
 /// Request abstraction for
 /// the Gossip protocol.
 #[derive(PartialEq, Eq, Debug)]
 pub(crate) enum GossipRequest {
-    UpdateData(String),
+    /// Updates the data, with an optional TTL (in seconds)
+    /// and an optional idempotency key.
+    UpdateData(String, Option<u64>, Option<String>),
+    /// Updates the data, then waits until it is
+    /// accepted by the given number of nodes.
+    UpdateDataWithAck(String, usize, Option<u64>, Option<String>),
     ShareData(Option<State>),
+    /// Reads the state of the node, without sharing
+    /// one, for observation tools (`GET_DATA;`).
+    GetData,
+}
+
+/// Converts `state` into the text-based representation
+/// shared by the `SHARE_DATA` request and the response with data:
+/// `[<data>][<timestamp>]`, followed by `[TTL=<seconds>]` if any,
+/// then by `[ENCODING=DEFLATE]` if the data is compressed.
+///
+/// The data is compressed according to `compression`,
+/// or escaped (see `DATA_ESCAPES`) if it is not.
+pub(crate) fn state_to_protocol_text(state: &State, compression: GossipCompression) -> String {
+    let ttl = match state.ttl {
+        Some(ttl) => format!("[TTL={}]", ttl),
+        None => String::new(),
+    };
+
+    match compression::compress(&state.data, compression) {
+        Some(compressed_data) => format!(
+            "[{}][{}]{}[ENCODING=DEFLATE]",
+            compressed_data, state.timestamp, ttl
+        ),
+        None => format!(
+            "[{}][{}]{}",
+            escape_text(&state.data, &DATA_ESCAPES),
+            state.timestamp,
+            ttl
+        ),
+    }
+}
+
+/// Parses the data of a state, decompressing it if it
+/// is flagged by an `encoding`, unescaping it otherwise.
+///
+/// Returns `None` if the compressed data is invalid.
+fn parse_state_data(data: &str, encoding: Option<regex::Match>) -> Option<String> {
+    match encoding {
+        Some(_) => compression::decompress(data),
+        None => Some(unescape_text(data, &DATA_ESCAPES)),
+    }
 }
 
 impl GossipRequest {
@@ -22,47 +84,150 @@ impl GossipRequest {
             return Ok(gossip_request);
         }
 
+        // UPDATE_DATA_WITH_ACK request protocol parsing
+        if let Some(gossip_request) = Self::parse_update_data_with_ack_request_protocol(request)? {
+            return Ok(gossip_request);
+        }
+
         // SHARE_DATA request protocol parsing
         if let Some(gossip_request) = Self::parse_share_data_request_protocol(request) {
             return Ok(gossip_request);
         }
 
+        // GET_DATA request protocol parsing
+        if request == "GET_DATA;" {
+            return Ok(Self::GetData);
+        }
+
         Err("invalid request (protocol error)")
     }
 
-    fn parse_update_data_request_protocol(request: &str) -> Option<Self> {
-        let update_data_request_regex = Regex::new(r"^UPDATE_DATA=\[(.+)\];$").unwrap();
+    /// Converts the request into its text-based representation,
+    /// the shared data being compressed according to `compression`.
+    pub(crate) fn to_protocol_text(&self, compression: GossipCompression) -> String {
+        let ttl_text = |ttl: Option<u64>| match ttl {
+            Some(ttl) => format!("[TTL={}]", ttl),
+            None => String::new(),
+        };
+
+        let key_text = |key: &Option<String>| match key {
+            Some(key) => format!("[KEY={}]", key),
+            None => String::new(),
+        };
+
+        match *self {
+            Self::UpdateData(ref data, ttl, ref key) => format!(
+                "UPDATE_DATA=[{}]{}{};",
+                escape_text(data, &DATA_ESCAPES),
+                ttl_text(ttl),
+                key_text(key)
+            ),
+            Self::UpdateDataWithAck(ref data, required_acks, ttl, ref key) => format!(
+                "UPDATE_DATA_WITH_ACK=[{}][{}]{}{};",
+                escape_text(data, &DATA_ESCAPES),
+                required_acks,
+                ttl_text(ttl),
+                key_text(key)
+            ),
+            Self::ShareData(Some(ref state)) => {
+                format!("SHARE_DATA={};", state_to_protocol_text(state, compression))
+            }
+            Self::ShareData(None) => "SHARE_DATA=NONE;".to_string(),
+            Self::GetData => "GET_DATA;".to_string(),
+        }
+    }
 
-        if update_data_request_regex.is_match(request) {
-            let request_datas = update_data_request_regex.captures(request).unwrap();
-            let data = request_datas[1].to_string();
-            return Some(Self::UpdateData(data));
+    fn parse_update_data_request_protocol(request: &str) -> Option<Self> {
+        static UPDATE_DATA_REQUEST_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+                r"^UPDATE_DATA=\[(.+?)\](?:\[TTL=([0-9]+)\])?{};$",
+                IDEMPOTENCY_KEY_PATTERN
+            ))
+            .unwrap()
+        });
+
+        if UPDATE_DATA_REQUEST_REGEX.is_match(request) {
+            let request_datas = UPDATE_DATA_REQUEST_REGEX.captures(request).unwrap();
+            let data = unescape_text(&request_datas[1], &DATA_ESCAPES);
+            let ttl = parse_ttl(request_datas.get(2))?;
+            let key = request_datas.get(3).map(|key| key.as_str().to_string());
+            return Some(Self::UpdateData(data, ttl, key));
         }
 
         None
     }
 
+    fn parse_update_data_with_ack_request_protocol(
+        request: &str,
+    ) -> Result<Option<Self>, &'static str> {
+        static UPDATE_DATA_WITH_ACK_REQUEST_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(&format!(
+                r"^UPDATE_DATA_WITH_ACK=\[(.+?)\]\[([1-9][0-9]*)\](?:\[TTL=([0-9]+)\])?{};$",
+                IDEMPOTENCY_KEY_PATTERN
+            ))
+            .unwrap()
+        });
+
+        if UPDATE_DATA_WITH_ACK_REQUEST_REGEX.is_match(request) {
+            let request_datas = UPDATE_DATA_WITH_ACK_REQUEST_REGEX
+                .captures(request)
+                .unwrap();
+            let data = unescape_text(&request_datas[1], &DATA_ESCAPES);
+            let required_acks = request_datas[2]
+                .parse::<usize>()
+                .map_err(|_| "invalid request (invalid acknowledgment count)")?;
+            let ttl = parse_ttl(request_datas.get(3)).ok_or("invalid request (invalid TTL)")?;
+            let key = request_datas.get(4).map(|key| key.as_str().to_string());
+            return Ok(Some(Self::UpdateDataWithAck(data, required_acks, ttl, key)));
+        }
+
+        Ok(None)
+    }
+
     fn parse_share_data_request_protocol(request: &str) -> Option<Self> {
         if request == "SHARE_DATA=NONE;" {
             return Some(Self::ShareData(None));
         }
 
-        let share_data_request_regex = Regex::new(r"^SHARE_DATA=\[(.+)\]\[([0-9]+)\];$").unwrap();
-
-        if share_data_request_regex.is_match(request) {
-            let request_datas = share_data_request_regex.captures(request).unwrap();
-            let data = request_datas[1].to_string();
-            let timestamp = request_datas[2].parse::<u128>().unwrap();
-            return Some(Self::ShareData(Some(State { data, timestamp })));
+        static SHARE_DATA_REQUEST_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(
+                r"^SHARE_DATA=\[(.+?)\]\[([0-9]+)\](?:\[TTL=([0-9]+)\])?(\[ENCODING=DEFLATE\])?;$",
+            )
+            .unwrap()
+        });
+
+        if SHARE_DATA_REQUEST_REGEX.is_match(request) {
+            let request_datas = SHARE_DATA_REQUEST_REGEX.captures(request).unwrap();
+            let data = parse_state_data(&request_datas[1], request_datas.get(4))?;
+            let timestamp = request_datas[2].parse::<u128>().ok()?;
+            let ttl = parse_ttl(request_datas.get(3))?;
+            return Some(Self::ShareData(Some(State {
+                data,
+                timestamp,
+                ttl,
+            })));
         }
 
         None
     }
 }
 
+/// Parses the optional TTL of a request or a response.
+///
+/// Returns `None` if the TTL is present but invalid
+/// (out of range), and `Some(None)` if it is absent.
+fn parse_ttl(ttl: Option<regex::Match>) -> Option<Option<u64>> {
+    match ttl {
+        Some(ttl) => ttl.as_str().parse::<u64>().ok().map(Some),
+        None => Some(None),
+    }
+}
+
 #[cfg(test)]
 mod gossip_request_protocol_test {
-    use super::GossipRequest;
+    use crate::{cli::GossipCompression, gossip::State};
+
+    use super::{state_to_protocol_text, GossipRequest};
 
     #[test]
     fn update_data_request_protocol_parse_test() {
@@ -70,11 +235,117 @@ mod gossip_request_protocol_test {
 
         let gossip_request = GossipRequest::parse(request).unwrap();
 
-        if let GossipRequest::UpdateData(data) = gossip_request {
+        if let GossipRequest::UpdateData(data, ttl, _) = gossip_request {
             assert_eq!(data, String::from("Some data ..."));
+            assert_eq!(ttl, None);
         } else {
             panic!("parsing error");
         }
+
+        // UPDATE_DATA request protocol with TTL
+        let request = "UPDATE_DATA=[Some data ...][TTL=10];";
+
+        assert_eq!(
+            GossipRequest::parse(request).unwrap(),
+            GossipRequest::UpdateData(String::from("Some data ..."), Some(10), None)
+        );
+
+        // Brackets inside the data
+        let request = "UPDATE_DATA=[[a][b]];";
+
+        assert_eq!(
+            GossipRequest::parse(request).unwrap(),
+            GossipRequest::UpdateData(String::from("[a][b]"), None, None)
+        );
+    }
+
+    #[test]
+    fn data_ending_like_metadata_round_trips_test() {
+        let state = State {
+            data: String::from("100% [a][TTL=5]"),
+            timestamp: 1,
+            ttl: None,
+        };
+
+        let gossip_request = GossipRequest::UpdateData(state.data.clone(), None, None);
+        let request = gossip_request.to_protocol_text(GossipCompression::None);
+
+        assert_eq!(request, "UPDATE_DATA=[100%25 %5Ba%5D%5BTTL=5%5D];");
+        assert_eq!(GossipRequest::parse(&request).unwrap(), gossip_request);
+
+        let gossip_request =
+            GossipRequest::UpdateDataWithAck(state.data.clone(), 2, None, Some("k".to_string()));
+        let request = gossip_request.to_protocol_text(GossipCompression::None);
+
+        assert_eq!(GossipRequest::parse(&request).unwrap(), gossip_request);
+
+        let gossip_request = GossipRequest::ShareData(Some(state));
+        let request = gossip_request.to_protocol_text(GossipCompression::None);
+
+        assert_eq!(request, "SHARE_DATA=[100%25 %5Ba%5D%5BTTL=5%5D][1];");
+        assert_eq!(GossipRequest::parse(&request).unwrap(), gossip_request);
+    }
+
+    #[test]
+    fn idempotency_key_protocol_test() {
+        let request = "UPDATE_DATA=[Some data ...][TTL=10][KEY=retry-1];";
+        let gossip_request = GossipRequest::parse(request).unwrap();
+
+        assert_eq!(
+            gossip_request,
+            GossipRequest::UpdateData(
+                String::from("Some data ..."),
+                Some(10),
+                Some(String::from("retry-1"))
+            )
+        );
+        assert_eq!(
+            gossip_request.to_protocol_text(GossipCompression::None),
+            request
+        );
+
+        let request = "UPDATE_DATA_WITH_ACK=[Some data ...][3][KEY=retry-1];";
+        let gossip_request = GossipRequest::parse(request).unwrap();
+
+        assert_eq!(
+            gossip_request,
+            GossipRequest::UpdateDataWithAck(
+                String::from("Some data ..."),
+                3,
+                None,
+                Some(String::from("retry-1"))
+            )
+        );
+        assert_eq!(
+            gossip_request.to_protocol_text(GossipCompression::None),
+            request
+        );
+
+        // A key which is not a token is part of the data
+        assert_eq!(
+            GossipRequest::parse("UPDATE_DATA=[Some data ...][KEY=a b];").unwrap(),
+            GossipRequest::UpdateData(String::from("Some data ...][KEY=a b"), None, None)
+        );
+    }
+
+    #[test]
+    fn update_data_with_ack_request_protocol_parse_test() {
+        let request = "UPDATE_DATA_WITH_ACK=[Some data ...][3];";
+
+        assert_eq!(
+            GossipRequest::parse(request).unwrap(),
+            GossipRequest::UpdateDataWithAck(String::from("Some data ..."), 3, None, None)
+        );
+
+        let request = "UPDATE_DATA_WITH_ACK=[Some data ...][3][TTL=60];";
+
+        assert_eq!(
+            GossipRequest::parse(request).unwrap(),
+            GossipRequest::UpdateDataWithAck(String::from("Some data ..."), 3, Some(60), None)
+        );
+
+        // At least one acknowledgment is required
+        assert!(GossipRequest::parse("UPDATE_DATA_WITH_ACK=[Some data ...][0];").is_err());
     }
 
     #[test]
@@ -95,10 +366,86 @@ mod gossip_request_protocol_test {
             let data = data.unwrap();
             assert_eq!(data.data, String::from("Some data ..."));
             assert_eq!(data.timestamp, 7851391275623);
+            assert_eq!(data.ttl, None);
+        } else {
+            panic!("parsing error");
+        }
+
+        // SHARE_DATA request protocol with DATA and TTL
+        let request = "SHARE_DATA=[Some data ...][7851391275623][TTL=10];";
+
+        let gossip_request = GossipRequest::parse(request).unwrap();
+
+        if let GossipRequest::ShareData(Some(data)) = gossip_request {
+            assert_eq!(data.data, String::from("Some data ..."));
+            assert_eq!(data.timestamp, 7851391275623);
+            assert_eq!(data.ttl, Some(10));
         } else {
             panic!("parsing error");
         }
     }
+
+    #[test]
+    fn oversized_timestamp_is_rejected_test() {
+        // Beyond u128::MAX (39 digits)
+        let timestamp = "9".repeat(50);
+
+        assert!(
+            GossipRequest::parse(&format!("SHARE_DATA=[Some data ...][{}];", timestamp)).is_err()
+        );
+        assert!(GossipRequest::parse(&format!(
+            "SHARE_DATA=[Some data ...][{}][TTL=10];",
+            timestamp
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn get_data_request_protocol_parse_test() {
+        assert_eq!(
+            GossipRequest::parse("GET_DATA;").unwrap(),
+            GossipRequest::GetData
+        );
+        assert!(GossipRequest::parse("GET_DATA=NONE;").is_err());
+    }
+
+    #[test]
+    fn compressed_share_data_request_protocol_test() {
+        let state = State {
+            data: "[sensor-1][21.5]".repeat(500),
+            timestamp: 7851391275623,
+            ttl: Some(10),
+        };
+
+        let request = format!(
+            "SHARE_DATA={};",
+            state_to_protocol_text(&state, GossipCompression::Deflate)
+        );
+
+        assert!(request.ends_with("[TTL=10][ENCODING=DEFLATE];"));
+        assert!(request.len() * 20 < state.data.len());
+        assert_eq!(
+            GossipRequest::parse(&request).unwrap(),
+            GossipRequest::ShareData(Some(state))
+        );
+
+        // Small data is sent as is
+        let state = State {
+            data: String::from("Some data ..."),
+            timestamp: 7851391275623,
+            ttl: None,
+        };
+
+        assert_eq!(
+            state_to_protocol_text(&state, GossipCompression::Deflate),
+            "[Some data ...][7851391275623]"
+        );
+
+        // Invalid compressed data
+        assert!(
+            GossipRequest::parse("SHARE_DATA=[abc][7851391275623][ENCODING=DEFLATE];").is_err()
+        );
+    }
 }
 
 /// Response abstraction for
@@ -107,6 +454,12 @@ mod gossip_request_protocol_test {
 pub(crate) enum GossipResponse {
     Ignore,
     ResponseWithData(State),
+    /// Number of nodes which accepted
+    /// the data, out of the required number.
+    Acknowledged {
+        acks: usize,
+        required_acks: usize,
+    },
 }
 
 impl GossipResponse {
@@ -123,6 +476,11 @@ impl GossipResponse {
             return Ok(gossip_reponse);
         }
 
+        // ACKNOWLEDGED response protocol parsing
+        if let Some(gossip_reponse) = Self::parse_acknowledged_response_protocol(response) {
+            return Ok(gossip_reponse);
+        }
+
         Err("invalid response (protocol error)")
     }
 
@@ -135,31 +493,76 @@ impl GossipResponse {
     }
 
     fn parse_response_with_data_protocol(response: &str) -> Option<Self> {
-        let response_with_data_regex = Regex::new(r"^RESPONSE=\[(.+)\]\[([0-9]+)\];$").unwrap();
+        static RESPONSE_WITH_DATA_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(
+                r"^RESPONSE=\[(.+?)\]\[([0-9]+)\](?:\[TTL=([0-9]+)\])?(\[ENCODING=DEFLATE\])?;$",
+            )
+            .unwrap()
+        });
+
+        if RESPONSE_WITH_DATA_REGEX.is_match(response) {
+            let request_datas = RESPONSE_WITH_DATA_REGEX.captures(response).unwrap();
+            let data = parse_state_data(&request_datas[1], request_datas.get(4))?;
+            let timestamp = request_datas[2].parse::<u128>().ok()?;
+            let ttl = parse_ttl(request_datas.get(3))?;
+            return Some(Self::ResponseWithData(State {
+                data,
+                timestamp,
+                ttl,
+            }));
+        }
+
+        None
+    }
 
-        if response_with_data_regex.is_match(response) {
-            let request_datas = response_with_data_regex.captures(response).unwrap();
-            let data = request_datas[1].to_string();
-            let timestamp = request_datas[2].parse::<u128>().unwrap();
-            return Some(Self::ResponseWithData(State { data, timestamp }));
+    fn parse_acknowledged_response_protocol(response: &str) -> Option<Self> {
+        static ACKNOWLEDGED_RESPONSE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+            Regex::new(r"^RESPONSE=ACKNOWLEDGED\[([0-9]+)\]\[([0-9]+)\];$").unwrap()
+        });
+
+        if ACKNOWLEDGED_RESPONSE_REGEX.is_match(response) {
+            let response_datas = ACKNOWLEDGED_RESPONSE_REGEX.captures(response).unwrap();
+            let acks = response_datas[1].parse::<usize>().ok()?;
+            let required_acks = response_datas[2].parse::<usize>().ok()?;
+            return Some(Self::Acknowledged {
+                acks,
+                required_acks,
+            });
         }
 
         None
     }
 
-    pub(crate) fn to_protocol_text(&self) -> String {
+    /// Converts the response into its text-based representation,
+    /// the data of a response with data being compressed
+    /// according to `compression`.
+    pub(crate) fn to_protocol_text(&self, compression: GossipCompression) -> String {
         match *self {
             Self::Ignore => "RESPONSE=IGNORE;".to_string(),
             Self::ResponseWithData(ref state) => {
-                format!("RESPONSE=[{}][{}];", state.data, state.timestamp)
+                format!("RESPONSE={};", state_to_protocol_text(state, compression))
             }
+            Self::Acknowledged {
+                acks,
+                required_acks,
+            } => format!("RESPONSE=ACKNOWLEDGED[{}][{}];", acks, required_acks),
         }
     }
 }
 
 #[cfg(test)]
 mod gossip_response_protocol_test {
-    use crate::gossip::protocol::GossipResponse;
+    use crate::{
+        cli::GossipCompression,
+        gossip::{protocol::GossipResponse, State},
+    };
+
+    #[test]
+    fn oversized_timestamp_response_is_rejected_test() {
+        let response = format!("RESPONSE=[Some data ...][{}];", "9".repeat(50));
+
+        assert!(GossipResponse::parse(&response).is_err());
+    }
 
     #[test]
     fn ignore_response_protocol_parse_test() {
@@ -180,8 +583,58 @@ mod gossip_response_protocol_test {
         if let GossipResponse::ResponseWithData(data) = gossip_response {
             assert_eq!(data.data, String::from("Some data ..."));
             assert_eq!(data.timestamp, 7851391275623);
+            assert_eq!(data.ttl, None);
         } else {
             panic!("parsing error");
         }
     }
+
+    #[test]
+    fn response_with_data_and_ttl_protocol_test() {
+        let response = GossipResponse::ResponseWithData(State {
+            data: String::from("Some data ..."),
+            timestamp: 7851391275623,
+            ttl: Some(10),
+        });
+
+        assert_eq!(
+            response.to_protocol_text(GossipCompression::None),
+            "RESPONSE=[Some data ...][7851391275623][TTL=10];"
+        );
+        assert_eq!(
+            GossipResponse::parse(&response.to_protocol_text(GossipCompression::None)).unwrap(),
+            response
+        );
+    }
+
+    #[test]
+    fn compressed_response_with_data_protocol_test() {
+        let response = GossipResponse::ResponseWithData(State {
+            data: "Some data ...".repeat(100),
+            timestamp: 7851391275623,
+            ttl: None,
+        });
+
+        let response_msg = response.to_protocol_text(GossipCompression::Deflate);
+
+        assert!(response_msg.ends_with("][7851391275623][ENCODING=DEFLATE];"));
+        assert_eq!(GossipResponse::parse(&response_msg).unwrap(), response);
+    }
+
+    #[test]
+    fn acknowledged_response_protocol_test() {
+        let response = GossipResponse::Acknowledged {
+            acks: 2,
+            required_acks: 3,
+        };
+
+        assert_eq!(
+            response.to_protocol_text(GossipCompression::None),
+            "RESPONSE=ACKNOWLEDGED[2][3];"
+        );
+        assert_eq!(
+            GossipResponse::parse(&response.to_protocol_text(GossipCompression::None)).unwrap(),
+            response
+        );
+    }
 }
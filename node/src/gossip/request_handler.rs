@@ -1,58 +1,446 @@
 use std::{
     sync::{Arc, RwLock},
-    time::{SystemTime, UNIX_EPOCH},
+    time::Instant,
 };
 
-use super::{protocol::GossipResponse, State};
+use crate::{context::NodeContext, gossip, observer::NodeObserver};
 
-pub(crate) fn update_data_request_handler(
-    self_node_gossip_data: Arc<RwLock<Option<State>>>,
+use super::{
+    clock::GossipClock,
+    idempotency::IdempotencyKeys,
+    journal::{Journal, JournalSource},
+    protocol::GossipResponse,
+    request_initiator, State, QUORUM_WRITE_TIMEOUT,
+};
+
+/// Replaces the local state with `received_data`,
+/// timestamped now by `gossip_clock` and living for
+/// `ttl` seconds (if any), and returns the new state.
+///
+/// The new state is appended to the `journal`, if any.
+fn write_data(
+    self_node_gossip_data: &RwLock<Option<State>>,
+    gossip_clock: &GossipClock,
     received_data: String,
-) -> GossipResponse {
+    ttl: Option<u64>,
+    journal: Option<&Journal>,
+    observer: &dyn NodeObserver,
+) -> State {
+    let mut self_node_gossip_data_lock = self_node_gossip_data.write().unwrap();
+
     let data = State {
         data: received_data,
-        timestamp: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis(),
+        timestamp: gossip_clock.timestamp_now(
+            self_node_gossip_data_lock
+                .as_ref()
+                .map(|state| state.timestamp),
+        ),
+        ttl,
     };
 
-    let mut self_node_gossip_data_lock = self_node_gossip_data.write().unwrap();
-    *self_node_gossip_data_lock = Some(data);
+    observer.gossip_state_changed(&data);
+
+    if let Some(journal) = journal {
+        journal.append(&data, JournalSource::Client, gossip_clock.now());
+    }
+
+    *self_node_gossip_data_lock = Some(data.clone());
+
+    data
+}
+
+/// Writes the update of `received_data` (see `write_data`), and
+/// returns the new state. With an `idempotency` key, an update
+/// retried with the same key is not written again: the state
+/// written by the first attempt is returned instead.
+///
+/// Returns an error if the key was used for a different update.
+fn update_data(
+    self_node_gossip_data: &RwLock<Option<State>>,
+    gossip_clock: &GossipClock,
+    received_data: String,
+    ttl: Option<u64>,
+    idempotency: Option<(&IdempotencyKeys, &str)>,
+    journal: Option<&Journal>,
+    observer: &dyn NodeObserver,
+) -> Result<State, String> {
+    let Some((idempotency_keys, key)) = idempotency else {
+        return Ok(write_data(
+            self_node_gossip_data,
+            gossip_clock,
+            received_data,
+            ttl,
+            journal,
+            observer,
+        ));
+    };
+
+    let data = received_data.clone();
+
+    idempotency_keys.write_once(key, &data, ttl, Instant::now(), || {
+        write_data(
+            self_node_gossip_data,
+            gossip_clock,
+            received_data,
+            ttl,
+            journal,
+            observer,
+        )
+    })
+}
+
+pub(crate) fn update_data_request_handler(
+    self_node_gossip_data: Arc<RwLock<Option<State>>>,
+    gossip_clock: &GossipClock,
+    received_data: String,
+    ttl: Option<u64>,
+    idempotency: Option<(&IdempotencyKeys, &str)>,
+    journal: Option<&Journal>,
+    observer: &dyn NodeObserver,
+) -> Result<GossipResponse, String> {
+    update_data(
+        &self_node_gossip_data,
+        gossip_clock,
+        received_data,
+        ttl,
+        idempotency,
+        journal,
+        observer,
+    )?;
+
+    Ok(GossipResponse::Ignore)
+}
+
+/// Updates the data of the node running in `context`, then
+/// pushes it synchronously to the peers of its successor list,
+/// until `required_acks` nodes (including the node) accepted it.
+///
+/// Reports a partial acknowledgment if not enough peers
+/// accepted it within `QUORUM_WRITE_TIMEOUT`. The pushed
+/// data is compressed according to `gossip-compression`.
+///
+/// An update retried with the same idempotency `key` pushes
+/// the state written by the first attempt, without writing it.
+pub(crate) fn update_data_with_ack_request_handler(
+    context: &NodeContext,
+    received_data: String,
+    required_acks: usize,
+    ttl: Option<u64>,
+    key: Option<&str>,
+) -> Result<GossipResponse, String> {
+    let deadline = Instant::now() + QUORUM_WRITE_TIMEOUT;
+    let data = update_data(
+        &context.self_node_gossip_data,
+        &context.gossip_clock,
+        received_data,
+        ttl,
+        key.map(|key| (context.idempotency_keys.as_ref(), key)),
+        context.gossip_journal.as_deref(),
+        context.observer.as_ref(),
+    )?;
+
+    let self_node_successor_list = context.self_node_successor_list.read().unwrap().clone();
+    let mut peers = Vec::new();
+
+    for successor in self_node_successor_list {
+        if successor != context.self_node && !peers.contains(&successor.get_public_addr()) {
+            peers.push(successor.get_public_addr());
+        }
+    }
+
+    // The node accepted the data
+    let peer_acks = request_initiator::push_until_acknowledged(
+        context.gossip_transport.as_ref(),
+        &data,
+        &peers,
+        required_acks.saturating_sub(1),
+        deadline,
+        context.options.gossip_compression,
+    );
 
-    GossipResponse::Ignore
+    Ok(GossipResponse::Acknowledged {
+        acks: peer_acks + 1,
+        required_acks,
+    })
 }
 
+/// Merges the state shared by a peer, or sends back the local
+/// one if the peer has none or an outdated one. A merged state
+/// is appended to the `journal`, if any, with its source.
+///
+/// Expiry is checked at `now` (in milliseconds since the UNIX epoch).
 pub(crate) fn share_data_request_handler(
     self_node_gossip_data: Arc<RwLock<Option<State>>>,
     received_data: Option<State>,
+    journal: Option<(&Journal, JournalSource)>,
+    now: u128,
+    observer: &dyn NodeObserver,
 ) -> GossipResponse {
-    let self_node_gossip_data_content = self_node_gossip_data.read().unwrap().clone();
+    // Expired data is no longer disseminated
+    let self_node_gossip_data_content = gossip::live_state(&self_node_gossip_data, now);
 
-    if received_data.is_none() && self_node_gossip_data_content.is_some() {
-        return GossipResponse::ResponseWithData(self_node_gossip_data_content.unwrap());
+    match (received_data, self_node_gossip_data_content) {
+        (None, Some(self_node_gossip_data_content)) => {
+            GossipResponse::ResponseWithData(self_node_gossip_data_content)
+        }
+        (Some(received_data), Some(self_node_gossip_data_content))
+            if self_node_gossip_data_content.supersedes(&received_data) =>
+        {
+            GossipResponse::ResponseWithData(self_node_gossip_data_content)
+        }
+        (Some(received_data), _) => {
+            if gossip::merge_state(&self_node_gossip_data, received_data.clone(), journal, now) {
+                observer.gossip_state_changed(&received_data);
+            }
+
+            GossipResponse::Ignore
+        }
+        (None, None) => GossipResponse::Ignore,
     }
+}
 
-    if received_data.is_some() && self_node_gossip_data_content.is_none() {
-        let mut self_node_gossip_data_lock = self_node_gossip_data.write().unwrap();
-        *self_node_gossip_data_lock = Some(received_data.unwrap());
-        return GossipResponse::Ignore;
+/// Returns the state of the node live at `now`, if any, to
+/// an observation tool. The state is left untouched.
+pub(crate) fn get_data_request_handler(
+    self_node_gossip_data: Arc<RwLock<Option<State>>>,
+    now: u128,
+) -> GossipResponse {
+    match gossip::live_state(&self_node_gossip_data, now) {
+        Some(state) => GossipResponse::ResponseWithData(state),
+        None => GossipResponse::Ignore,
     }
+}
 
-    if received_data.is_some() && self_node_gossip_data_content.is_some() {
-        let received_data = received_data.unwrap();
-        let self_node_gossip_data_content = self_node_gossip_data_content.unwrap();
+#[cfg(test)]
+mod update_data_request_handler_test {
+    use std::{
+        collections::VecDeque,
+        sync::{Arc, Mutex, RwLock},
+    };
 
-        if received_data.timestamp > self_node_gossip_data_content.timestamp {
-            let mut self_node_gossip_data_lock = self_node_gossip_data.write().unwrap();
-            *self_node_gossip_data_lock = Some(received_data);
-            return GossipResponse::Ignore;
+    use crate::{
+        cli::TimestampSource,
+        gossip::{
+            clock::GossipClock, idempotency::IdempotencyKeys, protocol::GossipResponse, State,
+        },
+        observer::NoopObserver,
+    };
+
+    use super::{share_data_request_handler, update_data_request_handler};
+
+    /// Clock whose wall time reads `timestamps`, in order.
+    fn fixed_clock(source: TimestampSource, timestamps: &[u128]) -> GossipClock {
+        let timestamps = Mutex::new(VecDeque::from(timestamps.to_vec()));
+
+        GossipClock::with_wall_clock(source, move || {
+            timestamps
+                .lock()
+                .unwrap()
+                .pop_front()
+                .expect("no wall time left")
+        })
+    }
+
+    fn update(data: &Arc<RwLock<Option<State>>>, clock: &GossipClock, value: &str) -> State {
+        update_data_request_handler(
+            Arc::clone(data),
+            clock,
+            value.to_string(),
+            None,
+            None,
+            None,
+            &NoopObserver,
+        )
+        .unwrap();
+
+        data.read().unwrap().clone().unwrap()
+    }
+
+    /// Shares the state of `from` with `to`, and merges the state
+    /// `to` sends back, if any, once the wall clocks read 20s.
+    fn gossip(from: &Arc<RwLock<Option<State>>>, to: &Arc<RwLock<Option<State>>>) {
+        let state = from.read().unwrap().clone();
+
+        if let GossipResponse::ResponseWithData(state) =
+            share_data_request_handler(Arc::clone(to), state, None, 20_000, &NoopObserver)
+        {
+            share_data_request_handler(Arc::clone(from), Some(state), None, 20_000, &NoopObserver);
         }
+    }
+
+    /// Node A, whose wall clock reads 10s, updates the data.
+    /// Node B, whose wall clock is 5s behind, updates it, receives
+    /// the update of A, then updates it again. Returns the states
+    /// of A and B once B gossiped with A.
+    fn states_after_updates(source: TimestampSource) -> (State, State) {
+        let clock_a = fixed_clock(source, &[10_000]);
+        let clock_b = fixed_clock(source, &[5_000, 6_000]);
+        let (data_a, data_b) = (Arc::new(RwLock::new(None)), Arc::new(RwLock::new(None)));
 
-        if received_data.timestamp < self_node_gossip_data_content.timestamp {
-            return GossipResponse::ResponseWithData(self_node_gossip_data_content);
+        assert_eq!(update(&data_a, &clock_a, "a1").timestamp, 10_000);
+        assert_eq!(update(&data_b, &clock_b, "b1").timestamp, 5_000);
+
+        gossip(&data_a, &data_b);
+        assert_eq!(data_b.read().unwrap().as_ref().unwrap().data, "a1");
+
+        update(&data_b, &clock_b, "b2");
+        gossip(&data_b, &data_a);
+
+        let state_of = |data: &Arc<RwLock<Option<State>>>| data.read().unwrap().clone().unwrap();
+
+        (state_of(&data_a), state_of(&data_b))
+    }
+
+    #[test]
+    fn retried_update_is_stamped_once_test() {
+        // A second write would need another wall time
+        let clock = fixed_clock(TimestampSource::WallClock, &[10_000]);
+        let data = Arc::new(RwLock::new(None));
+        let idempotency_keys = IdempotencyKeys::default();
+
+        for _ in 0..2 {
+            assert_eq!(
+                update_data_request_handler(
+                    Arc::clone(&data),
+                    &clock,
+                    String::from("a1"),
+                    None,
+                    Some((&idempotency_keys, "retry-1")),
+                    None,
+                    &NoopObserver,
+                ),
+                Ok(GossipResponse::Ignore)
+            );
         }
+
+        assert_eq!(data.read().unwrap().as_ref().unwrap().timestamp, 10_000);
+
+        // The key cannot be reused for another update
+        assert!(update_data_request_handler(
+            Arc::clone(&data),
+            &clock,
+            String::from("a2"),
+            None,
+            Some((&idempotency_keys, "retry-1")),
+            None,
+            &NoopObserver,
+        )
+        .is_err());
+        assert_eq!(data.read().unwrap().as_ref().unwrap().data, "a1");
     }
 
-    GossipResponse::Ignore
+    #[test]
+    fn merge_outcome_with_controlled_timestamps_test() {
+        let state = |data: &str, timestamp: u128| State {
+            data: data.to_string(),
+            timestamp,
+            ttl: None,
+        };
+
+        // The later update of B follows the one it replaced
+        assert_eq!(
+            states_after_updates(TimestampSource::Hybrid),
+            (state("b2", 10_001), state("b2", 10_001))
+        );
+
+        // With wall clocks, the update of A, timestamped ahead, wins
+        assert_eq!(
+            states_after_updates(TimestampSource::WallClock),
+            (state("a1", 10_000), state("a1", 10_000))
+        );
+    }
+}
+
+#[cfg(test)]
+mod update_data_with_ack_request_handler_test {
+    use std::{
+        io,
+        sync::{Arc, RwLock},
+    };
+
+    use crate::{
+        chord::Node,
+        cli::GossipCompression,
+        context::NodeContext,
+        gossip::protocol::{GossipRequest, GossipResponse},
+        transport::mock::MockTransport,
+    };
+
+    use super::update_data_with_ack_request_handler;
+
+    /// Mock transport where the node at port 300
+    /// is unreachable, and every other node accepts the data.
+    fn transport() -> Arc<MockTransport> {
+        Arc::new(MockTransport::new(|remote_addr, request_msg| {
+            if remote_addr.port() == 300 {
+                return Err(io::Error::from(io::ErrorKind::ConnectionRefused));
+            }
+
+            match GossipRequest::parse(request_msg) {
+                Ok(GossipRequest::ShareData(Some(_))) => {
+                    Ok(GossipResponse::Ignore.to_protocol_text(GossipCompression::None))
+                }
+                _ => Err(io::Error::from(io::ErrorKind::InvalidData)),
+            }
+        }))
+    }
+
+    fn request_acks(transport: &Arc<MockTransport>, required_acks: usize) -> GossipResponse {
+        let self_node = Node::for_test_at(100);
+        let context = NodeContext {
+            self_node: self_node.clone(),
+            self_node_successor_list: Arc::new(RwLock::new([
+                Node::for_test_at(200),
+                Node::for_test_at(300),
+                Node::for_test_at(400),
+                Node::for_test_at(100),
+                Node::for_test_at(200),
+            ])),
+            gossip_transport: transport.clone(),
+            ..NodeContext::for_test(self_node.get_public_addr())
+        };
+
+        update_data_with_ack_request_handler(
+            &context,
+            String::from("data"),
+            required_acks,
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn quorum_is_reached_test() {
+        let transport = transport();
+
+        assert_eq!(
+            request_acks(&transport, 3),
+            GossipResponse::Acknowledged {
+                acks: 3,
+                required_acks: 3
+            }
+        );
+
+        // Pushes stop once the quorum is reached
+        assert_eq!(
+            request_acks(&transport, 1),
+            GossipResponse::Acknowledged {
+                acks: 1,
+                required_acks: 1
+            }
+        );
+        assert_eq!(transport.sent_requests().len(), 3);
+    }
+
+    #[test]
+    fn partial_acknowledgment_is_reported_test() {
+        assert_eq!(
+            request_acks(&transport(), 5),
+            GossipResponse::Acknowledged {
+                acks: 3,
+                required_acks: 5
+            }
+        );
+    }
 }
@@ -1,43 +1,488 @@
-use std::{
-    io::{Read, Write},
-    net::{Shutdown, SocketAddr, TcpStream},
-    time::Duration,
+use std::{net::SocketAddr, time::Instant};
+
+use crate::{
+    cli::{GossipCompression, GossipMode},
+    context::NodeContext,
+    transport::Transport,
+};
+
+use super::{
+    journal::JournalSource,
+    live_state, merge_state,
+    protocol::{GossipRequest, GossipResponse},
+    skew::{self, SkewEstimate, SkewMonitor},
+    State,
 };
 
-use super::{protocol::GossipResponse, State};
+/// Runs a gossip exchange of the node running in `context`
+/// with `remote_addr`, in the mode given by `gossip-mode`:
+///
+/// - push-pull: shares the local state, then merges the state
+///   sent back by the remote node if it is fresher than the local one,
+/// - push: shares the local state, ignoring the reply
+///   (nothing is sent without live data),
+/// - pull: asks for the remote node's state (`SHARE_DATA=NONE;`),
+///   then merges it if it is fresher than the local one.
+///
+/// The shared data is compressed according to `gossip-compression`.
+/// A merged state is appended to the gossip journal, if any. Expiry
+/// is checked at the wall time of the gossip clock. With a skew
+/// monitor (`clock-skew-threshold`), the skew of the remote clock
+/// is checked as well. Returns `true` if the local state was updated.
+pub(crate) fn exchange_data(context: &NodeContext, remote_addr: SocketAddr) -> bool {
+    let transport = context.gossip_transport.as_ref();
+    let self_node_gossip_data = &context.self_node_gossip_data;
+    let gossip_clock = &context.gossip_clock;
+    let mode = context.options.gossip_mode;
+    let compression = context.options.gossip_compression;
 
-/// Initiates a request to `remote_addr` to share `data`.
-pub(crate) fn share_data(data: Option<State>, remote_addr: SocketAddr) -> GossipResponse {
-    let mut request_stream = match TcpStream::connect(remote_addr) {
-        Ok(stream) => stream,
-        Err(_) => return GossipResponse::Ignore,
+    // Expired data is no longer disseminated
+    let data = match mode {
+        GossipMode::PushPull | GossipMode::Push => {
+            live_state(self_node_gossip_data, gossip_clock.now())
+        }
+        GossipMode::Pull => None,
     };
 
-    let request_msg = match data {
-        Some(state) => format!("SHARE_DATA=[{}][{}];", state.data, state.timestamp),
-        None => "SHARE_DATA=NONE;".to_string(),
+    if mode == GossipMode::Push && data.is_none() {
+        return false;
+    }
+
+    let response = match context.skew_monitor.as_deref() {
+        Some(skew_monitor) => {
+            share_data_with_clock(transport, data, remote_addr, compression, skew_monitor)
+        }
+        None => share_data(transport, data, remote_addr, compression),
     };
 
-    if request_stream.write(request_msg.as_bytes()).is_err() {
-        return GossipResponse::Ignore;
+    match response {
+        GossipResponse::ResponseWithData(_) if mode == GossipMode::Push => false,
+        GossipResponse::ResponseWithData(response_data) => {
+            let is_updated = merge_state(
+                self_node_gossip_data,
+                response_data.clone(),
+                context
+                    .gossip_journal
+                    .as_deref()
+                    .map(|journal| (journal, JournalSource::Peer(Some(remote_addr)))),
+                gossip_clock.now(),
+            );
+
+            if is_updated {
+                context.observer.gossip_state_changed(&response_data);
+            }
+
+            is_updated
+        }
+        GossipResponse::Ignore | GossipResponse::Acknowledged { .. } => false,
     }
+}
 
-    if request_stream.shutdown(Shutdown::Write).is_err() {
-        return GossipResponse::Ignore;
+/// Pushes `state` to `peers`, one at a time, until `required_acks`
+/// of them accepted it, or until `deadline` is reached.
+///
+/// A peer accepts the state unless it holds a fresher one.
+/// Returns the number of peers which accepted the state.
+pub(crate) fn push_until_acknowledged(
+    transport: &dyn Transport,
+    state: &State,
+    peers: &[SocketAddr],
+    required_acks: usize,
+    deadline: Instant,
+    compression: GossipCompression,
+) -> usize {
+    let mut acks = 0;
+
+    for peer in peers {
+        if acks >= required_acks || Instant::now() >= deadline {
+            break;
+        }
+
+        // Unreachable peers do not acknowledge the state
+        if try_share_data(transport, Some(state.clone()), *peer, compression)
+            == Some(GossipResponse::Ignore)
+        {
+            acks += 1;
+        }
     }
 
-    request_stream
-        .set_read_timeout(Some(Duration::from_secs(5)))
-        .unwrap();
+    acks
+}
+
+/// Initiates a request to `remote_addr` to share `data`,
+/// compressed according to `compression`.
+pub(crate) fn share_data(
+    transport: &dyn Transport,
+    data: Option<State>,
+    remote_addr: SocketAddr,
+    compression: GossipCompression,
+) -> GossipResponse {
+    try_share_data(transport, data, remote_addr, compression).unwrap_or(GossipResponse::Ignore)
+}
+
+/// Same as `share_data`, but returns `None` if `remote_addr`
+/// is unreachable or its response is invalid.
+fn try_share_data(
+    transport: &dyn Transport,
+    data: Option<State>,
+    remote_addr: SocketAddr,
+    compression: GossipCompression,
+) -> Option<GossipResponse> {
+    let request_msg = GossipRequest::ShareData(data).to_protocol_text(compression);
+
+    let response_msg = transport.send(remote_addr, &request_msg).ok()?;
+
+    GossipResponse::parse(&response_msg).ok()
+}
 
-    let mut response_msg = String::new();
+/// Same as `share_data`, along with the wall time of the node, so that
+/// `remote_addr` answers with its own: `skew_monitor` then estimates
+/// the skew of its clock over the round trip.
+fn share_data_with_clock(
+    transport: &dyn Transport,
+    data: Option<State>,
+    remote_addr: SocketAddr,
+    compression: GossipCompression,
+    skew_monitor: &SkewMonitor,
+) -> GossipResponse {
+    let sent_at = skew_monitor.now();
+    let request_msg = skew::with_clock(
+        &GossipRequest::ShareData(data).to_protocol_text(compression),
+        sent_at,
+    );
 
-    if request_stream.read_to_string(&mut response_msg).is_err() {
+    let start = Instant::now();
+
+    let Ok(response_msg) = transport.send(remote_addr, &request_msg) else {
         return GossipResponse::Ignore;
+    };
+
+    let (remote_clock, response_msg) = skew::split_clock(&response_msg);
+
+    if let Some(remote_clock) = remote_clock {
+        skew_monitor.record(
+            remote_addr,
+            SkewEstimate::new(sent_at, start.elapsed(), remote_clock),
+        );
+    }
+
+    GossipResponse::parse(response_msg).unwrap_or(GossipResponse::Ignore)
+}
+
+#[cfg(test)]
+mod exchange_data_test {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        sync::{Arc, RwLock},
+        thread,
+        time::Duration,
+    };
+
+    use crate::{
+        cli::{GossipCompression, GossipMode, Options},
+        context::NodeContext,
+        gossip::{
+            self,
+            protocol::GossipRequest,
+            request_handler::share_data_request_handler,
+            skew::{self, SkewMonitor},
+            State,
+        },
+        observer::NoopObserver,
+    };
+
+    use super::exchange_data;
+
+    /// Context of a node holding `data`, exchanging
+    /// it in `mode` and compressing it as stated.
+    fn initiator_context(
+        data: Option<State>,
+        mode: GossipMode,
+        compression: GossipCompression,
+    ) -> NodeContext {
+        NodeContext {
+            self_node_gossip_data: Arc::new(RwLock::new(data)),
+            options: Options {
+                gossip_mode: mode,
+                gossip_compression: compression,
+                ..Options::default()
+            },
+            ..NodeContext::for_test("127.0.0.1:8000".parse().unwrap())
+        }
+    }
+
+    /// Serves a single gossip request on a loopback listener, on
+    /// behalf of a node holding `data` and compressing it as stated.
+    fn serve_once(
+        data: Arc<RwLock<Option<State>>>,
+        compression: GossipCompression,
+    ) -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request_msg = String::new();
+            stream.read_to_string(&mut request_msg).unwrap();
+
+            if let Ok(GossipRequest::ShareData(received_data)) = GossipRequest::parse(&request_msg)
+            {
+                let response = share_data_request_handler(
+                    data,
+                    received_data,
+                    None,
+                    gossip::now(),
+                    &NoopObserver,
+                );
+                stream
+                    .write_all(response.to_protocol_text(compression).as_bytes())
+                    .unwrap();
+            }
+        });
+
+        addr
     }
 
-    match GossipResponse::parse(&response_msg) {
-        Ok(response) => response,
-        Err(_) => GossipResponse::Ignore,
+    #[test]
+    fn stale_initiator_converges_test() {
+        let fresh_state = State {
+            data: String::from("fresh"),
+            timestamp: 2000,
+            ttl: None,
+        };
+
+        let fresh_node_data = Arc::new(RwLock::new(Some(fresh_state.clone())));
+        let stale_node = initiator_context(
+            Some(State {
+                data: String::from("stale"),
+                timestamp: 1000,
+                ttl: None,
+            }),
+            GossipMode::PushPull,
+            GossipCompression::None,
+        );
+
+        // Only the stale node initiates gossip.
+        let fresh_node_addr = serve_once(Arc::clone(&fresh_node_data), GossipCompression::None);
+
+        assert!(exchange_data(&stale_node, fresh_node_addr));
+        assert_eq!(
+            *stale_node.self_node_gossip_data.read().unwrap(),
+            Some(fresh_state.clone())
+        );
+        assert_eq!(*fresh_node_data.read().unwrap(), Some(fresh_state));
+    }
+
+    #[test]
+    fn empty_initiator_converges_test() {
+        let fresh_state = State {
+            data: String::from("fresh"),
+            timestamp: 2000,
+            ttl: None,
+        };
+
+        let fresh_node_addr = serve_once(
+            Arc::new(RwLock::new(Some(fresh_state.clone()))),
+            GossipCompression::None,
+        );
+        let empty_node = initiator_context(None, GossipMode::PushPull, GossipCompression::None);
+
+        assert!(exchange_data(&empty_node, fresh_node_addr));
+        assert_eq!(
+            *empty_node.self_node_gossip_data.read().unwrap(),
+            Some(fresh_state)
+        );
+    }
+
+    #[test]
+    fn compressed_exchange_converges_test() {
+        let fresh_state = State {
+            data: "fresh ".repeat(1000),
+            timestamp: 2000,
+            ttl: None,
+        };
+        let stale_state = State {
+            data: "stale ".repeat(1000),
+            timestamp: 1000,
+            ttl: None,
+        };
+
+        // Both the shared and the returned data are compressed
+        let fresh_node_data = Arc::new(RwLock::new(Some(fresh_state.clone())));
+        let fresh_node_addr = serve_once(Arc::clone(&fresh_node_data), GossipCompression::Deflate);
+        let stale_node = initiator_context(
+            Some(stale_state),
+            GossipMode::PushPull,
+            GossipCompression::Deflate,
+        );
+
+        assert!(exchange_data(&stale_node, fresh_node_addr));
+        assert_eq!(
+            *stale_node.self_node_gossip_data.read().unwrap(),
+            Some(fresh_state)
+        );
+    }
+
+    fn state(data: &str, timestamp: u128) -> Option<State> {
+        Some(State {
+            data: String::from(data),
+            timestamp,
+            ttl: None,
+        })
+    }
+
+    /// Runs an exchange in `mode`, from a node holding
+    /// `initiator_state` to a node holding `remote_state`,
+    /// and returns the states both nodes end up with.
+    fn exchange_in_mode(
+        mode: GossipMode,
+        initiator_state: Option<State>,
+        remote_state: Option<State>,
+    ) -> (Option<State>, Option<State>) {
+        let remote_node_data = Arc::new(RwLock::new(remote_state));
+        let remote_node_addr = serve_once(Arc::clone(&remote_node_data), GossipCompression::None);
+        let initiator = initiator_context(initiator_state, mode, GossipCompression::None);
+
+        exchange_data(&initiator, remote_node_addr);
+
+        let initiator_state = initiator.self_node_gossip_data.read().unwrap().clone();
+        let remote_state = remote_node_data.read().unwrap().clone();
+        (initiator_state, remote_state)
+    }
+
+    #[test]
+    fn push_only_exchange_test() {
+        // The fresh initiator updates the remote node
+        assert_eq!(
+            exchange_in_mode(GossipMode::Push, state("fresh", 2000), state("stale", 1000)),
+            (state("fresh", 2000), state("fresh", 2000))
+        );
+
+        // The stale initiator ignores the reply
+        assert_eq!(
+            exchange_in_mode(GossipMode::Push, state("stale", 1000), state("fresh", 2000)),
+            (state("stale", 1000), state("fresh", 2000))
+        );
+    }
+
+    #[test]
+    fn pull_only_exchange_test() {
+        // A new node converges by pulling from its neighbor
+        assert_eq!(
+            exchange_in_mode(GossipMode::Pull, None, state("fresh", 2000)),
+            (state("fresh", 2000), state("fresh", 2000))
+        );
+
+        // The fresh initiator does not share its data
+        assert_eq!(
+            exchange_in_mode(GossipMode::Pull, state("fresh", 2000), state("stale", 1000)),
+            (state("fresh", 2000), state("stale", 1000))
+        );
+    }
+
+    #[test]
+    fn push_pull_exchange_test() {
+        assert_eq!(
+            exchange_in_mode(
+                GossipMode::PushPull,
+                state("fresh", 2000),
+                state("stale", 1000)
+            ),
+            (state("fresh", 2000), state("fresh", 2000))
+        );
+        assert_eq!(
+            exchange_in_mode(
+                GossipMode::PushPull,
+                state("stale", 1000),
+                state("fresh", 2000)
+            ),
+            (state("fresh", 2000), state("fresh", 2000))
+        );
+    }
+
+    #[test]
+    fn equal_timestamps_converge_test() {
+        // Both nodes agree on the greatest data, whichever initiates
+        assert_eq!(
+            exchange_in_mode(
+                GossipMode::PushPull,
+                state("apple", 1500),
+                state("banana", 1500)
+            ),
+            (state("banana", 1500), state("banana", 1500))
+        );
+        assert_eq!(
+            exchange_in_mode(
+                GossipMode::PushPull,
+                state("banana", 1500),
+                state("apple", 1500)
+            ),
+            (state("banana", 1500), state("banana", 1500))
+        );
+        assert_eq!(
+            exchange_in_mode(
+                GossipMode::Pull,
+                state("apple", 1500),
+                state("banana", 1500)
+            ),
+            (state("banana", 1500), state("banana", 1500))
+        );
+        assert_eq!(
+            exchange_in_mode(
+                GossipMode::Push,
+                state("banana", 1500),
+                state("apple", 1500)
+            ),
+            (state("banana", 1500), state("banana", 1500))
+        );
+
+        // The losing data is never adopted
+        assert_eq!(
+            exchange_in_mode(
+                GossipMode::Push,
+                state("apple", 1500),
+                state("banana", 1500)
+            ),
+            (state("apple", 1500), state("banana", 1500))
+        );
+    }
+
+    #[test]
+    fn skewed_remote_clock_is_detected_test() {
+        let mut initiator = initiator_context(
+            state("data", 1000),
+            GossipMode::PushPull,
+            GossipCompression::None,
+        );
+        let skew_monitor = Arc::new(SkewMonitor::new(
+            Duration::from_secs(1),
+            Arc::clone(&initiator.gossip_clock),
+        ));
+        initiator.skew_monitor = Some(Arc::clone(&skew_monitor));
+
+        // A remote node whose clock is a minute ahead
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let remote_node_addr = listener.local_addr().unwrap();
+
+        let remote_node = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request_msg = String::new();
+            stream.read_to_string(&mut request_msg).unwrap();
+
+            let (initiator_clock, request_msg) = skew::split_clock(&request_msg);
+            let response_msg = skew::with_clock("RESPONSE=IGNORE;", gossip::now() + 60_000);
+            stream.write_all(response_msg.as_bytes()).unwrap();
+
+            (initiator_clock.is_some(), request_msg.to_string())
+        });
+
+        exchange_data(&initiator, remote_node_addr);
+
+        assert_eq!(
+            remote_node.join().unwrap(),
+            (true, "SHARE_DATA=[data][1000];".to_string())
+        );
+        assert!(skew_monitor.is_skewed(remote_node_addr));
     }
 }
@@ -0,0 +1,205 @@
+//! Detection of the clock skew between gossiping nodes
+//! (`clock-skew-threshold`).
+//!
+//! Merged states are ordered by wall-clock timestamp, so a node whose
+//! clock is ahead keeps winning over the later updates of the other
+//! nodes, without any visible failure. To make it visible, the initiator
+//! of a `SHARE_DATA` exchange prefixes its request with its wall time
+//! (`CLOCK=[<ms>];`), and the remote node prefixes its response with
+//! its own. The initiator estimates the offset of the remote clock
+//! against the midpoint of the round trip, which is exact within half
+//! the round-trip time, and logs the peers skewed beyond the threshold.
+//!
+//! The skew is only reported, not corrected. A request without
+//! the prefix (e.g. from an older node) is answered without it.
+
+use std::{
+    collections::HashSet,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use super::clock::GossipClock;
+
+/// Prefixes `msg` with the wall time `now`
+/// (in milliseconds since the UNIX epoch).
+pub(crate) fn with_clock(msg: &str, now: u128) -> String {
+    format!("CLOCK=[{}];{}", now, msg)
+}
+
+/// Splits the wall time prefixing `msg`, if any, from the rest of it.
+pub(crate) fn split_clock(msg: &str) -> (Option<u128>, &str) {
+    let clock = msg
+        .strip_prefix("CLOCK=[")
+        .and_then(|msg| msg.split_once("];"))
+        .and_then(|(clock, rest)| Some((clock.parse::<u128>().ok()?, rest)));
+
+    match clock {
+        Some((clock, rest)) => (Some(clock), rest),
+        None => (None, msg),
+    }
+}
+
+/// Offset of the clock of a peer from the local clock,
+/// estimated over a round trip.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct SkewEstimate {
+    /// Positive if the peer's clock is ahead, in milliseconds.
+    offset: i128,
+    /// Bound of the estimation error, in milliseconds: the
+    /// peer read its clock at some point of the round trip.
+    uncertainty: u128,
+}
+
+impl SkewEstimate {
+    /// Estimates the skew of a peer, which read `peer_clock` while
+    /// answering a request sent at local wall time `sent_at` and
+    /// answered after `round_trip`.
+    pub(crate) fn new(sent_at: u128, round_trip: Duration, peer_clock: u128) -> Self {
+        let uncertainty = round_trip.as_millis() / 2;
+        let midpoint = sent_at.saturating_add(uncertainty);
+
+        Self {
+            offset: peer_clock as i128 - midpoint as i128,
+            uncertainty,
+        }
+    }
+
+    /// Whether the skew exceeds `threshold`, whatever
+    /// the split of the round trip between both ways.
+    fn exceeds(&self, threshold: Duration) -> bool {
+        self.offset.unsigned_abs() > threshold.as_millis().saturating_add(self.uncertainty)
+    }
+}
+
+/// Logs the peers whose clock is skewed beyond `threshold`: once
+/// when a peer is found skewed, and once when it is back in sync.
+#[derive(Debug)]
+pub(crate) struct SkewMonitor {
+    threshold: Duration,
+    /// Clock of the gossip updates of the node.
+    clock: Arc<GossipClock>,
+    skewed_peers: Mutex<HashSet<SocketAddr>>,
+}
+
+impl SkewMonitor {
+    pub(crate) fn new(threshold: Duration, clock: Arc<GossipClock>) -> Self {
+        Self {
+            threshold,
+            clock,
+            skewed_peers: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// Returns the wall time of the node, in
+    /// milliseconds since the UNIX epoch.
+    pub(crate) fn now(&self) -> u128 {
+        self.clock.now()
+    }
+
+    /// Records the skew `estimate` of `peer`.
+    ///
+    /// Returns whether `peer` is skewed beyond the threshold.
+    pub(crate) fn record(&self, peer: SocketAddr, estimate: SkewEstimate) -> bool {
+        let is_skewed = estimate.exceeds(self.threshold);
+        let mut skewed_peers = self.skewed_peers.lock().unwrap();
+
+        if is_skewed && skewed_peers.insert(peer) {
+            let (direction, consequence) = if estimate.offset > 0 {
+                ("ahead", "its updates win over later ones")
+            } else {
+                ("behind", "its updates lose to earlier ones")
+            };
+
+            eprintln!(
+                "clock skew: the clock of [{}] is {} by about {} ms (± {} ms), beyond {} ms: {}",
+                peer,
+                direction,
+                estimate.offset.unsigned_abs(),
+                estimate.uncertainty,
+                self.threshold.as_millis(),
+                consequence
+            );
+        } else if !is_skewed && skewed_peers.remove(&peer) {
+            eprintln!(
+                "clock skew: the clock of [{}] is back within {} ms",
+                peer,
+                self.threshold.as_millis()
+            );
+        }
+
+        is_skewed
+    }
+
+    /// Whether `peer` was last found skewed.
+    #[cfg(test)]
+    pub(crate) fn is_skewed(&self, peer: SocketAddr) -> bool {
+        self.skewed_peers.lock().unwrap().contains(&peer)
+    }
+}
+
+#[cfg(test)]
+mod skew_test {
+    use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+    use crate::{cli::TimestampSource, gossip::clock::GossipClock};
+
+    use super::{split_clock, with_clock, SkewEstimate, SkewMonitor};
+
+    #[test]
+    fn clock_prefix_round_trip_test() {
+        let msg = with_clock("SHARE_DATA=NONE;", 1_700_000_000_000);
+
+        assert_eq!(msg, "CLOCK=[1700000000000];SHARE_DATA=NONE;");
+        assert_eq!(
+            split_clock(&msg),
+            (Some(1_700_000_000_000), "SHARE_DATA=NONE;")
+        );
+
+        // Requests without a valid prefix are left as is
+        assert_eq!(split_clock("SHARE_DATA=NONE;"), (None, "SHARE_DATA=NONE;"));
+        assert_eq!(
+            split_clock("CLOCK=[soon];SHARE_DATA=NONE;"),
+            (None, "CLOCK=[soon];SHARE_DATA=NONE;")
+        );
+    }
+
+    #[test]
+    fn skew_accounts_for_round_trip_test() {
+        let threshold = Duration::from_millis(1000);
+
+        // Read halfway through a 200 ms round trip
+        let estimate = SkewEstimate::new(10_000, Duration::from_millis(200), 10_100);
+        assert_eq!(estimate.offset, 0);
+        assert!(!estimate.exceeds(threshold));
+
+        let estimate = SkewEstimate::new(10_000, Duration::from_millis(200), 15_100);
+        assert_eq!(estimate.offset, 5_000);
+        assert!(estimate.exceeds(threshold));
+
+        let estimate = SkewEstimate::new(10_000, Duration::from_millis(200), 5_100);
+        assert_eq!(estimate.offset, -5_000);
+        assert!(estimate.exceeds(threshold));
+
+        // Within the threshold, up to the uncertainty of a slow round trip
+        let estimate = SkewEstimate::new(10_000, Duration::from_millis(4000), 13_500);
+        assert!(!estimate.exceeds(threshold));
+    }
+
+    #[test]
+    fn skewed_peer_is_tracked_until_back_in_sync_test() {
+        let monitor = SkewMonitor::new(
+            Duration::from_millis(1000),
+            Arc::new(GossipClock::new(TimestampSource::WallClock)),
+        );
+        let peer = SocketAddr::from(([127, 0, 0, 1], 8000));
+        let round_trip = Duration::from_millis(10);
+
+        assert!(monitor.record(peer, SkewEstimate::new(10_000, round_trip, 70_000)));
+        assert!(monitor.is_skewed(peer));
+
+        assert!(!monitor.record(peer, SkewEstimate::new(10_000, round_trip, 10_005)));
+        assert!(!monitor.is_skewed(peer));
+    }
+}
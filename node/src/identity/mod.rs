@@ -0,0 +1,172 @@
+//! Derivation of a node's identifier
+//! from its public socket address, and of
+//! the position of a key in the same space.
+//!
+//! External tools must use these functions, instead of
+//! reimplementing the derivation: a different byte layout
+//! (e.g. the port's endianness) silently yields a different position.
+
+use std::net::{IpAddr, SocketAddr};
+
+/// Returns the identifier, in raw bytes format, of the node
+/// reachable at `public_addr`: the MD5 hash of the IP octets
+/// followed by the big-endian port.
+pub fn identifier_of(public_addr: SocketAddr) -> [u8; 16] {
+    identifier_of_key(&socket_addr_bytes(public_addr))
+}
+
+/// Returns the identifier, in raw bytes format, of an arbitrary
+/// application `key`: its MD5 hash, the hash function of the node
+/// identifiers, so that keys and nodes share the same ring.
+pub fn identifier_of_key(key: &[u8]) -> [u8; 16] {
+    md5::compute(key).0
+}
+
+/// Returns the identifier, in raw bytes format, of virtual node
+/// `vnode` of the node reachable at `public_addr`: the MD5 hash
+/// of the IP octets, the big-endian port, then the big-endian index.
+///
+/// Virtual node 0 is the node itself, identified by `identifier_of`.
+pub fn virtual_identifier_of(public_addr: SocketAddr, vnode: u16) -> [u8; 16] {
+    if vnode == 0 {
+        return identifier_of(public_addr);
+    }
+
+    let mut virtual_node_bytes = socket_addr_bytes(public_addr);
+    virtual_node_bytes.extend_from_slice(&vnode.to_be_bytes());
+
+    identifier_of_key(&virtual_node_bytes)
+}
+
+/// Returns the hashed bytes of `public_addr`:
+/// the IP octets followed by the big-endian port.
+fn socket_addr_bytes(public_addr: SocketAddr) -> Vec<u8> {
+    let mut socket_addr_bytes = Vec::new();
+
+    match public_addr.ip() {
+        IpAddr::V4(ip_v4) => socket_addr_bytes.extend_from_slice(&ip_v4.octets()),
+        IpAddr::V6(ip_v6) => socket_addr_bytes.extend_from_slice(&ip_v6.octets()),
+    }
+
+    socket_addr_bytes.extend_from_slice(&public_addr.port().to_be_bytes());
+
+    socket_addr_bytes
+}
+
+/// Returns `identifier` on a ring of `bit_length` bits: its
+/// leading `bit_length` bits, followed by zero bytes.
+///
+/// # Panics
+///
+/// Panics if `bit_length` is not a multiple of 8, at most 128.
+pub fn identifier_prefix(mut identifier: [u8; 16], bit_length: usize) -> [u8; 16] {
+    assert!(
+        bit_length.is_multiple_of(8) && bit_length <= 128,
+        "invalid identifier bit length"
+    );
+
+    identifier[bit_length / 8..].fill(0);
+
+    identifier
+}
+
+/// Returns the position in a Chord ring of `bit_length` bits of
+/// the node reachable at `public_addr`, left-aligned (see
+/// `identifier_prefix`), as the positions compared by the nodes.
+///
+/// # Panics
+///
+/// Panics if `bit_length` is not a multiple of 8, at most 128.
+pub fn ring_position_of(public_addr: SocketAddr, bit_length: usize) -> u128 {
+    u128::from_be_bytes(identifier_prefix(identifier_of(public_addr), bit_length))
+}
+
+/// Returns the identifier of the node reachable at `public_addr`
+/// in a Chord ring of `bit_length` bits, as the hash string
+/// (hexadecimal format, `bit_length / 4` digits) used by the protocol.
+///
+/// # Panics
+///
+/// Panics if `bit_length` is not a multiple of 8, at most 128.
+pub fn hash_id_of(public_addr: SocketAddr, bit_length: usize) -> String {
+    hex::encode(&identifier_prefix(identifier_of(public_addr), bit_length)[..bit_length / 8])
+}
+
+#[cfg(test)]
+mod identity_test {
+    use std::net::SocketAddr;
+
+    use super::{
+        hash_id_of, identifier_of, identifier_of_key, identifier_prefix, ring_position_of,
+        virtual_identifier_of,
+    };
+
+    #[test]
+    fn hash_id_of_test() {
+        assert_eq!(
+            hash_id_of("127.0.0.1:8000".parse::<SocketAddr>().unwrap(), 128),
+            "f41fec42855bc4c2fa9dd3e504a846d8"
+        );
+        assert_eq!(
+            hash_id_of("[2001:db8::1]:4040".parse::<SocketAddr>().unwrap(), 128),
+            "c93fe6b3c5de0b8225c6e0d220f653da"
+        );
+        assert_eq!(
+            hash_id_of("127.0.0.1:8000".parse::<SocketAddr>().unwrap(), 32),
+            "f41fec42"
+        );
+    }
+
+    #[test]
+    fn identifier_of_key_test() {
+        assert_eq!(
+            hex::encode(identifier_of_key(b"user:42")),
+            "56dadf1868c3ba340467c942a86c29de"
+        );
+        assert_eq!(
+            identifier_of_key(&[127, 0, 0, 1, 0x1f, 0x40]),
+            identifier_of("127.0.0.1:8000".parse::<SocketAddr>().unwrap())
+        );
+    }
+
+    #[test]
+    fn ring_position_of_test() {
+        assert_eq!(
+            ring_position_of("127.0.0.1:8000".parse::<SocketAddr>().unwrap(), 128),
+            0xf41fec42855bc4c2fa9dd3e504a846d8
+        );
+        assert_eq!(
+            ring_position_of("127.0.0.1:8000".parse::<SocketAddr>().unwrap(), 32),
+            0xf41fec42 << 96
+        );
+    }
+
+    #[test]
+    fn identifier_prefix_test() {
+        let identifier = identifier_of("127.0.0.1:8000".parse::<SocketAddr>().unwrap());
+
+        assert_eq!(
+            u128::from_be_bytes(identifier_prefix(identifier, 32)),
+            0xf41fec42 << 96
+        );
+        assert_eq!(identifier_prefix(identifier, 128), identifier);
+    }
+
+    #[test]
+    fn virtual_identifier_of_test() {
+        let public_addr = "127.0.0.1:8000".parse::<SocketAddr>().unwrap();
+
+        assert_eq!(
+            virtual_identifier_of(public_addr, 0),
+            identifier_of(public_addr)
+        );
+        assert_eq!(
+            virtual_identifier_of(public_addr, 1),
+            md5::compute([127, 0, 0, 1, 0x1f, 0x40, 0, 1]).0
+        );
+        assert_ne!(
+            virtual_identifier_of(public_addr, 1),
+            virtual_identifier_of(public_addr, 2)
+        );
+    }
+}
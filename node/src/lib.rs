@@ -0,0 +1,411 @@
+//! A Chord node disseminating data by gossip, run by the
+//! `node` binary (see `run`) or embedded in an application
+//! (see `RunningNode`), and utilities shared with external
+//! tools (clients, admin tools) that need to reproduce the
+//! behavior of a Chord node.
+
+use std::{
+    env,
+    error::Error,
+    net::TcpListener,
+    process,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use chord::{Node, SUCCESSOR_LIST_LENGTH};
+use cli::{Args, IdentityMode, PrintIdArgs};
+use context::VirtualNodes;
+use global_request_handler::Listener;
+use gossip::State;
+use observer::{NodeObserver, NoopObserver};
+use runtime::shutdown::Shutdown;
+
+/// Time given to the threads of the `node` binary
+/// to stop, once its shutdown is requested.
+const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+mod admin_http;
+mod chord;
+mod cli;
+pub mod client;
+mod context;
+mod global_request_handler;
+mod gossip;
+pub mod identity;
+pub mod observer;
+pub mod ring;
+mod runtime;
+mod transport;
+
+/// A node running in the process, along with
+/// the application embedding it.
+///
+/// Its requests are served, and its network stabilization
+/// and data dissemination run, in background threads.
+#[derive(Clone)]
+pub struct RunningNode {
+    virtual_nodes: VirtualNodes,
+}
+
+impl RunningNode {
+    /// Starts the node described by `args`, the arguments
+    /// of the `node` binary (e.g. `["join", "self-port=8001",
+    /// "public-addr=127.0.0.1:8001", "remote-addr=127.0.0.1:8000"]`),
+    /// reporting its events to `observer`.
+    ///
+    /// Settings missing from `args` are read from the config
+    /// file (`config=<path>`), if any, but not from the
+    /// environment, which belongs to the embedding application.
+    pub fn start<I, S>(args: I, observer: Arc<dyn NodeObserver>) -> Result<Self, Box<dyn Error>>
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let args = Args::parse_from(args.into_iter().map(Into::into), |_| None)?;
+        let (local_listener, virtual_nodes) = launch(&args, observer)?;
+
+        let server_virtual_nodes = virtual_nodes.clone();
+
+        virtual_nodes.first().shutdown.spawn("server", move || {
+            if let Err(err) = runtime::serve(local_listener, server_virtual_nodes, Listener::Main) {
+                eprintln!("{}", err);
+            }
+        });
+
+        Ok(Self { virtual_nodes })
+    }
+
+    /// Returns virtual node 0 of the process.
+    pub fn self_node(&self) -> Node {
+        self.virtual_nodes.first().self_node
+    }
+
+    /// Leaves the network gracefully with every virtual node, as
+    /// on a `SHUTDOWN` request, then waits for the background
+    /// threads of the node to stop.
+    ///
+    /// Returns an error naming the threads still running
+    /// after `timeout`, which are left detached, or the
+    /// failure which stopped the node (e.g. isolated
+    /// with `on-isolation=exit`), if any.
+    pub fn shutdown(&self, timeout: Duration) -> Result<(), String> {
+        if !self.virtual_nodes.first().shutdown.is_requested() {
+            global_request_handler::leave_network(&self.virtual_nodes);
+        }
+
+        self.virtual_nodes.first().shutdown.stop(timeout)
+    }
+
+    /// Starts a node initiating a new network on a free loopback
+    /// port, with the options `extra_args`, reporting to `observer`.
+    #[cfg(test)]
+    pub(crate) fn init_for_test(extra_args: &[&str], observer: Arc<dyn NodeObserver>) -> Self {
+        // The public address is set before the node binds its port
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+
+        let mut args = vec![
+            "init".to_string(),
+            format!("self-port={}", port),
+            format!("public-addr=127.0.0.1:{}", port),
+        ];
+        args.extend(extra_args.iter().map(|arg| arg.to_string()));
+
+        let node = Self::start(args, observer).unwrap();
+        assert_eq!(node.self_node().get_public_addr().port(), port);
+
+        node
+    }
+}
+
+/// Runs the `node` binary: initiates or joins the network
+/// described by the command line (or checks the protocol,
+/// or prints the node's identifiers), then serves requests
+/// until a `SHUTDOWN` or `DRAIN` request, once every thread
+/// of the node has stopped (within `SHUTDOWN_TIMEOUT`).
+///
+/// Returns an error if the node failed (e.g. isolated
+/// with `on-isolation=exit`), for the binary to exit.
+pub fn run() -> Result<(), Box<dyn Error>> {
+    if env::args().nth(1).as_deref() == Some(cli::SELF_TEST_ACTION) {
+        match runtime::self_test::check_protocol() {
+            Ok(()) => {
+                println!("protocol OK");
+                return Ok(());
+            }
+            Err(err) => {
+                eprintln!("protocol self-test failed: {}", err);
+                process::exit(1);
+            }
+        }
+    }
+
+    if env::args().nth(1).as_deref() == Some(cli::PRINT_ID_ACTION) {
+        let print_id_args = PrintIdArgs::parse_from(env::args().skip(2)).unwrap_or_else(|err| {
+            eprintln!("{}", err);
+            process::exit(2);
+        });
+        let options = &print_id_args.options;
+
+        // One line per virtual node, as in the startup report
+        let self_node = runtime::create_self_node(
+            print_id_args.public_addr,
+            options.identity_mode,
+            options.ring_bit_length,
+        );
+        println!("{}", runtime::report::node_json(&self_node));
+
+        for vnode in 1..options.vnodes {
+            let virtual_node = Node::new_virtual(print_id_args.public_addr, vnode)
+                .on_ring(options.ring_bit_length);
+            println!("{}", runtime::report::node_json(&virtual_node));
+        }
+
+        return Ok(());
+    }
+
+    let args = Args::parse().unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        process::exit(2);
+    });
+
+    let (local_listener, virtual_nodes) = launch(&args, Arc::new(NoopObserver))?;
+
+    runtime::serve(local_listener, virtual_nodes.clone(), Listener::Main)?;
+
+    virtual_nodes.first().shutdown.stop(SHUTDOWN_TIMEOUT)?;
+
+    Ok(())
+}
+
+/// Binds the listeners of the node described by `args`, starts
+/// it (see `runtime::start_node`) along with its virtual nodes,
+/// and serves its data and admin HTTP listeners, if any, in
+/// background threads.
+///
+/// Returns the main listener, which the caller serves.
+fn launch(
+    args: &Args,
+    observer: Arc<dyn NodeObserver>,
+) -> Result<(TcpListener, VirtualNodes), Box<dyn Error>> {
+    let local_listener = runtime::accept::bind_local_listener(
+        args.get_self_port(),
+        args.get_options().listen_backlog,
+    )
+    .map_err(|err| format!("cannot establish a TCP local listener: {}", err))?;
+
+    let data_listener = match args.get_options().data_port {
+        Some(data_port) => Some(
+            runtime::accept::bind_local_listener(data_port, args.get_options().listen_backlog)
+                .map_err(|err| format!("cannot establish the TCP data listener: {}", err))?,
+        ),
+        None => None,
+    };
+
+    let admin_http_listener = match args.get_options().admin_http_addr {
+        Some(admin_http_addr) => Some(
+            TcpListener::bind(admin_http_addr)
+                .map_err(|err| format!("cannot establish the admin HTTP listener: {}", err))?,
+        ),
+        None => None,
+    };
+
+    let context = runtime::start_node(args, &local_listener, observer)?;
+
+    runtime::report::emit_startup_report(
+        args,
+        local_listener.local_addr()?,
+        &context.self_node,
+        &context.self_node_successor_list.read().unwrap().clone(),
+    )?;
+
+    if let Some(status_interval) = context.options.status_interval {
+        print_self_node_core_components(
+            context.self_node.clone(),
+            Arc::clone(&context.self_node_predecessor),
+            Arc::clone(&context.self_node_successor_list),
+            Arc::clone(&context.self_node_gossip_data),
+            context.options.identity_mode,
+            status_interval,
+            &context.shutdown,
+        );
+    }
+
+    let virtual_nodes = VirtualNodes::new(context);
+    runtime::start_virtual_nodes(&virtual_nodes);
+
+    if let Some(data_listener) = data_listener {
+        let virtual_nodes = virtual_nodes.clone();
+
+        virtual_nodes
+            .first()
+            .shutdown
+            .spawn("data listener", move || {
+                if let Err(err) = runtime::serve(data_listener, virtual_nodes, Listener::Data) {
+                    eprintln!("{}", err);
+                    process::exit(1);
+                }
+            });
+    }
+
+    if let Some(admin_http_listener) = admin_http_listener {
+        let virtual_nodes = virtual_nodes.clone();
+
+        virtual_nodes
+            .first()
+            .shutdown
+            .spawn("admin HTTP listener", move || {
+                admin_http::serve(admin_http_listener, virtual_nodes)
+            });
+    }
+
+    Ok((local_listener, virtual_nodes))
+}
+
+/// Prints the current node `self_node` and its Chord core
+/// components every `interval`, in a separate thread,
+/// until `shutdown` is requested.
+fn print_self_node_core_components(
+    self_node: Node,
+    self_node_predecessor: Arc<RwLock<Option<Node>>>,
+    self_node_successor_list: Arc<RwLock<[Node; SUCCESSOR_LIST_LENGTH]>>,
+    self_node_gossip_data: Arc<RwLock<Option<State>>>,
+    identity_mode: IdentityMode,
+    interval: Duration,
+    shutdown: &Shutdown,
+) {
+    let thread_shutdown = shutdown.clone();
+
+    shutdown.spawn("status", move || loop {
+        println!("SELF-NODE: [{}]", describe_node(&self_node, identity_mode));
+
+        println!("-------------------------------------------------");
+
+        println!(
+            "PREDECESSOR: [{}]",
+            match *self_node_predecessor.read().unwrap() {
+                Some(ref node) => describe_node(node, identity_mode),
+                None => String::from("NONE"),
+            }
+        );
+
+        println!("-------------------------------------------------");
+
+        let gossip_data = {
+            let gossip_data = self_node_gossip_data.read().unwrap().clone();
+            match gossip_data {
+                Some(state) => state.data,
+                None => "NONE".to_string(),
+            }
+        };
+
+        println!("DATA: [{}]", gossip_data);
+
+        println!("-------------------------------------------------");
+
+        println!("SUCCESSOR LIST:");
+
+        for (i, node) in self_node_successor_list.read().unwrap().iter().enumerate() {
+            println!("\t {} => [{}]", i + 1, describe_node(node, identity_mode))
+        }
+
+        println!("#################################################");
+
+        if !thread_shutdown.sleep(interval) {
+            break;
+        }
+    });
+}
+
+/// Describes `node` in the status output: its public socket
+/// address, followed by its ring position in sequential identity mode.
+fn describe_node(node: &Node, identity_mode: IdentityMode) -> String {
+    match identity_mode {
+        IdentityMode::Hashed => format!("{:?}", node.get_public_addr()),
+        IdentityMode::Sequential(_) => {
+            format!("{:?} #{}", node.get_public_addr(), node.get_identifier())
+        }
+    }
+}
+
+#[cfg(test)]
+mod running_node_test {
+    use std::{
+        net::TcpStream,
+        sync::{Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        observer::{NodeObserver, NoopObserver, State},
+        transport::{TcpTransport, Transport},
+    };
+
+    use super::RunningNode;
+
+    /// Observer recording the gossip states of a node.
+    #[derive(Default)]
+    struct StateRecorder {
+        states: Mutex<Vec<State>>,
+    }
+
+    impl NodeObserver for StateRecorder {
+        fn gossip_state_changed(&self, state: &State) {
+            self.states.lock().unwrap().push(state.clone());
+        }
+    }
+
+    #[test]
+    fn embedded_node_reports_to_its_observer_test() {
+        let observer = Arc::new(StateRecorder::default());
+        let node = RunningNode::init_for_test(&[], observer.clone());
+        let public_addr = node.self_node().get_public_addr();
+
+        assert_eq!(
+            TcpTransport::new()
+                .send(public_addr, "UPDATE_DATA=[embedded];")
+                .unwrap(),
+            "RESPONSE=IGNORE;"
+        );
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+
+        while !observer
+            .states
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|state| state.data == "embedded")
+        {
+            assert!(Instant::now() < deadline, "no gossip state change reported");
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        assert_eq!(node.shutdown(Duration::from_secs(10)), Ok(()));
+        assert!(TcpStream::connect(public_addr).is_err());
+    }
+
+    #[test]
+    fn shutdown_request_stops_the_embedded_node_test() {
+        let node = RunningNode::init_for_test(&["admin-secret=s3cr3t"], Arc::new(NoopObserver));
+        let public_addr = node.self_node().get_public_addr();
+
+        assert_eq!(
+            TcpTransport::new()
+                .send(public_addr, "SHUTDOWN=[s3cr3t];")
+                .unwrap(),
+            "ACKNOWLEDGED;"
+        );
+
+        let start = Instant::now();
+
+        assert_eq!(node.shutdown(Duration::from_secs(10)), Ok(()));
+        assert!(start.elapsed() < Duration::from_secs(10));
+        assert!(TcpStream::connect(public_addr).is_err());
+    }
+}
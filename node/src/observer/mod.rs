@@ -0,0 +1,32 @@
+//! Hook notifying an embedding application
+//! of the events happening on a node.
+
+pub use crate::{chord::Node, gossip::State};
+
+/// Receives the events of a running node.
+///
+/// Callbacks are invoked synchronously, from the server
+/// and background threads: they must return quickly.
+/// Every callback does nothing by default.
+pub trait NodeObserver: Send + Sync {
+    /// A new predecessor has been accepted.
+    fn predecessor_accepted(&self, _predecessor: &Node) {}
+
+    /// A successor list entry did not answer
+    /// during network stabilization.
+    fn successor_unreachable(&self, _successor: &Node) {}
+
+    /// A neighbor announced its departure from the network.
+    fn neighbor_left(&self, _neighbor: &Node) {}
+
+    /// The successor of `target_node` has been located.
+    fn lookup_completed(&self, _target_node: &Node, _successor: &Node) {}
+
+    /// The local gossip state has changed.
+    fn gossip_state_changed(&self, _state: &State) {}
+}
+
+/// Observer ignoring every event.
+pub struct NoopObserver;
+
+impl NodeObserver for NoopObserver {}
@@ -0,0 +1,8 @@
+//! Ownership of the keys of a ring, computed from a known
+//! view of its members without any network request, for
+//! external tools (e.g. to check the owner a lookup returns).
+//!
+//! The members are rebuilt from the responses listing them
+//! with `Node::at_position`.
+
+pub use crate::chord::{key_position, utils::owner_of, Node};
@@ -0,0 +1,220 @@
+//! Local listener of a node, and handling of the
+//! errors returned when accepting connections.
+
+use std::{
+    io,
+    net::{Ipv4Addr, SocketAddr, TcpListener},
+    time::{Duration, Instant},
+};
+
+use socket2::{Domain, Socket, Type};
+
+/// Delay before accepting again, after the
+/// first failure due to resource exhaustion.
+const MIN_ACCEPT_BACKOFF: Duration = Duration::from_millis(10);
+
+/// Maximum delay before accepting again,
+/// while resources remain exhausted.
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Minimum interval between two logged failures,
+/// while resources remain exhausted.
+const ACCEPT_FAILURE_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Binds the local listener on all interfaces at `self_port`,
+/// queuing at most `backlog` pending connections.
+pub(crate) fn bind_local_listener(self_port: u16, backlog: u32) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::IPV4, Type::STREAM, None)?;
+
+    // Same as `TcpListener::bind`, so that a restarted
+    // node can bind its port right away
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+
+    socket.bind(&SocketAddr::from((Ipv4Addr::UNSPECIFIED, self_port)).into())?;
+    socket.listen(backlog.min(i32::MAX as u32) as i32)?;
+
+    Ok(socket.into())
+}
+
+/// How the accept loop reacts to an error returned by `accept()`.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum AcceptError {
+    /// The pending connection failed (e.g. it was reset
+    /// by the remote node). The next one can be accepted.
+    Connection,
+    /// The process or the system ran out of resources (file
+    /// descriptors, memory). Accepting again right away would
+    /// fail the same way, so the loop backs off.
+    ResourceExhaustion,
+    /// The listener itself is unusable.
+    Listener,
+}
+
+impl AcceptError {
+    pub(crate) fn classify(err: &io::Error) -> Self {
+        match err.raw_os_error() {
+            Some(libc::EMFILE | libc::ENFILE | libc::ENOBUFS | libc::ENOMEM) => {
+                Self::ResourceExhaustion
+            }
+            Some(libc::EBADF | libc::EINVAL | libc::ENOTSOCK | libc::EOPNOTSUPP | libc::EFAULT) => {
+                Self::Listener
+            }
+            _ if err.kind() == io::ErrorKind::OutOfMemory => Self::ResourceExhaustion,
+            _ => Self::Connection,
+        }
+    }
+}
+
+/// Backoff of the accept loop while resources are exhausted.
+///
+/// The delay doubles after each failure, up to `MAX_ACCEPT_BACKOFF`,
+/// and failures are logged at most once per `ACCEPT_FAILURE_LOG_INTERVAL`.
+#[derive(Debug, Default)]
+pub(crate) struct AcceptBackoff {
+    delay: Option<Duration>,
+    failures: u64,
+    unlogged_failures: u64,
+    last_logged_at: Option<Instant>,
+}
+
+/// Outcome of a failure due to resource exhaustion.
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) struct Backoff {
+    /// Delay before accepting again.
+    pub delay: Duration,
+    /// Number of failures to report in the log (including
+    /// this one), or `None` if the log is throttled.
+    pub failures_to_log: Option<u64>,
+}
+
+impl AcceptBackoff {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records a failure due to resource exhaustion at `now`.
+    pub(crate) fn on_resource_exhaustion(&mut self, now: Instant) -> Backoff {
+        let delay = match self.delay {
+            Some(delay) => (delay * 2).min(MAX_ACCEPT_BACKOFF),
+            None => MIN_ACCEPT_BACKOFF,
+        };
+
+        self.delay = Some(delay);
+        self.failures += 1;
+        self.unlogged_failures += 1;
+
+        let is_logged = match self.last_logged_at {
+            Some(last_logged_at) => {
+                now.saturating_duration_since(last_logged_at) >= ACCEPT_FAILURE_LOG_INTERVAL
+            }
+            None => true,
+        };
+
+        let failures_to_log = if is_logged {
+            self.last_logged_at = Some(now);
+            Some(std::mem::take(&mut self.unlogged_failures))
+        } else {
+            None
+        };
+
+        Backoff {
+            delay,
+            failures_to_log,
+        }
+    }
+
+    /// Records an accepted connection.
+    ///
+    /// Returns the number of failures since the last
+    /// accepted connection, if resources were exhausted.
+    pub(crate) fn on_accepted(&mut self) -> Option<u64> {
+        let failures = self.failures;
+        *self = Self::default();
+
+        (failures > 0).then_some(failures)
+    }
+}
+
+#[cfg(test)]
+mod accept_test {
+    use std::{
+        io,
+        time::{Duration, Instant},
+    };
+
+    use super::{
+        bind_local_listener, AcceptBackoff, AcceptError, Backoff, ACCEPT_FAILURE_LOG_INTERVAL,
+        MAX_ACCEPT_BACKOFF, MIN_ACCEPT_BACKOFF,
+    };
+
+    #[test]
+    fn accept_error_classification_test() {
+        for (errno, accept_error) in [
+            (libc::EMFILE, AcceptError::ResourceExhaustion),
+            (libc::ENFILE, AcceptError::ResourceExhaustion),
+            (libc::ENOBUFS, AcceptError::ResourceExhaustion),
+            (libc::ECONNABORTED, AcceptError::Connection),
+            (libc::EPROTO, AcceptError::Connection),
+            (libc::EINTR, AcceptError::Connection),
+            (libc::EBADF, AcceptError::Listener),
+            (libc::EINVAL, AcceptError::Listener),
+        ] {
+            assert_eq!(
+                AcceptError::classify(&io::Error::from_raw_os_error(errno)),
+                accept_error
+            );
+        }
+    }
+
+    #[test]
+    fn backoff_grows_and_log_is_throttled_test() {
+        let mut backoff = AcceptBackoff::new();
+        let start = Instant::now();
+
+        assert_eq!(
+            backoff.on_resource_exhaustion(start),
+            Backoff {
+                delay: MIN_ACCEPT_BACKOFF,
+                failures_to_log: Some(1),
+            }
+        );
+
+        let mut delays = Vec::new();
+
+        for i in 1..10 {
+            let now = start + Duration::from_millis(i);
+            let backoff = backoff.on_resource_exhaustion(now);
+
+            assert_eq!(backoff.failures_to_log, None);
+            delays.push(backoff.delay);
+        }
+
+        assert_eq!(delays[0], MIN_ACCEPT_BACKOFF * 2);
+        assert!(delays.windows(2).all(|pair| pair[0] <= pair[1]));
+        assert_eq!(*delays.last().unwrap(), MAX_ACCEPT_BACKOFF);
+
+        // The throttled failures are reported with the next logged one
+        let backoff_after_interval =
+            backoff.on_resource_exhaustion(start + ACCEPT_FAILURE_LOG_INTERVAL);
+        assert_eq!(backoff_after_interval.failures_to_log, Some(10));
+
+        // Recovery resets the backoff
+        assert_eq!(backoff.on_accepted(), Some(11));
+        assert_eq!(backoff.on_accepted(), None);
+        assert_eq!(
+            backoff.on_resource_exhaustion(start).delay,
+            MIN_ACCEPT_BACKOFF
+        );
+    }
+
+    #[test]
+    fn local_listener_accepts_connections_test() {
+        let local_listener = bind_local_listener(0, 16).unwrap();
+        let local_addr = local_listener.local_addr().unwrap();
+
+        let _stream = std::net::TcpStream::connect(("127.0.0.1", local_addr.port())).unwrap();
+
+        assert!(local_listener.accept().is_ok());
+    }
+}
@@ -0,0 +1,1183 @@
+//! Startup path of a node: joins or initiates the
+//! network, runs the background tasks and serves requests.
+//!
+//! Kept apart from `main` so that nodes can be
+//! started in-process, e.g. by integration tests.
+
+use std::{
+    error::Error,
+    net::{SocketAddr, TcpListener, TcpStream},
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Sender, SyncSender},
+        Arc, Mutex, RwLock,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use accept::{AcceptBackoff, AcceptError};
+use shutdown::Shutdown;
+
+use crate::{
+    chord::{
+        self,
+        finger_table::FingerTable,
+        integrity::RingIntegrityCheck,
+        lookup_cache::LookupCache,
+        peer_pool::PeerPool,
+        reachability::ReachabilityAudit,
+        snapshot::MembershipSnapshot,
+        stabilization::{self, IsolationAction, IsolationTracker, StabilizationPacer},
+        JoinOutcome, Node, NodeAddr, SUCCESSOR_LIST_LENGTH,
+    },
+    cli::{AddressFamily, Args, IdentityMode, Options, DEFAULT_TLS_SERVER_NAME},
+    context::{NodeContext, VirtualNodes},
+    global_request_handler::{self, Listener},
+    gossip::{
+        self, clock::GossipClock, idempotency::IdempotencyKeys, journal::Journal,
+        limiter::InFlightLimiter, peer_selector::PeerSelector, skew::SkewMonitor, State,
+    },
+    observer::NodeObserver,
+    transport::{
+        auth::MessageAuthenticator, retry::RetryingTransport, tls::TlsConfig, TcpTransport,
+        Transport,
+    },
+};
+
+pub(crate) mod accept;
+pub(crate) mod report;
+pub(crate) mod self_test;
+pub(crate) mod shutdown;
+
+const SERVER_THREAD_POOL_SIZE: u8 = 10;
+
+/// Maximum number of connections waiting to be rejected.
+const REJECTION_QUEUE_LENGTH: usize = 64;
+
+/// A task executed by one of the background threads.
+type Task = Box<dyn FnOnce() + Send + 'static>;
+
+/// Starts the node described by `args`, reachable through
+/// `local_listener`: initiates or joins the network (or
+/// takes its place in the ring of a membership snapshot),
+/// then runs network stabilization and data dissemination
+/// in background threads.
+///
+/// The events of the node are reported to `observer`.
+/// Requests are only served once `serve` is called.
+pub(crate) fn start_node(
+    args: &Args,
+    local_listener: &TcpListener,
+    observer: Arc<dyn NodeObserver>,
+) -> Result<NodeContext, Box<dyn Error>> {
+    let self_node = create_self_node(
+        args.get_public_addr(),
+        args.get_options().identity_mode,
+        args.get_options().ring_bit_length,
+    );
+
+    if args.get_options().verify_public_addr {
+        chord::verify_self_node_public_addr(self_node.get_public_addr(), local_listener).map_err(
+            |err| {
+                format!(
+                    "the assigned public socket address does not correspond to the current node: {}",
+                    err
+                )
+            },
+        )?;
+    } else {
+        eprintln!(
+            "public socket address [{:?}] not verified: the other nodes must be able to reach it",
+            self_node.get_public_addr()
+        );
+    }
+
+    let gossip_journal = match args.get_options().gossip_journal_path {
+        Some(ref path) => Some(Arc::new(Journal::open(path).map_err(|err| {
+            format!(
+                "cannot open the gossip journal [{}]: {}",
+                path.display(),
+                err
+            )
+        })?)),
+        None => None,
+    };
+
+    let authenticator = args
+        .get_options()
+        .hmac_key
+        .as_ref()
+        .map(|hmac_key| MessageAuthenticator::new(hmac_key.as_bytes()));
+
+    let tls = match (
+        &args.get_options().tls_cert_path,
+        &args.get_options().tls_key_path,
+    ) {
+        (Some(cert_path), Some(key_path)) => Some(Arc::new(
+            TlsConfig::load(
+                cert_path,
+                key_path,
+                args.get_options().tls_ca_path.as_deref(),
+                args.get_options()
+                    .tls_server_name
+                    .as_deref()
+                    .unwrap_or(DEFAULT_TLS_SERVER_NAME),
+            )
+            .map_err(|err| format!("cannot load the TLS configuration: {}", err))?,
+        )),
+        _ => None,
+    };
+
+    let tcp_transport = || {
+        let tcp_transport = match authenticator {
+            Some(ref authenticator) => TcpTransport::with_authenticator(authenticator.clone()),
+            None => TcpTransport::new(),
+        };
+
+        match tls {
+            Some(ref tls) => tcp_transport.with_tls(Arc::clone(tls)),
+            None => tcp_transport,
+        }
+    };
+
+    let transport: Arc<dyn Transport> = Arc::new(RetryingTransport::new(
+        tcp_transport().with_response_timeout(args.get_options().chord_response_timeout),
+        args.get_options().connect_retries,
+    ));
+
+    // Not retried: a peer not reached is skipped until the next round
+    let gossip_transport: Arc<dyn Transport> = Arc::new(
+        tcp_transport()
+            .with_connect_timeout(args.get_options().gossip_connect_timeout)
+            .with_response_timeout(args.get_options().gossip_response_timeout),
+    );
+
+    let snapshot = args
+        .get_options()
+        .membership_snapshot
+        .as_ref()
+        .map(|addrs| MembershipSnapshot::new(&self_node, addrs));
+
+    let join_outcome = match snapshot {
+        Some(ref snapshot) => JoinOutcome::new(&self_node, snapshot.successor_list()),
+        None => chord::initialize_self_node_successor_list(
+            transport.as_ref(),
+            &self_node,
+            args.get_remote_addr().map(NodeAddr::from),
+            args.get_options().address_family,
+        )?,
+    };
+
+    if let Some(remote_addr) = args.get_remote_addr() {
+        eprintln!(
+            "node joined the network through [{}]: {}",
+            remote_addr, join_outcome
+        );
+    }
+
+    let JoinOutcome {
+        successor_list: self_node_successor_list,
+        is_first_node,
+        ..
+    } = join_outcome;
+
+    let self_node_finger_table = match snapshot {
+        Some(ref snapshot) => snapshot.finger_table(),
+        None => FingerTable::new(self_node.get_ring_bit_length()),
+    };
+
+    let self_node_lookup_cache = args
+        .get_options()
+        .lookup_cache_ttl
+        .map(|ttl| Arc::new(RwLock::new(LookupCache::new(ttl))));
+
+    let gossip_clock = Arc::new(GossipClock::new(args.get_options().timestamp_source));
+
+    let skew_monitor = args
+        .get_options()
+        .clock_skew_threshold
+        .map(|threshold| Arc::new(SkewMonitor::new(threshold, Arc::clone(&gossip_clock))));
+
+    let context = NodeContext {
+        self_node,
+        self_node_successor_list: Arc::new(RwLock::new(self_node_successor_list)),
+        self_node_predecessor: Arc::new(RwLock::new(None)),
+        self_node_finger_table: Arc::new(RwLock::new(self_node_finger_table)),
+        self_node_asymmetric_successor: Arc::new(RwLock::new(None)),
+        self_node_is_ready: Arc::new(AtomicBool::new(false)),
+        is_draining: Arc::new(AtomicBool::new(false)),
+        self_node_lookup_cache,
+        self_node_gossip_data: Arc::new(RwLock::new(None)),
+        gossip_clock,
+        skew_monitor,
+        idempotency_keys: Arc::new(IdempotencyKeys::default()),
+        gossip_journal,
+        transport,
+        gossip_transport,
+        forward_limiter: InFlightLimiter::new(args.get_options().max_forwarded_lookups),
+        authenticator,
+        tls,
+        observer,
+        shutdown: Shutdown::default(),
+        started_at: Instant::now(),
+        options: args.get_options().clone(),
+    };
+
+    disseminate_data_periodically(&context);
+
+    sweep_expired_data_periodically(
+        Arc::clone(&context.self_node_gossip_data),
+        Arc::clone(&context.gossip_clock),
+        context.shutdown.clone(),
+    );
+
+    run_network_stabilization(
+        &context,
+        snapshot,
+        args.get_remote_addr().map(NodeAddr::from),
+        is_first_node,
+    );
+
+    Ok(context)
+}
+
+/// Starts the other virtual nodes of the process
+/// of virtual node 0 (`vnodes`) in a separate thread,
+/// each joining the network through virtual node 0.
+///
+/// The virtual nodes are added to `virtual_nodes`
+/// one by one, once joined. They run network
+/// stabilization, and share the gossip data of
+/// virtual node 0, which alone disseminates it.
+pub(crate) fn start_virtual_nodes(virtual_nodes: &VirtualNodes) {
+    let shutdown = virtual_nodes.first().shutdown;
+    let virtual_nodes = virtual_nodes.clone();
+
+    shutdown.spawn("virtual node startup", move || {
+        let first_context = virtual_nodes.first();
+
+        for vnode in 1..first_context.options.vnodes {
+            if first_context.shutdown.is_requested() {
+                return;
+            }
+
+            match start_virtual_node(&first_context, vnode) {
+                Ok(context) => virtual_nodes.push(context),
+                Err(err) => {
+                    eprintln!("failed to start virtual node {}: {}", vnode, err);
+                    return;
+                }
+            }
+        }
+    });
+}
+
+/// Creates virtual node 0 of the process reachable at
+/// `public_addr`, identified according to `identity_mode`
+/// on a ring of `bit_length` bits.
+pub(crate) fn create_self_node(
+    public_addr: SocketAddr,
+    identity_mode: IdentityMode,
+    bit_length: usize,
+) -> Node {
+    match identity_mode {
+        IdentityMode::Hashed => Node::new(public_addr).on_ring(bit_length),
+        IdentityMode::Sequential(id) => Node::with_sequential_id(id, public_addr, bit_length),
+    }
+}
+
+/// Starts virtual node `vnode` of the process
+/// of virtual node 0, running in `first_context`.
+fn start_virtual_node(
+    first_context: &NodeContext,
+    vnode: u16,
+) -> Result<NodeContext, Box<dyn Error>> {
+    let self_node = Node::new_virtual(first_context.self_node.get_public_addr(), vnode)
+        .on_ring(first_context.self_node.get_ring_bit_length());
+
+    let JoinOutcome {
+        successor_list: self_node_successor_list,
+        is_first_node,
+        ..
+    } = chord::initialize_self_node_successor_list(
+        first_context.transport.as_ref(),
+        &self_node,
+        Some(first_context.self_node.get_node_addr()),
+        first_context.options.address_family,
+    )?;
+
+    let finger_table = FingerTable::new(self_node.get_ring_bit_length());
+    let self_node_lookup_cache = first_context
+        .options
+        .lookup_cache_ttl
+        .map(|ttl| Arc::new(RwLock::new(LookupCache::new(ttl))));
+
+    let context = NodeContext {
+        self_node,
+        self_node_successor_list: Arc::new(RwLock::new(self_node_successor_list)),
+        self_node_predecessor: Arc::new(RwLock::new(None)),
+        self_node_finger_table: Arc::new(RwLock::new(finger_table)),
+        self_node_asymmetric_successor: Arc::new(RwLock::new(None)),
+        self_node_is_ready: Arc::new(AtomicBool::new(false)),
+        self_node_lookup_cache,
+        ..first_context.clone()
+    };
+
+    run_network_stabilization(
+        &context,
+        None,
+        Some(first_context.self_node.get_node_addr()),
+        is_first_node,
+    );
+
+    Ok(context)
+}
+
+/// Serves the requests received on `local_listener`, addressed
+/// to any of `virtual_nodes`, with a pool of background threads.
+/// Blocks as long as the listener accepts connections.
+///
+/// Each listener (`listener`) has its own pool and connection
+/// cap, so that the data listener, if any, cannot starve
+/// the Chord maintenance requests of the main one.
+///
+/// At most `max-connections` connections are served at once,
+/// the queued ones included. Connections accepted beyond this
+/// cap are rejected with an error response by a dedicated thread,
+/// and closed right away if this thread falls behind.
+///
+/// While resources are exhausted (e.g. no file descriptor
+/// is left), the listener backs off before accepting again.
+/// Returns an error if the listener itself fails, and returns
+/// once the shutdown of the node is requested.
+pub(crate) fn serve(
+    local_listener: TcpListener,
+    virtual_nodes: VirtualNodes,
+    listener: Listener,
+) -> Result<(), Box<dyn Error>> {
+    let context = virtual_nodes.first();
+    let server_task_sender = spawn_background_threads(SERVER_THREAD_POOL_SIZE, &context.shutdown)?;
+    let mut accept_backoff = AcceptBackoff::new();
+    let connection_limiter = InFlightLimiter::new(context.options.max_connections);
+    let rejection_sender = spawn_rejection_thread(context.clone());
+
+    context
+        .shutdown
+        .register_listener(local_listener.local_addr()?);
+
+    for stream in local_listener.incoming() {
+        // Woken up by `Shutdown::request`: the pool threads stop
+        // once the tasks queued before are run
+        if context.shutdown.is_requested() {
+            break;
+        }
+
+        let request_stream = match stream {
+            Ok(request_stream) => request_stream,
+            Err(err) => match AcceptError::classify(&err) {
+                AcceptError::Connection => {
+                    eprintln!("failed to handle the request: {}", err);
+                    continue;
+                }
+                AcceptError::ResourceExhaustion => {
+                    let backoff = accept_backoff.on_resource_exhaustion(Instant::now());
+
+                    if let Some(failures) = backoff.failures_to_log {
+                        eprintln!(
+                            "cannot accept connections ({} failure(s)), retrying in {:?}: {}",
+                            failures, backoff.delay, err
+                        );
+                    }
+
+                    if !context.shutdown.sleep(backoff.delay) {
+                        break;
+                    }
+
+                    continue;
+                }
+                AcceptError::Listener => {
+                    return Err(format!("the local listener failed: {}", err).into());
+                }
+            },
+        };
+
+        if let Some(failures) = accept_backoff.on_accepted() {
+            eprintln!("accepting connections again, after {} failure(s)", failures);
+        }
+
+        let permit = match connection_limiter.try_acquire() {
+            Some(permit) => permit,
+            None => {
+                eprintln!(
+                    "rejected connection from [{}]: {} connections already served",
+                    request_stream
+                        .peer_addr()
+                        .map(|addr| addr.to_string())
+                        .unwrap_or("unknown".to_string()),
+                    connection_limiter.in_flight()
+                );
+                let _ = rejection_sender.try_send(request_stream);
+                continue;
+            }
+        };
+
+        let request_handler = global_request_handler::build_request_handler(
+            request_stream,
+            virtual_nodes.clone(),
+            listener,
+        );
+
+        server_task_sender
+            .send(Box::new(move || {
+                request_handler();
+                drop(permit);
+            }))
+            .unwrap();
+    }
+
+    Ok(())
+}
+
+/// Spawns `n` background threads to run tasks in parallel,
+/// joined by `shutdown`. These threads remain alive as long
+/// as the returned `Sender` is.
+///
+/// Tasks can be pushed and executed in these threads using the provided `Sender`.
+///
+/// A task which panics is logged, and its thread keeps running
+/// the next tasks, so that the pool does not shrink over time.
+fn spawn_background_threads(n: u8, shutdown: &Shutdown) -> Result<Sender<Task>, Box<dyn Error>> {
+    if n == 0 {
+        return Err(From::from("number of threads invalid"));
+    }
+
+    let (sender, receiver) = mpsc::channel::<Task>();
+    let receiver = Arc::new(Mutex::new(receiver));
+
+    for _ in 1..=n {
+        let receiver = Arc::clone(&receiver);
+
+        shutdown.spawn("server pool", move || loop {
+            let task = {
+                let receiver_lock = receiver.lock().unwrap();
+
+                match receiver_lock.recv() {
+                    Ok(task) => task,
+                    Err(_) => return,
+                }
+            };
+
+            if panic::catch_unwind(AssertUnwindSafe(task)).is_err() {
+                eprintln!("a background task panicked, its thread runs the next tasks");
+            }
+        });
+    }
+
+    Ok(sender)
+}
+
+/// Spawns the thread rejecting the connections accepted
+/// beyond the cap, and returns the queue feeding it.
+///
+/// At most `REJECTION_QUEUE_LENGTH` connections wait to be rejected.
+fn spawn_rejection_thread(context: NodeContext) -> SyncSender<TcpStream> {
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(REJECTION_QUEUE_LENGTH);
+    let shutdown = context.shutdown.clone();
+
+    shutdown.spawn("connection rejection", move || {
+        for stream in receiver {
+            global_request_handler::reject_connection(stream, &context);
+        }
+    });
+
+    sender
+}
+
+/// Runs network stabilization of the node running in
+/// `context`, followed by a refresh of the finger table,
+/// in a separate thread.
+///
+/// The peers seen along the way are kept in a pool. If a
+/// stabilization pass fails, the node rejoins the network
+/// through one of them. If none is live, the node is isolated:
+/// the isolation tracker (`on-isolation`) decides, after enough
+/// such passes in a row, whether it joins the network again
+/// through `bootstrap_addr` (restarting the ring alone if
+/// `None`), keeps retrying, or exits.
+///
+/// A node started from a membership `snapshot` tries the next
+/// members of the snapshot instead, until one of them is reached:
+/// the others may be dead, or not started yet.
+///
+/// The lookup cache, if any, is cleared whenever the
+/// predecessor or the successor list changes. These changes
+/// also shorten the interval before the next pass, which
+/// the pacer lengthens while the neighborhood is stable.
+///
+/// With `reachability-audit`, the successor is periodically
+/// asked to probe the node back. With `ring-integrity-check`,
+/// the successors are periodically followed around the
+/// ring, and a fork is repaired.
+///
+/// Once a pass finds the node part of the ring (see
+/// `stabilization::is_part_of_ring`, given `is_first_node`),
+/// the node is marked ready and the readiness report is printed.
+///
+/// The thread stops once the shutdown of the node is requested.
+fn run_network_stabilization(
+    context: &NodeContext,
+    mut snapshot: Option<MembershipSnapshot>,
+    bootstrap_addr: Option<NodeAddr>,
+    is_first_node: bool,
+) {
+    let context = context.clone();
+    let mut pacer = stabilization_pacer(&context.options);
+    let mut reachability_audit =
+        reachability_audit(&context.options, &context.self_node_asymmetric_successor);
+    let mut ring_integrity_check = ring_integrity_check(&context.options);
+    let mut isolation_tracker = isolation_tracker(&context.options);
+
+    // Neighborhood of the node after the last pass. Changes
+    // made by notifications since then are caught as well.
+    let mut last_neighborhood = None;
+    let mut peer_pool = PeerPool::new(context.self_node.clone());
+
+    context.shutdown.clone().spawn("stabilization", move || loop {
+        let self_node = &context.self_node;
+        let transport = context.transport.as_ref();
+        let address_family = context.options.address_family;
+
+        match chord::stabilize(
+            transport,
+            self_node,
+            &context.self_node_predecessor,
+            &context.self_node_successor_list,
+            address_family,
+            context.observer.as_ref(),
+        ) {
+            Ok(successor_list) => {
+                snapshot = None;
+                isolation_tracker.reset();
+
+                let predecessor = context.self_node_predecessor.read().unwrap().clone();
+
+                if !context.self_node_is_ready.load(Ordering::Relaxed)
+                    && stabilization::is_part_of_ring(
+                        self_node,
+                        is_first_node,
+                        predecessor.as_ref(),
+                        &successor_list[0],
+                    )
+                {
+                    context.self_node_is_ready.store(true, Ordering::Relaxed);
+                    println!(
+                        "{}",
+                        report::ready_report(self_node, predecessor.as_ref(), &successor_list[0])
+                    );
+                }
+            }
+            Err(err) => match snapshot {
+                Some(ref mut snapshot) => {
+                    eprintln!(
+                        "no member of the membership snapshot reached yet, trying the next ones: {}",
+                        err
+                    );
+                    *context.self_node_successor_list.write().unwrap() =
+                        snapshot.skip_successor_list();
+                }
+                None => {
+                    eprintln!("network failure: {}", err);
+
+                    match chord::rejoin(
+                        transport,
+                        self_node,
+                        &context.self_node_successor_list,
+                        &mut peer_pool,
+                        address_family,
+                    ) {
+                        Ok(peer) => {
+                            isolation_tracker.reset();
+                            eprintln!(
+                                "node rejoined the network through [{:?}]",
+                                peer.get_public_addr()
+                            );
+                        }
+                        Err(err) => {
+                            eprintln!("network failure: {}", err);
+
+                            if let Err(err) = handle_isolation(
+                                transport,
+                                self_node,
+                                &context.self_node_successor_list,
+                                &mut isolation_tracker,
+                                bootstrap_addr,
+                                address_family,
+                            ) {
+                                // Reported by `Shutdown::stop`, once every thread stopped
+                                context.shutdown.fail(err);
+                                break;
+                            }
+                        }
+                    }
+                }
+            },
+        }
+
+        chord::fix_fingers(
+            transport,
+            self_node,
+            &context.self_node_successor_list,
+            &context.self_node_finger_table,
+        );
+
+        if let Some(ref mut reachability_audit) = reachability_audit {
+            let successor = context.self_node_successor_list.read().unwrap()[0].clone();
+            reachability_audit.run_if_due(transport, self_node, &successor, Instant::now());
+        }
+
+        if let Some(ref mut ring_integrity_check) = ring_integrity_check {
+            let successor = context.self_node_successor_list.read().unwrap()[0].clone();
+            ring_integrity_check.run_if_due(transport, self_node, &successor, Instant::now());
+        }
+
+        chord::harvest_peers(
+            &mut peer_pool,
+            &context.self_node_predecessor,
+            &context.self_node_successor_list,
+            &context.self_node_finger_table,
+        );
+
+        let neighborhood = Some((
+            context.self_node_predecessor.read().unwrap().clone(),
+            context.self_node_successor_list.read().unwrap().clone(),
+        ));
+        let is_changed = neighborhood != last_neighborhood;
+
+        if is_changed {
+            if let Some(ref lookup_cache) = context.self_node_lookup_cache {
+                lookup_cache.write().unwrap().clear();
+            }
+
+            last_neighborhood = neighborhood;
+        }
+
+        if !context.shutdown.sleep(pacer.next_interval(is_changed)) {
+            break;
+        }
+    });
+}
+
+/// Creates the reachability audit of a node, recording
+/// an asymmetric link in `asymmetric_successor`,
+/// if enabled by `reachability-audit`.
+fn reachability_audit(
+    options: &Options,
+    asymmetric_successor: &Arc<RwLock<Option<Node>>>,
+) -> Option<ReachabilityAudit> {
+    options.reachability_audit_interval.map(|interval| {
+        ReachabilityAudit::new(interval, Arc::clone(asymmetric_successor), Instant::now())
+    })
+}
+
+/// Creates the ring integrity check of a node,
+/// if enabled by `ring-integrity-check`.
+fn ring_integrity_check(options: &Options) -> Option<RingIntegrityCheck> {
+    options
+        .ring_integrity_check_interval
+        .map(|interval| RingIntegrityCheck::new(interval, Instant::now()))
+}
+
+/// Creates the tracker of the stabilization passes the node cannot
+/// recover from, applying `on-isolation` after
+/// `max-stabilization-failures` of them in a row.
+fn isolation_tracker(options: &Options) -> IsolationTracker {
+    IsolationTracker::new(options.max_stabilization_failures, options.on_isolation)
+}
+
+/// Handles a stabilization pass which `self_node` could not recover
+/// from through a known peer, according to `isolation_tracker`.
+///
+/// A rejoin rebuilds `self_node_successor_list` by joining the
+/// network through `bootstrap_addr`, or from `self_node` alone
+/// if `None` (the node initiated the network).
+///
+/// Returns an error if the node must exit (`on-isolation=exit`).
+fn handle_isolation(
+    transport: &dyn Transport,
+    self_node: &Node,
+    self_node_successor_list: &RwLock<[Node; SUCCESSOR_LIST_LENGTH]>,
+    isolation_tracker: &mut IsolationTracker,
+    bootstrap_addr: Option<NodeAddr>,
+    address_family: AddressFamily,
+) -> Result<(), String> {
+    let action = isolation_tracker.record_failure();
+    let failures = isolation_tracker.consecutive_failures();
+
+    match action {
+        IsolationAction::Retry => eprintln!(
+            "node isolated for {} stabilization pass(es) in a row, retrying",
+            failures
+        ),
+        IsolationAction::Exit => {
+            return Err(format!(
+                "node isolated for {} stabilization pass(es) in a row, exiting",
+                failures
+            ));
+        }
+        IsolationAction::Rejoin => {
+            eprintln!(
+                "node isolated for {} stabilization pass(es) in a row, rejoining the network",
+                failures
+            );
+            isolation_tracker.reset();
+
+            match chord::initialize_self_node_successor_list(
+                transport,
+                self_node,
+                bootstrap_addr,
+                address_family,
+            ) {
+                Ok(join_outcome) => {
+                    match bootstrap_addr {
+                        Some(bootstrap_addr) => eprintln!(
+                            "node rejoined the network through its bootstrap node [{}]: {}",
+                            bootstrap_addr, join_outcome
+                        ),
+                        None => eprintln!("node restarted the network alone"),
+                    }
+
+                    *self_node_successor_list.write().unwrap() = join_outcome.successor_list;
+                }
+                Err(err) => eprintln!("failed to rejoin the network: {}", err),
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Creates the pacer of the stabilization passes,
+/// within the bounds of `stabilization-interval`.
+fn stabilization_pacer(options: &Options) -> StabilizationPacer {
+    let (min_interval, max_interval) = options.stabilization_interval;
+    StabilizationPacer::new(min_interval, max_interval)
+}
+
+/// Disseminate the gossip data of the node running
+/// in `context` periodically to a node drawn by a
+/// `PeerSelector` in a separate thread, in the
+/// direction given by `gossip-mode`.
+///
+/// Each exchange runs in its own thread, and at most
+/// `gossip-max-in-flight` exchanges are in flight at once.
+/// When the cap is reached, the selected peer is skipped.
+/// Expiry is checked at the wall time of the gossip clock.
+/// With `clock-skew-threshold`, each exchange checks the
+/// clock of the peer.
+///
+/// The thread stops once the shutdown of the node is
+/// requested, and the exchanges in flight are over.
+fn disseminate_data_periodically(context: &NodeContext) {
+    let context = context.clone();
+    let mut peer_selector = PeerSelector::new();
+    let limiter = InFlightLimiter::new(context.options.gossip_max_in_flight);
+
+    context.shutdown.clone().spawn("gossip", move || {
+        loop {
+            let successor_list = context.self_node_successor_list.read().unwrap().clone();
+
+            if let Some(random_remote_node) =
+                peer_selector.select(&context.self_node, &successor_list)
+            {
+                let context = context.clone();
+
+                let is_spawned = limiter.spawn(move || {
+                    gossip::request_initiator::exchange_data(
+                        &context,
+                        random_remote_node.get_public_addr(),
+                    );
+                });
+
+                if !is_spawned {
+                    eprintln!(
+                        "gossip round skipped: {} gossip exchanges already in flight",
+                        limiter.in_flight()
+                    );
+                }
+            }
+
+            if !context.shutdown.sleep(Duration::from_secs(2)) {
+                break;
+            }
+        }
+
+        // Bounded by the connect and response timeouts of the exchanges
+        while limiter.in_flight() > 0 {
+            thread::sleep(Duration::from_millis(10));
+        }
+    });
+}
+
+/// Clears `self_node_gossip_data` once it outlived its TTL at
+/// the wall time of `gossip_clock`, checking every second in
+/// a separate thread, until `shutdown` is requested.
+fn sweep_expired_data_periodically(
+    self_node_gossip_data: Arc<RwLock<Option<State>>>,
+    gossip_clock: Arc<GossipClock>,
+    shutdown: Shutdown,
+) {
+    shutdown.clone().spawn("expired data sweep", move || loop {
+        gossip::remove_expired_state(&self_node_gossip_data, gossip_clock.now());
+
+        if !shutdown.sleep(Duration::from_secs(1)) {
+            break;
+        }
+    });
+}
+
+#[cfg(test)]
+mod ring_integration_test {
+    use std::{
+        net::{SocketAddr, TcpListener},
+        sync::{Arc, Mutex},
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use crate::{
+        chord::{self, protocol::ChordResponse, request_initiator, utils, Node},
+        cli::{Args, Options},
+        context::{NodeContext, VirtualNodes},
+        observer::{NodeObserver, NoopObserver},
+        transport::TcpTransport,
+    };
+
+    use super::{serve, start_node, Listener};
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    enum NodeEvent {
+        PredecessorAccepted(Node),
+        NeighborLeft(Node),
+        LookupCompleted(Node),
+    }
+
+    /// Observer recording the events
+    /// relevant to a join/leave sequence.
+    #[derive(Default)]
+    struct RecordingObserver {
+        events: Mutex<Vec<NodeEvent>>,
+    }
+
+    impl RecordingObserver {
+        fn events(&self) -> Vec<NodeEvent> {
+            self.events.lock().unwrap().clone()
+        }
+    }
+
+    impl NodeObserver for RecordingObserver {
+        fn predecessor_accepted(&self, predecessor: &Node) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(NodeEvent::PredecessorAccepted(predecessor.clone()));
+        }
+
+        fn neighbor_left(&self, neighbor: &Node) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(NodeEvent::NeighborLeft(neighbor.clone()));
+        }
+
+        fn lookup_completed(&self, target_node: &Node, _successor: &Node) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(NodeEvent::LookupCompleted(target_node.clone()));
+        }
+    }
+
+    /// Starts an in-process node on an ephemeral loopback port,
+    /// initiating a new network or joining `remote_addr`.
+    fn spawn_node(remote_addr: Option<SocketAddr>) -> NodeContext {
+        spawn_observed_node(remote_addr, Arc::new(NoopObserver))
+    }
+
+    /// Same as `spawn_node`, reporting
+    /// the node's events to `observer`.
+    fn spawn_observed_node(
+        remote_addr: Option<SocketAddr>,
+        observer: Arc<dyn NodeObserver>,
+    ) -> NodeContext {
+        let local_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let public_addr = local_listener.local_addr().unwrap();
+
+        let args = match remote_addr {
+            Some(remote_addr) => Args::Join {
+                self_port: public_addr.port(),
+                public_addr,
+                remote_addr,
+                options: Options::default(),
+            },
+            None => Args::Init {
+                self_port: public_addr.port(),
+                public_addr,
+                options: Options::default(),
+            },
+        };
+
+        let context = start_node(&args, &local_listener, observer).unwrap();
+
+        let server_context = context.clone();
+        context.shutdown.spawn("server", move || {
+            let _ = serve(
+                local_listener,
+                VirtualNodes::new(server_context),
+                Listener::Main,
+            );
+        });
+
+        context
+    }
+
+    /// Checks that the successor and predecessor
+    /// pointers of `ring` follow the ring order.
+    fn is_consistent_cycle(ring: &[NodeContext]) -> bool {
+        let mut nodes = ring
+            .iter()
+            .map(|context| context.self_node.clone())
+            .collect::<Vec<_>>();
+        nodes.sort_by_key(|node| node.get_ring_position());
+
+        ring.iter().all(|context| {
+            let i = nodes
+                .iter()
+                .position(|node| *node == context.self_node)
+                .unwrap();
+            let expected_successor = &nodes[(i + 1) % nodes.len()];
+            let expected_predecessor = &nodes[(i + nodes.len() - 1) % nodes.len()];
+
+            context.self_node_successor_list.read().unwrap()[0] == *expected_successor
+                && context.self_node_predecessor.read().unwrap().as_ref()
+                    == Some(expected_predecessor)
+        })
+    }
+
+    #[test]
+    #[ignore = "spins up a real ring, takes several stabilization rounds"]
+    fn loopback_ring_forms_consistent_cycle_test() {
+        const RING_SIZE: usize = 4;
+
+        let mut ring = vec![spawn_node(None)];
+        let bootstrap_addr = ring[0].self_node.get_public_addr();
+
+        for _ in 1..RING_SIZE {
+            ring.push(spawn_node(Some(bootstrap_addr)));
+            thread::sleep(Duration::from_millis(500));
+        }
+
+        let deadline = Instant::now() + Duration::from_secs(60);
+
+        while !is_consistent_cycle(&ring) {
+            assert!(
+                Instant::now() < deadline,
+                "the ring did not converge to a consistent cycle"
+            );
+            thread::sleep(Duration::from_millis(500));
+        }
+
+        // Lookups for arbitrary keys, from any node,
+        // resolve to the owner of the key.
+        let nodes = ring
+            .iter()
+            .map(|context| context.self_node.clone())
+            .collect::<Vec<_>>();
+        let transport = TcpTransport::new();
+
+        for key_position in [0, 1 << 64, u128::MAX / 3, u128::MAX - 1] {
+            let key = Node::create_from(
+                key_position.to_be_bytes(),
+                "127.0.0.1:1".parse::<SocketAddr>().unwrap(),
+            );
+
+            for context in ring.iter() {
+                let response = request_initiator::find_successor_of_node(
+                    &transport,
+                    &key,
+                    context.self_node.get_node_addr(),
+                    None,
+                );
+
+                assert_eq!(
+                    response,
+                    ChordResponse::Successor(utils::owner_of(key_position, &nodes).clone())
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn observer_reports_join_and_leave_test() {
+        let observer = Arc::new(RecordingObserver::default());
+
+        let bootstrap_node = spawn_observed_node(None, observer.clone());
+        let joining_node = spawn_node(Some(bootstrap_node.self_node.get_public_addr()));
+
+        // The joining node's first stabilization pass
+        // notifies the bootstrap node.
+        let deadline = Instant::now() + Duration::from_secs(10);
+
+        while !observer.events().contains(&NodeEvent::PredecessorAccepted(
+            joining_node.self_node.clone(),
+        )) {
+            assert!(Instant::now() < deadline, "no predecessor accepted");
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        chord::leave(
+            joining_node.transport.as_ref(),
+            &joining_node.self_node,
+            &joining_node.self_node_predecessor,
+            &joining_node.self_node_successor_list,
+        );
+
+        let events = observer.events();
+
+        assert_eq!(
+            events[0],
+            NodeEvent::LookupCompleted(joining_node.self_node.clone())
+        );
+        assert!(events.contains(&NodeEvent::NeighborLeft(joining_node.self_node.clone())));
+    }
+
+    #[test]
+    fn shutdown_joins_the_background_threads_test() {
+        let bootstrap_node = spawn_node(None);
+        let joining_node = spawn_node(Some(bootstrap_node.self_node.get_public_addr()));
+        let ring = [bootstrap_node.clone(), joining_node.clone()];
+
+        let deadline = Instant::now() + Duration::from_secs(20);
+
+        while !is_consistent_cycle(&ring) {
+            assert!(
+                Instant::now() < deadline,
+                "the ring did not converge to a consistent cycle"
+            );
+            thread::sleep(Duration::from_millis(50));
+        }
+
+        let start = Instant::now();
+
+        assert_eq!(joining_node.shutdown.stop(Duration::from_secs(10)), Ok(()));
+        assert!(start.elapsed() < Duration::from_secs(10));
+
+        // Its listener is closed along with the server thread
+        assert!(matches!(
+            request_initiator::check_remote_node(
+                bootstrap_node.transport.as_ref(),
+                joining_node.self_node.get_node_addr()
+            ),
+            ChordResponse::Error(..)
+        ));
+
+        assert_eq!(
+            bootstrap_node.shutdown.stop(Duration::from_secs(10)),
+            Ok(())
+        );
+    }
+}
+
+#[cfg(test)]
+mod serve_test {
+    use std::{
+        net::{SocketAddr, TcpListener, TcpStream},
+        thread,
+        time::Duration,
+    };
+
+    use crate::{
+        cli::Options,
+        context::{NodeContext, VirtualNodes},
+        transport::{TcpTransport, Transport},
+    };
+
+    use super::{serve, Listener};
+
+    /// Serves a single-node ring on an ephemeral loopback
+    /// port, without its background tasks, and returns its address.
+    fn serve_single_node(options: Options) -> SocketAddr {
+        let local_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let node_addr = local_listener.local_addr().unwrap();
+
+        let context = NodeContext {
+            options,
+            ..NodeContext::for_test(node_addr)
+        };
+
+        thread::spawn(move || {
+            let _ = serve(local_listener, VirtualNodes::new(context), Listener::Main);
+        });
+
+        node_addr
+    }
+
+    #[test]
+    fn connections_beyond_cap_are_rejected_test() {
+        let node_addr = serve_single_node(Options {
+            max_connections: 2,
+            read_timeout: Duration::from_millis(500),
+            ..Options::default()
+        });
+
+        // Idle clients, holding every slot until the read timeout
+        let idle_clients = (0..2)
+            .map(|_| TcpStream::connect(node_addr).unwrap())
+            .collect::<Vec<_>>();
+        thread::sleep(Duration::from_millis(100));
+
+        let transport = TcpTransport::new();
+
+        for _ in 0..5 {
+            assert_eq!(
+                transport.send(node_addr, "CHECK_NODE;").unwrap(),
+                "ERROR=[RETRYABLE][too many connections];"
+            );
+        }
+
+        // The slots are released once the idle connections time out
+        thread::sleep(Duration::from_millis(600));
+        assert_eq!(transport.send(node_addr, "CHECK_NODE;").unwrap(), "ACTIVE;");
+
+        drop(idle_clients);
+    }
+}
+
+#[cfg(test)]
+mod background_threads_test {
+    use std::{sync::mpsc, time::Duration};
+
+    use super::{spawn_background_threads, Shutdown};
+
+    #[test]
+    fn panicking_task_does_not_shrink_the_pool_test() {
+        let task_sender = spawn_background_threads(1, &Shutdown::default()).unwrap();
+        let (done_sender, done_receiver) = mpsc::channel();
+
+        task_sender
+            .send(Box::new(|| panic!("injected task panic")))
+            .unwrap();
+
+        for i in 0..3 {
+            let done_sender = done_sender.clone();
+            task_sender
+                .send(Box::new(move || done_sender.send(i).unwrap()))
+                .unwrap();
+        }
+
+        for i in 0..3 {
+            assert_eq!(done_receiver.recv_timeout(Duration::from_secs(5)), Ok(i));
+        }
+    }
+}
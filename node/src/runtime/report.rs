@@ -0,0 +1,177 @@
+//! Startup report of a node: a single JSON line giving
+//! its identity and initial topology, so that orchestration
+//! can check how a node came up without scraping the
+//! periodic status output.
+
+use std::{error::Error, fs, net::SocketAddr};
+
+use crate::{chord::Node, cli::Args};
+
+/// Returns the startup report of `self_node`, listening
+/// on `bind_addr`, with its initial `successor_list`.
+///
+/// Every value is made of digits, hex digits or address
+/// characters, so that none needs escaping. Ring positions
+/// are strings, as they may exceed the range of JSON numbers.
+pub(crate) fn startup_report(
+    args: &Args,
+    bind_addr: SocketAddr,
+    self_node: &Node,
+    successor_list: &[Node],
+) -> String {
+    let (mode, remote_addr) = match args.get_remote_addr() {
+        Some(remote_addr) => ("join", format!("\"{}\"", remote_addr)),
+        None => ("init", "null".to_string()),
+    };
+
+    let successor_list = successor_list
+        .iter()
+        .map(node_json)
+        .collect::<Vec<_>>()
+        .join(",");
+
+    format!(
+        "{{\"event\":\"node_started\",\"id\":\"{}\",\"ring_position\":\"{}\",\
+         \"bind_addr\":\"{}\",\"public_addr\":\"{}\",\"mode\":\"{}\",\
+         \"remote_addr\":{},\"vnodes\":{},\"successor_list\":[{}]}}",
+        self_node.get_hash_id(),
+        self_node.get_identifier(),
+        bind_addr,
+        self_node.get_public_addr(),
+        mode,
+        remote_addr,
+        args.get_options().vnodes,
+        successor_list
+    )
+}
+
+/// Returns the readiness report of `self_node`, once it is
+/// part of the ring, between `predecessor` and `successor`:
+/// a single JSON line, which a startup script can wait for.
+pub(crate) fn ready_report(
+    self_node: &Node,
+    predecessor: Option<&Node>,
+    successor: &Node,
+) -> String {
+    format!(
+        "{{\"event\":\"node_ready\",\"node\":{},\"predecessor\":{},\"successor\":{}}}",
+        node_json(self_node),
+        predecessor.map_or("null".to_string(), node_json),
+        node_json(successor)
+    )
+}
+
+/// Returns the JSON object describing `node`: its
+/// identifier, ring position and address (see `startup_report`).
+pub(crate) fn node_json(node: &Node) -> String {
+    format!(
+        "{{\"id\":\"{}\",\"ring_position\":\"{}\",\"addr\":\"{}\"}}",
+        node.get_hash_id(),
+        node.get_identifier(),
+        node.get_node_addr()
+    )
+}
+
+/// Prints the startup report on the first line of the standard
+/// output, and writes it to the `startup-report` file, if any.
+pub(crate) fn emit_startup_report(
+    args: &Args,
+    bind_addr: SocketAddr,
+    self_node: &Node,
+    successor_list: &[Node],
+) -> Result<(), Box<dyn Error>> {
+    let report = startup_report(args, bind_addr, self_node, successor_list);
+
+    println!("{}", report);
+
+    if let Some(ref path) = args.get_options().startup_report_path {
+        fs::write(path, format!("{}\n", report)).map_err(|err| {
+            format!(
+                "cannot write the startup report to [{}]: {}",
+                path.display(),
+                err
+            )
+        })?;
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod report_test {
+    use std::net::SocketAddr;
+
+    use crate::{
+        chord::{Node, NodeAddr},
+        cli::{Args, Options},
+    };
+
+    use super::{ready_report, startup_report};
+
+    #[test]
+    fn startup_report_test() {
+        let public_addr = "127.0.0.1:7000".parse::<SocketAddr>().unwrap();
+        let self_node = Node::create_from(1u128.to_be_bytes(), public_addr);
+        let successor = Node::create_at(
+            u128::MAX.to_be_bytes(),
+            NodeAddr::new("127.0.0.1:7001".parse().unwrap(), 2),
+        );
+
+        let args = Args::Join {
+            self_port: 7000,
+            public_addr,
+            remote_addr: "127.0.0.1:7001".parse().unwrap(),
+            options: Options::default(),
+        };
+
+        assert_eq!(
+            startup_report(
+                &args,
+                "0.0.0.0:7000".parse().unwrap(),
+                &self_node,
+                &[successor]
+            ),
+            concat!(
+                r#"{"event":"node_started","#,
+                r#""id":"00000000000000000000000000000001","ring_position":"1","#,
+                r#""bind_addr":"0.0.0.0:7000","public_addr":"127.0.0.1:7000","#,
+                r#""mode":"join","remote_addr":"127.0.0.1:7001","vnodes":1,"#,
+                r#""successor_list":[{"id":"ffffffffffffffffffffffffffffffff","#,
+                r#""ring_position":"340282366920938463463374607431768211455","#,
+                r#""addr":"127.0.0.1:7001#2"}]}"#
+            )
+        );
+
+        let args = Args::Init {
+            self_port: 7000,
+            public_addr,
+            options: Options::default(),
+        };
+
+        assert!(startup_report(
+            &args,
+            public_addr,
+            &self_node,
+            std::slice::from_ref(&self_node)
+        )
+        .contains(r#""mode":"init","remote_addr":null,"#));
+    }
+
+    #[test]
+    fn ready_report_test() {
+        let self_node = Node::create_from(1u128.to_be_bytes(), "127.0.0.1:7000".parse().unwrap());
+        let peer = Node::create_from(2u128.to_be_bytes(), "127.0.0.1:7001".parse().unwrap());
+
+        assert_eq!(
+            ready_report(&self_node, Some(&peer), &peer),
+            concat!(
+                r#"{"event":"node_ready","#,
+                r#""node":{"id":"00000000000000000000000000000001","ring_position":"1","addr":"127.0.0.1:7000"},"#,
+                r#""predecessor":{"id":"00000000000000000000000000000002","ring_position":"2","addr":"127.0.0.1:7001"},"#,
+                r#""successor":{"id":"00000000000000000000000000000002","ring_position":"2","addr":"127.0.0.1:7001"}}"#
+            )
+        );
+
+        assert!(ready_report(&self_node, None, &self_node).contains(r#""predecessor":null,"#));
+    }
+}
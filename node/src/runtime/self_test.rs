@@ -0,0 +1,512 @@
+//! Protocol self-test of a node (`node self-test`).
+//!
+//! Every message of the Chord and Gossip protocols is built with
+//! representative values, converted to its text representation,
+//! then parsed back: a serializer out of step with its parser is
+//! caught before the node joins a ring and feeds it bad messages.
+
+use std::{net::SocketAddr, time::Duration};
+
+use crate::{
+    chord::{
+        node_info::NodeInfo,
+        protocol::{ChordRequest, ChordResponse, ErrorKind},
+        ring_range::RingRange,
+        trace::TraceId,
+        Node, NodeAddr,
+    },
+    cli::GossipCompression,
+    gossip::{
+        protocol::{GossipRequest, GossipResponse},
+        State,
+    },
+};
+
+/// Checks that every protocol message round-trips through
+/// its text representation and back.
+///
+/// Returns the diagnostic of the first message which does not.
+pub(crate) fn check_protocol() -> Result<(), String> {
+    for request in chord_requests() {
+        let text = request.to_protocol_text();
+        check_round_trip(chord_request_name(&request), &text, &request, |text| {
+            ChordRequest::parse(text)
+        })?;
+    }
+
+    for response in chord_responses() {
+        let text = response.to_protocol_text();
+        check_round_trip(chord_response_name(&response), &text, &response, |text| {
+            ChordResponse::parse(text)
+        })?;
+    }
+
+    for compression in [GossipCompression::None, GossipCompression::Deflate] {
+        for request in gossip_requests() {
+            let text = request.to_protocol_text(compression);
+            check_round_trip(gossip_request_name(&request), &text, &request, |text| {
+                GossipRequest::parse(text)
+            })?;
+        }
+
+        for response in gossip_responses() {
+            let text = response.to_protocol_text(compression);
+            check_round_trip(gossip_response_name(&response), &text, &response, |text| {
+                GossipResponse::parse(text)
+            })?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Checks that `text`, the representation of `message`,
+/// is parsed back into `message`.
+fn check_round_trip<T: PartialEq + std::fmt::Debug>(
+    name: &str,
+    text: &str,
+    message: &T,
+    parse: impl Fn(&str) -> Result<T, &'static str>,
+) -> Result<(), String> {
+    match parse(text) {
+        Ok(ref parsed) if parsed == message => Ok(()),
+        Ok(parsed) => Err(format!(
+            "{} does not round-trip: [{}] is parsed as {:?}, expected {:?}",
+            name, text, parsed, message
+        )),
+        Err(err) => Err(format!(
+            "{} does not round-trip: [{}] is rejected: {}",
+            name, text, err
+        )),
+    }
+}
+
+fn nodes() -> [Node; 3] {
+    [
+        Node::new(SocketAddr::from(([127, 0, 0, 1], 8000))),
+        Node::new_virtual(SocketAddr::from(([10, 0, 0, 2], 9000)), 3),
+        // Identifiers of a ring of fewer bits are shorter
+        Node::new("[2001:db8::1]:4040".parse().unwrap()).on_ring(32),
+    ]
+}
+
+fn states() -> [State; 3] {
+    [
+        State {
+            data: "Some data ...".to_string(),
+            timestamp: 1_700_000_000_000,
+            ttl: None,
+        },
+        State {
+            data: "key=value; other=[1, 2]".to_string(),
+            timestamp: u128::MAX,
+            ttl: Some(60),
+        },
+        // Compressed with GossipCompression::Deflate
+        State {
+            data: "repeated data ".repeat(64),
+            timestamp: 1,
+            ttl: Some(u64::MAX),
+        },
+    ]
+}
+
+fn chord_requests() -> Vec<ChordRequest> {
+    let [node, vnode, ipv6_node] = nodes();
+
+    vec![
+        ChordRequest::FindSuccessorOfNode(node.clone(), None),
+        ChordRequest::FindSuccessorOfNode(vnode.clone(), Some(TraceId::generate())),
+        ChordRequest::FindSuccessorOfKey(0, None),
+        ChordRequest::FindSuccessorOfKey(u128::MAX, Some(TraceId::generate())),
+        ChordRequest::FindSuccessorOfKey(0xf41fec42 << 96, None),
+        ChordRequest::GetSuccessorList,
+        ChordRequest::GetPredecessor,
+        ChordRequest::GetNeighborhood,
+        ChordRequest::NotificationBy(ipv6_node.clone()),
+        ChordRequest::CheckNode,
+        ChordRequest::Stabilize,
+        ChordRequest::Leaving(vnode.clone()),
+        ChordRequest::Shutdown("admin secret".to_string()),
+        ChordRequest::Drain("admin secret".to_string()),
+        ChordRequest::GetFingerTable,
+        ChordRequest::GetOwnedRange,
+        ChordRequest::GetNodeInfo,
+        ChordRequest::SetPredecessor("admin secret".to_string(), node.clone()),
+        ChordRequest::ProbeBack(NodeAddr::new(vnode.get_public_addr(), 3)),
+        ChordRequest::ProbeBack(ipv6_node.get_node_addr()),
+    ]
+}
+
+fn chord_responses() -> Vec<ChordResponse> {
+    let [node, vnode, ipv6_node] = nodes();
+
+    vec![
+        ChordResponse::Successor(vnode.clone()),
+        ChordResponse::TracedSuccessor(ipv6_node.clone(), 4),
+        ChordResponse::SuccessorList([
+            node.clone(),
+            vnode.clone(),
+            ipv6_node.clone(),
+            node.clone(),
+            node.clone(),
+        ]),
+        ChordResponse::Predecessor(Some(ipv6_node.clone())),
+        ChordResponse::Predecessor(None),
+        ChordResponse::Neighborhood(
+            Some(vnode.clone()),
+            [
+                ipv6_node.clone(),
+                node.clone(),
+                vnode.clone(),
+                node.clone(),
+                node.clone(),
+            ],
+        ),
+        ChordResponse::Neighborhood(None, std::array::from_fn(|_| node.clone())),
+        ChordResponse::Active,
+        ChordResponse::Acknowledged,
+        ChordResponse::FingerTable(vec![(0, node.clone()), (127, vnode.clone())]),
+        ChordResponse::FingerTable(Vec::new()),
+        ChordResponse::Redirect(ipv6_node.clone()),
+        ChordResponse::OwnedRange(RingRange::new(u128::MAX, 42)),
+        ChordResponse::NodeInfo(NodeInfo::current(Duration::from_secs(90_061), 32)),
+        ChordResponse::Unreachable,
+        ChordResponse::Error(ErrorKind::Retryable, "too many connections".to_string()),
+        ChordResponse::Error(ErrorKind::Fatal, "invalid secret".to_string()),
+        ChordResponse::Error(ErrorKind::Timeout, "no response".to_string()),
+        ChordResponse::Error(ErrorKind::Fatal, "bad [request];\n100%".to_string()),
+    ]
+}
+
+fn gossip_requests() -> Vec<GossipRequest> {
+    let mut requests = vec![
+        GossipRequest::UpdateData("Some data ...".to_string(), None, None),
+        GossipRequest::UpdateData("Some data ...".to_string(), Some(30), None),
+        GossipRequest::UpdateData(
+            "Some data ...".to_string(),
+            Some(30),
+            Some("retry-1".to_string()),
+        ),
+        GossipRequest::UpdateDataWithAck("Some data ...".to_string(), 3, None, None),
+        GossipRequest::UpdateDataWithAck("Some data ...".to_string(), 1, Some(30), None),
+        GossipRequest::UpdateDataWithAck(
+            "Some data ...".to_string(),
+            1,
+            None,
+            Some("retry-1".to_string()),
+        ),
+        GossipRequest::ShareData(None),
+        GossipRequest::GetData,
+    ];
+
+    requests.extend(
+        states()
+            .into_iter()
+            .map(|state| GossipRequest::ShareData(Some(state))),
+    );
+    requests
+}
+
+fn gossip_responses() -> Vec<GossipResponse> {
+    let mut responses = vec![
+        GossipResponse::Ignore,
+        GossipResponse::Acknowledged {
+            acks: 2,
+            required_acks: 3,
+        },
+    ];
+
+    responses.extend(states().into_iter().map(GossipResponse::ResponseWithData));
+    responses
+}
+
+// The names below match every variant, so that a new
+// variant does not compile until it is named here, next
+// to the samples it should be added to.
+
+fn chord_request_name(request: &ChordRequest) -> &'static str {
+    match request {
+        ChordRequest::FindSuccessorOfNode(..) => "FIND_SUCCESSOR_OF_NODE request",
+        ChordRequest::FindSuccessorOfKey(..) => "FIND_SUCCESSOR_OF_KEY request",
+        ChordRequest::GetSuccessorList => "GET_SUCCESSOR_LIST request",
+        ChordRequest::GetPredecessor => "GET_PREDECESSOR request",
+        ChordRequest::GetNeighborhood => "GET_NEIGHBORHOOD request",
+        ChordRequest::NotificationBy(_) => "NOTIFICATION_BY request",
+        ChordRequest::CheckNode => "CHECK_NODE request",
+        ChordRequest::Stabilize => "STABILIZE request",
+        ChordRequest::Leaving(_) => "LEAVING request",
+        ChordRequest::Shutdown(_) => "SHUTDOWN request",
+        ChordRequest::Drain(_) => "DRAIN request",
+        ChordRequest::GetFingerTable => "GET_FINGER_TABLE request",
+        ChordRequest::GetOwnedRange => "GET_OWNED_RANGE request",
+        ChordRequest::GetNodeInfo => "GET_NODE_INFO request",
+        ChordRequest::SetPredecessor(..) => "SET_PREDECESSOR request",
+        ChordRequest::ProbeBack(_) => "PROBE_BACK request",
+    }
+}
+
+fn chord_response_name(response: &ChordResponse) -> &'static str {
+    match response {
+        ChordResponse::Successor(_) => "SUCCESSOR response",
+        ChordResponse::TracedSuccessor(..) => "traced SUCCESSOR response",
+        ChordResponse::SuccessorList(_) => "SUCCESSOR_LIST response",
+        ChordResponse::Predecessor(_) => "PREDECESSOR response",
+        ChordResponse::Neighborhood(..) => "NEIGHBORHOOD response",
+        ChordResponse::Active => "ACTIVE response",
+        ChordResponse::Acknowledged => "ACKNOWLEDGED response",
+        ChordResponse::FingerTable(_) => "FINGER_TABLE response",
+        ChordResponse::Redirect(_) => "REDIRECT response",
+        ChordResponse::OwnedRange(_) => "OWNED_RANGE response",
+        ChordResponse::NodeInfo(_) => "NODE_INFO response",
+        ChordResponse::Unreachable => "UNREACHABLE response",
+        ChordResponse::Error(..) => "ERROR response",
+    }
+}
+
+fn gossip_request_name(request: &GossipRequest) -> &'static str {
+    match request {
+        GossipRequest::UpdateData(..) => "UPDATE_DATA request",
+        GossipRequest::UpdateDataWithAck(..) => "UPDATE_DATA_WITH_ACK request",
+        GossipRequest::ShareData(_) => "SHARE_DATA request",
+        GossipRequest::GetData => "GET_DATA request",
+    }
+}
+
+fn gossip_response_name(response: &GossipResponse) -> &'static str {
+    match response {
+        GossipResponse::Ignore => "IGNORE response",
+        GossipResponse::ResponseWithData(_) => "RESPONSE with data",
+        GossipResponse::Acknowledged { .. } => "ACKNOWLEDGED gossip response",
+    }
+}
+
+#[cfg(test)]
+mod self_test_test {
+    use crate::chord::protocol::ChordResponse;
+
+    use super::{check_protocol, check_round_trip};
+
+    #[test]
+    fn protocol_round_trips_test() {
+        assert_eq!(check_protocol(), Ok(()));
+    }
+
+    #[test]
+    fn mismatch_is_named_test() {
+        let err = check_round_trip(
+            "ACTIVE response",
+            "ACTIVE;",
+            &ChordResponse::Acknowledged,
+            ChordResponse::parse,
+        )
+        .unwrap_err();
+
+        assert!(
+            err.starts_with("ACTIVE response does not round-trip: [ACTIVE;] is parsed as Active")
+        );
+    }
+}
+
+/// Property test of the parsers on malformed input: a peer (or
+/// anything connecting to the node) may send any bytes, which
+/// must be rejected with an error, never with a panic.
+#[cfg(test)]
+mod protocol_fuzz_test {
+    use std::panic;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use crate::{
+        chord::{
+            protocol::{ChordRequest, ChordResponse},
+            NodeAddr,
+        },
+        cli::GossipCompression,
+        gossip::{
+            protocol::{GossipRequest, GossipResponse},
+            skew,
+        },
+    };
+
+    use super::{chord_requests, chord_responses, gossip_requests, gossip_responses};
+
+    /// Inputs generated per seed message, and from scratch,
+    /// and the factor of the longer (ignored) run.
+    const MUTATIONS_PER_SEED: usize = 1000;
+    const RANDOM_INPUTS: usize = 20_000;
+    const LONG_RUN_FACTOR: usize = 100;
+
+    /// Characters of the protocol, likelier than arbitrary
+    /// ones to get a malformed message past the regexes.
+    const ALPHABET: &[char] = &[
+        '[', ']', '{', '}', ';', '=', ',', ':', '.', '#', '%', '0', '1', '9', 'a', 'f', 'g', 'A',
+        'Z', '_', '-', '+', '/', ' ', '\n', '\0', 'é', '€', '😀',
+    ];
+
+    /// Fragments of the protocol, spliced into the inputs.
+    const TOKENS: &[&str] = &[
+        "NONE",
+        "[TTL=",
+        "[KEY=",
+        "[TRACE=",
+        "[ENCODING=DEFLATE]",
+        "CLOCK=[",
+        "VNODE=[",
+        "[127.0.0.1:8000]",
+        "[[::1]:8000#0]",
+        "[18446744073709551616]",
+        "[340282366920938463463374607431768211456]",
+        "%5",
+        "];",
+    ];
+
+    /// Texts of every message of the protocols.
+    fn seeds() -> Vec<String> {
+        let mut seeds = Vec::new();
+
+        seeds.extend(chord_requests().iter().map(ChordRequest::to_protocol_text));
+        seeds.extend(
+            chord_responses()
+                .iter()
+                .map(ChordResponse::to_protocol_text),
+        );
+
+        for compression in [GossipCompression::None, GossipCompression::Deflate] {
+            seeds.extend(
+                gossip_requests()
+                    .iter()
+                    .map(|request| request.to_protocol_text(compression)),
+            );
+            seeds.extend(
+                gossip_responses()
+                    .iter()
+                    .map(|response| response.to_protocol_text(compression)),
+            );
+        }
+
+        seeds
+    }
+
+    /// Random character, mostly from the protocol alphabet.
+    fn random_char(rng: &mut StdRng) -> char {
+        if rng.random_bool(0.8) {
+            ALPHABET[rng.random_range(0..ALPHABET.len())]
+        } else {
+            rng.random()
+        }
+    }
+
+    /// Applies a few random edits to `seed`.
+    fn mutate(seed: &str, rng: &mut StdRng) -> String {
+        let mut chars = seed.chars().collect::<Vec<_>>();
+
+        for _ in 0..rng.random_range(1..=4) {
+            let at = rng.random_range(0..=chars.len());
+
+            match rng.random_range(0..5) {
+                // Insertion
+                0 => chars.insert(at, random_char(rng)),
+                // Replacement
+                1 if at < chars.len() => chars[at] = random_char(rng),
+                // Deletion of a span
+                2 => {
+                    let end = rng.random_range(at..=chars.len());
+                    chars.drain(at..end);
+                }
+                // Duplication of a span
+                3 => {
+                    let end = rng.random_range(at..=chars.len());
+                    let span = chars[at..end].to_vec();
+                    chars.splice(at..at, span);
+                }
+                // Splice of a protocol fragment
+                _ => {
+                    let token = TOKENS[rng.random_range(0..TOKENS.len())];
+                    chars.splice(at..at, token.chars());
+                }
+            }
+        }
+
+        chars.into_iter().collect()
+    }
+
+    /// Random input of up to 64 characters.
+    fn random_input(rng: &mut StdRng) -> String {
+        (0..rng.random_range(0..=64))
+            .map(|_| random_char(rng))
+            .collect()
+    }
+
+    /// Runs every parser of the inbound messages on `input`.
+    ///
+    /// Returns whether a parser accepted it.
+    fn parse_all(input: &str) -> bool {
+        let (_, request) = NodeAddr::split_request(input).unwrap_or((0, input));
+        let (_, request) = skew::split_clock(request);
+
+        let accepted = [
+            ChordRequest::parse(request).is_ok(),
+            ChordResponse::parse(request).is_ok(),
+            GossipRequest::parse(request).is_ok(),
+            GossipResponse::parse(request).is_ok(),
+        ];
+
+        accepted.contains(&true)
+    }
+
+    /// Parses `input`, failing with the input if a parser panics.
+    fn parse_without_panic(input: &str) -> bool {
+        match panic::catch_unwind(|| parse_all(input)) {
+            Ok(accepted) => accepted,
+            Err(_) => panic!("a protocol parser panicked on {:?}", input),
+        }
+    }
+
+    /// Parses `mutations_per_seed` mutations of every message, with
+    /// the generator seeded by `rng_seed`. Returns how many were
+    /// accepted, which shows the mutations reach past the regexes.
+    fn fuzz_mutated_messages(mutations_per_seed: usize, rng_seed: u64) -> usize {
+        let mut rng = StdRng::seed_from_u64(rng_seed);
+        let mut accepted = 0;
+
+        for seed in seeds() {
+            assert!(parse_without_panic(&seed), "{:?} is rejected", seed);
+
+            for _ in 0..mutations_per_seed {
+                if parse_without_panic(&mutate(&seed, &mut rng)) {
+                    accepted += 1;
+                }
+            }
+        }
+
+        accepted
+    }
+
+    fn fuzz_random_inputs(random_inputs: usize, rng_seed: u64) {
+        let mut rng = StdRng::seed_from_u64(rng_seed);
+
+        for _ in 0..random_inputs {
+            parse_without_panic(&random_input(&mut rng));
+        }
+    }
+
+    #[test]
+    fn mutated_messages_do_not_panic_test() {
+        assert!(fuzz_mutated_messages(MUTATIONS_PER_SEED, 912) > 0);
+    }
+
+    #[test]
+    fn random_inputs_do_not_panic_test() {
+        fuzz_random_inputs(RANDOM_INPUTS, 912);
+    }
+
+    #[test]
+    #[ignore = "parses millions of inputs, takes minutes"]
+    fn long_fuzz_run_test() {
+        for rng_seed in 0..10 {
+            fuzz_mutated_messages(MUTATIONS_PER_SEED * LONG_RUN_FACTOR / 10, rng_seed);
+            fuzz_random_inputs(RANDOM_INPUTS * LONG_RUN_FACTOR / 10, rng_seed);
+        }
+    }
+}
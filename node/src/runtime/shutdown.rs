@@ -0,0 +1,256 @@
+//! Shutdown of a node, requested by a `SHUTDOWN` or `DRAIN`
+//! request or by the application embedding it: its background
+//! threads are signaled to stop, then joined within a timeout.
+//!
+//! The periodic threads sleep on the signal, so that they stop
+//! right away rather than at the end of their interval. The
+//! listeners blocked accepting a connection are woken up by
+//! a connection to themselves. A thread busy with a request
+//! stops once the request completes, within its timeouts.
+
+use std::{
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, TcpStream},
+    sync::{Arc, Condvar, Mutex},
+    thread::{self, JoinHandle},
+    time::{Duration, Instant},
+};
+
+/// Interval between two checks of the threads left to join.
+const JOIN_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Connect timeout of the connection waking up a listener.
+const LISTENER_WAKE_UP_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Spawned thread, with its name.
+type NamedThread = (String, JoinHandle<()>);
+
+/// Signal stopping the background threads of a node,
+/// shared by its virtual nodes, along with the threads
+/// to join once it is sent.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Shutdown {
+    /// Whether the shutdown is requested, notified
+    /// to the threads sleeping on it.
+    is_requested: Arc<(Mutex<bool>, Condvar)>,
+    threads: Arc<Mutex<Vec<NamedThread>>>,
+    /// Local addresses of the listeners accepting connections.
+    listener_addrs: Arc<Mutex<Vec<SocketAddr>>>,
+    /// Failure which requested the shutdown, if any.
+    failure: Arc<Mutex<Option<String>>>,
+}
+
+impl Shutdown {
+    pub(crate) fn is_requested(&self) -> bool {
+        *self.is_requested.0.lock().unwrap()
+    }
+
+    /// Runs `task` in a separate thread, joined by `stop`.
+    /// The thread is named after `name` in the errors.
+    pub(crate) fn spawn<F>(&self, name: &str, task: F)
+    where
+        F: FnOnce() + Send + 'static,
+    {
+        let handle = thread::spawn(task);
+        self.threads
+            .lock()
+            .unwrap()
+            .push((name.to_string(), handle));
+    }
+
+    /// Sleeps for `duration`, unless the shutdown is requested.
+    ///
+    /// Returns `false` if the shutdown is requested,
+    /// before or while sleeping: the thread then stops.
+    pub(crate) fn sleep(&self, duration: Duration) -> bool {
+        let (is_requested, condvar) = &*self.is_requested;
+
+        let is_requested = condvar
+            .wait_timeout_while(is_requested.lock().unwrap(), duration, |is_requested| {
+                !*is_requested
+            })
+            .unwrap()
+            .0;
+
+        !*is_requested
+    }
+
+    /// Registers the listener bound to `local_addr`,
+    /// to be woken up by `stop`.
+    pub(crate) fn register_listener(&self, local_addr: SocketAddr) {
+        self.listener_addrs.lock().unwrap().push(local_addr);
+    }
+
+    /// Requests the shutdown, without waiting for the threads:
+    /// the sleeping ones and the listeners are woken up.
+    ///
+    /// Called from a spawned thread, which `stop` could not join.
+    pub(crate) fn request(&self) {
+        {
+            let (is_requested, condvar) = &*self.is_requested;
+            *is_requested.lock().unwrap() = true;
+            condvar.notify_all();
+        }
+
+        for local_addr in self.listener_addrs.lock().unwrap().drain(..) {
+            wake_up_listener(local_addr);
+        }
+    }
+
+    /// Requests the shutdown on a failure of the node (e.g. an
+    /// isolated node giving up), reported by `stop`: the first
+    /// failure is kept.
+    ///
+    /// Called from a spawned thread, which then stops.
+    pub(crate) fn fail(&self, failure: String) {
+        self.failure.lock().unwrap().get_or_insert(failure);
+        self.request();
+    }
+
+    /// Requests the shutdown, then waits for the spawned threads
+    /// (including the ones spawned meanwhile) to stop.
+    ///
+    /// Returns an error naming the threads still running
+    /// after `timeout`, which are left detached, or else
+    /// the failure which requested the shutdown, if any.
+    pub(crate) fn stop(&self, timeout: Duration) -> Result<(), String> {
+        let deadline = Instant::now() + timeout;
+
+        self.request();
+
+        loop {
+            let mut threads = self.threads.lock().unwrap();
+
+            let (stopped_threads, running_threads) = threads
+                .drain(..)
+                .partition::<Vec<_>, _>(|(_, handle)| handle.is_finished());
+            *threads = running_threads;
+
+            drop(threads);
+
+            for (name, handle) in stopped_threads {
+                if handle.join().is_err() {
+                    eprintln!("the {} thread panicked", name);
+                }
+            }
+
+            let threads = self.threads.lock().unwrap();
+
+            if threads.is_empty() {
+                return match self.failure.lock().unwrap().clone() {
+                    Some(failure) => Err(failure),
+                    None => Ok(()),
+                };
+            }
+
+            if Instant::now() >= deadline {
+                return Err(format!(
+                    "{} thread(s) still running after {:?}: {}",
+                    threads.len(),
+                    timeout,
+                    threads
+                        .iter()
+                        .map(|(name, _)| name.as_str())
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ));
+            }
+
+            drop(threads);
+            thread::sleep(JOIN_POLL_INTERVAL);
+        }
+    }
+}
+
+/// Connects to the listener bound to `local_addr`, so that
+/// it returns from `accept` and sees the shutdown request.
+fn wake_up_listener(local_addr: SocketAddr) {
+    let ip = match local_addr.ip() {
+        IpAddr::V4(ip) if ip.is_unspecified() => IpAddr::V4(Ipv4Addr::LOCALHOST),
+        IpAddr::V6(ip) if ip.is_unspecified() => IpAddr::V6(Ipv6Addr::LOCALHOST),
+        ip => ip,
+    };
+
+    let _ = TcpStream::connect_timeout(
+        &SocketAddr::new(ip, local_addr.port()),
+        LISTENER_WAKE_UP_TIMEOUT,
+    );
+}
+
+#[cfg(test)]
+mod shutdown_test {
+    use std::{
+        net::TcpListener,
+        thread,
+        time::{Duration, Instant},
+    };
+
+    use super::Shutdown;
+
+    #[test]
+    fn sleeping_threads_stop_right_away_test() {
+        let shutdown = Shutdown::default();
+
+        for _ in 0..3 {
+            let thread_shutdown = shutdown.clone();
+            shutdown.spawn("periodic", move || {
+                while thread_shutdown.sleep(Duration::from_secs(60)) {}
+            });
+        }
+
+        let start = Instant::now();
+
+        assert_eq!(shutdown.stop(Duration::from_secs(5)), Ok(()));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert!(!shutdown.sleep(Duration::from_secs(60)));
+    }
+
+    #[test]
+    fn blocked_listener_is_woken_up_test() {
+        let shutdown = Shutdown::default();
+        let listener = TcpListener::bind("0.0.0.0:0").unwrap();
+        shutdown.register_listener(listener.local_addr().unwrap());
+
+        let thread_shutdown = shutdown.clone();
+        shutdown.spawn("listener", move || {
+            for _ in listener.incoming() {
+                if thread_shutdown.is_requested() {
+                    break;
+                }
+            }
+        });
+
+        assert_eq!(shutdown.stop(Duration::from_secs(5)), Ok(()));
+    }
+
+    #[test]
+    fn failure_is_reported_once_stopped_test() {
+        let shutdown = Shutdown::default();
+
+        let thread_shutdown = shutdown.clone();
+        shutdown.spawn("failing", move || {
+            thread_shutdown.fail("first failure".to_string());
+            thread_shutdown.fail("second failure".to_string());
+        });
+
+        let thread_shutdown = shutdown.clone();
+        shutdown.spawn("periodic", move || {
+            while thread_shutdown.sleep(Duration::from_secs(60)) {}
+        });
+
+        assert_eq!(
+            shutdown.stop(Duration::from_secs(5)),
+            Err("first failure".to_string())
+        );
+    }
+
+    #[test]
+    fn threads_still_running_are_reported_test() {
+        let shutdown = Shutdown::default();
+        shutdown.spawn("stuck", || thread::sleep(Duration::from_secs(2)));
+
+        assert_eq!(
+            shutdown.stop(Duration::from_millis(50)),
+            Err("1 thread(s) still running after 50ms: stuck".to_string())
+        );
+    }
+}
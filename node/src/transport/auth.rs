@@ -0,0 +1,116 @@
+//! Optional authentication of protocol messages
+//! with a pre-shared key (HMAC-SHA256).
+//!
+//! A signed message is the message text followed
+//! by its HMAC: `<message>HMAC=[<hex digest>];`.
+
+use std::fmt;
+
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// Length of the HMAC suffix appended to a signed message.
+const SIGNATURE_SUFFIX_LENGTH: usize = "HMAC=[".len() + 64 + "];".len();
+
+/// Signs and verifies protocol messages
+/// with a key shared by all nodes.
+#[derive(Clone)]
+pub(crate) struct MessageAuthenticator {
+    key: Vec<u8>,
+}
+
+impl fmt::Debug for MessageAuthenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never prints the key
+        f.debug_struct("MessageAuthenticator")
+            .finish_non_exhaustive()
+    }
+}
+
+impl MessageAuthenticator {
+    pub(crate) fn new(key: &[u8]) -> Self {
+        Self { key: key.to_vec() }
+    }
+
+    fn mac(&self) -> HmacSha256 {
+        HmacSha256::new_from_slice(&self.key).unwrap() // Safe unwrap, any key length is valid
+    }
+
+    /// Appends the HMAC of `msg` to `msg`.
+    pub(crate) fn sign(&self, msg: &str) -> String {
+        let mut mac = self.mac();
+        mac.update(msg.as_bytes());
+
+        format!(
+            "{}HMAC=[{}];",
+            msg,
+            hex::encode(mac.finalize().into_bytes())
+        )
+    }
+
+    /// Verifies the HMAC of `signed_msg`, and returns the
+    /// message text without its HMAC if it is authentic.
+    ///
+    /// Returns `None` for unsigned or forged messages.
+    pub(crate) fn verify<'a>(&self, signed_msg: &'a str) -> Option<&'a str> {
+        let msg_length = signed_msg.len().checked_sub(SIGNATURE_SUFFIX_LENGTH)?;
+
+        if !signed_msg.is_char_boundary(msg_length) {
+            return None;
+        }
+
+        let (msg, signature) = signed_msg.split_at(msg_length);
+
+        let digest = signature
+            .strip_prefix("HMAC=[")
+            .and_then(|signature| signature.strip_suffix("];"))
+            .and_then(|digest| hex::decode(digest).ok())?;
+
+        let mut mac = self.mac();
+        mac.update(msg.as_bytes());
+
+        // Constant-time comparison
+        mac.verify_slice(&digest).ok()?;
+
+        Some(msg)
+    }
+}
+
+#[cfg(test)]
+mod message_authenticator_test {
+    use super::MessageAuthenticator;
+
+    #[test]
+    fn signed_message_is_accepted_test() {
+        let authenticator = MessageAuthenticator::new(b"shared-key");
+
+        let signed_msg = authenticator.sign("CHECK_NODE;");
+
+        assert!(signed_msg.starts_with("CHECK_NODE;HMAC=["));
+        assert_eq!(authenticator.verify(&signed_msg), Some("CHECK_NODE;"));
+    }
+
+    #[test]
+    fn unsigned_or_forged_message_is_rejected_test() {
+        let authenticator = MessageAuthenticator::new(b"shared-key");
+
+        // Unsigned message
+        assert_eq!(authenticator.verify("CHECK_NODE;"), None);
+        assert_eq!(authenticator.verify(""), None);
+
+        // Message signed with another key
+        let forged_msg = MessageAuthenticator::new(b"other-key").sign("CHECK_NODE;");
+        assert_eq!(authenticator.verify(&forged_msg), None);
+
+        // Tampered message, with a valid signature of the original one
+        let signed_msg = authenticator.sign("UPDATE_DATA=[a][1];");
+        let tampered_msg = signed_msg.replacen("[a]", "[b]", 1);
+        assert_eq!(authenticator.verify(&tampered_msg), None);
+
+        // Non-ASCII text where the signature is expected
+        let garbage = "é".repeat(40);
+        assert_eq!(authenticator.verify(&garbage), None);
+    }
+}
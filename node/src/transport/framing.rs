@@ -0,0 +1,119 @@
+//! Framing of protocol messages, allowing several
+//! messages to be exchanged on a single connection.
+//!
+//! A frame is a netstring: `<length>:<message>,`, where `<length>`
+//! is the decimal length of the message in bytes. As protocol
+//! messages start with a letter, a framed connection is recognized
+//! by its first byte being a digit.
+
+use std::io::{self, BufRead, Read, Write};
+
+/// Maximum length of a framed message, in bytes.
+pub(crate) const MAX_FRAME_LENGTH: usize = 1024 * 1024;
+
+/// Maximum number of digits of a frame length.
+const MAX_LENGTH_DIGITS: usize = 7;
+
+/// Writes `msg` as a single frame on `writer`.
+pub(crate) fn write_frame<W: Write>(writer: &mut W, msg: &[u8]) -> io::Result<()> {
+    writer.write_all(format!("{}:", msg.len()).as_bytes())?;
+    writer.write_all(msg)?;
+    writer.write_all(b",")?;
+    writer.flush()
+}
+
+fn invalid_frame(reason: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("invalid frame ({})", reason),
+    )
+}
+
+/// Reads the next frame from `reader`, and returns its message.
+///
+/// Returns `None` if the peer closed the
+/// connection before the start of a frame.
+pub(crate) fn read_frame<R: BufRead>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut length_bytes = Vec::new();
+
+    reader
+        .by_ref()
+        .take(MAX_LENGTH_DIGITS as u64 + 1)
+        .read_until(b':', &mut length_bytes)?;
+
+    if length_bytes.is_empty() {
+        return Ok(None);
+    }
+
+    let length = match length_bytes.split_last() {
+        Some((b':', digits)) if !digits.is_empty() && digits.iter().all(u8::is_ascii_digit) => {
+            // Safe unwrap, ASCII digits only
+            std::str::from_utf8(digits)
+                .unwrap()
+                .parse::<usize>()
+                .unwrap()
+        }
+        _ => return Err(invalid_frame("malformed length")),
+    };
+
+    if length > MAX_FRAME_LENGTH {
+        return Err(invalid_frame("message too long"));
+    }
+
+    let mut msg = vec![0; length + 1];
+    reader.read_exact(&mut msg)?;
+
+    if msg.pop() != Some(b',') {
+        return Err(invalid_frame("missing terminator"));
+    }
+
+    Ok(Some(msg))
+}
+
+#[cfg(test)]
+mod framing_test {
+    use std::io::{self, Cursor};
+
+    use super::{read_frame, write_frame, MAX_FRAME_LENGTH};
+
+    #[test]
+    fn frames_round_trip_test() {
+        let mut buffer = Vec::new();
+        write_frame(&mut buffer, b"CHECK_NODE;").unwrap();
+        write_frame(&mut buffer, b"").unwrap();
+        write_frame(&mut buffer, b"GET_PREDECESSOR;").unwrap();
+
+        assert_eq!(buffer, b"11:CHECK_NODE;,0:,16:GET_PREDECESSOR;,");
+
+        let mut reader = Cursor::new(buffer);
+
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"CHECK_NODE;");
+        assert_eq!(read_frame(&mut reader).unwrap().unwrap(), b"");
+        assert_eq!(
+            read_frame(&mut reader).unwrap().unwrap(),
+            b"GET_PREDECESSOR;"
+        );
+        assert_eq!(read_frame(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn invalid_frames_are_rejected_test() {
+        let oversized_frame = format!("{}:", MAX_FRAME_LENGTH + 1);
+
+        for frame in [
+            "x:CHECK_NODE;,",
+            ":CHECK_NODE;,",
+            "11CHECK_NODE;,",
+            "11:CHECK_NODE;;",
+            "123456789:",
+            oversized_frame.as_str(),
+        ] {
+            let err = read_frame(&mut Cursor::new(frame)).unwrap_err();
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData, "{}", frame);
+        }
+
+        // Connection closed in the middle of a frame
+        let err = read_frame(&mut Cursor::new("11:CHECK")).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    }
+}
@@ -0,0 +1,55 @@
+//! In-memory transport, used to test components
+//! sending requests without opening connections.
+
+use std::{collections::HashMap, io, net::SocketAddr, sync::Mutex, time::Duration};
+
+use super::Transport;
+
+type Responder = dyn Fn(SocketAddr, &str) -> io::Result<String> + Send + Sync;
+
+/// Transport answering each request with a `responder`
+/// function, and recording every request sent.
+pub(crate) struct MockTransport {
+    responder: Box<Responder>,
+    latencies: HashMap<SocketAddr, Duration>,
+    sent_requests: Mutex<Vec<(SocketAddr, String)>>,
+}
+
+impl MockTransport {
+    pub(crate) fn new<F>(responder: F) -> Self
+    where
+        F: Fn(SocketAddr, &str) -> io::Result<String> + Send + Sync + 'static,
+    {
+        Self {
+            responder: Box::new(responder),
+            latencies: HashMap::new(),
+            sent_requests: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Reports `latency` as the round-trip time with `remote_addr`.
+    pub(crate) fn with_latency(mut self, remote_addr: SocketAddr, latency: Duration) -> Self {
+        self.latencies.insert(remote_addr, latency);
+        self
+    }
+
+    /// Returns all requests sent so far, in order.
+    pub(crate) fn sent_requests(&self) -> Vec<(SocketAddr, String)> {
+        self.sent_requests.lock().unwrap().clone()
+    }
+}
+
+impl Transport for MockTransport {
+    fn send(&self, remote_addr: SocketAddr, request_msg: &str) -> io::Result<String> {
+        self.sent_requests
+            .lock()
+            .unwrap()
+            .push((remote_addr, request_msg.to_string()));
+
+        (self.responder)(remote_addr, request_msg)
+    }
+
+    fn latency(&self, remote_addr: SocketAddr) -> Option<Duration> {
+        self.latencies.get(&remote_addr).copied()
+    }
+}
@@ -0,0 +1,234 @@
+//! Abstraction over the connections used to
+//! exchange protocol messages between nodes.
+
+use std::{
+    collections::HashMap,
+    io::{self, Read, Write},
+    net::{SocketAddr, TcpStream},
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+pub(crate) mod auth;
+pub(crate) mod framing;
+#[cfg(test)]
+pub(crate) mod mock;
+pub(crate) mod retry;
+pub(crate) mod tls;
+
+use auth::MessageAuthenticator;
+use tls::{Stream, TlsConfig};
+
+/// Default read timeout when waiting for a response.
+pub(crate) const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Sends a request message to a remote
+/// node and returns its response message.
+pub(crate) trait Transport: Send + Sync {
+    /// Sends `request_msg` to `remote_addr`,
+    /// and waits for the response message.
+    fn send(&self, remote_addr: SocketAddr, request_msg: &str) -> io::Result<String>;
+
+    /// Returns the recent round-trip time
+    /// measured with `remote_addr`, if any.
+    fn latency(&self, _remote_addr: SocketAddr) -> Option<Duration> {
+        None
+    }
+}
+
+/// Transport sending each request over a new TCP connection,
+/// and tracking the round-trip time of each remote node.
+///
+/// With an authenticator, requests are signed and
+/// responses failing verification are rejected
+/// (an empty response, to a dropped request, is kept).
+/// With a TLS configuration, connections are encrypted.
+#[derive(Debug, Default)]
+pub(crate) struct TcpTransport {
+    latency_table: LatencyTable,
+    authenticator: Option<MessageAuthenticator>,
+    tls: Option<Arc<TlsConfig>>,
+    /// Bound of the connection attempts, the
+    /// OS default connect timeout if `None`.
+    connect_timeout: Option<Duration>,
+    /// Read timeout when waiting for a response,
+    /// `DEFAULT_RESPONSE_TIMEOUT` if `None`.
+    response_timeout: Option<Duration>,
+}
+
+impl TcpTransport {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a transport signing and verifying
+    /// messages with `authenticator`.
+    pub(crate) fn with_authenticator(authenticator: MessageAuthenticator) -> Self {
+        Self {
+            authenticator: Some(authenticator),
+            ..Self::default()
+        }
+    }
+
+    /// Encrypts the connections with TLS.
+    pub(crate) fn with_tls(mut self, tls: Arc<TlsConfig>) -> Self {
+        self.tls = Some(tls);
+        self
+    }
+
+    /// Gives up the connection attempts after `connect_timeout`.
+    pub(crate) fn with_connect_timeout(mut self, connect_timeout: Duration) -> Self {
+        self.connect_timeout = Some(connect_timeout);
+        self
+    }
+
+    /// Gives up waiting for a response after `response_timeout`.
+    pub(crate) fn with_response_timeout(mut self, response_timeout: Duration) -> Self {
+        self.response_timeout = Some(response_timeout);
+        self
+    }
+}
+
+impl Transport for TcpTransport {
+    fn send(&self, remote_addr: SocketAddr, request_msg: &str) -> io::Result<String> {
+        let started_at = Instant::now();
+
+        let tcp_stream = match self.connect_timeout {
+            Some(connect_timeout) => TcpStream::connect_timeout(&remote_addr, connect_timeout)?,
+            None => TcpStream::connect(remote_addr)?,
+        };
+
+        // Also bounds the TLS handshake
+        tcp_stream.set_read_timeout(Some(
+            self.response_timeout.unwrap_or(DEFAULT_RESPONSE_TIMEOUT),
+        ))?;
+
+        let mut request_stream = Stream::connect(tcp_stream, self.tls.as_deref())?;
+
+        let request_msg = match self.authenticator {
+            Some(ref authenticator) => authenticator.sign(request_msg),
+            None => request_msg.to_string(),
+        };
+
+        request_stream.write_all(request_msg.as_bytes())?;
+        request_stream.shutdown_write()?;
+
+        let mut response_msg = String::new();
+        request_stream.read_to_string(&mut response_msg)?;
+
+        self.latency_table.record(remote_addr, started_at.elapsed());
+
+        match self.authenticator {
+            // Dropped request (e.g. unauthenticated), nothing to verify
+            Some(_) if response_msg.is_empty() => Ok(response_msg),
+            Some(ref authenticator) => match authenticator.verify(&response_msg) {
+                Some(response_msg) => Ok(response_msg.to_string()),
+                None => {
+                    eprintln!("dropped unauthenticated response from [{:?}]", remote_addr);
+                    Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "response failed authentication",
+                    ))
+                }
+            },
+            None => Ok(response_msg),
+        }
+    }
+
+    fn latency(&self, remote_addr: SocketAddr) -> Option<Duration> {
+        self.latency_table.get(remote_addr)
+    }
+}
+
+/// Recent round-trip time of each remote node, smoothed
+/// with an exponentially weighted moving average.
+#[derive(Debug, Default)]
+pub(crate) struct LatencyTable {
+    latencies: Mutex<HashMap<SocketAddr, Duration>>,
+}
+
+impl LatencyTable {
+    /// Records a new round-trip time
+    /// measurement with `remote_addr`.
+    pub(crate) fn record(&self, remote_addr: SocketAddr, rtt: Duration) {
+        let mut latencies = self.latencies.lock().unwrap();
+
+        let latency = match latencies.get(&remote_addr) {
+            // New sample weighted at 1/4
+            Some(latency) => (*latency * 3 + rtt) / 4,
+            None => rtt,
+        };
+
+        latencies.insert(remote_addr, latency);
+    }
+
+    /// Returns the smoothed round-trip
+    /// time with `remote_addr`, if any.
+    pub(crate) fn get(&self, remote_addr: SocketAddr) -> Option<Duration> {
+        self.latencies.lock().unwrap().get(&remote_addr).copied()
+    }
+}
+
+#[cfg(test)]
+mod tcp_transport_test {
+    use std::{
+        net::{SocketAddr, TcpListener},
+        time::{Duration, Instant},
+    };
+
+    use super::{TcpTransport, Transport};
+
+    #[test]
+    fn connect_timeout_bounds_unreachable_peer_test() {
+        // Non-routable address, never answering the connection attempts
+        let black_hole = "10.255.255.1:9".parse::<SocketAddr>().unwrap();
+        let transport = TcpTransport::new().with_connect_timeout(Duration::from_millis(200));
+
+        let started_at = Instant::now();
+
+        assert!(transport.send(black_hole, "SHARE_DATA=NONE;").is_err());
+        assert!(started_at.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn response_timeout_bounds_silent_peer_test() {
+        // Accepts the connection (in its backlog), never answers
+        let silent_listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let transport = TcpTransport::new().with_response_timeout(Duration::from_millis(200));
+
+        let started_at = Instant::now();
+
+        assert!(transport
+            .send(silent_listener.local_addr().unwrap(), "CHECK_NODE;")
+            .is_err());
+        assert!(started_at.elapsed() >= Duration::from_millis(200));
+        assert!(started_at.elapsed() < Duration::from_secs(2));
+    }
+}
+
+#[cfg(test)]
+mod latency_table_test {
+    use std::{net::SocketAddr, time::Duration};
+
+    use super::LatencyTable;
+
+    #[test]
+    fn latency_table_smoothing_test() {
+        let latency_table = LatencyTable::default();
+        let remote_addr = "10.0.0.1:8080".parse::<SocketAddr>().unwrap();
+
+        assert_eq!(latency_table.get(remote_addr), None);
+
+        latency_table.record(remote_addr, Duration::from_millis(100));
+        assert_eq!(
+            latency_table.get(remote_addr),
+            Some(Duration::from_millis(100))
+        );
+
+        latency_table.record(remote_addr, Duration::from_millis(20));
+        assert_eq!(
+            latency_table.get(remote_addr),
+            Some(Duration::from_millis(80))
+        );
+    }
+}
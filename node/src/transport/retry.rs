@@ -0,0 +1,136 @@
+//! Bounded retry of the transport failures to connect.
+
+use std::{io, net::SocketAddr, thread, time::Duration};
+
+use super::Transport;
+
+/// Base delay before a retry, grown with each attempt.
+const RETRY_BASE_DELAY: Duration = Duration::from_millis(50);
+
+/// Transport retrying requests whose connection was refused
+/// or reset, so that a single dropped connection attempt
+/// (e.g. a full accept queue) does not mark a remote node
+/// as unreachable.
+///
+/// Other failures are returned immediately. A timeout in
+/// particular is left to the caller, which knows whether
+/// a slow node is worth waiting for again (see
+/// `chord::SLOW_SUCCESSOR_RETRIES`).
+pub(crate) struct RetryingTransport<T: Transport> {
+    inner: T,
+    max_retries: u32,
+}
+
+impl<T: Transport> RetryingTransport<T> {
+    pub(crate) fn new(inner: T, max_retries: u32) -> Self {
+        Self { inner, max_retries }
+    }
+}
+
+/// Checks if a failure happened while connecting, and
+/// may not happen again on a new attempt.
+fn is_connect_failure(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ConnectionRefused | io::ErrorKind::ConnectionReset
+    )
+}
+
+/// Delay before the retry following `attempt` (starting at 1):
+/// linear backoff, with a random jitter to avoid retries
+/// of different nodes hitting a peer at the same time.
+fn retry_delay(attempt: u32) -> Duration {
+    RETRY_BASE_DELAY * attempt + RETRY_BASE_DELAY.mul_f64(rand::random::<f64>())
+}
+
+impl<T: Transport> Transport for RetryingTransport<T> {
+    fn send(&self, remote_addr: SocketAddr, request_msg: &str) -> io::Result<String> {
+        let mut attempt = 1;
+
+        loop {
+            match self.inner.send(remote_addr, request_msg) {
+                Err(err) if is_connect_failure(&err) && attempt <= self.max_retries => {
+                    thread::sleep(retry_delay(attempt));
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    fn latency(&self, remote_addr: SocketAddr) -> Option<Duration> {
+        self.inner.latency(remote_addr)
+    }
+}
+
+#[cfg(test)]
+mod retrying_transport_test {
+    use std::{
+        io,
+        net::SocketAddr,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+
+    use crate::transport::{mock::MockTransport, Transport};
+
+    use super::RetryingTransport;
+
+    /// Mock transport failing the first `failures`
+    /// attempts with `kind`, then answering `ACTIVE;`.
+    fn failing_transport(failures: usize, kind: io::ErrorKind) -> MockTransport {
+        let attempts = AtomicUsize::new(0);
+
+        MockTransport::new(move |_, _| {
+            if attempts.fetch_add(1, Ordering::SeqCst) < failures {
+                Err(io::Error::from(kind))
+            } else {
+                Ok("ACTIVE;".to_string())
+            }
+        })
+    }
+
+    fn remote_addr() -> SocketAddr {
+        "10.0.0.1:8080".parse().unwrap()
+    }
+
+    #[test]
+    fn connect_failure_is_retried_test() {
+        let transport =
+            RetryingTransport::new(failing_transport(1, io::ErrorKind::ConnectionRefused), 2);
+
+        assert_eq!(
+            transport.send(remote_addr(), "CHECK_NODE;").unwrap(),
+            "ACTIVE;"
+        );
+        assert_eq!(transport.inner.sent_requests().len(), 2);
+    }
+
+    #[test]
+    fn retries_are_bounded_test() {
+        let transport =
+            RetryingTransport::new(failing_transport(10, io::ErrorKind::ConnectionReset), 2);
+
+        assert_eq!(
+            transport
+                .send(remote_addr(), "CHECK_NODE;")
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::ConnectionReset
+        );
+        assert_eq!(transport.inner.sent_requests().len(), 3);
+    }
+
+    #[test]
+    fn timeout_is_not_retried_test() {
+        let transport = RetryingTransport::new(failing_transport(1, io::ErrorKind::TimedOut), 2);
+
+        assert_eq!(
+            transport
+                .send(remote_addr(), "CHECK_NODE;")
+                .unwrap_err()
+                .kind(),
+            io::ErrorKind::TimedOut
+        );
+        assert_eq!(transport.inner.sent_requests().len(), 1);
+    }
+}
@@ -0,0 +1,370 @@
+//! Optional encryption of the connections with TLS
+//! (`tls-cert`, `tls-key`, `tls-ca`, `tls-server-name`).
+//! External tools only connect to the nodes: they trust
+//! the certificates of the nodes, without a certificate
+//! of their own (see `TlsConfig::load_client`).
+//!
+//! All nodes of a ring must agree on TLS: a node with TLS
+//! rejects plaintext connections, whose bytes fail the
+//! handshake. The certificates of the nodes are issued for
+//! a name shared by the ring (`tls-server-name`), as nodes
+//! are reached by socket address rather than by host name.
+//! A self-signed certificate shared by the ring must not be
+//! a CA certificate (e.g. `openssl req -x509 ... -addext
+//! basicConstraints=critical,CA:FALSE`), which is rejected
+//! as the certificate of a node.
+//!
+//! A connection is delimited by TLS `close_notify` alerts, so
+//! that a request read until the peer shuts down its write
+//! half is not mistaken for a truncated one.
+
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{Shutdown, SocketAddr, TcpStream},
+    path::Path,
+    sync::Arc,
+};
+
+use rustls::{
+    crypto::ring,
+    pki_types::{pem::PemObject, CertificateDer, PrivateKeyDer, ServerName},
+    ClientConfig, ClientConnection, RootCertStore, ServerConfig, ServerConnection, StreamOwned,
+};
+
+/// Client and server TLS configurations of a node,
+/// shared by its inbound and outbound connections.
+#[derive(Clone)]
+pub(crate) struct TlsConfig {
+    client_config: Arc<ClientConfig>,
+    /// `None` for an external tool, which does not accept connections.
+    server_config: Option<Arc<ServerConfig>>,
+    /// Name the certificates of the nodes are issued for.
+    server_name: ServerName<'static>,
+}
+
+impl fmt::Debug for TlsConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Never prints the private key
+        f.debug_struct("TlsConfig")
+            .field("server_name", &self.server_name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl TlsConfig {
+    /// Loads the PEM certificate chain and private key of the
+    /// node, and the PEM certificates trusted for the other nodes
+    /// (`ca_path`, the node's own certificate chain if `None`,
+    /// for a ring sharing a self-signed certificate).
+    pub(crate) fn load(
+        cert_path: &Path,
+        key_path: &Path,
+        ca_path: Option<&Path>,
+        server_name: &str,
+    ) -> Result<Self, String> {
+        let cert_chain = load_certificates(cert_path)?;
+        let trusted_certificates = match ca_path {
+            Some(ca_path) => load_certificates(ca_path)?,
+            None => cert_chain.clone(),
+        };
+
+        let private_key = PrivateKeyDer::from_pem_file(key_path).map_err(|err| {
+            format!(
+                "cannot read the private key [{}]: {}",
+                key_path.display(),
+                err
+            )
+        })?;
+
+        let server_config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|err| err.to_string())?
+            .with_no_client_auth()
+            .with_single_cert(cert_chain, private_key)
+            .map_err(|err| format!("invalid certificate or private key: {}", err))?;
+
+        Ok(Self {
+            server_config: Some(Arc::new(server_config)),
+            ..Self::client_config(trusted_certificates, server_name)?
+        })
+    }
+
+    /// Loads the PEM certificates trusted for the nodes
+    /// (`ca_path`), for an external tool connecting to them.
+    pub(crate) fn load_client(ca_path: &Path, server_name: &str) -> Result<Self, String> {
+        Self::client_config(load_certificates(ca_path)?, server_name)
+    }
+
+    /// Creates the configuration of the outbound connections, trusting
+    /// `trusted_certificates` for the nodes, issued for `server_name`.
+    fn client_config(
+        trusted_certificates: Vec<CertificateDer<'static>>,
+        server_name: &str,
+    ) -> Result<Self, String> {
+        let mut root_store = RootCertStore::empty();
+
+        for certificate in trusted_certificates {
+            root_store
+                .add(certificate)
+                .map_err(|err| format!("invalid trusted certificate: {}", err))?;
+        }
+
+        let server_name = ServerName::try_from(server_name.to_string())
+            .map_err(|_| format!("invalid TLS server name `{}`", server_name))?;
+
+        let client_config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .map_err(|err| err.to_string())?
+            .with_root_certificates(root_store)
+            .with_no_client_auth();
+
+        Ok(Self {
+            client_config: Arc::new(client_config),
+            server_config: None,
+            server_name,
+        })
+    }
+}
+
+/// Reads every PEM certificate of the file at `path`.
+fn load_certificates(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let certificates = CertificateDer::pem_file_iter(path)
+        .and_then(|certificates| certificates.collect::<Result<Vec<_>, _>>())
+        .map_err(|err| format!("cannot read the certificates [{}]: {}", path.display(), err))?;
+
+    if certificates.is_empty() {
+        return Err(format!("no certificate found in [{}]", path.display()));
+    }
+
+    Ok(certificates)
+}
+
+/// Connection between two nodes, encrypted
+/// with TLS if the node is configured for it.
+#[derive(Debug)]
+pub(crate) enum Stream {
+    Plain(TcpStream),
+    Client(Box<StreamOwned<ClientConnection, TcpStream>>),
+    Server(Box<StreamOwned<ServerConnection, TcpStream>>),
+}
+
+impl Stream {
+    /// Wraps the outbound connection `tcp_stream`,
+    /// performing the TLS handshake if `tls` is set.
+    pub(crate) fn connect(tcp_stream: TcpStream, tls: Option<&TlsConfig>) -> io::Result<Self> {
+        let Some(tls) = tls else {
+            return Ok(Self::Plain(tcp_stream));
+        };
+
+        let connection =
+            ClientConnection::new(Arc::clone(&tls.client_config), tls.server_name.clone())
+                .map_err(io::Error::other)?;
+
+        let mut stream = StreamOwned::new(connection, tcp_stream);
+
+        while stream.conn.is_handshaking() {
+            stream.conn.complete_io(&mut stream.sock)?;
+        }
+
+        Ok(Self::Client(Box::new(stream)))
+    }
+
+    /// Wraps the accepted connection `tcp_stream`,
+    /// performing the TLS handshake if `tls` is set.
+    ///
+    /// The handshake of a plaintext peer fails
+    /// with an `InvalidData` error.
+    pub(crate) fn accept(tcp_stream: TcpStream, tls: Option<&TlsConfig>) -> io::Result<Self> {
+        let Some(tls) = tls else {
+            return Ok(Self::Plain(tcp_stream));
+        };
+
+        let Some(ref server_config) = tls.server_config else {
+            return Err(io::Error::other("no certificate to accept TLS connections"));
+        };
+
+        let connection =
+            ServerConnection::new(Arc::clone(server_config)).map_err(io::Error::other)?;
+
+        let mut stream = StreamOwned::new(connection, tcp_stream);
+
+        while stream.conn.is_handshaking() {
+            stream.conn.complete_io(&mut stream.sock)?;
+        }
+
+        Ok(Self::Server(Box::new(stream)))
+    }
+
+    fn tcp_stream(&self) -> &TcpStream {
+        match self {
+            Self::Plain(tcp_stream) => tcp_stream,
+            Self::Client(stream) => &stream.sock,
+            Self::Server(stream) => &stream.sock,
+        }
+    }
+
+    pub(crate) fn peer_addr(&self) -> io::Result<SocketAddr> {
+        self.tcp_stream().peer_addr()
+    }
+
+    /// Shuts down the write half of the connection, once
+    /// the TLS session (if any) is closed on this side.
+    pub(crate) fn shutdown_write(&mut self) -> io::Result<()> {
+        self.send_close_notify()?;
+        self.tcp_stream().shutdown(Shutdown::Write)
+    }
+
+    /// Sends the TLS `close_notify` alert, once.
+    fn send_close_notify(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(_) => Ok(()),
+            Self::Client(stream) => {
+                stream.conn.send_close_notify();
+                stream.flush()
+            }
+            Self::Server(stream) => {
+                stream.conn.send_close_notify();
+                stream.flush()
+            }
+        }
+    }
+}
+
+impl Drop for Stream {
+    /// Closes the TLS session cleanly, the peer
+    /// treating a bare TCP close as a truncation.
+    fn drop(&mut self) {
+        let _ = self.send_close_notify();
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(tcp_stream) => tcp_stream.read(buf),
+            Self::Client(stream) => stream.read(buf),
+            Self::Server(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(tcp_stream) => tcp_stream.write(buf),
+            Self::Client(stream) => stream.write(buf),
+            Self::Server(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Plain(tcp_stream) => tcp_stream.flush(),
+            Self::Client(stream) => stream.flush(),
+            Self::Server(stream) => stream.flush(),
+        }
+    }
+}
+
+/// Writes a self-signed certificate issued for `chord-node`,
+/// and its private key, to temporary files named after
+/// `file_prefix`, and returns their paths.
+#[cfg(test)]
+pub(crate) fn self_signed_files(file_prefix: &str) -> (std::path::PathBuf, std::path::PathBuf) {
+    use std::{env, fs};
+
+    let certified_key = rcgen::generate_simple_self_signed(vec!["chord-node".to_string()]).unwrap();
+
+    let cert_path = env::temp_dir().join(format!("{}-{}.pem", file_prefix, std::process::id()));
+    let key_path = env::temp_dir().join(format!("{}-{}.key", file_prefix, std::process::id()));
+    fs::write(&cert_path, certified_key.cert.pem()).unwrap();
+    fs::write(&key_path, certified_key.signing_key.serialize_pem()).unwrap();
+
+    (cert_path, key_path)
+}
+
+/// Configuration of a ring sharing a self-signed certificate
+/// issued for `chord-node` (see `self_signed_files`).
+#[cfg(test)]
+pub(crate) fn self_signed_config(file_prefix: &str) -> TlsConfig {
+    use std::fs;
+
+    let (cert_path, key_path) = self_signed_files(file_prefix);
+
+    let tls_config = TlsConfig::load(&cert_path, &key_path, None, "chord-node").unwrap();
+
+    let _ = fs::remove_file(cert_path);
+    let _ = fs::remove_file(key_path);
+
+    tls_config
+}
+
+#[cfg(test)]
+mod tls_test {
+    use std::{
+        env, fs,
+        io::{Read, Write},
+        net::{TcpListener, TcpStream},
+        thread,
+    };
+
+    use super::{self_signed_config, Stream, TlsConfig};
+
+    #[test]
+    fn request_round_trip_test() {
+        let tls_config = self_signed_config("chord-tls-round-trip");
+        let server_tls_config = tls_config.clone();
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let listener_addr = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let (tcp_stream, _) = listener.accept().unwrap();
+            let mut stream = Stream::accept(tcp_stream, Some(&server_tls_config)).unwrap();
+
+            // Read until the peer closes its TLS session
+            let mut request_msg = String::new();
+            stream.read_to_string(&mut request_msg).unwrap();
+            stream.write_all(b"PREDECESSOR=NONE;").unwrap();
+
+            request_msg
+        });
+
+        let tcp_stream = TcpStream::connect(listener_addr).unwrap();
+        let mut stream = Stream::connect(tcp_stream, Some(&tls_config)).unwrap();
+        stream.write_all(b"GET_PREDECESSOR;").unwrap();
+        stream.shutdown_write().unwrap();
+
+        let mut response_msg = String::new();
+        stream.read_to_string(&mut response_msg).unwrap();
+
+        assert_eq!(server.join().unwrap(), "GET_PREDECESSOR;");
+        assert_eq!(response_msg, "PREDECESSOR=NONE;");
+    }
+
+    #[test]
+    fn plaintext_peer_fails_handshake_test() {
+        let tls_config = self_signed_config("chord-tls-plaintext");
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let mut client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        client.write_all(b"GET_PREDECESSOR;").unwrap();
+
+        let (tcp_stream, _) = listener.accept().unwrap();
+        let err = Stream::accept(tcp_stream, Some(&tls_config)).unwrap_err();
+
+        assert_eq!(err.kind(), std::io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn missing_files_fail_to_load_test() {
+        let missing_path =
+            env::temp_dir().join(format!("chord-tls-missing-{}", std::process::id()));
+        let _ = fs::remove_file(&missing_path);
+
+        let err = TlsConfig::load(&missing_path, &missing_path, None, "chord-node").unwrap_err();
+
+        assert!(err.starts_with("cannot read the certificates"));
+    }
+}